@@ -0,0 +1,98 @@
+-- Initial schema
+--
+-- Every table stores the record as JSON in `data`; `site_id` and `user_id`
+-- are copied out of the record so list filters can use an index.
+
+CREATE TABLE IF NOT EXISTS sites (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_sites_site_id ON sites (site_id);
+
+CREATE TABLE IF NOT EXISTS shows (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_shows_site_id ON shows (site_id);
+
+CREATE TABLE IF NOT EXISTS songs (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_songs_site_id ON songs (site_id);
+
+CREATE TABLE IF NOT EXISTS posts (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_posts_site_id ON posts (site_id);
+
+CREATE TABLE IF NOT EXISTS photos (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_photos_site_id ON photos (site_id);
+
+CREATE TABLE IF NOT EXISTS videos (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_videos_site_id ON videos (site_id);
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS role_definitions (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_role_definitions_site_id ON role_definitions (site_id);
+
+CREATE TABLE IF NOT EXISTS access_entries (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_access_entries_user_id ON access_entries (user_id);
+
+CREATE TABLE IF NOT EXISTS credentials (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_credentials_user_id ON credentials (user_id);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions (user_id);
+
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
@@ -0,0 +1,33 @@
+-- Galleries, setlists, band members and contact form submissions
+
+CREATE TABLE IF NOT EXISTS galleries (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_galleries_site_id ON galleries (site_id);
+
+CREATE TABLE IF NOT EXISTS setlists (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_setlists_site_id ON setlists (site_id);
+
+CREATE TABLE IF NOT EXISTS band_members (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_band_members_site_id ON band_members (site_id);
+
+CREATE TABLE IF NOT EXISTS contact_submissions (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_contact_submissions_site_id ON contact_submissions (site_id);
@@ -0,0 +1,9 @@
+-- Revision history for blog posts and shows
+
+CREATE TABLE IF NOT EXISTS revisions (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_revisions_site_id ON revisions (site_id);
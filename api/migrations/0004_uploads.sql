@@ -0,0 +1,9 @@
+-- Media uploads (pending and completed)
+
+CREATE TABLE IF NOT EXISTS uploads (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_uploads_site_id ON uploads (site_id);
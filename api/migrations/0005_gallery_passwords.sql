@@ -0,0 +1,32 @@
+-- Gallery passwords, kept apart from gallery records
+
+CREATE TABLE IF NOT EXISTS gallery_passwords (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_gallery_passwords_site_id ON gallery_passwords (site_id);
+
+-- Galleries used to carry their password in plain text inside `visibility`.
+-- Move those out (marked "plain:"; the API hashes them at startup and never
+-- checks a password against one) and leave the gallery with a bare "password"
+-- visibility.
+INSERT OR REPLACE INTO gallery_passwords (id, site_id, user_id, data)
+SELECT
+    id,
+    site_id,
+    NULL,
+    json_object(
+        'galleryId', id,
+        'siteId', site_id,
+        'hash', 'plain:' || json_extract(data, '$.visibility.password.password'),
+        'updatedAt', CAST(strftime('%s', 'now') AS INTEGER)
+    )
+FROM galleries
+WHERE json_extract(data, '$.visibility.password.password') IS NOT NULL;
+
+UPDATE galleries
+SET data = json_set(data, '$.visibility', 'password')
+WHERE json_type(data, '$.visibility') = 'object'
+  AND json_type(data, '$.visibility.password') IS NOT NULL;
@@ -0,0 +1,5 @@
+-- One account per email address. Registration inserts with
+-- `ON CONFLICT DO NOTHING`, so two concurrent sign-ups for the same address
+-- can't both succeed.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email ON users (json_extract(data, '$.email'));
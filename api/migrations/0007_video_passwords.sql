@@ -0,0 +1,32 @@
+-- Video passwords, kept apart from video records
+
+CREATE TABLE IF NOT EXISTS video_passwords (
+    id TEXT PRIMARY KEY,
+    site_id TEXT,
+    user_id TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_video_passwords_site_id ON video_passwords (site_id);
+
+-- Videos share `GalleryVisibility`, so they carried their password in plain
+-- text inside `visibility` too. Move those out as 0005 did for galleries
+-- (marked "plain:" until the API hashes them at startup) and leave the video
+-- with a bare "password" visibility.
+INSERT OR REPLACE INTO video_passwords (id, site_id, user_id, data)
+SELECT
+    id,
+    site_id,
+    NULL,
+    json_object(
+        'videoId', id,
+        'siteId', site_id,
+        'hash', 'plain:' || json_extract(data, '$.visibility.password.password'),
+        'updatedAt', CAST(strftime('%s', 'now') AS INTEGER)
+    )
+FROM videos
+WHERE json_extract(data, '$.visibility.password.password') IS NOT NULL;
+
+UPDATE videos
+SET data = json_set(data, '$.visibility', 'password')
+WHERE json_type(data, '$.visibility') = 'object'
+  AND json_type(data, '$.visibility.password') IS NOT NULL;
@@ -0,0 +1,81 @@
+// Web Nexus API - Local Development Server
+//
+// Serves the same route table as the Cloudflare Worker on a native HTTP
+// server, so the API can be developed and integration-tested without wrangler.
+// A background task runs the cron trigger's housekeeping (`scheduled`).
+// Uploaded media is kept in a local directory.
+//
+// Environment:
+//   API_DEV_ADDR   - listen address (default 127.0.0.1:8787)
+//   API_DEV_DB     - SQLite database file (default api-dev.sqlite3)
+//   API_DEV_STATE  - optional AppState snapshot (JSON) to import on startup
+//   API_DEV_MEDIA  - directory for uploaded media (default api-dev-media)
+//   JWT_KEYS / JWT_ACTIVE_KID / JWT_SECRET - token signing keys (see `keys`)
+//   API_DEV_MODE   - "1" to sign with a throwaway key when none is configured
+//   ADMIN_BOOTSTRAP_TOKEN - lets the first admin register (see `auth::register`)
+
+use std::sync::Arc;
+use web_nexus_api::storage::{migrations, sqlite::SqliteExecutor, Storage};
+use web_nexus_api::objects::fs::FsObjectStore;
+use web_nexus_api::{keys::KeyRing, router, scheduled, ApiState};
+use web_nexus_state::deserialize_state;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    let keys = match KeyRing::from_lookup(|name| std::env::var(name).ok()) {
+        Ok(keys) => keys,
+        Err(e) => {
+            eprintln!("api-dev: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let addr = std::env::var("API_DEV_ADDR").unwrap_or_else(|_| "127.0.0.1:8787".to_string());
+
+    let db_path = std::env::var("API_DEV_DB").unwrap_or_else(|_| "api-dev.sqlite3".to_string());
+    let executor = Arc::new(SqliteExecutor::open(&db_path)?);
+    migrations::migrate(executor.as_ref()).await?;
+    let storage = Storage::sql(executor);
+    tracing::info!("using database {}", db_path);
+
+    if let Ok(path) = std::env::var("API_DEV_STATE") {
+        let state = deserialize_state(&std::fs::read(&path)?)?;
+        storage.import(&state).await?;
+        tracing::info!("imported state snapshot from {}", path);
+    }
+
+    let media_path = std::env::var("API_DEV_MEDIA").unwrap_or_else(|_| "api-dev-media".to_string());
+    let objects = FsObjectStore::new(&media_path, format!("http://{}/api/media", addr));
+    tracing::info!("storing media in {}", media_path);
+
+    let mut state = ApiState::new(storage, keys).with_objects(Arc::new(objects));
+    if let Ok(token) = std::env::var("ADMIN_BOOTSTRAP_TOKEN") {
+        state = state.with_bootstrap_token(token);
+    }
+    let hashed = state.hash_legacy_passwords().await?;
+    if hashed > 0 {
+        tracing::info!("hashed {} legacy passwords", hashed);
+    }
+
+    // Stand-in for the Worker's cron trigger
+    let ticker = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            scheduled(&ticker, chrono::Utc::now().timestamp()).await;
+        }
+    });
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    tracing::info!("api-dev listening on http://{}", addr);
+
+    axum::serve(listener, router::build(state)).await?;
+    Ok(())
+}
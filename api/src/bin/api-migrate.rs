@@ -0,0 +1,53 @@
+// Web Nexus API - Schema Migrations
+//
+// Applies and reports the versioned SQL migrations in api/migrations against
+// a local SQLite database. D1 databases are migrated with
+// `wrangler d1 migrations apply`, which records into the same table.
+//
+// Usage:
+//   api-migrate status [DB]  - list migrations and when each was applied
+//   api-migrate apply [DB]   - apply pending migrations in order
+//
+// DB defaults to $API_DEV_DB, then api-dev.sqlite3.
+
+use web_nexus_api::storage::migrations::{self, MIGRATIONS};
+use web_nexus_api::storage::sqlite::SqliteExecutor;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = args.first().map(String::as_str).unwrap_or("status");
+    let db_path = args
+        .get(1)
+        .cloned()
+        .or_else(|| std::env::var("API_DEV_DB").ok())
+        .unwrap_or_else(|| "api-dev.sqlite3".to_string());
+
+    let executor = SqliteExecutor::open(&db_path)?;
+
+    match command {
+        "status" => {
+            println!("{}:", db_path);
+            for migration in migrations::status(&executor, MIGRATIONS).await? {
+                match migration.applied_at {
+                    Some(at) => println!("  {}  applied {}", migration.name, at),
+                    None => println!("  {}  pending", migration.name),
+                }
+            }
+        }
+        "apply" => {
+            let applied = migrations::apply(&executor, MIGRATIONS).await?;
+            if applied.is_empty() {
+                println!("{}: up to date", db_path);
+            }
+            for migration in applied {
+                println!("{}: applied {}", db_path, migration.name);
+            }
+        }
+        other => {
+            eprintln!("api-migrate: unknown command \"{}\" (expected status or apply)", other);
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,302 @@
+// Credential Storage
+//
+// Argon2id password hashes, kept apart from `User` (and gallery and video
+// passwords apart from `Gallery` and `Video`) so that those records can be
+// synced to clients without ever carrying secrets.
+
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use password_hash::{rand_core::OsRng, SaltString};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use web_nexus_contracts::ApiErrorKind;
+
+use crate::storage::{Entity, Filter, Repository, StorageResult};
+
+/// Stored password credential for a single user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordCredential {
+    /// Owning user ID
+    pub user_id: String,
+    /// Argon2id hash in PHC string format
+    pub hash: String,
+    /// Last time the password was set
+    pub updated_at: i64,
+}
+
+impl Entity for PasswordCredential {
+    const TABLE: &'static str = "credentials";
+
+    fn id(&self) -> &str {
+        &self.user_id
+    }
+
+    fn user_id(&self) -> Option<&str> {
+        Some(&self.user_id)
+    }
+}
+
+/// Password credentials keyed by user ID
+#[derive(Clone)]
+pub struct CredentialStore {
+    credentials: Arc<dyn Repository<PasswordCredential>>,
+}
+
+impl CredentialStore {
+    /// Create a credential store over a repository
+    pub fn new(credentials: Arc<dyn Repository<PasswordCredential>>) -> Self {
+        Self { credentials }
+    }
+
+    /// Get the credential for a user
+    pub async fn get(&self, user_id: &str) -> StorageResult<Option<PasswordCredential>> {
+        self.credentials.get(user_id).await
+    }
+
+    /// Store a password hash for a user, replacing any existing one
+    pub async fn set_hash(&self, user_id: &str, hash: String) -> StorageResult<()> {
+        let credential = PasswordCredential {
+            user_id: user_id.to_string(),
+            hash,
+            updated_at: chrono::Utc::now().timestamp(),
+        };
+        self.credentials.put(&credential).await
+    }
+
+    /// Verify a password for a user.
+    ///
+    /// Users without a credential are checked against a dummy hash so that
+    /// unknown and known accounts take the same time to reject.
+    pub async fn verify(&self, user_id: Option<&str>, password: &str) -> StorageResult<bool> {
+        let credential = match user_id {
+            Some(id) => self.credentials.get(id).await?,
+            None => None,
+        };
+        Ok(match credential {
+            Some(credential) => verify_password(password, &credential.hash),
+            None => {
+                verify_password(password, dummy_hash());
+                false
+            }
+        })
+    }
+}
+
+/// A password guarding one gallery or video, stored apart from it
+pub trait RecordPassword: Entity {
+    /// Stored password of record `id`
+    fn new(id: &str, site_id: &str, hash: String, updated_at: i64) -> Self;
+
+    /// Argon2id hash in PHC string format (or, until hashed at startup, a
+    /// legacy plain password; see `LEGACY_PLAIN_PREFIX`)
+    fn hash(&self) -> &str;
+}
+
+/// Stored password of a password-protected gallery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GalleryPassword {
+    /// Gallery the password unlocks
+    pub gallery_id: String,
+    pub site_id: String,
+    /// See `RecordPassword::hash`
+    pub hash: String,
+    /// Last time the password was set
+    pub updated_at: i64,
+}
+
+impl Entity for GalleryPassword {
+    const TABLE: &'static str = "gallery_passwords";
+
+    fn id(&self) -> &str {
+        &self.gallery_id
+    }
+
+    fn site_id(&self) -> Option<&str> {
+        Some(&self.site_id)
+    }
+}
+
+impl RecordPassword for GalleryPassword {
+    fn new(id: &str, site_id: &str, hash: String, updated_at: i64) -> Self {
+        Self { gallery_id: id.to_string(), site_id: site_id.to_string(), hash, updated_at }
+    }
+
+    fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+/// Stored password of a password-protected video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoPassword {
+    /// Video the password unlocks
+    pub video_id: String,
+    pub site_id: String,
+    /// See `RecordPassword::hash`
+    pub hash: String,
+    /// Last time the password was set
+    pub updated_at: i64,
+}
+
+impl Entity for VideoPassword {
+    const TABLE: &'static str = "video_passwords";
+
+    fn id(&self) -> &str {
+        &self.video_id
+    }
+
+    fn site_id(&self) -> Option<&str> {
+        Some(&self.site_id)
+    }
+}
+
+impl RecordPassword for VideoPassword {
+    fn new(id: &str, site_id: &str, hash: String, updated_at: i64) -> Self {
+        Self { video_id: id.to_string(), site_id: site_id.to_string(), hash, updated_at }
+    }
+
+    fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+/// Marks a password migrations 0005 and 0007 moved out of gallery and video
+/// records before it was ever hashed; `PasswordStore::hash_legacy` hashes it
+/// at startup
+pub const LEGACY_PLAIN_PREFIX: &str = "plain:";
+
+/// Passwords of one kind of record, keyed by record ID
+#[derive(Clone)]
+pub struct PasswordStore<T> {
+    passwords: Arc<dyn Repository<T>>,
+}
+
+/// Gallery passwords keyed by gallery ID
+pub type GalleryPasswordStore = PasswordStore<GalleryPassword>;
+
+/// Video passwords keyed by video ID
+pub type VideoPasswordStore = PasswordStore<VideoPassword>;
+
+impl<T: RecordPassword> PasswordStore<T> {
+    /// Create a password store over a repository
+    pub fn new(passwords: Arc<dyn Repository<T>>) -> Self {
+        Self { passwords }
+    }
+
+    /// Get the stored password of a record
+    pub async fn get(&self, id: &str) -> StorageResult<Option<T>> {
+        self.passwords.get(id).await
+    }
+
+    /// Whether a record has a password
+    pub async fn exists(&self, id: &str) -> StorageResult<bool> {
+        Ok(self.get(id).await?.is_some())
+    }
+
+    /// Store a password hash for a record, replacing any existing one
+    pub async fn set_hash(&self, id: &str, site_id: &str, hash: String) -> StorageResult<()> {
+        self.passwords.put(&T::new(id, site_id, hash, chrono::Utc::now().timestamp())).await
+    }
+
+    /// Forget a record's password
+    pub async fn remove(&self, id: &str) -> StorageResult<()> {
+        self.passwords.delete(id).await.map(|_| ())
+    }
+
+    /// Verify a password for a record. Records without a password are
+    /// checked against a dummy hash, like unknown users.
+    pub async fn verify(&self, id: &str, password: &str) -> Result<bool, ApiErrorKind> {
+        let Some(stored) = self.passwords.get(id).await? else {
+            verify_password(password, dummy_hash());
+            return Ok(false);
+        };
+        Ok(verify_password(password, stored.hash()))
+    }
+
+    /// Hash the passwords migrations moved out of records in plain text (see
+    /// `LEGACY_PLAIN_PREFIX`), returning how many there were. Until then they
+    /// unlock nothing.
+    pub async fn hash_legacy(&self) -> Result<usize, ApiErrorKind> {
+        let mut hashed = 0;
+        for stored in self.passwords.list(&Filter::all()).await? {
+            if let Some(plain) = stored.hash().strip_prefix(LEGACY_PLAIN_PREFIX) {
+                let site_id = stored.site_id().unwrap_or_default();
+                self.set_hash(stored.id(), site_id, hash_password(plain)?).await?;
+                hashed += 1;
+            }
+        }
+        Ok(hashed)
+    }
+}
+
+/// Hash a password with Argon2id and a random salt
+pub fn hash_password(password: &str) -> Result<String, ApiErrorKind> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| ApiErrorKind::Internal(format!("Password hashing failed: {}", e)))
+}
+
+/// Verify a password against a PHC-format hash
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// Compare secrets without leaking where they differ
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Hash used to burn the same verification time when no credential exists
+fn dummy_hash() -> &'static str {
+    static DUMMY: OnceLock<String> = OnceLock::new();
+    DUMMY.get_or_init(|| hash_password("web-nexus-dummy-password").unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryRepository;
+
+    #[test]
+    fn test_hash_is_argon2id_and_verifies() {
+        let hash = hash_password("correct horse battery").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("correct horse battery", &hash));
+        assert!(!verify_password("wrong horse battery", &hash));
+    }
+
+    #[tokio::test]
+    async fn test_store_rejects_unknown_user() {
+        let store = CredentialStore::new(Arc::new(MemoryRepository::new()));
+        store.set_hash("user-1", hash_password("s3cret-password").unwrap()).await.unwrap();
+
+        assert!(store.verify(Some("user-1"), "s3cret-password").await.unwrap());
+        assert!(!store.verify(Some("user-1"), "not-the-password").await.unwrap());
+        assert!(!store.verify(Some("user-2"), "s3cret-password").await.unwrap());
+        assert!(!store.verify(None, "s3cret-password").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_gallery_passwords_are_hashed() {
+        let repo = Arc::new(MemoryRepository::<GalleryPassword>::new());
+        let store = GalleryPasswordStore::new(repo.clone());
+        store.set_hash("g1", "s1", format!("{}encore", LEGACY_PLAIN_PREFIX)).await.unwrap();
+        store.set_hash("g2", "s1", hash_password("b-side").unwrap()).await.unwrap();
+
+        // A plain password unlocks nothing, even when it is the right one
+        assert!(!store.verify("g1", "encore").await.unwrap());
+        assert_eq!(store.hash_legacy().await.unwrap(), 1);
+        assert!(repo.get("g1").await.unwrap().unwrap().hash.starts_with("$argon2id$"));
+        assert!(store.verify("g1", "encore").await.unwrap());
+        assert!(!store.verify("g1", "encorf").await.unwrap());
+        assert!(store.verify("g2", "b-side").await.unwrap());
+        assert_eq!(store.hash_legacy().await.unwrap(), 0);
+        assert!(!store.verify("g3", "encore").await.unwrap());
+    }
+}
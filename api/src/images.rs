@@ -0,0 +1,111 @@
+// Image Processing
+//
+// Produces the derivatives `images::plan` asks for. The Worker can't afford
+// to decode and re-encode photos itself, so the production processor hands
+// the work to Cloudflare Image Transformations: each derivative is a
+// `/cdn-cgi/image/...` URL over the original, rendered at the edge on first
+// request and cached. Processors that write derivative files of their own can
+// implement the same trait.
+
+use async_trait::async_trait;
+use thiserror::Error;
+use web_nexus_contracts::images::{DerivativeKind, DerivativeSpec};
+use web_nexus_contracts::ApiErrorKind;
+
+/// Image processing error
+#[derive(Debug, Error)]
+pub enum ImageError {
+    #[error("Image processing error: {0}")]
+    Backend(String),
+}
+
+impl From<ImageError> for ApiErrorKind {
+    fn from(error: ImageError) -> Self {
+        tracing::error!("{}", error);
+        ApiErrorKind::Internal("Image processing error".to_string())
+    }
+}
+
+/// The original a derivative is made from
+#[derive(Debug, Clone, Copy)]
+pub struct Original<'a> {
+    /// Public URL of the original
+    pub url: &'a str,
+    /// Object storage key, for uploaded photos
+    pub key: Option<&'a str>,
+}
+
+/// Makes photo derivatives
+#[async_trait]
+pub trait ImageProcessor: Send + Sync {
+    /// Produce `spec` from `original`, returning the URL it is served at
+    async fn derive(&self, original: Original<'_>, spec: &DerivativeSpec) -> Result<String, ImageError>;
+}
+
+/// Cloudflare Image Transformations on the zone at `base`
+/// (e.g. `https://media.example.com`). Transformations must be enabled for
+/// the zone, and originals on other hosts must be allowed as sources.
+#[derive(Debug, Clone)]
+pub struct CloudflareImages {
+    base: String,
+    quality: u8,
+}
+
+impl CloudflareImages {
+    pub fn new(base: impl Into<String>) -> Self {
+        Self { base: base.into().trim_end_matches('/').to_string(), quality: 82 }
+    }
+
+    /// Encoding quality, 1-100 (default 82)
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Transformation URL for `spec` over the original at `source`
+    pub fn url(&self, source: &str, spec: &DerivativeSpec) -> String {
+        let fit = match spec.kind {
+            DerivativeKind::Thumbnail => "cover",
+            DerivativeKind::Responsive => "scale-down",
+        };
+        let format = spec.format.mime().trim_start_matches("image/");
+        // Originals on the same zone are referenced by path
+        let source = match source.strip_prefix(&self.base) {
+            Some(path) if path.starts_with('/') => path,
+            _ => source,
+        };
+        let separator = if source.starts_with('/') { "" } else { "/" };
+        format!(
+            "{}/cdn-cgi/image/width={},height={},fit={},format={},quality={},metadata=none{}{}",
+            self.base, spec.width, spec.height, fit, format, self.quality, separator, source
+        )
+    }
+}
+
+#[async_trait]
+impl ImageProcessor for CloudflareImages {
+    async fn derive(&self, original: Original<'_>, spec: &DerivativeSpec) -> Result<String, ImageError> {
+        Ok(self.url(original.url, spec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web_nexus_contracts::images::ImageFormat;
+
+    #[test]
+    fn test_cloudflare_transformation_urls() {
+        let images = CloudflareImages::new("https://media.example.com/");
+        let thumb = DerivativeSpec { kind: DerivativeKind::Thumbnail, width: 400, height: 400, format: ImageFormat::Webp };
+        assert_eq!(
+            images.url("https://media.example.com/sites/s/a.jpg", &thumb),
+            "https://media.example.com/cdn-cgi/image/width=400,height=400,fit=cover,format=webp,quality=82,metadata=none/sites/s/a.jpg"
+        );
+        let wide = DerivativeSpec { kind: DerivativeKind::Responsive, width: 1280, height: 720, format: ImageFormat::Avif };
+        assert_eq!(
+            images.with_quality(60).url("https://cdn.other.net/a.jpg", &wide),
+            "https://media.example.com/cdn-cgi/image/width=1280,height=720,fit=scale-down,format=avif,quality=60,metadata=none/https://cdn.other.net/a.jpg"
+        );
+    }
+}
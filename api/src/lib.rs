@@ -4,20 +4,62 @@
 //
 // Based on WEB-NEXUS V2 Architecture - Section 3.1
 
-use worker::*;
+pub mod credentials;
+pub mod images;
+pub mod keys;
+pub mod objects;
+pub mod oembed;
+pub mod principal;
+pub mod query;
+pub mod sessions;
+pub mod storage;
+pub mod tenant;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
 use serde_json::json;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use web_nexus_contracts::{
-    Show, Song, Photo, Video, BlogPost, CreateShowRequest, UpdateShowRequest,
-    CreateSongRequest, CreateBlogPostRequest, CreatePhotoRequest, CreateVideoRequest,
-    ApiErrorKind, PaginatedResponse, ShowStatus, PostStatus, GalleryVisibility, VideoSource,
-    ImageDimensions, User, Role,
+    Show, Song, Photo, Video, BlogPost, Gallery, Setlist, BandMember, Site, SiteStatus, SiteView, ContactSubmission,
+    CreateShowRequest, UpdateShowRequest, CreateSongRequest, UpdateSongRequest,
+    CreateBlogPostRequest, UpdateBlogPostRequest, CreatePhotoRequest, UpdatePhotoRequest,
+    CreateVideoRequest, UpdateVideoRequest, VideoAccessToken, CreateGalleryRequest, UpdateGalleryRequest,
+    CreateSetlistRequest, UpdateSetlistRequest, ReorderSongsRequest, MoveSongRequest, ReorderSetsRequest, SetlistDetail, ShowSetlist, MemberNote,
+    Revision, RevisionComparison, RevisionSummary,
+    CreateBandMemberRequest, UpdateBandMemberRequest,
+    CreateSiteRequest, UpdateSiteRequest, AddSiteMemberRequest, SITE_DOMAIN_SUFFIX, CreateContactSubmissionRequest, UpdateContactSubmissionRequest,
+    ApiError, ApiErrorKind, PaginatedResponse, ShowStatus, PostStatus, PostTransition, SchedulePostRequest, GalleryVisibility, VideoSource,
+    ImageDimensions, User, UserStatus, Role, RegisterRequest, ChangePasswordRequest, Permission, SiteScope,
+    GrantAccessRequest, ResourceAccess, can_access_resource, RESOURCE_TYPES,
+    RoleDefinition, CreateRoleRequest, UpdateRoleRequest, AssignRolesRequest,
+};
+use web_nexus_contracts::export::{ExportOptions, MemberNotes, SetlistChart};
+use web_nexus_contracts::gallery::{
+    AddGalleryPhotosRequest, GalleryAccess, GalleryAccessToken, GalleryImages, GalleryReader,
+    ReorderGalleryPhotosRequest, SetGalleryCoverRequest, UnlockGalleryRequest,
 };
-use web_nexus_state::AppState;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use web_nexus_contracts::images::{plan as image_plan, DerivativeKind, ImageFormat, ResponsiveImage, DEFAULT_SIZES};
+use web_nexus_contracts::content::RENDERER_VERSION;
+use web_nexus_contracts::video;
+use keys::{KeyError, KeyRing};
+use images::ImageProcessor;
+use oembed::OEmbedFetcher;
+use objects::{memory::MemoryObjectStore, ObjectStore};
+use principal::Principal;
+use query::ListQuery;
+use sessions::{RefreshError, Session, ACCESS_TOKEN_TTL};
+use storage::{Filter, Storage, StorageError, StorageResult};
+use tenant::SiteContext;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use garde::Validate;
-use jsonwebtoken::{encode, decode, Validation, Algorithm, Header, EncodingKey, DecodingKey};
 use chrono::{Utc, Duration};
 
 /// JWT claims for user authentication
@@ -28,273 +70,504 @@ struct Claims {
     /// User email
     email: String,
     /// User roles
-    roles: Vec<String>,
+    roles: Vec<Role>,
+    /// Sites the roles reach
+    sites: SiteScope,
     /// Issued at
     iat: i64,
     /// Expiration time
     exp: i64,
     /// Issuer
     iss: String,
+    /// Session ID
+    sid: String,
+    /// Token ID (for revocation)
+    jti: String,
 }
 
-/// Generate JWT token for user
-fn generate_jwt_token(user: &User, secret: &str) -> worker::Result<String> {
+/// Generate a short-lived JWT access token for a user session.
+///
+/// Returns the token and its expiry timestamp.
+fn generate_jwt_token(
+    user: &User,
+    session_id: &str,
+    keys: &KeyRing,
+) -> std::result::Result<(String, i64), ApiErrorKind> {
     let now = Utc::now();
-    let expiration = now + Duration::hours(24);
+    let expiration = now + Duration::seconds(ACCESS_TOKEN_TTL);
 
     let claims = Claims {
         sub: user.id.clone(),
         email: user.email.clone(),
-        roles: user.roles.iter().map(|r| format!("{:?}", r)).collect(),
+        roles: user.roles.clone(),
+        sites: SiteScope::for_user(user),
         iat: now.timestamp(),
         exp: expiration.timestamp(),
         iss: "web-nexus-cms".to_string(),
+        sid: session_id.to_string(),
+        jti: uuid::Uuid::new_v4().to_string(),
     };
 
-    let mut header = Header::default();
-    header.alg = Algorithm::HS512;
-
-    encode(&header, &claims, &EncodingKey::from_secret(secret.as_ref()))
-        .map_err(|e| worker::Error::from(format!("JWT encoding failed: {}", e)))
+    keys.sign(&claims).map(|token| (token, claims.exp))
 }
 
 /// Validate JWT token and extract claims
-fn validate_jwt_token(token: &str, secret: &str) -> worker::Result<Claims> {
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::new(Algorithm::HS512)
-    )
-    .map_err(|e| worker::Error::from(format!("JWT validation failed: {}", e)))?;
-
-    Ok(token_data.claims)
-}
-
-/// Extract user ID from JWT token
-fn extract_user_id_from_claims(claims: &Claims) -> String {
-    claims.sub.clone()
+fn validate_jwt_token(token: &str, keys: &KeyRing) -> std::result::Result<Claims, ApiErrorKind> {
+    keys.verify(token)
 }
 
 /// Shared application state for the Workers
 #[derive(Clone)]
 pub struct ApiState {
-    pub app_state: Arc<RwLock<AppState>>,
-    pub jwt_secret: String,
+    pub storage: Storage,
+    pub keys: Arc<KeyRing>,
+    /// Uploaded files
+    pub objects: Arc<dyn ObjectStore>,
+    /// Makes photo thumbnails and responsive sizes; without one, photos are
+    /// only offered at their original size
+    pub images: Option<Arc<dyn ImageProcessor>>,
+    /// Looks up thumbnails and durations of linked videos; without one,
+    /// videos keep what the client sent
+    pub oembed: Option<Arc<dyn OEmbedFetcher>>,
+    /// Secret that lets `auth::register` create the first admin; without
+    /// one, admins can only be made by an existing admin
+    pub bootstrap_token: Option<Arc<str>>,
+}
+
+/// Why API state could not be built from the environment
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Keys(#[from] KeyError),
+
+    #[error("D1 binding \"{0}\" is missing")]
+    Database(&'static str),
+
+    #[error("R2 binding \"{0}\" is missing")]
+    Bucket(&'static str),
 }
 
 impl ApiState {
-    /// Create a new API state
-    pub fn new() -> Self {
-        Self {
-            app_state: Arc::new(RwLock::new(AppState::new())),
-            jwt_secret: std::env::var("JWT_SECRET").unwrap_or_else(|_| "dev-secret".to_string()),
+    /// Create API state over `storage` that signs tokens with `keys`, keeping
+    /// uploads in memory until `with_objects` says otherwise
+    pub fn new(storage: Storage, keys: KeyRing) -> Self {
+        Self { storage, keys: Arc::new(keys), objects: Arc::new(MemoryObjectStore::new()), images: None, oembed: None, bootstrap_token: None }
+    }
+
+    /// Keep uploaded files in `objects`
+    pub fn with_objects(mut self, objects: Arc<dyn ObjectStore>) -> Self {
+        self.objects = objects;
+        self
+    }
+
+    /// Make photo derivatives with `images`
+    pub fn with_images(mut self, images: Arc<dyn ImageProcessor>) -> Self {
+        self.images = Some(images);
+        self
+    }
+
+    /// Let the holder of `token` register the first admin
+    pub fn with_bootstrap_token(mut self, token: impl Into<Arc<str>>) -> Self {
+        self.bootstrap_token = Some(token.into());
+        self
+    }
+
+    /// Look up video metadata with `oembed`
+    pub fn with_oembed(mut self, oembed: Arc<dyn OEmbedFetcher>) -> Self {
+        self.oembed = Some(oembed);
+        self
+    }
+
+    /// Create API state from Workers bindings (`std::env` is empty inside a Worker)
+    pub fn from_env(env: &worker::Env) -> std::result::Result<Self, ConfigError> {
+        let keys = KeyRing::from_lookup(|name| {
+            env.secret(name)
+                .or_else(|_| env.var(name))
+                .map(|v| v.to_string())
+                .ok()
+        })?;
+        let database = env.d1("DB").map_err(|_| ConfigError::Database("DB"))?;
+        let executor = Arc::new(storage::d1::D1Executor::new(database));
+
+        let var = |name: &str| env.secret(name).or_else(|_| env.var(name)).map(|v| v.to_string()).ok();
+        let bucket = env.bucket("MEDIA").map_err(|_| ConfigError::Bucket("MEDIA"))?;
+        let public_base = var("MEDIA_PUBLIC_URL").unwrap_or_else(|| objects::DEFAULT_PUBLIC_BASE.to_string());
+        let mut media = objects::r2::R2ObjectStore::new(bucket, public_base);
+        // Direct uploads need an R2 API token for the bucket's S3 endpoint
+        if let (Some(account), Some(bucket), Some(key_id), Some(secret)) =
+            (var("R2_ACCOUNT_ID"), var("R2_BUCKET"), var("R2_ACCESS_KEY_ID"), var("R2_SECRET_ACCESS_KEY"))
+        {
+            media = media.with_presigner(objects::presign::S3Presigner::r2(&account, &bucket, &key_id, &secret));
+        }
+
+        let mut state = Self::new(Storage::sql(executor), keys)
+            .with_objects(Arc::new(media))
+            .with_oembed(Arc::new(oembed::WorkerOEmbed));
+        if let Some(token) = var("ADMIN_BOOTSTRAP_TOKEN").filter(|t| !t.is_empty()) {
+            state = state.with_bootstrap_token(token);
+        }
+        if let Some(base) = var("IMAGE_TRANSFORM_BASE") {
+            state = state.with_images(Arc::new(images::CloudflareImages::new(base)));
         }
+        Ok(state)
     }
 
     /// Get a show by ID
-    pub async fn get_show(&self, id: &str) -> Option<Show> {
-        let state = self.app_state.read().await;
-        state.shows.get(id).cloned()
+    pub async fn get_show(&self, id: &str) -> StorageResult<Option<Show>> {
+        self.storage.shows.get(id).await
     }
 
-    /// List all shows with optional pagination
-    pub async fn list_shows(&self, page: u32, per_page: u32) -> Vec<Show> {
-        let state = self.app_state.read().await;
-        let shows: Vec<Show> = state.shows.values().cloned().collect();
-        let start = (page * per_page) as usize;
-        let end = ((page + 1) * per_page) as usize;
-        shows.into_iter().skip(start).take(end - start).collect()
+    /// List shows visible in `scope`
+    pub async fn list_shows(&self, scope: &SiteScope, query: &ListQuery) -> std::result::Result<PaginatedResponse<Show>, ApiErrorKind> {
+        query.fetch(self.storage.shows.as_ref(), query.storage_filter(scope)).await
     }
 
     /// Create a new show
     pub async fn create_show(&self, show: Show) -> std::result::Result<Show, ApiErrorKind> {
-        let mut state = self.app_state.write().await;
-        let id = show.id.clone();
-        state.shows.insert(id.clone(), show.clone());
+        self.storage.shows.put(&show).await?;
         Ok(show)
     }
 
     /// Update an existing show
     pub async fn update_show(&self, id: &str, show: Show) -> std::result::Result<Show, ApiErrorKind> {
-        let mut state = self.app_state.write().await;
-        if !state.shows.contains_key(id) {
+        if self.storage.shows.get(id).await?.is_none() {
             return Err(ApiErrorKind::NotFound("Show not found".to_string()));
         }
-        state.shows.insert(id.to_string(), show.clone());
+        self.storage.shows.put(&show).await?;
         Ok(show)
     }
 
     /// Delete a show
     pub async fn delete_show(&self, id: &str) -> std::result::Result<(), ApiErrorKind> {
-        let mut state = self.app_state.write().await;
-        state.shows.remove(id)
-            .map(|_| ())
-            .ok_or_else(|| ApiErrorKind::NotFound("Show not found".to_string()))
+        if self.storage.shows.delete(id).await? {
+            Ok(())
+        } else {
+            Err(ApiErrorKind::NotFound("Show not found".to_string()))
+        }
     }
 
-    /// Get all songs
-    pub async fn list_songs(&self) -> Vec<Song> {
-        let state = self.app_state.read().await;
-        state.songs.values().cloned().collect()
+    /// List songs visible in `scope`
+    pub async fn list_songs(&self, scope: &SiteScope, query: &ListQuery) -> std::result::Result<PaginatedResponse<Song>, ApiErrorKind> {
+        query.fetch(self.storage.songs.as_ref(), query.storage_filter(scope)).await
     }
 
     /// Create a song
     pub async fn create_song(&self, song: Song) -> std::result::Result<Song, ApiErrorKind> {
-        let mut state = self.app_state.write().await;
-        let id = song.id.clone();
-        state.songs.insert(id.clone(), song.clone());
+        self.storage.songs.put(&song).await?;
         Ok(song)
     }
 
-    /// Get blog posts with pagination
-    pub async fn list_posts(&self, page: u32, per_page: u32) -> Vec<BlogPost> {
-        let state = self.app_state.read().await;
-        let posts: Vec<BlogPost> = state.posts.values().cloned().collect();
-        let start = (page * per_page) as usize;
-        let end = ((page + 1) * per_page) as usize;
-        posts.into_iter().skip(start).take(end - start).collect()
+    /// List blog posts visible to `principal`: published posts, plus every
+    /// post on the sites where they edit posts
+    pub async fn list_posts(&self, principal: Option<&Principal>, query: &ListQuery) -> std::result::Result<PaginatedResponse<BlogPost>, ApiErrorKind> {
+        let mut filter = query.storage_filter(&read_scope(principal));
+        match principal.map(|p| p.sites_with(Permission::EditPost)) {
+            Some(SiteScope::All) => {}
+            editable => {
+                let mut visible = vec![Filter::all().field("status", "published")];
+                visible.extend(editable.map(|sites| Filter::scope(&sites)));
+                filter = filter.any(visible);
+            }
+        }
+        let mut response = query.fetch(self.storage.posts.as_ref(), filter).await?;
+        for post in &mut response.data {
+            posts::refresh_rendered(post);
+        }
+        Ok(response)
     }
 
     /// Create a blog post
     pub async fn create_post(&self, post: BlogPost) -> std::result::Result<BlogPost, ApiErrorKind> {
-        let mut state = self.app_state.write().await;
-        let id = post.id.clone();
-        state.posts.insert(id.clone(), post.clone());
+        self.storage.posts.put(&post).await?;
         Ok(post)
     }
 
     /// Update a blog post
     pub async fn update_post(&self, id: &str, post: BlogPost) -> std::result::Result<BlogPost, ApiErrorKind> {
-        let mut state = self.app_state.write().await;
-        if !state.posts.contains_key(id) {
+        if self.storage.posts.get(id).await?.is_none() {
             return Err(ApiErrorKind::NotFound("Blog post not found".to_string()));
         }
-        state.posts.insert(id.to_string(), post.clone());
+        self.storage.posts.put(&post).await?;
         Ok(post)
     }
 
     /// Delete a blog post
     pub async fn delete_post(&self, id: &str) -> std::result::Result<(), ApiErrorKind> {
-        let mut state = self.app_state.write().await;
-        state.posts.remove(id)
-            .map(|_| ())
-            .ok_or_else(|| ApiErrorKind::NotFound("Blog post not found".to_string()))
+        if self.storage.posts.delete(id).await? {
+            Ok(())
+        } else {
+            Err(ApiErrorKind::NotFound("Blog post not found".to_string()))
+        }
+    }
+
+    /// List photos visible to `principal` (with gallery `unlocked`
+    /// opened): those in no gallery, or in one they may see in full
+    pub async fn list_photos(
+        &self,
+        principal: Option<&Principal>,
+        unlocked: Option<&str>,
+        query: &ListQuery,
+    ) -> std::result::Result<PaginatedResponse<Photo>, ApiErrorKind> {
+        let filter = query.storage_filter(&read_scope(principal));
+        let hidden = galleries::hidden_photos(self, principal, unlocked, &filter).await?;
+        query.fetch(self.storage.photos.as_ref(), filter.except(hidden)).await
+    }
+
+    /// (Re)make a photo's thumbnail and responsive sizes, pointing
+    /// `url_thumb` at the new thumbnail. Does nothing without an image
+    /// processor or for photos of unknown size.
+    pub async fn derive_photo_images(&self, photo: &mut Photo) -> std::result::Result<(), ApiErrorKind> {
+        let Some(processor) = &self.images else {
+            return Ok(());
+        };
+        let format = photo.content_type.as_deref().and_then(ImageFormat::from_mime);
+        let original = images::Original { url: &photo.url_full, key: photo.storage_key.as_deref() };
+
+        let mut derivatives = Vec::new();
+        for spec in image_plan(&photo.dimensions, format) {
+            derivatives.push(spec.at(processor.derive(original, &spec).await?));
+        }
+        if let Some(thumbnail) = derivatives.iter().find(|d| d.kind == DerivativeKind::Thumbnail) {
+            photo.url_thumb = thumbnail.url.clone();
+        }
+        photo.derivatives = derivatives;
+        Ok(())
+    }
+
+    /// Hash the passwords migrations left in plain text, returning how many
+    /// there were. Run at startup: until then they unlock nothing.
+    pub async fn hash_legacy_passwords(&self) -> std::result::Result<usize, ApiErrorKind> {
+        Ok(self.storage.gallery_passwords.hash_legacy().await? + self.storage.video_passwords.hash_legacy().await?)
+    }
+
+    /// Fill in a video's missing thumbnail and duration from its provider's
+    /// oEmbed. Does nothing without an oEmbed fetcher or for direct videos.
+    /// The video plays without them, so a provider that fails (a private or
+    /// removed video, an outage, a timeout) only gets logged.
+    pub async fn fill_video_metadata(&self, video: &mut Video) {
+        let Some(fetcher) = &self.oembed else {
+            return;
+        };
+        if video.thumbnail_url.is_some() && video.duration_seconds.is_some() {
+            return;
+        }
+        match fetcher.fetch(&video.source).await {
+            Ok(Some(oembed)) => {
+                video.thumbnail_url = video.thumbnail_url.take().or(oembed.thumbnail_url.clone());
+                video.duration_seconds = video.duration_seconds.or(oembed.duration_seconds());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("could not look up metadata of video {}: {}", video.id, e),
+        }
     }
 
-    /// Get photos with pagination
-    pub async fn list_photos(&self, page: u32, per_page: u32) -> Vec<Photo> {
-        let state = self.app_state.read().await;
-        let photos: Vec<Photo> = state.photos.values().cloned().collect();
-        let start = (page * per_page) as usize;
-        let end = ((page + 1) * per_page) as usize;
-        photos.into_iter().skip(start).take(end - start).collect()
+    /// List videos visible to `principal` (with video `unlocked` opened),
+    /// by the rule galleries follow. Password-protected videos are only
+    /// listed once unlocked.
+    pub async fn list_videos(
+        &self,
+        principal: Option<&Principal>,
+        unlocked: Option<&str>,
+        query: &ListQuery,
+    ) -> std::result::Result<PaginatedResponse<Video>, ApiErrorKind> {
+        let protected = unlocked.map(|id| Filter::all().field("id", id).field("visibility", "password"));
+        let filter = query.storage_filter(&read_scope(principal));
+        let filter = galleries::visible(self, principal, Permission::EditVideo, protected, filter).await?;
+        query.fetch(self.storage.videos.as_ref(), filter).await
+    }
+
+    /// Find a user by (normalized) email address
+    pub async fn find_user_by_email(&self, email: &str) -> StorageResult<Option<User>> {
+        self.storage.users.find(&Filter::all().field("email", email)).await
+    }
+
+    /// Definitions of a user's custom roles
+    pub async fn user_role_definitions(&self, user: &User) -> StorageResult<Vec<RoleDefinition>> {
+        let mut definitions = Vec::new();
+        for role in &user.roles {
+            if let Role::Custom { role_id, .. } = role {
+                if let Some(definition) = self.storage.role_definitions.get(role_id).await? {
+                    definitions.push(definition);
+                }
+            }
+        }
+        Ok(definitions)
+    }
+}
+
+/// Handler error: wraps an `ApiErrorKind` so handlers can use `?`
+#[derive(Debug)]
+pub struct HandlerError(pub ApiErrorKind);
+
+impl From<ApiErrorKind> for HandlerError {
+    fn from(kind: ApiErrorKind) -> Self {
+        Self(kind)
+    }
+}
+
+impl From<StorageError> for HandlerError {
+    fn from(error: StorageError) -> Self {
+        Self(error.into())
+    }
+}
+
+impl From<objects::ObjectStoreError> for HandlerError {
+    fn from(error: objects::ObjectStoreError) -> Self {
+        Self(error.into())
     }
+}
 
-    /// Get all videos
-    pub async fn list_videos(&self) -> Vec<Video> {
-        let state = self.app_state.read().await;
-        state.videos.values().cloned().collect()
+impl IntoResponse for HandlerError {
+    fn into_response(self) -> Response {
+        error_response(self.0)
     }
 }
 
-/// Extract claims from JWT token in Authorization header
-fn extract_claims(req: &Request, jwt_secret: &str) -> worker::Result<Claims> {
-    // Extract Authorization header
-    let auth_header = req.headers().get("Authorization")
-        .map_err(|e| worker::Error::from(format!("Invalid Authorization header: {}", e)))?
-        .ok_or_else(|| worker::Error::from("Missing Authorization header"))?;
+/// Result type returned by every route handler
+pub type HandlerResult = std::result::Result<Response, HandlerError>;
 
-    let auth_str = auth_header.to_string();
+/// Authenticate a bearer token
+///
+/// Tokens whose session was revoked (logout, reuse detection, admin action)
+/// are rejected even before they expire.
+async fn authenticate(token: &str, state: &ApiState) -> std::result::Result<Principal, ApiErrorKind> {
+    let claims = validate_jwt_token(token, &state.keys)?;
 
-    if !auth_str.starts_with("Bearer ") {
-        return Err(worker::Error::from("Invalid Authorization header format"));
+    let now = Utc::now().timestamp();
+    if !state.storage.sessions.is_active(&claims.sid, &claims.jti, now).await? {
+        return Err(ApiErrorKind::Unauthorized);
     }
 
-    let token = &auth_str[7..]; // Skip "Bearer "
+    // Roles and status come from the live record, so changes apply immediately
+    match state.storage.users.get(&claims.sub).await? {
+        Some(user) if user.status == UserStatus::Active => {
+            let definitions = state.user_role_definitions(&user).await?;
+            Ok(Principal::new(user, definitions, claims.sid, claims.jti, claims.exp))
+        }
+        _ => Err(ApiErrorKind::Unauthorized),
+    }
+}
 
-    // Validate JWT token and return claims
-    validate_jwt_token(token, jwt_secret)
+/// Require a permission, returning 403 if the principal's roles don't grant it
+fn check_permission(principal: &Principal, permission: Permission) -> std::result::Result<(), ApiErrorKind> {
+    if principal.has_permission(permission) {
+        Ok(())
+    } else {
+        Err(ApiErrorKind::Forbidden)
+    }
 }
 
-/// Extract user ID from JWT token
-fn extract_user_id(req: &Request, jwt_secret: &str) -> worker::Result<String> {
-    let claims = extract_claims(req, jwt_secret)?;
-    Ok(extract_user_id_from_claims(&claims))
+/// Require a permission on the site a resource belongs to
+fn check_site_permission(
+    principal: &Principal,
+    permission: Permission,
+    site_id: &str,
+) -> std::result::Result<(), ApiErrorKind> {
+    if principal.has_site_permission(permission, site_id) {
+        Ok(())
+    } else {
+        Err(ApiErrorKind::Forbidden)
+    }
 }
 
-/// Check if user has required permission based on JWT claims
-fn check_permission_from_claims(claims: &Claims, permission: &str) -> worker::Result<()> {
-    // Check if user has Admin role (has all permissions)
-    if claims.roles.contains(&"Admin".to_string()) {
+/// Require a permission on a single resource, either through the caller's
+/// roles on the resource's site or through an access entry on the resource
+async fn authorize_resource(
+    state: &ApiState,
+    principal: &Principal,
+    permission: Permission,
+    resource_type: &str,
+    resource: (&str, &str),
+) -> std::result::Result<(), ApiErrorKind> {
+    let (resource_id, site_id) = resource;
+    if principal.has_site_permission(permission, site_id) {
         return Ok(());
     }
 
-    // Check specific permissions based on roles
-    match permission {
-        "create_shows" | "update_shows" | "delete_shows" => {
-            if claims.roles.contains(&"Content".to_string()) {
-                return Ok(());
-            }
-        }
-        "create_songs" => {
-            if claims.roles.contains(&"Content".to_string()) {
-                return Ok(());
-            }
-        }
-        "create_posts" | "update_posts" | "delete_posts" => {
-            if claims.roles.contains(&"Content".to_string()) {
-                return Ok(());
-            }
-        }
-        "create_photos" | "create_videos" => {
-            if claims.roles.contains(&"Media".to_string()) || claims.roles.contains(&"Content".to_string()) {
-                return Ok(());
-            }
-        }
-        _ => {}
+    let access = state.storage.access_entries
+        .list(&Filter::user(principal.user_id())
+            .field("resourceType", resource_type)
+            .field("resourceId", resource_id))
+        .await?;
+    if can_access_resource(&principal.user, resource_type, resource_id, permission, &principal.role_definitions, &access) {
+        Ok(())
+    } else {
+        Err(ApiErrorKind::Forbidden)
+    }
+}
+
+/// Sites whose content a request may read; anonymous readers see public content
+fn read_scope(principal: Option<&Principal>) -> SiteScope {
+    principal.map(|p| p.site_scope.clone()).unwrap_or(SiteScope::All)
+}
+
+/// Helper: Load a record by ID, or 404 with `missing` as the message
+async fn load<T: storage::Entity>(
+    repo: &Arc<dyn storage::Repository<T>>,
+    id: &str,
+    missing: &str,
+) -> std::result::Result<T, ApiErrorKind> {
+    repo.get(id).await?.ok_or_else(|| ApiErrorKind::NotFound(missing.to_string()))
+}
+
+/// Helper: Load a record the caller may read; records on sites outside the
+/// caller's read scope, or on another site than the request's, are reported
+/// as missing
+async fn load_readable<T: storage::Entity>(
+    repo: &Arc<dyn storage::Repository<T>>,
+    id: &str,
+    principal: Option<&Principal>,
+    site: Option<&SiteContext>,
+    missing: &str,
+) -> std::result::Result<T, ApiErrorKind> {
+    let record = load(repo, id, missing).await?;
+    match record.site_id() {
+        Some(site_id) if !read_scope(principal).contains(site_id) => Err(ApiErrorKind::NotFound(missing.to_string())),
+        Some(site_id) if site.is_some_and(|s| s.id() != site_id) => Err(ApiErrorKind::NotFound(missing.to_string())),
+        _ => Ok(record),
     }
+}
 
-    Err(worker::Error::from("Forbidden: Insufficient permissions"))
+/// Helper: Parse list query parameters, pinned to the request's site if it has one
+fn list_query(
+    params: &HashMap<String, String>,
+    site: Option<&SiteContext>,
+) -> std::result::Result<ListQuery, ApiErrorKind> {
+    let mut query = ListQuery::from_params(params)?;
+    if let Some(site) = site {
+        query.site_id = Some(site.id().to_string());
+    }
+    Ok(query)
 }
 
-/// Helper: Convert ApiErrorKind to Worker Response
-fn error_response(error: ApiErrorKind) -> worker::Result<Response> {
+/// Helper: Convert ApiErrorKind to an `ApiError` JSON response
+fn error_response(error: ApiErrorKind) -> Response {
     let status = match &error {
-        ApiErrorKind::NotFound(_) => 404,
-        ApiErrorKind::Unauthorized => 401,
-        ApiErrorKind::Forbidden => 403,
-        ApiErrorKind::ValidationError(_) => 400,
-        ApiErrorKind::Internal(_) => 500,
+        ApiErrorKind::NotFound(_) => StatusCode::NOT_FOUND,
+        ApiErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+        ApiErrorKind::Forbidden => StatusCode::FORBIDDEN,
+        ApiErrorKind::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+        ApiErrorKind::ValidationError(_) => StatusCode::BAD_REQUEST,
+        ApiErrorKind::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
     };
 
-    Response::error(format!("{}", error), status)
+    (status, axum::Json(ApiError::from(error))).into_response()
 }
 
-/// Helper: Parse ID from path
-fn extract_id(req: &Request) -> worker::Result<String> {
-    let url = req.url()?;
-    let path_segments: Vec<&str> = url.path_segments().map(|s| s.collect()).unwrap_or_default();
-    path_segments
-        .last()
-        .map(|s| s.to_string())
-        .ok_or_else(|| worker::Error::from("Missing ID in path"))
+/// Helper: Serialize a value as a 200 JSON response
+fn json_response<T: Serialize>(value: &T) -> Response {
+    axum::Json(value).into_response()
 }
 
-/// Helper: Parse query parameter with default
-fn parse_query_param<T: std::str::FromStr>(
-    req: &Request,
-    param: &str,
-    default: T,
-) -> T {
-    let url = match req.url() {
-        Ok(u) => u,
-        Err(_) => return default,
-    };
+/// Helper: Parse a JSON request body into a DTO
+fn parse_json<T: DeserializeOwned>(body: &[u8]) -> std::result::Result<T, ApiErrorKind> {
+    serde_json::from_slice(body)
+        .map_err(|e| ApiErrorKind::ValidationError(format!("Invalid request: {}", e)))
+}
 
-    url.query_pairs()
-        .find(|(k, _)| k == param)
-        .and_then(|(_, v)| v.parse().ok())
-        .unwrap_or(default)
+/// Helper: Normalize an email address for storage and lookup
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
 }
 
 /// Login request
@@ -306,11 +579,21 @@ struct LoginRequest {
 
 /// Login response
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct LoginResponse {
     token: String,
+    refresh_token: String,
+    expires_at: i64,
     user: UserInfo,
 }
 
+/// Refresh token exchange request
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
 /// User info returned in login response
 #[derive(Debug, Deserialize, Serialize)]
 struct UserInfo {
@@ -327,29 +610,99 @@ pub mod auth {
     use super::*;
 
     /// POST /api/auth/login - User login
-    pub async fn login(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let body = req.json().await?;
-        let login_req: LoginRequest = serde_json::from_value(body)
-            .map_err(|e| worker::Error::from(format!("Invalid request: {}", e)))?;
+    pub async fn login(State(state): State<ApiState>, body: Bytes) -> HandlerResult {
+        let login_req: LoginRequest = parse_json(&body)?;
 
         // Validate request (basic validation)
         if login_req.email.is_empty() || login_req.password.is_empty() {
-            return error_response(ApiErrorKind::ValidationError(
+            return Ok(error_response(ApiErrorKind::ValidationError(
                 "Email and password are required".to_string()
-            ));
+            )));
+        }
+
+        // Look up user by email
+        let email = normalize_email(&login_req.email);
+        let user = state.find_user_by_email(&email).await?;
+
+        // Always run a hash verification, so unknown emails and wrong
+        // passwords fail after the same amount of work
+        let verified = state.storage.credentials
+            .verify(user.as_ref().map(|u| u.id.as_str()), &login_req.password)
+            .await?;
+        let mut user = match user {
+            Some(user) if verified => user,
+            _ => return Err(ApiErrorKind::Unauthorized.into()),
+        };
+
+        // Only active accounts may sign in
+        if user.status != UserStatus::Active {
+            return Err(ApiErrorKind::Forbidden.into());
         }
 
-        // Look up user by email (in production, would verify password hash)
-        let state = ctx.data.app_state.read().await;
-        let user = state.users.values()
-            .find(|u| u.email == login_req.email)
-            .ok_or_else(|| worker::Error::from("Unauthorized: User not found"))?;
+        let now = Utc::now().timestamp();
+        user.last_login = Some(now);
+        state.storage.users.put(&user).await?;
+
+        let (session, refresh_token) = state.storage.sessions.create(&user.id, now).await?;
+        Ok(json_response(&token_response(&user, &session, refresh_token, &state)?))
+    }
+
+    /// POST /api/auth/refresh - Exchange a refresh token for a new token pair
+    pub async fn refresh(State(state): State<ApiState>, body: Bytes) -> HandlerResult {
+        let refresh_req: RefreshRequest = parse_json(&body)?;
+        let now = Utc::now().timestamp();
+
+        let rotated = state.storage.sessions.rotate(&refresh_req.refresh_token, now).await?;
+        let (session, refresh_token) = match rotated {
+            Ok(pair) => pair,
+            Err(RefreshError::Reused) => {
+                tracing::warn!("refresh token reuse detected; session revoked");
+                return Err(ApiErrorKind::Unauthorized.into());
+            }
+            Err(_) => return Err(ApiErrorKind::Unauthorized.into()),
+        };
+
+        // The account may have been suspended since the session started
+        let user = match state.storage.users.get(&session.user_id).await? {
+            Some(user) if user.status == UserStatus::Active => user,
+            _ => {
+                state.storage.sessions.revoke_session(&session.id).await?;
+                return Err(ApiErrorKind::Unauthorized.into());
+            }
+        };
+
+        Ok(json_response(&token_response(&user, &session, refresh_token, &state)?))
+    }
+
+    /// POST /api/auth/logout - Revoke the caller's session and access token
+    pub async fn logout(State(state): State<ApiState>, principal: Principal) -> HandlerResult {
+        let sessions = &state.storage.sessions;
+        sessions.revoke_session(&principal.session_id).await?;
+        sessions.revoke_access_token(&principal.token_id, principal.expires_at).await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// DELETE /api/users/:id/sessions - Sign a user out everywhere
+    pub async fn revoke_user_sessions(
+        State(state): State<ApiState>,
+        Path(user_id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::EditUser)?;
 
-        // TODO: Verify password hash (using bcrypt or similar)
-        // For now, accept any password in development mode
+        state.storage.sessions.revoke_user(&user_id, None).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
 
-        // Generate JWT token
-        let token = generate_jwt_token(user, &ctx.data.jwt_secret)?;
+    /// Build the token pair response for a session
+    fn token_response(
+        user: &User,
+        session: &Session,
+        refresh_token: String,
+        state: &ApiState,
+    ) -> std::result::Result<LoginResponse, ApiErrorKind> {
+        let (token, expires_at) = generate_jwt_token(user, &session.id, &state.keys)?;
 
         // Prepare user info response
         let user_info = UserInfo {
@@ -358,12 +711,103 @@ pub mod auth {
             roles: user.roles.iter().map(|r| format!("{:?}", r)).collect(),
         };
 
-        let response = LoginResponse {
+        Ok(LoginResponse {
             token,
+            refresh_token,
+            expires_at,
             user: user_info,
+        })
+    }
+
+    /// POST /api/auth/register - Create an account
+    ///
+    /// Accounts start out `Pending` with no roles until an admin activates
+    /// them. The first admin registers with the deploy-time bootstrap token,
+    /// which stops working once an active admin exists.
+    pub async fn register(State(state): State<ApiState>, body: Bytes) -> HandlerResult {
+        let register_req: RegisterRequest = parse_json(&body)?;
+
+        if let Err(errors) = register_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let is_admin = match register_req.bootstrap_token.as_deref() {
+            Some(token) => {
+                check_bootstrap(&state, token).await?;
+                true
+            }
+            None => false,
         };
+        let hash = credentials::hash_password(&register_req.password)?;
+        let email = normalize_email(&register_req.email);
+
+        if state.find_user_by_email(&email).await?.is_some() {
+            return Err(ApiErrorKind::ValidationError("Email is already registered".to_string()).into());
+        }
+
+        let user = User {
+            id: uuid::Uuid::new_v4().to_string(),
+            email,
+            name: register_req.name,
+            roles: if is_admin { vec![Role::Admin] } else { vec![] },
+            status: if is_admin { UserStatus::Active } else { UserStatus::Pending },
+            created_at: Utc::now().timestamp(),
+            last_login: None,
+        };
+
+        // The unique email index settles concurrent sign-ups the check above
+        // let through
+        if !state.storage.users.insert(&user).await? {
+            return Err(ApiErrorKind::ValidationError("Email is already registered".to_string()).into());
+        }
+        state.storage.credentials.set_hash(&user.id, hash).await?;
+
+        Ok(json_response(&user))
+    }
 
-        Response::from_json(&response)
+    /// Helper: Require the bootstrap token, while there is no active admin
+    async fn check_bootstrap(state: &ApiState, token: &str) -> std::result::Result<(), ApiErrorKind> {
+        let valid = state
+            .bootstrap_token
+            .as_deref()
+            .is_some_and(|expected| credentials::constant_time_eq(expected.as_bytes(), token.as_bytes()));
+        if !valid {
+            return Err(ApiErrorKind::Forbidden);
+        }
+        let admins = Filter::all().field("status", json!(UserStatus::Active)).contains("roles", json!(Role::Admin));
+        if state.storage.users.find(&admins).await?.is_some() {
+            return Err(ApiErrorKind::Forbidden);
+        }
+        Ok(())
+    }
+
+    /// POST /api/auth/change-password - Change the caller's password
+    pub async fn change_password(
+        State(state): State<ApiState>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let change_req: ChangePasswordRequest = parse_json(&body)?;
+
+        if let Err(errors) = change_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let user_id = principal.user_id().to_string();
+        let verified = state.storage.credentials
+            .verify(Some(&user_id), &change_req.current_password)
+            .await?;
+        if !verified {
+            return Err(ApiErrorKind::Unauthorized.into());
+        }
+
+        let hash = credentials::hash_password(&change_req.new_password)?;
+        state.storage.credentials.set_hash(&user_id, hash).await?;
+
+        // Sign out every other device
+        state.storage.sessions.revoke_user(&user_id, Some(&principal.session_id)).await?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
     }
 }
 
@@ -372,8 +816,8 @@ pub mod auth {
 // ============================================================================
 
 /// Root API handler
-pub fn root_handler() -> worker::Result<Response> {
-    Response::from_json(&json!({
+pub async fn root_handler() -> Response {
+    json_response(&json!({
         "version": env!("CARGO_PKG_VERSION"),
         "status": "ok",
         "message": "Web Nexus CMS API",
@@ -383,14 +827,21 @@ pub fn root_handler() -> worker::Result<Response> {
             "songs": "/api/songs",
             "posts": "/api/posts",
             "photos": "/api/photos",
-            "videos": "/api/videos"
+            "videos": "/api/videos",
+            "uploads": "/api/uploads",
+            "galleries": "/api/galleries",
+            "setlists": "/api/setlists",
+            "members": "/api/members",
+            "site": "/api/site",
+            "sites": "/api/sites",
+            "contact": "/api/contact"
         }
     }))
 }
 
 /// Health check endpoint
-pub fn health_handler() -> worker::Result<Response> {
-    Response::from_json(&json!({
+pub async fn health_handler() -> Response {
+    json_response(&json!({
         "status": "healthy",
         "timestamp": chrono::Utc::now().to_rfc3339()
     }))
@@ -403,59 +854,53 @@ pub fn health_handler() -> worker::Result<Response> {
 pub mod shows {
     use super::*;
 
-    /// GET /api/shows - List all shows with pagination
-    pub async fn list(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let page: u32 = parse_query_param(&req, "page", 0u32);
-        let per_page: u32 = parse_query_param(&req, "per_page", 20u32);
-
-        let shows = ctx.data.list_shows(page, per_page).await;
-        let total = ctx.data.app_state.read().await.shows.len() as i64;
-        let total_pages = ((total as f64) / (per_page as f64)).ceil() as i32;
-
-        let response = PaginatedResponse {
-            data: shows,
-            page: page as i32,
-            per_page: per_page as i32,
-            total,
-            total_pages,
-            has_next: (page as i32 + 1) < total_pages,
-            has_prev: page > 0,
-        };
-
-        Response::from_json(&response)
+    /// GET /api/shows - List shows (see `query` for sorting, filters and paging)
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let response = state.list_shows(&read_scope(principal.as_ref()), &query).await?;
+        Ok(json_response(&response))
     }
 
     /// GET /api/shows/:id - Get a specific show
-    pub async fn get(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let id = extract_id(&req)?;
-
-        match ctx.data.get_show(&id).await {
-            Some(show) => Response::from_json(&show),
-            None => error_response(ApiErrorKind::NotFound("Show not found".to_string())),
-        }
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let show = load_readable(&state.storage.shows, &id, principal.as_ref(), site.as_ref(), "Show not found").await?;
+        Ok(json_response(&show))
     }
 
     /// POST /api/shows - Create a new show
-    pub async fn create(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let claims = extract_claims(&req, &ctx.data.jwt_secret)?;
-        check_permission_from_claims(&claims, "create_shows")?;
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::CreateShow)?;
 
-        let body = req.json().await?;
-        let create_req: CreateShowRequest = serde_json::from_value(body)
-            .map_err(|e| worker::Error::from(format!("Invalid request: {}", e)))?;
+        let create_req: CreateShowRequest = parse_json(&body)?;
 
         // Validate using garde
         if let Err(errors) = create_req.validate() {
-            return error_response(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)));
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
         }
+        check_site_permission(&principal, Permission::CreateShow, site.id())?;
 
         let id = uuid::Uuid::new_v4().to_string();
         let now = chrono::Utc::now().timestamp();
-        let user_id = extract_user_id_from_claims(&claims);
+        let user_id = principal.user_id().to_string();
 
         let show = Show {
             id: id.clone(),
-            site_id: create_req.site_id,
+            site_id: site.id().to_string(),
             title: format!("Show at {}", create_req.venue), // Default title
             venue: create_req.venue,
             address: create_req.city,
@@ -469,27 +914,37 @@ pub mod shows {
             updated_at: now,
         };
 
-        match ctx.data.create_show(show.clone()).await {
-            Ok(_) => Response::from_json(&show),
-            Err(e) => error_response(e),
+        // Check the setlist before anything is written
+        let setlist = match &create_req.setlist_id {
+            Some(setlist_id) => Some(setlists::load_for_site(&state, setlist_id, site.id()).await?),
+            None => None,
+        };
+
+        state.create_show(show.clone()).await?;
+        revisions::record(&state, &show, Some(principal.user_id()), Some("Created")).await?;
+        if let Some(setlist) = setlist {
+            setlists::link_show(&state, setlist, &show.id).await?;
         }
+        Ok(json_response(&show))
     }
 
     /// PUT /api/shows/:id - Update a show
-    pub async fn update(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let claims = extract_claims(&req, &ctx.data.jwt_secret)?;
-        check_permission_from_claims(&claims, "update_shows")?;
-
-        let id = extract_id(&req)?;
-        let body = req.json().await?;
-        let update_req: UpdateShowRequest = serde_json::from_value(body)
-            .map_err(|e| worker::Error::from(format!("Invalid request: {}", e)))?;
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateShowRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
 
         // Get existing show
-        let mut existing = match ctx.data.get_show(&id).await {
-            Some(show) => show,
-            None => return error_response(ApiErrorKind::NotFound("Show not found".to_string())),
-        };
+        let mut existing = state.get_show(&id).await?
+            .ok_or_else(|| ApiErrorKind::NotFound("Show not found".to_string()))?;
+        authorize_resource(&state, &principal, Permission::EditShow, "show", (&id, &existing.site_id)).await?;
 
         // Update fields
         if let Some(date) = update_req.date {
@@ -501,29 +956,80 @@ pub mod shows {
         if let Some(city) = update_req.city {
             existing.address = Some(city);
         }
-        if let Some(setlist_id) = update_req.setlist_id {
-            // Store setlist_id in description for now (contracts need update)
-            existing.description = Some(setlist_id);
-        }
+        let setlist = match &update_req.setlist_id {
+            Some(setlist_id) => Some(setlists::load_for_site(&state, setlist_id, &existing.site_id).await?),
+            None => None,
+        };
         existing.updated_at = chrono::Utc::now().timestamp();
 
-        match ctx.data.update_show(&id, existing.clone()).await {
-            Ok(_) => Response::from_json(&existing),
-            Err(e) => error_response(e),
+        state.update_show(&id, existing.clone()).await?;
+        revisions::record(&state, &existing, Some(principal.user_id()), None).await?;
+        if let Some(setlist) = setlist.filter(|s| s.show_id.as_deref() != Some(id.as_str())) {
+            setlists::link_show(&state, setlist, &id).await?;
         }
+        Ok(json_response(&existing))
     }
 
     /// DELETE /api/shows/:id - Delete a show
-    pub async fn delete(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let claims = extract_claims(&req, &ctx.data.jwt_secret)?;
-        check_permission_from_claims(&claims, "delete_shows")?;
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let existing = state.get_show(&id).await?
+            .ok_or_else(|| ApiErrorKind::NotFound("Show not found".to_string()))?;
+        authorize_resource(&state, &principal, Permission::DeleteShow, "show", (&id, &existing.site_id)).await?;
+
+        // The sets outlive the show; they just stop being linked to it
+        for mut setlist in setlists::show_sets(&state, &id).await? {
+            setlist.show_id = None;
+            setlist.position = 0;
+            state.storage.setlists.put(&setlist).await?;
+        }
+        state.delete_show(&id).await?;
+        revisions::forget::<Show>(&state, &id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// GET /api/shows/:id/setlist - Every set of a show with songs, running
+    /// time and key-change warnings
+    pub async fn setlist(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let show = load_readable(&state.storage.shows, &id, principal.as_ref(), site.as_ref(), "Show not found").await?;
+        Ok(json_response(&setlists::show_setlist(&state, &show.id, principal.as_ref()).await?))
+    }
 
-        let id = extract_id(&req)?;
+    /// PUT /api/shows/:id/sets - Put a show's sets in a new order
+    pub async fn reorder_sets(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let reorder_req: ReorderSetsRequest = parse_json(&body)?;
+
+        let existing = state.get_show(&id).await?
+            .ok_or_else(|| ApiErrorKind::NotFound("Show not found".to_string()))?;
+        authorize_resource(&state, &principal, Permission::EditShow, "show", (&id, &existing.site_id)).await?;
+
+        let sets = setlists::show_sets(&state, &id).await?;
+        let current: Vec<String> = sets.iter().map(|s| s.id.clone()).collect();
+        if !setlists::is_permutation(&current, &reorder_req.setlist_ids) {
+            return Err(ApiErrorKind::ValidationError("setlistIds must list each of the show's sets exactly once".to_string()).into());
+        }
 
-        match ctx.data.delete_show(&id).await {
-            Ok(_) => Response::empty().map(|r| r.with_status(204)),
-            Err(e) => error_response(e),
+        for mut setlist in sets {
+            let position = reorder_req.setlist_ids.iter().position(|s| *s == setlist.id).unwrap_or_default();
+            if setlist.position != position as i32 {
+                setlist.position = position as i32;
+                state.storage.setlists.put(&setlist).await?;
+            }
         }
+        Ok(json_response(&setlists::show_setlist(&state, &id, Some(&principal)).await?))
     }
 }
 
@@ -534,31 +1040,40 @@ pub mod shows {
 pub mod songs {
     use super::*;
 
-    /// GET /api/songs - List all songs
-    pub async fn list(_req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let songs = ctx.data.list_songs().await;
-        Response::from_json(&songs)
+    /// GET /api/songs - List songs (see `query`)
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let response = state.list_songs(&read_scope(principal.as_ref()), &query).await?;
+        Ok(json_response(&response))
     }
 
     /// POST /api/songs - Create a new song
-    pub async fn create(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let claims = extract_claims(&req, &ctx.data.jwt_secret)?;
-        check_permission_from_claims(&claims, "create_songs")?;
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::CreateSong)?;
 
-        let body = req.json().await?;
-        let create_req: CreateSongRequest = serde_json::from_value(body)
-            .map_err(|e| worker::Error::from(format!("Invalid request: {}", e)))?;
+        let create_req: CreateSongRequest = parse_json(&body)?;
 
         if let Err(errors) = create_req.validate() {
-            return error_response(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)));
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
         }
+        check_site_permission(&principal, Permission::CreateSong, site.id())?;
 
         let id = uuid::Uuid::new_v4().to_string();
         let now = chrono::Utc::now().timestamp();
 
         let song = Song {
             id: id.clone(),
-            site_id: "default".to_string(), // TODO: Get from request
+            site_id: site.id().to_string(),
             title: create_req.title,
             artist: create_req.artist,
             genres: vec![],
@@ -569,10 +1084,74 @@ pub mod songs {
             created_at: now,
         };
 
-        match ctx.data.create_song(song.clone()).await {
-            Ok(_) => Response::from_json(&song),
-            Err(e) => error_response(e),
+        state.create_song(song.clone()).await?;
+        Ok(json_response(&song))
+    }
+
+    /// GET /api/songs/:id - Get a specific song
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let song = load_readable(&state.storage.songs, &id, principal.as_ref(), site.as_ref(), "Song not found").await?;
+        Ok(json_response(&song))
+    }
+
+    /// PUT /api/songs/:id - Update a song
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateSongRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut song = load(&state.storage.songs, &id, "Song not found").await?;
+        authorize_resource(&state, &principal, Permission::EditSong, "song", (&id, &song.site_id)).await?;
+
+        if let Some(title) = update_req.title {
+            song.title = title;
+        }
+        if let Some(artist) = update_req.artist {
+            song.artist = Some(artist);
+        }
+        if let Some(genres) = update_req.genres {
+            song.genres = genres;
         }
+        if let Some(duration) = update_req.duration_seconds {
+            song.duration_seconds = Some(duration as i32);
+        }
+        if let Some(is_original) = update_req.is_original {
+            song.is_original = is_original;
+        }
+        if let Some(musical_key) = update_req.musical_key {
+            song.musical_key = Some(musical_key);
+        }
+        if let Some(notes) = update_req.notes {
+            song.notes = Some(notes);
+        }
+
+        state.storage.songs.put(&song).await?;
+        Ok(json_response(&song))
+    }
+
+    /// DELETE /api/songs/:id - Delete a song
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let song = load(&state.storage.songs, &id, "Song not found").await?;
+        authorize_resource(&state, &principal, Permission::DeleteSong, "song", (&id, &song.site_id)).await?;
+
+        state.storage.songs.delete(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
     }
 }
 
@@ -583,211 +1162,3046 @@ pub mod songs {
 pub mod posts {
     use super::*;
 
-    /// GET /api/posts - List blog posts with pagination
-    pub async fn list(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let page: u32 = parse_query_param(&req, "page", 0u32);
-        let per_page: u32 = parse_query_param(&req, "per_page", 20u32);
-
-        let posts = ctx.data.list_posts(page, per_page).await;
-        let total = ctx.data.app_state.read().await.posts.len() as i64;
-        let total_pages = ((total as f64) / (per_page as f64)).ceil() as i32;
-
-        let response = PaginatedResponse {
-            data: posts,
-            page: page as i32,
-            per_page: per_page as i32,
-            total,
-            total_pages,
-            has_next: (page as i32 + 1) < total_pages,
-            has_prev: page > 0,
-        };
-
-        Response::from_json(&response)
+    /// GET /api/posts - List blog posts (see `query`)
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let response = state.list_posts(principal.as_ref(), &query).await?;
+        Ok(json_response(&response))
     }
 
     /// POST /api/posts - Create a new blog post
-    pub async fn create(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let claims = extract_claims(&req, &ctx.data.jwt_secret)?;
-        check_permission_from_claims(&claims, "create_posts")?;
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::CreatePost)?;
 
-        let body = req.json().await?;
-        let create_req: CreateBlogPostRequest = serde_json::from_value(body)
-            .map_err(|e| worker::Error::from(format!("Invalid request: {}", e)))?;
+        let create_req: CreateBlogPostRequest = parse_json(&body)?;
 
         if let Err(errors) = create_req.validate() {
-            return error_response(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)));
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
         }
+        check_site_permission(&principal, Permission::CreatePost, site.id())?;
 
         let id = uuid::Uuid::new_v4().to_string();
         let now = chrono::Utc::now().timestamp();
-        let user_id = extract_user_id_from_claims(&claims);
+        let user_id = principal.user_id().to_string();
+
+        // Creating a post live (or scheduled) is a publish step in itself
+        let published = create_req.published.unwrap_or(false);
+        let (status, published_at) = match create_req.published_at {
+            Some(at) if published && at > now => (PostStatus::Scheduled, Some(at)),
+            at if published => (PostStatus::Published, Some(at.unwrap_or(now))),
+            _ => (PostStatus::Draft, None),
+        };
+        if status != PostStatus::Draft {
+            check_site_permission(&principal, Permission::PublishPost, site.id())?;
+        }
 
-        let post = BlogPost {
+        let mut post = BlogPost {
             id: id.clone(),
-            site_id: create_req.site_id,
+            site_id: site.id().to_string(),
             title: create_req.title,
             slug: create_req.slug,
             content: create_req.content,
+            content_format: create_req.content_format.unwrap_or_default(),
             excerpt: create_req.excerpt,
+            rendered: None,
             cover_image_id: create_req.featured_image,
             author_id: user_id,
-            status: if create_req.published.unwrap_or(false) {
-                PostStatus::Published
-            } else {
-                PostStatus::Draft
-            },
-            published_at: if create_req.published.unwrap_or(false) {
-                Some(create_req.published_at.unwrap_or(now))
-            } else {
-                None
-            },
+            status,
+            published_at,
             created_at: now,
             updated_at: now,
         };
+        post.render();
+
+        state.create_post(post.clone()).await?;
+        revisions::record(&state, &post, Some(principal.user_id()), Some("Created")).await?;
+        Ok(json_response(&post))
+    }
 
-        match ctx.data.create_post(post.clone()).await {
-            Ok(_) => Response::from_json(&post),
-            Err(e) => error_response(e),
+    /// GET /api/posts/:id - Get a specific blog post
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let mut post = load_readable(&state.storage.posts, &id, principal.as_ref(), site.as_ref(), "Blog post not found").await?;
+        if post.status != PostStatus::Published {
+            // Unpublished posts only exist for their editors
+            let principal = principal.as_ref().ok_or_else(|| ApiErrorKind::NotFound("Blog post not found".to_string()))?;
+            authorize_resource(&state, principal, Permission::EditPost, "post", (&id, &post.site_id))
+                .await
+                .map_err(|_| ApiErrorKind::NotFound("Blog post not found".to_string()))?;
         }
+        refresh_rendered(&mut post);
+        Ok(json_response(&post))
     }
-}
 
-// ============================================================================
-// Photos Handlers
-// ============================================================================
+    /// Render a post saved before rendering was cached (or by an older
+    /// renderer) for the response; `render_stale` saves the rendering later
+    pub(crate) fn refresh_rendered(post: &mut BlogPost) {
+        if post.needs_render() {
+            post.render();
+        }
+    }
 
-pub mod photos {
-    use super::*;
+    /// PUT /api/posts/:id - Update a blog post
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateBlogPostRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
 
-    /// GET /api/photos - List photos with pagination
-    pub async fn list(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let page: u32 = parse_query_param(&req, "page", 0u32);
-        let per_page: u32 = parse_query_param(&req, "per_page", 20u32);
+        let mut post = load(&state.storage.posts, &id, "Blog post not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPost, "post", (&id, &post.site_id)).await?;
 
-        let photos = ctx.data.list_photos(page, per_page).await;
-        let total = ctx.data.app_state.read().await.photos.len() as i64;
-        let total_pages = ((total as f64) / (per_page as f64)).ceil() as i32;
+        if let Some(title) = update_req.title {
+            post.title = title;
+        }
+        if let Some(slug) = update_req.slug {
+            post.slug = slug;
+        }
+        if let Some(content) = update_req.content {
+            post.content = content;
+        }
+        if let Some(content_format) = update_req.content_format {
+            post.content_format = content_format;
+        }
+        if let Some(excerpt) = update_req.excerpt {
+            post.excerpt = Some(excerpt);
+        }
+        if let Some(cover_image_id) = update_req.cover_image_id {
+            post.cover_image_id = Some(cover_image_id);
+        }
+        post.render();
+        post.updated_at = chrono::Utc::now().timestamp();
 
-        let response = PaginatedResponse {
-            data: photos,
-            page: page as i32,
-            per_page: per_page as i32,
-            total,
-            total_pages,
-            has_next: (page as i32 + 1) < total_pages,
-            has_prev: page > 0,
-        };
+        state.update_post(&id, post.clone()).await?;
+        revisions::record(&state, &post, Some(principal.user_id()), None).await?;
+        Ok(json_response(&post))
+    }
 
-        Response::from_json(&response)
+    /// DELETE /api/posts/:id - Delete a blog post
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let post = load(&state.storage.posts, &id, "Blog post not found").await?;
+        authorize_resource(&state, &principal, Permission::DeletePost, "post", (&id, &post.site_id)).await?;
+
+        state.delete_post(&id).await?;
+        revisions::forget::<BlogPost>(&state, &id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
     }
 
-    /// POST /api/photos - Create a new photo
-    pub async fn create(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let claims = extract_claims(&req, &ctx.data.jwt_secret)?;
-        check_permission_from_claims(&claims, "create_photos")?;
+    /// POST /api/posts/:id/publish - Publish a draft or scheduled post now
+    pub async fn publish(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let post = transition(&state, &principal, &id, PostTransition::Publish, None).await?;
+        Ok(json_response(&post))
+    }
 
-        let body = req.json().await?;
-        let create_req: CreatePhotoRequest = serde_json::from_value(body)
-            .map_err(|e| worker::Error::from(format!("Invalid request: {}", e)))?;
+    /// POST /api/posts/:id/unpublish - Take a post back to draft
+    pub async fn unpublish(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let post = transition(&state, &principal, &id, PostTransition::Unpublish, None).await?;
+        Ok(json_response(&post))
+    }
 
-        if let Err(errors) = create_req.validate() {
-            return error_response(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)));
+    /// POST /api/posts/:id/schedule - Publish a post at a later time
+    pub async fn schedule(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let schedule_req: SchedulePostRequest = parse_json(&body)?;
+
+        if schedule_req.published_at <= chrono::Utc::now().timestamp() {
+            return Err(ApiErrorKind::ValidationError("publishedAt must be in the future".to_string()).into());
         }
+        let post = transition(&state, &principal, &id, PostTransition::Schedule, Some(schedule_req.published_at)).await?;
+        Ok(json_response(&post))
+    }
 
-        let id = uuid::Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp();
-        let user_id = extract_user_id_from_claims(&claims);
+    /// POST /api/posts/:id/archive - Archive a post
+    pub async fn archive(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let post = transition(&state, &principal, &id, PostTransition::Archive, None).await?;
+        Ok(json_response(&post))
+    }
 
-        // Extract filename from URL
-        let filename = create_req.url.split('/').last().unwrap_or("photo.jpg").to_string();
+    /// Helper: Move a post through one workflow step (needs `PublishPost`)
+    async fn transition(
+        state: &ApiState,
+        principal: &Principal,
+        id: &str,
+        transition: PostTransition,
+        publish_at: Option<i64>,
+    ) -> std::result::Result<BlogPost, ApiErrorKind> {
+        let mut post = load(&state.storage.posts, id, "Blog post not found").await?;
+        authorize_resource(state, principal, Permission::PublishPost, "post", (id, &post.site_id)).await?;
+
+        let status = post.status.after(transition).ok_or_else(|| {
+            let name = |value: String| value.to_lowercase();
+            ApiErrorKind::ValidationError(format!(
+                "Cannot {} a post that is {}",
+                name(format!("{:?}", transition)),
+                name(format!("{:?}", post.status))
+            ))
+        })?;
 
-        let photo = Photo {
-            id: id.clone(),
-            site_id: create_req.site_id,
-            filename,
-            url_full: create_req.url.clone(),
-            url_thumb: create_req.thumbnail_url.unwrap_or_else(|| create_req.url.clone()),
-            size_bytes: 0,
-            dimensions: ImageDimensions { width: 0, height: 0 },
-            alt_text: Some(create_req.title),
-            caption: create_req.caption,
-            tags: vec![],
-            uploaded_at: now,
-            uploaded_by: user_id,
+        let now = chrono::Utc::now().timestamp();
+        post.published_at = match transition {
+            PostTransition::Publish => Some(now),
+            PostTransition::Schedule => publish_at,
+            PostTransition::Unpublish => None,
+            PostTransition::Archive => post.published_at,
         };
+        post.status = status;
+        post.updated_at = now;
+
+        state.update_post(id, post.clone()).await?;
+        let note = match transition {
+            PostTransition::Publish => "Published",
+            PostTransition::Unpublish => "Unpublished",
+            PostTransition::Schedule => "Scheduled",
+            PostTransition::Archive => "Archived",
+        };
+        revisions::record(state, &post, Some(principal.user_id()), Some(note)).await?;
+        Ok(post)
+    }
 
-        // Add to state
-        let mut state = ctx.data.app_state.write().await;
-        state.photos.insert(id.clone(), photo.clone());
+    /// Save a fresh rendering for up to `limit` posts whose cached one is
+    /// missing or from an older renderer (the cron trigger). Returns how
+    /// many were rendered.
+    pub async fn render_stale(state: &ApiState, limit: u64) -> std::result::Result<usize, ApiErrorKind> {
+        let older = Filter {
+            ranges: vec![storage::Range { fields: &["rendered.version"], min: None, max: Some(i64::from(RENDERER_VERSION) - 1) }],
+            ..Filter::all()
+        };
+        let stale = Filter::all().any(vec![Filter::all().field("rendered", serde_json::Value::Null), older]).limit(limit);
+        let posts = state.storage.posts.list(&stale).await?;
+        for mut post in posts.iter().cloned() {
+            post.render();
+            state.storage.posts.put(&post).await?;
+        }
+        Ok(posts.len())
+    }
 
-        Response::from_json(&photo)
+    /// Publish up to `limit` scheduled posts whose time has come (the cron
+    /// trigger). Returns the posts published.
+    pub async fn publish_due(state: &ApiState, now: i64, limit: u64) -> std::result::Result<Vec<BlogPost>, ApiErrorKind> {
+        let due = Filter {
+            ranges: vec![storage::Range { fields: &["publishedAt"], min: None, max: Some(now) }],
+            ..Filter::all().field("status", "scheduled")
+        };
+        let mut published = Vec::new();
+        for mut post in state.storage.posts.list(&due.limit(limit)).await? {
+            post.status = PostStatus::Published;
+            post.updated_at = now;
+            state.update_post(&post.id.clone(), post.clone()).await?;
+            revisions::record(state, &post, None, Some("Published on schedule")).await?;
+            published.push(post);
+        }
+        Ok(published)
     }
 }
 
 // ============================================================================
-// Videos Handlers
+// Revision History Handlers
 // ============================================================================
 
-pub mod videos {
+pub mod revisions {
     use super::*;
-
-    /// GET /api/videos - List all videos
-    pub async fn list(_req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let videos = ctx.data.list_videos().await;
-        Response::from_json(&videos)
+    use serde_json::Value;
+    use storage::{Entity, Repository};
+    use web_nexus_contracts::revision::diff;
+
+    /// A record type whose every change is kept as a revision
+    pub trait Versioned: Entity {
+        /// `resourceType` of its revisions (also its ACL resource type)
+        const RESOURCE_TYPE: &'static str;
+        /// Permission to read its history and roll it back
+        const EDIT: Permission;
+        /// 404 message
+        const NOT_FOUND: &'static str;
+        /// Fields a rollback leaves as they are now
+        const KEPT_ON_ROLLBACK: &'static [&'static str];
+
+        fn repository(storage: &Storage) -> &Arc<dyn Repository<Self>>;
     }
 
-    /// POST /api/videos - Create a new video
-    pub async fn create(mut req: Request, ctx: RouteContext<ApiState>) -> worker::Result<Response> {
-        let claims = extract_claims(&req, &ctx.data.jwt_secret)?;
-        check_permission_from_claims(&claims, "create_videos")?;
+    impl Versioned for BlogPost {
+        const RESOURCE_TYPE: &'static str = "post";
+        const EDIT: Permission = Permission::EditPost;
+        const NOT_FOUND: &'static str = "Blog post not found";
+        // Going live or offline is the publishing workflow's job (`PublishPost`)
+        const KEPT_ON_ROLLBACK: &'static [&'static str] = &["id", "siteId", "authorId", "createdAt", "status", "publishedAt"];
 
-        let body = req.json().await?;
-        let create_req: CreateVideoRequest = serde_json::from_value(body)
-            .map_err(|e| worker::Error::from(format!("Invalid request: {}", e)))?;
+        fn repository(storage: &Storage) -> &Arc<dyn Repository<Self>> {
+            &storage.posts
+        }
+    }
 
-        if let Err(errors) = create_req.validate() {
-            return error_response(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)));
+    impl Versioned for Show {
+        const RESOURCE_TYPE: &'static str = "show";
+        const EDIT: Permission = Permission::EditShow;
+        const NOT_FOUND: &'static str = "Show not found";
+        const KEPT_ON_ROLLBACK: &'static [&'static str] = &["id", "siteId", "createdBy", "createdAt"];
+
+        fn repository(storage: &Storage) -> &Arc<dyn Repository<Self>> {
+            &storage.shows
         }
+    }
 
-        let id = uuid::Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp();
+    /// GET /api/{posts,shows}/:id/revisions - A record's history, newest first
+    pub async fn list<T: Versioned>(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        authorize::<T>(&state, &principal, &id).await?;
+        let history = history::<T>(&state, &id).await?;
+        let summaries: Vec<RevisionSummary> = history.iter().rev().map(RevisionSummary::from).collect();
+        Ok(json_response(&summaries))
+    }
 
-        // Parse video URL to determine source
-        let source = if let Some(video_type) = create_req.video_type {
-            match video_type.as_str() {
-                "youtube" => VideoSource::YouTube { video_id: extract_video_id(&create_req.url) },
-                "vimeo" => VideoSource::Vimeo { video_id: extract_video_id(&create_req.url) },
-                _ => VideoSource::Direct { url: create_req.url.clone() },
-            }
-        } else {
-            VideoSource::Direct { url: create_req.url.clone() }
-        };
+    /// GET /api/{posts,shows}/:id/revisions/:number - One revision with its snapshot
+    pub async fn get<T: Versioned>(
+        State(state): State<ApiState>,
+        Path((id, number)): Path<(String, u32)>,
+        principal: Principal,
+    ) -> HandlerResult {
+        authorize::<T>(&state, &principal, &id).await?;
+        let history = history::<T>(&state, &id).await?;
+        Ok(json_response(find(&history, number)?))
+    }
 
-        let video = Video {
-            id: id.clone(),
-            site_id: create_req.site_id,
-            title: create_req.title,
-            description: create_req.description,
-            source,
-            thumbnail_url: create_req.thumbnail_url,
-            duration_seconds: create_req.duration_seconds.map(|d| d as i32),
-            visibility: GalleryVisibility::Public,
-            view_count: 0,
-            published_at: now,
+    /// GET /api/{posts,shows}/:id/revisions/compare?from=N&to=M - Differences
+    /// between two revisions (`to` defaults to the latest)
+    pub async fn compare<T: Versioned>(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let number = |name: &str| -> std::result::Result<Option<u32>, ApiErrorKind> {
+            params.get(name).map(|v| v.parse().map_err(|_| {
+                ApiErrorKind::ValidationError(format!("Invalid {}: {}", name, v))
+            })).transpose()
         };
+        let from = number("from")?.ok_or_else(|| ApiErrorKind::ValidationError("from is required".to_string()))?;
+        let to = number("to")?;
+
+        authorize::<T>(&state, &principal, &id).await?;
+        let history = history::<T>(&state, &id).await?;
+        let from = find(&history, from)?;
+        let to = match to {
+            Some(to) => find(&history, to)?,
+            None => history.last().unwrap_or(from),
+        };
+
+        Ok(json_response(&RevisionComparison {
+            from: from.number,
+            to: to.number,
+            changes: diff(&from.snapshot, &to.snapshot),
+        }))
+    }
+
+    /// POST /api/{posts,shows}/:id/revisions/:number/rollback - Restore a
+    /// revision's content. The rollback is itself saved as a new revision.
+    pub async fn rollback<T: Versioned>(
+        State(state): State<ApiState>,
+        Path((id, number)): Path<(String, u32)>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let current = authorize::<T>(&state, &principal, &id).await?;
+        let history = history::<T>(&state, &id).await?;
+        let target = find(&history, number)?;
+
+        let (Value::Object(mut restored), Value::Object(current)) = (target.snapshot.clone(), to_json(&current)?) else {
+            return Err(ApiErrorKind::Internal("Revision snapshot is not an object".to_string()).into());
+        };
+        for field in T::KEPT_ON_ROLLBACK {
+            match current.get(*field) {
+                Some(value) => restored.insert(field.to_string(), value.clone()),
+                None => restored.remove(*field),
+            };
+        }
+        if current.contains_key("updatedAt") {
+            restored.insert("updatedAt".to_string(), chrono::Utc::now().timestamp().into());
+        }
+        let restored: T = serde_json::from_value(Value::Object(restored))
+            .map_err(|e| ApiErrorKind::Internal(format!("Revision {} no longer fits the record: {}", number, e)))?;
+
+        T::repository(&state.storage).put(&restored).await?;
+        let note = format!("Rolled back to revision {}", number);
+        record(&state, &restored, Some(principal.user_id()), Some(&note)).await?;
+        Ok(json_response(&restored))
+    }
+
+    /// Save a revision of a record that was just written
+    pub async fn record<T: Versioned>(
+        state: &ApiState,
+        record: &T,
+        author_id: Option<&str>,
+        note: Option<&str>,
+    ) -> std::result::Result<Revision, ApiErrorKind> {
+        let history = history::<T>(state, record.id()).await?;
+        let snapshot = to_json(record)?;
+        let previous = history.last().map_or(&Value::Null, |r| &r.snapshot);
+
+        let revision = Revision {
+            id: uuid::Uuid::new_v4().to_string(),
+            site_id: record.site_id().unwrap_or_default().to_string(),
+            resource_type: T::RESOURCE_TYPE.to_string(),
+            resource_id: record.id().to_string(),
+            number: history.last().map_or(1, |r| r.number + 1),
+            author_id: author_id.map(str::to_string),
+            created_at: chrono::Utc::now().timestamp(),
+            note: note.map(str::to_string),
+            changes: diff(previous, &snapshot),
+            snapshot,
+        };
+        state.storage.revisions.put(&revision).await?;
+        Ok(revision)
+    }
+
+    /// Delete a record's history (when the record is deleted)
+    pub async fn forget<T: Versioned>(state: &ApiState, id: &str) -> std::result::Result<(), ApiErrorKind> {
+        for revision in history::<T>(state, id).await? {
+            state.storage.revisions.delete(&revision.id).await?;
+        }
+        Ok(())
+    }
+
+    /// A record's revisions, oldest first
+    async fn history<T: Versioned>(state: &ApiState, id: &str) -> std::result::Result<Vec<Revision>, ApiErrorKind> {
+        let filter = Filter::all().field("resourceType", T::RESOURCE_TYPE).field("resourceId", id);
+        let mut revisions = state.storage.revisions.list(&filter).await?;
+        revisions.sort_by_key(|r| r.number);
+        Ok(revisions)
+    }
+
+    fn find(history: &[Revision], number: u32) -> std::result::Result<&Revision, ApiErrorKind> {
+        history.iter().find(|r| r.number == number)
+            .ok_or_else(|| ApiErrorKind::NotFound(format!("Revision {} not found", number)))
+    }
+
+    /// Load a record and require permission to edit it
+    async fn authorize<T: Versioned>(state: &ApiState, principal: &Principal, id: &str) -> std::result::Result<T, ApiErrorKind> {
+        let record = load(T::repository(&state.storage), id, T::NOT_FOUND).await?;
+        let site_id = record.site_id().unwrap_or_default().to_string();
+        authorize_resource(state, principal, T::EDIT, T::RESOURCE_TYPE, (id, &site_id)).await?;
+        Ok(record)
+    }
+
+    fn to_json<T: Serialize>(record: &T) -> std::result::Result<Value, ApiErrorKind> {
+        serde_json::to_value(record).map_err(|e| ApiErrorKind::Internal(e.to_string()))
+    }
+}
+
+// ============================================================================
+// Photos Handlers
+// ============================================================================
+
+pub mod photos {
+    use super::*;
+
+    /// GET /api/photos - List photos (see `query`). Photos only in
+    /// galleries the caller can't see in full are left out, as in
+    /// `galleries::get`.
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        headers: HeaderMap,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let unlocked = galleries::unlocked_gallery(&state, &headers);
+        let response = state.list_photos(principal.as_ref(), unlocked.as_deref(), &query).await?;
+        Ok(json_response(&response))
+    }
+
+    /// POST /api/photos - Create a new photo
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::CreatePhoto)?;
+
+        let create_req: CreatePhotoRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        check_site_permission(&principal, Permission::CreatePhoto, site.id())?;
+        let gallery = match create_req.gallery_id.as_deref() {
+            Some(gallery_id) => Some(galleries::check_target(&state, &principal, gallery_id, site.id()).await?),
+            None => None,
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        let user_id = principal.user_id().to_string();
+
+        // Extract filename from URL
+        let filename = create_req.url.split('/').next_back().unwrap_or("photo.jpg").to_string();
+
+        let photo = Photo {
+            id: id.clone(),
+            site_id: site.id().to_string(),
+            filename,
+            url_full: create_req.url.clone(),
+            url_thumb: create_req.thumbnail_url.unwrap_or_else(|| create_req.url.clone()),
+            size_bytes: 0,
+            dimensions: ImageDimensions { width: 0, height: 0 },
+            alt_text: Some(create_req.title),
+            caption: create_req.caption,
+            tags: vec![],
+            uploaded_at: now,
+            uploaded_by: user_id,
+            content_type: None,
+            content_hash: None,
+            storage_key: None,
+            exif: None,
+            derivatives: vec![],
+        };
+
+        state.storage.photos.put(&photo).await?;
+        if let Some(mut gallery) = gallery {
+            gallery.add_photos(std::slice::from_ref(&photo.id), None);
+            state.storage.galleries.put(&gallery).await?;
+        }
+
+        Ok(json_response(&photo))
+    }
+
+    /// GET /api/photos/:id - Get a specific photo
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let photo = load_readable(&state.storage.photos, &id, principal.as_ref(), site.as_ref(), "Photo not found").await?;
+        let unlocked = galleries::unlocked_gallery(&state, &headers);
+        if galleries::photo_access(&state, principal.as_ref(), unlocked.as_deref(), &photo).await? == galleries::MediaAccess::Denied {
+            return Err(ApiErrorKind::NotFound("Photo not found".to_string()).into());
+        }
+        Ok(json_response(&photo))
+    }
+
+    /// PUT /api/photos/:id - Update a photo's metadata
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdatePhotoRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut photo = load(&state.storage.photos, &id, "Photo not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPhoto, "photo", (&id, &photo.site_id)).await?;
+
+        if let Some(alt_text) = update_req.alt_text {
+            photo.alt_text = Some(alt_text);
+        }
+        if let Some(caption) = update_req.caption {
+            photo.caption = Some(caption);
+        }
+        if let Some(tags) = update_req.tags {
+            photo.tags = tags;
+        }
+
+        state.storage.photos.put(&photo).await?;
+        Ok(json_response(&photo))
+    }
+
+    /// POST /api/photos/:id/derivatives - Remake a photo's thumbnail and
+    /// responsive sizes
+    pub async fn derive(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let mut photo = load(&state.storage.photos, &id, "Photo not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPhoto, "photo", (&id, &photo.site_id)).await?;
+        if state.images.is_none() {
+            return Err(ApiErrorKind::ValidationError("Image processing is not configured".to_string()).into());
+        }
+
+        state.derive_photo_images(&mut photo).await?;
+        state.storage.photos.put(&photo).await?;
+        Ok(json_response(&photo))
+    }
+
+    /// DELETE /api/photos/:id - Delete a photo
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let photo = load(&state.storage.photos, &id, "Photo not found").await?;
+        authorize_resource(&state, &principal, Permission::DeletePhoto, "photo", (&id, &photo.site_id)).await?;
+
+        state.storage.photos.delete(&id).await?;
+        galleries::forget_photo(&state, &photo).await?;
+        if let Some(key) = &photo.storage_key {
+            state.objects.delete(key).await?;
+        }
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
+
+// ============================================================================
+// Videos Handlers
+// ============================================================================
+
+pub mod videos {
+    use super::*;
+
+    /// How long a video access token lasts (as long as a gallery's)
+    pub const VIDEO_TOKEN_TTL: i64 = galleries::GALLERY_TOKEN_TTL;
+
+    /// Header carrying a video access token (see `unlock`)
+    pub const VIDEO_TOKEN_HEADER: &str = "x-video-token";
+
+    /// Claims of a video access token
+    #[derive(Debug, Serialize, Deserialize)]
+    struct VideoClaims {
+        /// Video the token unlocks
+        video: String,
+        iat: i64,
+        exp: i64,
+    }
+
+    /// GET /api/videos - List videos the caller may see (see `query` and
+    /// `ApiState::list_videos`)
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        headers: HeaderMap,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let unlocked = unlocked_video(&state, &headers);
+        let response = state.list_videos(principal.as_ref(), unlocked.as_deref(), &query).await?;
+        Ok(json_response(&response))
+    }
+
+    /// POST /api/videos - Create a new video
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::CreateVideo)?;
+
+        let create_req: CreateVideoRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        check_site_permission(&principal, Permission::CreateVideo, site.id())?;
+        let visibility = create_req.visibility.unwrap_or(GalleryVisibility::Public);
+        let password_hash = match (&visibility, &create_req.password) {
+            (GalleryVisibility::Password, Some(password)) => Some(credentials::hash_password(password)?),
+            (GalleryVisibility::Password, None) => {
+                return Err(ApiErrorKind::ValidationError("A password is required for password-protected videos".to_string()).into());
+            }
+            (_, Some(_)) => return Err(ApiErrorKind::ValidationError("Only password-protected videos take a password".to_string()).into()),
+            (_, None) => None,
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        // The source comes from the URL itself; `video_type` is ignored
+        let source = video::classify(&create_req.url).map_err(|e| ApiErrorKind::ValidationError(e.to_string()))?;
+
+        let mut video = Video {
+            id: id.clone(),
+            site_id: site.id().to_string(),
+            title: create_req.title,
+            description: create_req.description,
+            source,
+            thumbnail_url: create_req.thumbnail_url,
+            duration_seconds: create_req.duration_seconds.map(|d| d as i32),
+            visibility,
+            view_count: 0,
+            published_at: now,
+        };
+
+        state.fill_video_metadata(&mut video).await;
+        state.storage.videos.put(&video).await?;
+        if let Some(hash) = password_hash {
+            state.storage.video_passwords.set_hash(&video.id, &video.site_id, hash).await?;
+        }
+
+        Ok(json_response(&video))
+    }
+
+    /// GET /api/videos/:id - Get a specific video. A password-protected one
+    /// answers 401 until unlocked (see `unlock`).
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let video = load_readable(&state.storage.videos, &id, principal.as_ref(), site.as_ref(), "Video not found").await?;
+        let unlocked = unlocked_video(&state, &headers);
+        match video_access(&state, principal.as_ref(), unlocked.as_deref(), &video).await? {
+            GalleryAccess::Full => Ok(json_response(&video)),
+            GalleryAccess::Locked => Err(ApiErrorKind::Unauthorized.into()),
+            GalleryAccess::Denied => Err(ApiErrorKind::NotFound("Video not found".to_string()).into()),
+        }
+    }
+
+    /// POST /api/videos/:id/unlock - Trade a password-protected video's
+    /// password for a short-lived access token to it
+    pub async fn unlock(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        site: Option<SiteContext>,
+        body: Bytes,
+    ) -> HandlerResult {
+        let unlock_req: UnlockGalleryRequest = parse_json(&body)?;
+
+        if let Err(errors) = unlock_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let video = load_readable(&state.storage.videos, &id, None, site.as_ref(), "Video not found").await?;
+        if video.visibility != GalleryVisibility::Password {
+            return Err(ApiErrorKind::ValidationError("Video is not password protected".to_string()).into());
+        }
+        if !state.storage.video_passwords.verify(&video.id, &unlock_req.password).await? {
+            return Err(ApiErrorKind::Unauthorized.into());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = VideoClaims { video: video.id.clone(), iat: now, exp: now + VIDEO_TOKEN_TTL };
+        Ok(json_response(&VideoAccessToken {
+            video_id: video.id,
+            token: state.keys.sign(&claims)?,
+            expires_at: claims.exp,
+        }))
+    }
+
+    /// Helper: Video unlocked by the request's access token, if any
+    pub(crate) fn unlocked_video(state: &ApiState, headers: &HeaderMap) -> Option<String> {
+        unlocked_by(state, headers.get(VIDEO_TOKEN_HEADER)?.to_str().ok()?)
+    }
+
+    /// Helper: Video an access token unlocks, if it is valid
+    pub(crate) fn unlocked_by(state: &ApiState, token: &str) -> Option<String> {
+        state.keys.verify::<VideoClaims>(token).ok().map(|claims| claims.video)
+    }
+
+    /// Helper: How the caller may read `video` (the gallery rule, with
+    /// `unlocked` the video their token unlocks)
+    pub(crate) async fn video_access(
+        state: &ApiState,
+        principal: Option<&Principal>,
+        unlocked: Option<&str>,
+        video: &Video,
+    ) -> std::result::Result<GalleryAccess, ApiErrorKind> {
+        let site = state.storage.sites.get(&video.site_id).await?;
+        let reader = GalleryReader {
+            manages_site: principal.is_some_and(|p| p.has_site_permission(Permission::EditVideo, &video.site_id)),
+            is_member: galleries::is_member(principal, site.as_ref()),
+            unlocked: unlocked == Some(video.id.as_str()),
+        };
+        Ok(video.visibility.access(reader))
+    }
+
+    /// PUT /api/videos/:id - Update a video's metadata
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateVideoRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut video = load(&state.storage.videos, &id, "Video not found").await?;
+        authorize_resource(&state, &principal, Permission::EditVideo, "video", (&id, &video.site_id)).await?;
+
+        if let Some(title) = update_req.title {
+            video.title = title;
+        }
+        if let Some(description) = update_req.description {
+            video.description = Some(description);
+        }
+        if let Some(thumbnail_url) = update_req.thumbnail_url {
+            video.thumbnail_url = Some(thumbnail_url);
+        }
+        if let Some(duration) = update_req.duration_seconds {
+            video.duration_seconds = Some(duration as i32);
+        }
+        if let Some(visibility) = update_req.visibility {
+            video.visibility = visibility;
+        }
+
+        // As with galleries, the password is kept hashed apart from the
+        // video and goes when it stops being password-protected
+        let protected = video.visibility == GalleryVisibility::Password;
+        match &update_req.password {
+            Some(_) if !protected => {
+                return Err(ApiErrorKind::ValidationError("Only password-protected videos take a password".to_string()).into());
+            }
+            None if protected && !state.storage.video_passwords.exists(&id).await? => {
+                return Err(ApiErrorKind::ValidationError("A password is required for password-protected videos".to_string()).into());
+            }
+            _ => {}
+        }
+
+        state.storage.videos.put(&video).await?;
+        match update_req.password {
+            Some(password) => {
+                let hash = credentials::hash_password(&password)?;
+                state.storage.video_passwords.set_hash(&id, &video.site_id, hash).await?;
+            }
+            None if !protected => state.storage.video_passwords.remove(&id).await?,
+            None => {}
+        }
+        Ok(json_response(&video))
+    }
+
+    /// DELETE /api/videos/:id - Delete a video
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let video = load(&state.storage.videos, &id, "Video not found").await?;
+        authorize_resource(&state, &principal, Permission::DeleteVideo, "video", (&id, &video.site_id)).await?;
+
+        state.storage.videos.delete(&id).await?;
+        state.storage.video_passwords.remove(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
+
+// ============================================================================
+// Uploads Handlers
+// ============================================================================
+
+pub mod uploads {
+    use super::*;
+    use axum::body::Body;
+    use objects::ObjectStoreError;
+    use sha2::{Digest, Sha256};
+    use web_nexus_contracts::media::{
+        probe, CompleteUploadRequest, CreateUploadRequest, MediaInfo, MediaKind, MediaUpload, UploadMethod,
+        UploadStatus, UploadTarget, UploadedPart, PROBE_BYTES,
+    };
+
+    /// How long an unfinished upload is accepted
+    pub const UPLOAD_TTL: i64 = 24 * 60 * 60;
+    /// How long a presigned URL is valid
+    const PRESIGN_TTL: u64 = 60 * 60;
+    /// Largest file sent in one presigned PUT (the S3 single-request limit)
+    const MAX_PRESIGNED_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+    /// Part size handed to clients. Every part but the last must be at least
+    /// 5 MiB for R2 to accept the upload.
+    pub const PART_SIZE: u64 = 8 * 1024 * 1024;
+    /// Largest part accepted (the route's body limit)
+    pub const MAX_PART_BYTES: usize = 32 * 1024 * 1024;
+    /// Highest part number
+    const MAX_PARTS: u16 = 10_000;
+    /// Bytes read at a time while hashing a completed upload
+    const READ_CHUNK: u64 = 8 * 1024 * 1024;
+    /// Bytes read at a time while serving a file
+    const STREAM_CHUNK: u64 = 1024 * 1024;
+
+    /// POST /api/uploads - Start an upload. Returns a presigned URL when the
+    /// object store can sign one (and `multipart` isn't requested), otherwise
+    /// a multipart upload to send in parts.
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        let create_req: CreateUploadRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        let permission = create_req.kind.upload_permission();
+        check_permission(&principal, permission)?;
+        check_site_permission(&principal, permission, site.id())?;
+
+        let content_type = create_req.content_type.trim().to_ascii_lowercase();
+        if !create_req.kind.accepts(&content_type) {
+            return Err(ApiErrorKind::ValidationError(format!(
+                "{} is not an accepted {} type",
+                content_type,
+                kind_name(create_req.kind)
+            )).into());
+        }
+        if create_req.size_bytes > create_req.kind.max_bytes() {
+            return Err(ApiErrorKind::ValidationError(format!(
+                "File is larger than the {} MB limit",
+                create_req.kind.max_bytes() / (1024 * 1024)
+            )).into());
+        }
+        if let Some(gallery_id) = create_req.gallery_id.as_deref() {
+            if create_req.kind != MediaKind::Photo {
+                return Err(ApiErrorKind::ValidationError("Only photos can be added to a gallery".to_string()).into());
+            }
+            galleries::check_target(&state, &principal, gallery_id, site.id()).await?;
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        let key = format!("sites/{}/uploads/{}/{}", site.id(), id, safe_filename(&create_req.filename));
+
+        let presigned_url = (!create_req.multipart && create_req.size_bytes <= MAX_PRESIGNED_BYTES)
+            .then(|| state.objects.presign_put(&key, &content_type, PRESIGN_TTL, now))
+            .flatten();
+        let multipart_id = match presigned_url {
+            Some(_) => None,
+            None => Some(state.objects.create_multipart(&key, &content_type).await?),
+        };
+
+        let upload = MediaUpload {
+            id,
+            site_id: site.id().to_string(),
+            kind: create_req.kind,
+            filename: create_req.filename,
+            declared_content_type: content_type.clone(),
+            declared_size_bytes: create_req.size_bytes,
+            key,
+            method: if presigned_url.is_some() { UploadMethod::Presigned } else { UploadMethod::Multipart },
+            multipart_id,
+            status: UploadStatus::Pending,
+            title: create_req.title,
+            caption: create_req.caption,
+            uploaded_by: principal.user_id().to_string(),
+            created_at: now,
+            expires_at: now + UPLOAD_TTL,
+            info: None,
+            url: None,
+            resource_id: None,
+            gallery_id: create_req.gallery_id,
+        };
+        state.storage.uploads.put(&upload).await?;
+
+        let target = UploadTarget {
+            headers: match presigned_url {
+                Some(_) => vec![("Content-Type".to_string(), content_type)],
+                None => Vec::new(),
+            },
+            part_size: upload.multipart_id.is_some().then_some(PART_SIZE),
+            url: presigned_url,
+            upload,
+        };
+        Ok(json_response(&target))
+    }
+
+    /// GET /api/uploads/:id - An upload's status (uploader only)
+    pub async fn get(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let upload = load_own(&state, &principal, &id).await?;
+        Ok(json_response(&upload))
+    }
+
+    /// PUT /api/uploads/:id/parts/:number - Send one part of a multipart upload
+    pub async fn upload_part(
+        State(state): State<ApiState>,
+        Path((id, part_number)): Path<(String, u16)>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let upload = load_pending(&state, &principal, &id).await?;
+        let Some(multipart_id) = upload.multipart_id.as_deref() else {
+            return Err(ApiErrorKind::ValidationError("This upload is sent with its presigned URL".to_string()).into());
+        };
+        if part_number == 0 || part_number > MAX_PARTS {
+            return Err(ApiErrorKind::ValidationError(format!("Part numbers run from 1 to {}", MAX_PARTS)).into());
+        }
+        if body.is_empty() {
+            return Err(ApiErrorKind::ValidationError("Part is empty".to_string()).into());
+        }
+
+        let etag = state.objects.upload_part(&upload.key, multipart_id, part_number, body.to_vec()).await?;
+        Ok(json_response(&UploadedPart { part_number, etag }))
+    }
+
+    /// POST /api/uploads/:id/complete - Finish an upload: inspect the file
+    /// and save it as a photo or video (audio stays an upload). A file that
+    /// isn't what the upload said it would be is deleted.
+    pub async fn complete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let complete_req: CompleteUploadRequest = if body.is_empty() { Default::default() } else { parse_json(&body)? };
+
+        let mut upload = load_pending(&state, &principal, &id).await?;
+        // Permissions may have changed since the upload started
+        check_site_permission(&principal, upload.kind.upload_permission(), &upload.site_id)?;
+
+        if let Some(multipart_id) = upload.multipart_id.as_deref() {
+            if complete_req.parts.is_empty() {
+                return Err(ApiErrorKind::ValidationError("parts are required to complete a multipart upload".to_string()).into());
+            }
+            state.objects.complete_multipart(&upload.key, multipart_id, &complete_req.parts).await?;
+        }
+
+        let info = match inspect(&state, &upload).await {
+            Ok(info) => info,
+            Err(e @ ApiErrorKind::ValidationError(_)) => {
+                state.objects.delete(&upload.key).await?;
+                upload.status = UploadStatus::Aborted;
+                state.storage.uploads.put(&upload).await?;
+                return Err(e.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let url = state.objects.public_url(&upload.key);
+        upload.resource_id = match upload.kind {
+            MediaKind::Photo => {
+                let mut photo = Photo {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    site_id: upload.site_id.clone(),
+                    filename: upload.filename.clone(),
+                    url_full: url.clone(),
+                    url_thumb: url.clone(),
+                    size_bytes: info.size_bytes as i64,
+                    dimensions: info.dimensions.clone().unwrap_or(ImageDimensions { width: 0, height: 0 }),
+                    alt_text: upload.title.clone(),
+                    caption: upload.caption.clone(),
+                    tags: vec![],
+                    uploaded_at: now,
+                    uploaded_by: upload.uploaded_by.clone(),
+                    content_type: Some(info.content_type.clone()),
+                    content_hash: Some(info.content_hash.clone()),
+                    storage_key: Some(upload.key.clone()),
+                    exif: info.exif.clone(),
+                    derivatives: vec![],
+                };
+                // The photo is usable at its original size; derivatives can
+                // be remade later
+                if let Err(e) = state.derive_photo_images(&mut photo).await {
+                    tracing::warn!("could not make derivatives of photo {}: {}", photo.id, e);
+                }
+                state.storage.photos.put(&photo).await?;
+                // The gallery may have gone since the upload started
+                let gallery = match upload.gallery_id.as_deref() {
+                    Some(gallery_id) => state.storage.galleries.get(gallery_id).await?.filter(|g| g.site_id == photo.site_id),
+                    None => None,
+                };
+                if let Some(mut gallery) = gallery {
+                    gallery.add_photos(std::slice::from_ref(&photo.id), None);
+                    state.storage.galleries.put(&gallery).await?;
+                }
+                Some(photo.id)
+            }
+            MediaKind::Video => {
+                let video = Video {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    site_id: upload.site_id.clone(),
+                    title: upload.title.clone().unwrap_or_else(|| upload.filename.clone()),
+                    description: upload.caption.clone(),
+                    source: VideoSource::Direct { url: url.clone() },
+                    thumbnail_url: None,
+                    duration_seconds: None,
+                    visibility: GalleryVisibility::Public,
+                    view_count: 0,
+                    published_at: now,
+                };
+                state.storage.videos.put(&video).await?;
+                Some(video.id)
+            }
+            MediaKind::Audio => None,
+        };
+        upload.status = UploadStatus::Completed;
+        upload.info = Some(info);
+        upload.url = Some(url);
+        state.storage.uploads.put(&upload).await?;
+        Ok(json_response(&upload))
+    }
+
+    /// DELETE /api/uploads/:id - Cancel an unfinished upload
+    pub async fn abort(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let mut upload = load_pending(&state, &principal, &id).await?;
+        discard(&state, &mut upload).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// Abort up to `limit` pending uploads past their expiry (the cron
+    /// trigger). Returns how many were aborted.
+    pub async fn abort_expired(state: &ApiState, now: i64, limit: u64) -> std::result::Result<usize, ApiErrorKind> {
+        let expired = Filter {
+            ranges: vec![storage::Range { fields: &["expiresAt"], min: None, max: Some(now) }],
+            ..Filter::all().field("status", "pending")
+        };
+        let mut aborted = 0;
+        for mut upload in state.storage.uploads.list(&expired.limit(limit)).await? {
+            discard(state, &mut upload).await?;
+            aborted += 1;
+        }
+        Ok(aborted)
+    }
+
+    /// GET /api/media/*key - Serve a completed upload's file (for object
+    /// stores without a public URL of their own). Photos only their
+    /// galleries' readers may see, and videos only their readers may see,
+    /// are missing to everyone else; as `<img>` and `<video>` tags can't
+    /// send headers, a gallery or video token may also come as `?token=`.
+    /// A single byte range is honored, and the file is streamed in chunks.
+    pub async fn serve(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        headers: HeaderMap,
+        Query(params): Query<HashMap<String, String>>,
+        Path(key): Path<String>,
+    ) -> HandlerResult {
+        let filter = Filter::all().field("key", key.as_str()).field("status", "completed");
+        let upload = state.storage.uploads.find(&filter).await?
+            .ok_or_else(|| ApiErrorKind::NotFound("File not found".to_string()))?;
+        let info = upload.info.ok_or_else(|| ApiErrorKind::NotFound("File not found".to_string()))?;
+
+        let token = params.get("token");
+        let unlocked = galleries::unlocked_gallery(&state, &headers)
+            .or_else(|| token.and_then(|token| galleries::unlocked_by(&state, token)));
+        let photos = state.storage.photos.list(&Filter::site(&upload.site_id).field("storageKey", key.as_str())).await?;
+        let mut access = Vec::new();
+        for photo in &photos {
+            access.push(galleries::photo_access(&state, principal.as_ref(), unlocked.as_deref(), photo).await?);
+        }
+        let unlocked = videos::unlocked_video(&state, &headers)
+            .or_else(|| token.and_then(|token| videos::unlocked_by(&state, token)));
+        let url = state.objects.public_url(&key);
+        let videos = state.storage.videos.list(&Filter::site(&upload.site_id).field("source.direct.url", url)).await?;
+        for video in &videos {
+            access.push(match (&video.visibility, videos::video_access(&state, principal.as_ref(), unlocked.as_deref(), video).await?) {
+                (GalleryVisibility::Public, _) => galleries::MediaAccess::Public,
+                (_, GalleryAccess::Full) => galleries::MediaAccess::Private,
+                _ => galleries::MediaAccess::Denied,
+            });
+        }
+        let cache = if access.is_empty() || access.contains(&galleries::MediaAccess::Public) {
+            "public, max-age=31536000, immutable"
+        } else if access.contains(&galleries::MediaAccess::Private) {
+            "private, no-store"
+        } else {
+            return Err(ApiErrorKind::NotFound("File not found".to_string()).into());
+        };
+
+        let size = info.size_bytes;
+        let requested = headers.get(header::RANGE).and_then(|value| value.to_str().ok()).and_then(|value| byte_range(value, size));
+        let (status, range) = match requested {
+            Some(range) if range.is_empty() => {
+                return Ok((StatusCode::RANGE_NOT_SATISFIABLE, [(header::CONTENT_RANGE, format!("bytes */{}", size))]).into_response());
+            }
+            Some(range) => (StatusCode::PARTIAL_CONTENT, range),
+            None => (StatusCode::OK, 0..size),
+        };
+        let content_range = (status == StatusCode::PARTIAL_CONTENT)
+            .then(|| [(header::CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end - 1, size))]);
+        Ok((
+            status,
+            [
+                (header::CONTENT_TYPE, info.content_type),
+                (header::CONTENT_LENGTH, (range.end - range.start).to_string()),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+                (header::CACHE_CONTROL, cache.to_string()),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            ],
+            content_range,
+            stream(&state, key, range),
+        ).into_response())
+    }
+
+    /// Helper: Byte range `bytes=a-b`, `bytes=a-` or `bytes=-n` of a
+    /// `size`-byte file, as `start..end`. `None` when the header isn't one
+    /// such range (it is then ignored); an empty range when it starts past
+    /// the end.
+    fn byte_range(value: &str, size: u64) -> Option<std::ops::Range<u64>> {
+        let (first, last) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+        let (first, last) = (first.trim(), last.trim());
+        if first.is_empty() {
+            let suffix: u64 = last.parse().ok()?;
+            return Some(size.saturating_sub(suffix)..size);
+        }
+        let start: u64 = first.parse().ok()?;
+        let end = match last {
+            "" => u64::MAX,
+            last => last.parse::<u64>().ok()?.saturating_add(1),
+        };
+        if end <= start {
+            return None;
+        }
+        Some(start.min(size)..end.min(size))
+    }
+
+    /// Helper: Body reading `range` of an object a chunk at a time
+    fn stream(state: &ApiState, key: String, range: std::ops::Range<u64>) -> Body {
+        let objects = state.objects.clone();
+        let end = range.end;
+        let chunks = futures_util::stream::try_unfold(range.start, move |offset| {
+            let (objects, key) = (objects.clone(), key.clone());
+            async move {
+                if offset >= end {
+                    return Ok(None);
+                }
+                let bytes = objects.read(&key, offset, STREAM_CHUNK.min(end - offset)).await?;
+                if bytes.is_empty() {
+                    return Err(ObjectStoreError::Backend(format!("{} ended early", key)));
+                }
+                let next = offset + bytes.len() as u64;
+                Ok(Some((bytes, next)))
+            }
+        });
+        Body::from_stream(chunks)
+    }
+
+    /// Read a stored file back and work out what it is
+    async fn inspect(state: &ApiState, upload: &MediaUpload) -> std::result::Result<MediaInfo, ApiErrorKind> {
+        let size = state.objects.size(&upload.key).await?
+            .ok_or_else(|| ApiErrorKind::ValidationError("The file has not been uploaded".to_string()))?;
+        if size > upload.kind.max_bytes() {
+            return Err(ApiErrorKind::ValidationError(format!(
+                "File is larger than the {} MB limit",
+                upload.kind.max_bytes() / (1024 * 1024)
+            )));
+        }
+
+        let mut hasher = Sha256::new();
+        let mut head = Vec::new();
+        let mut offset = 0;
+        while offset < size {
+            let chunk = state.objects.read(&upload.key, offset, READ_CHUNK).await?;
+            if chunk.is_empty() {
+                break;
+            }
+            if head.len() < PROBE_BYTES {
+                head.extend_from_slice(&chunk[..chunk.len().min(PROBE_BYTES - head.len())]);
+            }
+            hasher.update(&chunk);
+            offset += chunk.len() as u64;
+        }
+
+        let probe = probe(&head)
+            .ok_or_else(|| ApiErrorKind::ValidationError(format!("{} is not a recognized media file", upload.filename)))?;
+        if !upload.kind.accepts(probe.content_type) {
+            return Err(ApiErrorKind::ValidationError(format!(
+                "{} is {}, not {}",
+                upload.filename,
+                probe.content_type,
+                kind_name(upload.kind)
+            )));
+        }
+        if upload.kind == MediaKind::Photo && probe.dimensions.is_none() {
+            return Err(ApiErrorKind::ValidationError(format!("Could not read the size of {}", upload.filename)));
+        }
+
+        Ok(MediaInfo {
+            content_type: probe.content_type.to_string(),
+            size_bytes: offset,
+            content_hash: hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+            dimensions: probe.dimensions,
+            exif: probe.exif,
+        })
+    }
+
+    /// Throw away an unfinished upload and whatever was sent of it
+    async fn discard(state: &ApiState, upload: &mut MediaUpload) -> std::result::Result<(), ApiErrorKind> {
+        if let Some(multipart_id) = upload.multipart_id.as_deref() {
+            state.objects.abort_multipart(&upload.key, multipart_id).await?;
+        }
+        state.objects.delete(&upload.key).await?;
+        upload.status = UploadStatus::Aborted;
+        state.storage.uploads.put(upload).await?;
+        Ok(())
+    }
+
+    /// Load an upload made by the caller (other users' uploads are not found)
+    async fn load_own(state: &ApiState, principal: &Principal, id: &str) -> std::result::Result<MediaUpload, ApiErrorKind> {
+        let upload = load(&state.storage.uploads, id, "Upload not found").await?;
+        if upload.uploaded_by != principal.user_id() {
+            return Err(ApiErrorKind::NotFound("Upload not found".to_string()));
+        }
+        Ok(upload)
+    }
+
+    /// Load an upload of the caller's that is still waiting for its file
+    async fn load_pending(state: &ApiState, principal: &Principal, id: &str) -> std::result::Result<MediaUpload, ApiErrorKind> {
+        let upload = load_own(state, principal, id).await?;
+        if upload.status != UploadStatus::Pending {
+            return Err(ApiErrorKind::ValidationError(format!("Upload is already {:?}", upload.status).to_lowercase()));
+        }
+        if upload.expires_at <= chrono::Utc::now().timestamp() {
+            return Err(ApiErrorKind::ValidationError("Upload has expired".to_string()));
+        }
+        Ok(upload)
+    }
+
+    fn kind_name(kind: MediaKind) -> &'static str {
+        match kind {
+            MediaKind::Photo => "an image",
+            MediaKind::Video => "a video",
+            MediaKind::Audio => "an audio file",
+        }
+    }
+
+    /// A filename reduced to characters that are safe in an object key and URL
+    fn safe_filename(filename: &str) -> String {
+        let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+        let safe: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '-' })
+            .collect();
+        let safe = safe.trim_matches(['.', '-']);
+        if safe.is_empty() { "file".to_string() } else { safe.chars().take(100).collect() }
+    }
+}
+
+// ============================================================================
+// Galleries Handlers
+// ============================================================================
+
+pub mod galleries {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// How long a gallery access token lasts. Tokens are kept short-lived
+    /// because changing the password doesn't revoke them.
+    pub const GALLERY_TOKEN_TTL: i64 = 30 * 60;
+
+    /// Header carrying a gallery access token (see `unlock`)
+    pub const GALLERY_TOKEN_HEADER: &str = "x-gallery-token";
+
+    /// Claims of a gallery access token
+    #[derive(Debug, Serialize, Deserialize)]
+    struct GalleryClaims {
+        /// Gallery the token unlocks
+        gallery: String,
+        iat: i64,
+        exp: i64,
+    }
+
+    /// GET /api/galleries - List galleries the caller may see (see `query`).
+    /// Locked galleries are listed without their photos.
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        headers: HeaderMap,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let listed = Filter::all().field("visibility", "password");
+        let filter = query.storage_filter(&read_scope(principal.as_ref()));
+        let filter = visible(&state, principal.as_ref(), Permission::EditPhoto, Some(listed), filter).await?;
+        let mut page = query.fetch(state.storage.galleries.as_ref(), filter).await?;
+
+        let unlocked = unlocked_gallery(&state, &headers);
+        let mut sites: HashMap<String, Option<Site>> = HashMap::new();
+        let mut visible = Vec::with_capacity(page.data.len());
+        for gallery in std::mem::take(&mut page.data) {
+            if !sites.contains_key(&gallery.site_id) {
+                let site = state.storage.sites.get(&gallery.site_id).await?;
+                sites.insert(gallery.site_id.clone(), site);
+            }
+            let gallery_site = sites[&gallery.site_id].as_ref();
+            let reader = reader(principal.as_ref(), gallery_site, &gallery, unlocked.as_deref());
+            if let Some(gallery) = view(gallery, reader) {
+                visible.push(gallery);
+            }
+        }
+        page.data = visible;
+        Ok(json_response(&page))
+    }
+
+    /// GET /api/galleries/:id - Get a specific gallery (without its photos
+    /// while locked)
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let (gallery, _) = load_visible(&state, &id, principal.as_ref(), site.as_ref(), &headers).await?;
+        Ok(json_response(&gallery))
+    }
+
+    /// GET /api/galleries/:id/images - The gallery's photos in order and its
+    /// cover, ready for `srcset` markup (`?sizes=` sets the `sizes` attribute)
+    pub async fn images(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let (gallery, access) = load_visible(&state, &id, principal.as_ref(), site.as_ref(), &headers).await?;
+        if access == GalleryAccess::Locked {
+            return Err(ApiErrorKind::Unauthorized.into());
+        }
+        let sizes = params.get("sizes").map(String::as_str).unwrap_or(DEFAULT_SIZES);
+
+        let mut images: Vec<ResponsiveImage> = Vec::with_capacity(gallery.photo_ids.len());
+        for photo_id in &gallery.photo_ids {
+            if let Some(photo) = state.storage.photos.get(photo_id).await?.filter(|p| p.site_id == gallery.site_id) {
+                images.push(photo.responsive(sizes));
+            }
+        }
+        let cover = gallery
+            .cover_id()
+            .and_then(|cover| images.iter().find(|image| image.photo_id == cover))
+            .or(images.first())
+            .cloned();
+        Ok(json_response(&GalleryImages {
+            gallery_id: gallery.id,
+            title: gallery.title,
+            description: gallery.description,
+            cover,
+            images,
+        }))
+    }
+
+    /// POST /api/galleries - Create a gallery
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::CreatePhoto)?;
+
+        let create_req: CreateGalleryRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        check_site_permission(&principal, Permission::CreatePhoto, site.id())?;
+        check_photos(&state, site.id(), &create_req.photo_ids, create_req.cover_photo_id.as_deref()).await?;
+        check_cover(&create_req.photo_ids, create_req.cover_photo_id.as_deref())?;
+        let visibility = create_req.visibility.unwrap_or(GalleryVisibility::Public);
+        let password_hash = match (&visibility, &create_req.password) {
+            (GalleryVisibility::Password, Some(password)) => Some(credentials::hash_password(password)?),
+            (GalleryVisibility::Password, None) => {
+                return Err(ApiErrorKind::ValidationError("A password is required for password-protected galleries".to_string()).into());
+            }
+            (_, Some(_)) => return Err(ApiErrorKind::ValidationError("Only password-protected galleries take a password".to_string()).into()),
+            (_, None) => None,
+        };
+
+        let gallery = Gallery {
+            id: uuid::Uuid::new_v4().to_string(),
+            site_id: site.id().to_string(),
+            title: create_req.title,
+            description: create_req.description,
+            photo_ids: create_req.photo_ids,
+            cover_photo_id: create_req.cover_photo_id,
+            visibility,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+
+        state.storage.galleries.put(&gallery).await?;
+        if let Some(hash) = password_hash {
+            state.storage.gallery_passwords.set_hash(&gallery.id, &gallery.site_id, hash).await?;
+        }
+        Ok(json_response(&gallery))
+    }
+
+    /// PUT /api/galleries/:id - Update a gallery
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateGalleryRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut gallery = load(&state.storage.galleries, &id, "Gallery not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPhoto, "gallery", (&id, &gallery.site_id)).await?;
+        check_photos(
+            &state,
+            &gallery.site_id,
+            update_req.photo_ids.as_deref().unwrap_or_default(),
+            update_req.cover_photo_id.as_deref(),
+        ).await?;
+
+        if let Some(title) = update_req.title {
+            gallery.title = title;
+        }
+        if let Some(description) = update_req.description {
+            gallery.description = Some(description);
+        }
+        if let Some(photo_ids) = update_req.photo_ids {
+            gallery.photo_ids = photo_ids;
+        }
+        if let Some(cover_photo_id) = update_req.cover_photo_id {
+            gallery.cover_photo_id = Some(cover_photo_id);
+        }
+        if let Some(visibility) = update_req.visibility {
+            gallery.visibility = visibility;
+        }
+        check_cover(&gallery.photo_ids, gallery.cover_photo_id.as_deref())?;
+
+        // The password lives apart from the gallery, hashed; it goes when the
+        // gallery stops being password-protected
+        let protected = gallery.visibility == GalleryVisibility::Password;
+        match &update_req.password {
+            Some(_) if !protected => {
+                return Err(ApiErrorKind::ValidationError("Only password-protected galleries take a password".to_string()).into());
+            }
+            None if protected && !state.storage.gallery_passwords.exists(&id).await? => {
+                return Err(ApiErrorKind::ValidationError("A password is required for password-protected galleries".to_string()).into());
+            }
+            _ => {}
+        }
+
+        state.storage.galleries.put(&gallery).await?;
+        match update_req.password {
+            Some(password) => {
+                let hash = credentials::hash_password(&password)?;
+                state.storage.gallery_passwords.set_hash(&id, &gallery.site_id, hash).await?;
+            }
+            None if !protected => state.storage.gallery_passwords.remove(&id).await?,
+            None => {}
+        }
+        Ok(json_response(&gallery))
+    }
+
+    /// POST /api/galleries/:id/photos - Add photos (at `position`, or the end)
+    pub async fn add_photos(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let add_req: AddGalleryPhotosRequest = parse_json(&body)?;
+
+        if let Err(errors) = add_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut gallery = load(&state.storage.galleries, &id, "Gallery not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPhoto, "gallery", (&id, &gallery.site_id)).await?;
+        check_photos(&state, &gallery.site_id, &add_req.photo_ids, None).await?;
+
+        gallery.add_photos(&add_req.photo_ids, add_req.position);
+        state.storage.galleries.put(&gallery).await?;
+        Ok(json_response(&gallery))
+    }
+
+    /// PUT /api/galleries/:id/photos - Put the gallery's photos in a new order
+    pub async fn reorder_photos(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let reorder_req: ReorderGalleryPhotosRequest = parse_json(&body)?;
+
+        let mut gallery = load(&state.storage.galleries, &id, "Gallery not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPhoto, "gallery", (&id, &gallery.site_id)).await?;
+
+        if !setlists::is_permutation(&gallery.photo_ids, &reorder_req.photo_ids) {
+            return Err(ApiErrorKind::ValidationError("photoIds must be a reordering of the gallery's photos".to_string()).into());
+        }
+
+        gallery.photo_ids = reorder_req.photo_ids;
+        state.storage.galleries.put(&gallery).await?;
+        Ok(json_response(&gallery))
+    }
+
+    /// DELETE /api/galleries/:id/photos/:photo_id - Take a photo out of the
+    /// gallery (the photo itself is kept)
+    pub async fn remove_photo(
+        State(state): State<ApiState>,
+        Path((id, photo_id)): Path<(String, String)>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let mut gallery = load(&state.storage.galleries, &id, "Gallery not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPhoto, "gallery", (&id, &gallery.site_id)).await?;
+
+        if !gallery.remove_photo(&photo_id) {
+            return Err(ApiErrorKind::NotFound("Photo is not in this gallery".to_string()).into());
+        }
+        state.storage.galleries.put(&gallery).await?;
+        Ok(json_response(&gallery))
+    }
+
+    /// PUT /api/galleries/:id/cover - Choose the cover photo, or clear it to
+    /// fall back to the first photo
+    pub async fn set_cover(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let cover_req: SetGalleryCoverRequest = parse_json(&body)?;
+
+        let mut gallery = load(&state.storage.galleries, &id, "Gallery not found").await?;
+        authorize_resource(&state, &principal, Permission::EditPhoto, "gallery", (&id, &gallery.site_id)).await?;
+        check_cover(&gallery.photo_ids, cover_req.photo_id.as_deref())?;
+
+        gallery.cover_photo_id = cover_req.photo_id;
+        state.storage.galleries.put(&gallery).await?;
+        Ok(json_response(&gallery))
+    }
+
+    /// POST /api/galleries/:id/unlock - Trade a password-protected gallery's
+    /// password for an access token, sent back in `X-Gallery-Token`
+    pub async fn unlock(
+        State(state): State<ApiState>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+        body: Bytes,
+    ) -> HandlerResult {
+        let unlock_req: UnlockGalleryRequest = parse_json(&body)?;
+
+        if let Err(errors) = unlock_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let gallery = load_readable(&state.storage.galleries, &id, None, site.as_ref(), "Gallery not found").await?;
+        if gallery.visibility != GalleryVisibility::Password {
+            return Err(ApiErrorKind::ValidationError("Gallery is not password protected".to_string()).into());
+        }
+        if !state.storage.gallery_passwords.verify(&gallery.id, &unlock_req.password).await? {
+            return Err(ApiErrorKind::Unauthorized.into());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = GalleryClaims { gallery: gallery.id.clone(), iat: now, exp: now + GALLERY_TOKEN_TTL };
+        Ok(json_response(&GalleryAccessToken {
+            gallery_id: gallery.id,
+            token: state.keys.sign(&claims)?,
+            expires_at: claims.exp,
+        }))
+    }
+
+    /// Load the gallery new photos are going into, requiring the caller may
+    /// edit it
+    pub(crate) async fn check_target(
+        state: &ApiState,
+        principal: &Principal,
+        gallery_id: &str,
+        site_id: &str,
+    ) -> std::result::Result<Gallery, ApiErrorKind> {
+        let gallery = load(&state.storage.galleries, gallery_id, "Gallery not found").await?;
+        if gallery.site_id != site_id {
+            return Err(ApiErrorKind::NotFound("Gallery not found".to_string()));
+        }
+        authorize_resource(state, principal, Permission::EditPhoto, "gallery", (gallery_id, site_id)).await?;
+        Ok(gallery)
+    }
+
+    /// Take a deleted photo out of every gallery of its site
+    pub(crate) async fn forget_photo(state: &ApiState, photo: &Photo) -> std::result::Result<(), ApiErrorKind> {
+        for mut gallery in state.storage.galleries.list(&Filter::site(&photo.site_id)).await? {
+            if gallery.remove_photo(&photo.id) {
+                state.storage.galleries.put(&gallery).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Helper: Load a gallery as the caller may see it, with that access
+    async fn load_visible(
+        state: &ApiState,
+        id: &str,
+        principal: Option<&Principal>,
+        site: Option<&SiteContext>,
+        headers: &HeaderMap,
+    ) -> std::result::Result<(Gallery, GalleryAccess), ApiErrorKind> {
+        let gallery = load_readable(&state.storage.galleries, id, principal, site, "Gallery not found").await?;
+        let gallery_site = state.storage.sites.get(&gallery.site_id).await?;
+        let unlocked = unlocked_gallery(state, headers);
+        let reader = reader(principal, gallery_site.as_ref(), &gallery, unlocked.as_deref());
+        let access = gallery.access(reader);
+        let gallery = view(gallery, reader).ok_or_else(|| ApiErrorKind::NotFound("Gallery not found".to_string()))?;
+        Ok((gallery, access))
+    }
+
+    /// Helper: Who the caller is to `gallery`
+    fn reader(principal: Option<&Principal>, site: Option<&Site>, gallery: &Gallery, unlocked: Option<&str>) -> GalleryReader {
+        GalleryReader {
+            manages_site: principal.is_some_and(|p| p.has_site_permission(Permission::EditPhoto, &gallery.site_id)),
+            is_member: is_member(principal, site),
+            unlocked: unlocked == Some(gallery.id.as_str()),
+        }
+    }
+
+    /// Helper: Whether the caller belongs to `site` (its owner, on its
+    /// member list, or holding a role on it)
+    pub(crate) fn is_member(principal: Option<&Principal>, site: Option<&Site>) -> bool {
+        match (principal, site) {
+            (Some(principal), Some(site)) => {
+                let user_id = principal.user_id();
+                site.owner_id == user_id
+                    || site.member_ids.iter().any(|id| id == user_id)
+                    || principal.user.roles.iter().any(|role| role.site_id() == Some(site.id.as_str()))
+            }
+            _ => false,
+        }
+    }
+
+    /// Helper: Narrow `filter` to the galleries (or videos) a caller may see
+    /// at all, as `GalleryVisibility::access` decides. `manage` is the
+    /// permission that shows every record of a site; password-protected
+    /// records are only kept where they match `protected`.
+    pub(crate) async fn visible(
+        state: &ApiState,
+        principal: Option<&Principal>,
+        manage: Permission,
+        protected: Option<Filter>,
+        filter: Filter,
+    ) -> std::result::Result<Filter, ApiErrorKind> {
+        let mut alternatives = vec![Filter::all().field("visibility", "public")];
+        alternatives.extend(protected);
+        if let Some(principal) = principal {
+            match principal.sites_with(manage) {
+                SiteScope::All => return Ok(filter),
+                managed => alternatives.push(Filter::scope(&managed)),
+            }
+            let user_id = principal.user_id();
+            let mut site_ids: Vec<String> =
+                principal.user.roles.iter().filter_map(|role| role.site_id()).map(str::to_string).collect();
+            for sites in [Filter::all().field("ownerId", user_id), Filter::all().contains("memberIds", user_id)] {
+                site_ids.extend(state.storage.sites.list(&sites).await?.into_iter().map(|site| site.id));
+            }
+            alternatives.push(Filter::scope(&SiteScope::Sites { site_ids }).field("visibility", "membersOnly"));
+        }
+        Ok(filter.any(alternatives))
+    }
+
+    /// Helper: The gallery as `reader` sees it, or `None` if they can't
+    fn view(gallery: Gallery, reader: GalleryReader) -> Option<Gallery> {
+        match gallery.access(reader) {
+            GalleryAccess::Full => Some(gallery),
+            GalleryAccess::Locked => Some(gallery.locked()),
+            GalleryAccess::Denied => None,
+        }
+    }
+
+    /// Helper: Gallery unlocked by the request's access token, if any
+    pub(crate) fn unlocked_gallery(state: &ApiState, headers: &HeaderMap) -> Option<String> {
+        unlocked_by(state, headers.get(GALLERY_TOKEN_HEADER)?.to_str().ok()?)
+    }
+
+    /// Helper: Gallery an access token unlocks, if it is valid
+    pub(crate) fn unlocked_by(state: &ApiState, token: &str) -> Option<String> {
+        state.keys.verify::<GalleryClaims>(token).ok().map(|claims| claims.gallery)
+    }
+
+    /// Helper: Photos on the sites of `filter` the caller may not see:
+    /// those in galleries, none of which they may see in full
+    pub(crate) async fn hidden_photos(
+        state: &ApiState,
+        principal: Option<&Principal>,
+        unlocked: Option<&str>,
+        filter: &Filter,
+    ) -> std::result::Result<Vec<String>, ApiErrorKind> {
+        if principal.is_some_and(|p| p.sites_with(Permission::EditPhoto) == SiteScope::All) {
+            return Ok(Vec::new());
+        }
+        let mut sites: HashMap<String, Option<Site>> = HashMap::new();
+        let (mut shown, mut hidden) = (HashSet::new(), HashSet::new());
+        for gallery in state.storage.galleries.list(&filter.conditions()).await? {
+            if !sites.contains_key(&gallery.site_id) {
+                let site = state.storage.sites.get(&gallery.site_id).await?;
+                sites.insert(gallery.site_id.clone(), site);
+            }
+            let reader = reader(principal, sites[&gallery.site_id].as_ref(), &gallery, unlocked);
+            let photos = if gallery.access(reader) == GalleryAccess::Full { &mut shown } else { &mut hidden };
+            photos.extend(gallery.photo_ids);
+        }
+        Ok(hidden.difference(&shown).cloned().collect())
+    }
+
+    /// Who may see a photo (going by the galleries it is in) or a video
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum MediaAccess {
+        /// Anyone: a public video, or a photo in no gallery or a public one
+        Public,
+        /// The caller, through a site they manage or belong to, or a token
+        Private,
+        /// Not the caller
+        Denied,
+    }
+
+    /// Helper: How the caller may see `photo`. A photo is shown wherever
+    /// one of its galleries would show it in full.
+    pub(crate) async fn photo_access(
+        state: &ApiState,
+        principal: Option<&Principal>,
+        unlocked: Option<&str>,
+        photo: &Photo,
+    ) -> std::result::Result<MediaAccess, ApiErrorKind> {
+        let filter = Filter::site(&photo.site_id).contains("photoIds", photo.id.as_str());
+        let galleries = state.storage.galleries.list(&filter).await?;
+        if galleries.is_empty() || galleries.iter().any(|g| g.visibility == GalleryVisibility::Public) {
+            return Ok(MediaAccess::Public);
+        }
+        let site = state.storage.sites.get(&photo.site_id).await?;
+        let shown = galleries.iter().any(|g| g.access(reader(principal, site.as_ref(), g, unlocked)) == GalleryAccess::Full);
+        Ok(if shown { MediaAccess::Private } else { MediaAccess::Denied })
+    }
+
+    /// Helper: Require the cover to be one of the gallery's photos
+    fn check_cover(photo_ids: &[String], cover_photo_id: Option<&str>) -> std::result::Result<(), ApiErrorKind> {
+        match cover_photo_id {
+            Some(cover) if !photo_ids.iter().any(|id| id == cover) => {
+                Err(ApiErrorKind::ValidationError(format!("Cover photo {} is not in the gallery", cover)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// DELETE /api/galleries/:id - Delete a gallery (its photos are kept)
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let gallery = load(&state.storage.galleries, &id, "Gallery not found").await?;
+        authorize_resource(&state, &principal, Permission::DeletePhoto, "gallery", (&id, &gallery.site_id)).await?;
+
+        state.storage.galleries.delete(&id).await?;
+        state.storage.gallery_passwords.remove(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// Helper: Require every referenced photo to exist on the gallery's site
+    async fn check_photos(
+        state: &ApiState,
+        site_id: &str,
+        photo_ids: &[String],
+        cover_photo_id: Option<&str>,
+    ) -> std::result::Result<(), ApiErrorKind> {
+        for photo_id in photo_ids.iter().map(String::as_str).chain(cover_photo_id) {
+            if state.storage.photos.get(photo_id).await?.filter(|p| p.site_id == site_id).is_none() {
+                return Err(ApiErrorKind::ValidationError(format!("Unknown photo {} for site {}", photo_id, site_id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Setlists Handlers
+// ============================================================================
+
+pub mod setlists {
+    use super::*;
+
+    /// GET /api/setlists - List setlists (see `query`)
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let filter = query.storage_filter(&read_scope(principal.as_ref()));
+        let mut page = query.fetch(state.storage.setlists.as_ref(), filter).await?;
+        page.data = page.data.into_iter().map(|setlist| shown(setlist, principal.as_ref())).collect();
+        Ok(json_response(&page))
+    }
+
+    /// GET /api/setlists/:id - Get a setlist with its songs, running time and
+    /// key-change warnings
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let setlist = load_readable(&state.storage.setlists, &id, principal.as_ref(), site.as_ref(), "Setlist not found").await?;
+        Ok(json_response(&detail(&state, shown(setlist, principal.as_ref())).await?))
+    }
+
+    /// Helper: Member notes are only shown to the site's editors
+    fn shown(setlist: Setlist, principal: Option<&Principal>) -> Setlist {
+        if principal.is_some_and(|p| p.has_site_permission(Permission::EditShow, &setlist.site_id)) {
+            setlist
+        } else {
+            setlist.public()
+        }
+    }
+
+    /// POST /api/setlists - Create a setlist (needs show permissions)
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::CreateShow)?;
+
+        let create_req: CreateSetlistRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        check_site_permission(&principal, Permission::CreateShow, site.id())?;
+        check_references(&state, site.id(), create_req.show_id.as_deref(), &create_req.song_ids).await?;
+        check_member_notes(&state, site.id(), &create_req.member_notes).await?;
+
+        // A set added to a show goes after the show's existing sets
+        let position = match &create_req.show_id {
+            Some(show_id) => next_position(&state, show_id).await?,
+            None => 0,
+        };
+
+        let setlist = Setlist {
+            id: uuid::Uuid::new_v4().to_string(),
+            site_id: site.id().to_string(),
+            show_id: create_req.show_id,
+            song_ids: create_req.song_ids,
+            name: create_req.name,
+            notes: create_req.notes,
+            position,
+            member_notes: create_req.member_notes,
+        };
+
+        state.storage.setlists.put(&setlist).await?;
+        Ok(json_response(&shown(setlist, Some(&principal))))
+    }
+
+    /// PUT /api/setlists/:id - Update a setlist
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateSetlistRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut setlist = load(&state.storage.setlists, &id, "Setlist not found").await?;
+        authorize_resource(&state, &principal, Permission::EditShow, "setlist", (&id, &setlist.site_id)).await?;
+        check_references(
+            &state,
+            &setlist.site_id,
+            update_req.show_id.as_deref(),
+            update_req.song_ids.as_deref().unwrap_or_default(),
+        ).await?;
+        check_member_notes(&state, &setlist.site_id, update_req.member_notes.as_deref().unwrap_or_default()).await?;
+
+        if let Some(show_id) = update_req.show_id {
+            if setlist.show_id.as_deref() != Some(show_id.as_str()) {
+                setlist.position = next_position(&state, &show_id).await?;
+                setlist.show_id = Some(show_id);
+            }
+        }
+        if let Some(song_ids) = update_req.song_ids {
+            setlist.song_ids = song_ids;
+        }
+        if let Some(name) = update_req.name {
+            setlist.name = Some(name);
+        }
+        if let Some(notes) = update_req.notes {
+            setlist.notes = Some(notes);
+        }
+        if let Some(member_notes) = update_req.member_notes {
+            setlist.member_notes = member_notes;
+        }
+
+        state.storage.setlists.put(&setlist).await?;
+        Ok(json_response(&shown(setlist, Some(&principal))))
+    }
+
+    /// DELETE /api/setlists/:id - Delete a setlist
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let setlist = load(&state.storage.setlists, &id, "Setlist not found").await?;
+        authorize_resource(&state, &principal, Permission::DeleteShow, "setlist", (&id, &setlist.site_id)).await?;
+
+        state.storage.setlists.delete(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// PUT /api/setlists/:id/songs - Put a set's songs in a new order
+    pub async fn reorder_songs(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let reorder_req: ReorderSongsRequest = parse_json(&body)?;
+
+        let mut setlist = load(&state.storage.setlists, &id, "Setlist not found").await?;
+        authorize_resource(&state, &principal, Permission::EditShow, "setlist", (&id, &setlist.site_id)).await?;
+
+        if !is_permutation(&setlist.song_ids, &reorder_req.song_ids) {
+            return Err(ApiErrorKind::ValidationError("songIds must be a reordering of the set's songs".to_string()).into());
+        }
+
+        setlist.song_ids = reorder_req.song_ids;
+        state.storage.setlists.put(&setlist).await?;
+        Ok(json_response(&detail(&state, shown(setlist, Some(&principal))).await?))
+    }
+
+    /// POST /api/setlists/:id/songs/move - Move one song within the set or
+    /// into another set of the same show. Returns the sets that changed.
+    pub async fn move_song(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let move_req: MoveSongRequest = parse_json(&body)?;
+
+        let mut source = load(&state.storage.setlists, &id, "Setlist not found").await?;
+        authorize_resource(&state, &principal, Permission::EditShow, "setlist", (&id, &source.site_id)).await?;
+
+        if move_req.from >= source.song_ids.len() {
+            return Err(ApiErrorKind::ValidationError(format!("No song at position {}", move_req.from)).into());
+        }
+
+        let target_id = move_req.to_setlist_id.filter(|target| *target != source.id);
+        let Some(target_id) = target_id else {
+            if move_req.to >= source.song_ids.len() {
+                return Err(ApiErrorKind::ValidationError(format!("Position {} is past the end of the set", move_req.to)).into());
+            }
+            let song_id = source.song_ids.remove(move_req.from);
+            source.song_ids.insert(move_req.to, song_id);
+            state.storage.setlists.put(&source).await?;
+            return Ok(json_response(&vec![detail(&state, shown(source, Some(&principal))).await?]));
+        };
+
+        let mut target = load(&state.storage.setlists, &target_id, "Setlist not found").await?;
+        if target.site_id != source.site_id || source.show_id.is_none() || target.show_id != source.show_id {
+            return Err(ApiErrorKind::ValidationError("Songs can only move between sets of the same show".to_string()).into());
+        }
+        authorize_resource(&state, &principal, Permission::EditShow, "setlist", (&target.id, &target.site_id)).await?;
+        if move_req.to > target.song_ids.len() {
+            return Err(ApiErrorKind::ValidationError(format!("Position {} is past the end of the set", move_req.to)).into());
+        }
+
+        let song_id = source.song_ids.remove(move_req.from);
+        target.song_ids.insert(move_req.to, song_id);
+        state.storage.setlists.put(&source).await?;
+        state.storage.setlists.put(&target).await?;
+        Ok(json_response(&vec![
+            detail(&state, shown(source, Some(&principal))).await?,
+            detail(&state, shown(target, Some(&principal))).await?,
+        ]))
+    }
+
+    /// GET /api/setlists/:id/export - Download a printable setlist.
+    ///
+    /// Query: `format` (`text` or `pdf`), `layout` (`standard` or `stage`)
+    /// and `member_notes` (`all`, or a band member ID for their copy).
+    /// Notes are private: `all` needs a site editor, and a member's copy
+    /// that member (signed in with the email on their profile) or an editor.
+    pub async fn export(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let option = |name: &str| params.get(name).map(String::as_str);
+        let options = ExportOptions {
+            format: option("format").map(str::parse).transpose().map_err(ApiErrorKind::ValidationError)?.unwrap_or_default(),
+            layout: option("layout").map(str::parse).transpose().map_err(ApiErrorKind::ValidationError)?.unwrap_or_default(),
+            member_notes: match option("member_notes") {
+                None | Some("") | Some("none") => MemberNotes::None,
+                Some("all") => MemberNotes::All,
+                Some(member_id) => MemberNotes::Member(member_id.to_string()),
+            },
+        };
+
+        let setlist = load_readable(&state.storage.setlists, &id, principal.as_ref(), site.as_ref(), "Setlist not found").await?;
+        check_notes_reader(&state, principal.as_ref(), &setlist, &options.member_notes).await?;
+        let show = match &setlist.show_id {
+            Some(show_id) => state.get_show(show_id).await?,
+            None => None,
+        };
+        let members = state.storage.band_members.list(&Filter::site(&setlist.site_id)).await?;
+        let SetlistDetail { setlist, songs, .. } = detail(&state, setlist).await?;
+        let chart = SetlistChart { setlist, songs, show, members };
+
+        let disposition = format!("attachment; filename=\"{}\"", chart.file_name(options.format));
+        Ok((
+            [
+                (header::CONTENT_TYPE, options.format.content_type().to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            chart.render(&options),
+        ).into_response())
+    }
+
+    /// Helper: Require the caller to be allowed to read the requested notes
+    async fn check_notes_reader(
+        state: &ApiState,
+        principal: Option<&Principal>,
+        setlist: &Setlist,
+        notes: &MemberNotes,
+    ) -> std::result::Result<(), ApiErrorKind> {
+        if *notes == MemberNotes::None {
+            return Ok(());
+        }
+        let principal = principal.ok_or(ApiErrorKind::Unauthorized)?;
+        if principal.has_site_permission(Permission::EditShow, &setlist.site_id) {
+            return Ok(());
+        }
+        let MemberNotes::Member(member_id) = notes else {
+            return Err(ApiErrorKind::Forbidden);
+        };
+        let member = state.storage.band_members.get(member_id).await?.filter(|m| m.site_id == setlist.site_id);
+        match member.and_then(|m| m.email) {
+            Some(email) if normalize_email(&email) == normalize_email(&principal.user.email) => Ok(()),
+            _ => Err(ApiErrorKind::Forbidden),
+        }
+    }
+
+    /// Resolve a set's songs into a `SetlistDetail`
+    pub(crate) async fn detail(state: &ApiState, setlist: Setlist) -> std::result::Result<SetlistDetail, ApiErrorKind> {
+        let mut songs = Vec::with_capacity(setlist.song_ids.len());
+        for song_id in &setlist.song_ids {
+            if let Some(song) = state.storage.songs.get(song_id).await?.filter(|s| s.site_id == setlist.site_id) {
+                songs.push(song);
+            }
+        }
+        Ok(SetlistDetail::new(setlist, songs))
+    }
+
+    /// The sets linked to a show
+    pub(crate) async fn show_sets(state: &ApiState, show_id: &str) -> std::result::Result<Vec<Setlist>, ApiErrorKind> {
+        Ok(state.storage.setlists.list(&Filter::all().field("showId", show_id)).await?)
+    }
+
+    /// Every set of a show, resolved, as `principal` may see them
+    pub(crate) async fn show_setlist(
+        state: &ApiState,
+        show_id: &str,
+        principal: Option<&Principal>,
+    ) -> std::result::Result<ShowSetlist, ApiErrorKind> {
+        let mut sets = Vec::new();
+        for setlist in show_sets(state, show_id).await? {
+            sets.push(detail(state, shown(setlist, principal)).await?);
+        }
+        Ok(ShowSetlist::new(show_id.to_string(), sets))
+    }
+
+    /// Position after a show's last set
+    async fn next_position(state: &ApiState, show_id: &str) -> std::result::Result<i32, ApiErrorKind> {
+        let sets = show_sets(state, show_id).await?;
+        Ok(sets.iter().map(|s| s.position + 1).max().unwrap_or(0))
+    }
+
+    /// Load a setlist that a show on `site_id` is about to link to
+    pub(crate) async fn load_for_site(state: &ApiState, setlist_id: &str, site_id: &str) -> std::result::Result<Setlist, ApiErrorKind> {
+        state.storage.setlists.get(setlist_id).await?
+            .filter(|s| s.site_id == site_id)
+            .ok_or_else(|| ApiErrorKind::ValidationError(format!("Unknown setlist {} for site {}", setlist_id, site_id)))
+    }
+
+    /// Link a setlist to a show, after the show's existing sets
+    pub(crate) async fn link_show(state: &ApiState, mut setlist: Setlist, show_id: &str) -> std::result::Result<Setlist, ApiErrorKind> {
+        setlist.position = next_position(state, show_id).await?;
+        setlist.show_id = Some(show_id.to_string());
+        state.storage.setlists.put(&setlist).await?;
+        Ok(setlist)
+    }
+
+    /// Whether `reordered` holds exactly the IDs in `current`
+    pub(crate) fn is_permutation(current: &[String], reordered: &[String]) -> bool {
+        let mut current = current.to_vec();
+        let mut reordered = reordered.to_vec();
+        current.sort();
+        reordered.sort();
+        current == reordered
+    }
+
+    /// Helper: Require member notes to name band members of the site
+    async fn check_member_notes(
+        state: &ApiState,
+        site_id: &str,
+        notes: &[MemberNote],
+    ) -> std::result::Result<(), ApiErrorKind> {
+        for note in notes {
+            if state.storage.band_members.get(&note.member_id).await?.filter(|m| m.site_id == site_id).is_none() {
+                return Err(ApiErrorKind::ValidationError(format!("Unknown band member {} for site {}", note.member_id, site_id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Helper: Require the show and every song to exist on the setlist's site
+    async fn check_references(
+        state: &ApiState,
+        site_id: &str,
+        show_id: Option<&str>,
+        song_ids: &[String],
+    ) -> std::result::Result<(), ApiErrorKind> {
+        if let Some(show_id) = show_id {
+            if state.get_show(show_id).await?.filter(|s| s.site_id == site_id).is_none() {
+                return Err(ApiErrorKind::ValidationError(format!("Unknown show {} for site {}", show_id, site_id)));
+            }
+        }
+        for song_id in song_ids {
+            if state.storage.songs.get(song_id).await?.filter(|s| s.site_id == site_id).is_none() {
+                return Err(ApiErrorKind::ValidationError(format!("Unknown song {} for site {}", song_id, site_id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Band Members Handlers
+// ============================================================================
+
+pub mod members {
+    use super::*;
+
+    /// GET /api/members - List band members (see `query`)
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = list_query(&params, site.as_ref())?;
+        let filter = query.storage_filter(&read_scope(principal.as_ref()));
+        let mut page = query.fetch(state.storage.band_members.as_ref(), filter).await?;
+        page.data = page.data.into_iter().map(|member| shown(member, principal.as_ref())).collect();
+        Ok(json_response(&page))
+    }
+
+    /// GET /api/members/:id - Get a specific band member
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        site: Option<SiteContext>,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let member = load_readable(&state.storage.band_members, &id, principal.as_ref(), site.as_ref(), "Band member not found").await?;
+        Ok(json_response(&shown(member, principal.as_ref())))
+    }
+
+    /// Helper: Contact emails are only shown to the site's editors
+    fn shown(member: BandMember, principal: Option<&Principal>) -> BandMember {
+        if principal.is_some_and(|p| p.has_site_permission(Permission::EditSite, &member.site_id)) {
+            member
+        } else {
+            member.public()
+        }
+    }
+
+    /// POST /api/members - Add a band member (needs `EditSite` on the site)
+    pub async fn create(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: SiteContext,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::EditSite)?;
+
+        let create_req: CreateBandMemberRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        check_site_permission(&principal, Permission::EditSite, site.id())?;
+
+        let member = BandMember {
+            id: uuid::Uuid::new_v4().to_string(),
+            site_id: site.id().to_string(),
+            name: create_req.name,
+            role: create_req.role,
+            bio: create_req.bio,
+            photo_id: create_req.photo_id,
+            email: create_req.email.as_deref().map(normalize_email),
+            display_order: create_req.display_order,
+        };
+
+        state.storage.band_members.put(&member).await?;
+        Ok(json_response(&member))
+    }
+
+    /// PUT /api/members/:id - Update a band member
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateBandMemberRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut member = load(&state.storage.band_members, &id, "Band member not found").await?;
+        check_site_permission(&principal, Permission::EditSite, &member.site_id)?;
+
+        if let Some(name) = update_req.name {
+            member.name = name;
+        }
+        if let Some(role) = update_req.role {
+            member.role = role;
+        }
+        if let Some(bio) = update_req.bio {
+            member.bio = Some(bio);
+        }
+        if let Some(photo_id) = update_req.photo_id {
+            member.photo_id = Some(photo_id);
+        }
+        if let Some(email) = update_req.email {
+            member.email = Some(normalize_email(&email));
+        }
+        if let Some(display_order) = update_req.display_order {
+            member.display_order = display_order;
+        }
+
+        state.storage.band_members.put(&member).await?;
+        Ok(json_response(&member))
+    }
+
+    /// DELETE /api/members/:id - Remove a band member
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let member = load(&state.storage.band_members, &id, "Band member not found").await?;
+        check_site_permission(&principal, Permission::EditSite, &member.site_id)?;
+
+        state.storage.band_members.delete(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
+
+// ============================================================================
+// Sites Handlers
+// ============================================================================
+
+pub mod sites {
+    use super::*;
+
+    /// GET /api/sites - List sites (see `query`). Members and editors of a
+    /// site see it in full; everyone else only sees active sites, as
+    /// `PublicSite`s.
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let query = ListQuery::from_params(&params)?;
+        let filter = visible(principal.as_ref(), query.storage_filter(&read_scope(principal.as_ref())));
+        let page = query.fetch(state.storage.sites.as_ref(), filter).await?;
+        Ok(json_response(&page.map(|site| view(site, principal.as_ref()))))
+    }
+
+    /// GET /api/site - The site this request is for (see `tenant`), as the
+    /// caller may see it
+    pub async fn current(principal: Option<Principal>, site: SiteContext) -> HandlerResult {
+        shown(site.site, principal.as_ref())
+    }
+
+    /// GET /api/sites/:site_id - Get a specific site, as the caller may see it
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Option<Principal>,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let site = load_readable(&state.storage.sites, &id, principal.as_ref(), None, "Site not found").await?;
+        shown(site, principal.as_ref())
+    }
+
+    /// Helper: Narrow `filter` to active sites and those the caller belongs
+    /// to or may edit
+    fn visible(principal: Option<&Principal>, filter: Filter) -> Filter {
+        let mut alternatives = vec![Filter::all().field("status", json!(SiteStatus::Active))];
+        if let Some(principal) = principal {
+            match principal.sites_with(Permission::EditSite) {
+                SiteScope::All => return filter,
+                managed => alternatives.push(Filter::scope(&managed)),
+            }
+            let user_id = principal.user_id();
+            let site_ids = principal.user.roles.iter().filter_map(|role| role.site_id()).map(str::to_string).collect();
+            alternatives.push(Filter::all().field("ownerId", user_id));
+            alternatives.push(Filter::all().contains("memberIds", user_id));
+            alternatives.push(Filter::scope(&SiteScope::Sites { site_ids }));
+        }
+        filter.any(alternatives)
+    }
+
+    /// Helper: The site in full for its members and editors, its public
+    /// face for everyone else
+    fn view(site: Site, principal: Option<&Principal>) -> SiteView {
+        let full = galleries::is_member(principal, Some(&site))
+            || principal.is_some_and(|p| p.has_site_permission(Permission::EditSite, &site.id));
+        if full { SiteView::Full(site) } else { SiteView::Public(site.public()) }
+    }
+
+    /// Helper: Respond with a site as the caller may see it; inactive sites
+    /// are missing to visitors
+    fn shown(site: Site, principal: Option<&Principal>) -> HandlerResult {
+        let active = site.status == SiteStatus::Active;
+        match view(site, principal) {
+            SiteView::Public(_) if !active => Err(ApiErrorKind::NotFound("Site not found".to_string()).into()),
+            view => Ok(json_response(&view)),
+        }
+    }
+
+    /// POST /api/sites - Create a site owned by the caller
+    ///
+    /// A new site is outside every site scope, so this needs `CreateSite` globally.
+    pub async fn create(State(state): State<ApiState>, principal: Principal, body: Bytes) -> HandlerResult {
+        if !principal.has_global_permission(Permission::CreateSite) {
+            return Err(ApiErrorKind::Forbidden.into());
+        }
+
+        let create_req: CreateSiteRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        if state.storage.sites.find(&Filter::all().field("slug", create_req.slug.as_str())).await?.is_some() {
+            return Err(ApiErrorKind::ValidationError(format!("Slug {} is already taken", create_req.slug)).into());
+        }
+        let domain = match create_req.domain {
+            Some(domain) => Some(check_domain(&state, &domain, None).await?),
+            None => None,
+        };
+
+        let user_id = principal.user_id().to_string();
+        let site = Site {
+            id: uuid::Uuid::new_v4().to_string(),
+            slug: create_req.slug,
+            name: create_req.name,
+            domain,
+            description: create_req.description,
+            owner_id: user_id.clone(),
+            member_ids: vec![user_id],
+            theme: create_req.theme.unwrap_or_else(|| "default".to_string()),
+            config: json!({}),
+            status: SiteStatus::Active,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+
+        state.storage.sites.put(&site).await?;
+        Ok(json_response(&site))
+    }
+
+    /// PUT /api/sites/:site_id - Update a site's settings
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateSiteRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut site = load(&state.storage.sites, &id, "Site not found").await?;
+        authorize_resource(&state, &principal, Permission::EditSite, "site", (&id, &id)).await?;
+
+        if let Some(name) = update_req.name {
+            site.name = name;
+        }
+        if let Some(domain) = update_req.domain {
+            site.domain = Some(check_domain(&state, &domain, Some(&id)).await?);
+        }
+        if let Some(description) = update_req.description {
+            site.description = Some(description);
+        }
+        if let Some(theme) = update_req.theme {
+            site.theme = theme;
+        }
+        if let Some(config) = update_req.config {
+            site.config = config;
+        }
+        if let Some(status) = update_req.status {
+            site.status = status;
+        }
+
+        state.storage.sites.put(&site).await?;
+        Ok(json_response(&site))
+    }
+
+    /// DELETE /api/sites/:site_id - Delete a site
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        load(&state.storage.sites, &id, "Site not found").await?;
+        authorize_resource(&state, &principal, Permission::DeleteSite, "site", (&id, &id)).await?;
+
+        state.storage.sites.delete(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// GET /api/sites/:site_id/members - List a site's members
+    pub async fn members(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        check_site_permission(&principal, Permission::AssignRoles, &id)?;
+
+        let site = load(&state.storage.sites, &id, "Site not found").await?;
+        let mut members = Vec::new();
+        for user_id in &site.member_ids {
+            if let Some(user) = state.storage.users.get(user_id).await? {
+                members.push(user);
+            }
+        }
+        Ok(json_response(&members))
+    }
+
+    /// POST /api/sites/:site_id/members - Add a user to a site
+    pub async fn add_member(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_site_permission(&principal, Permission::AssignRoles, &id)?;
+
+        let add_req: AddSiteMemberRequest = parse_json(&body)?;
+
+        if let Err(errors) = add_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut site = load(&state.storage.sites, &id, "Site not found").await?;
+        load(&state.storage.users, &add_req.user_id, "User not found").await?;
+
+        if !site.member_ids.contains(&add_req.user_id) {
+            site.member_ids.push(add_req.user_id);
+            state.storage.sites.put(&site).await?;
+        }
+        Ok(json_response(&site))
+    }
+
+    /// DELETE /api/sites/:site_id/members/:user_id - Remove a user from a site
+    ///
+    /// The owner can't be removed.
+    pub async fn remove_member(
+        State(state): State<ApiState>,
+        Path((id, user_id)): Path<(String, String)>,
+        principal: Principal,
+    ) -> HandlerResult {
+        check_site_permission(&principal, Permission::AssignRoles, &id)?;
+
+        let mut site = load(&state.storage.sites, &id, "Site not found").await?;
+        if site.owner_id == user_id {
+            return Err(ApiErrorKind::ValidationError("The site owner can't be removed".to_string()).into());
+        }
+        if !site.member_ids.contains(&user_id) {
+            return Err(ApiErrorKind::NotFound("Member not found".to_string()).into());
+        }
+
+        site.member_ids.retain(|m| *m != user_id);
+        state.storage.sites.put(&site).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// Helper: Normalize a custom domain and require it to be free.
+    ///
+    /// Domains under the shared parent domain are reserved for default domains.
+    async fn check_domain(
+        state: &ApiState,
+        domain: &str,
+        site_id: Option<&str>,
+    ) -> std::result::Result<String, ApiErrorKind> {
+        let domain = tenant::normalize_domain(domain);
+        let reserved = domain.strip_suffix(SITE_DOMAIN_SUFFIX).is_some_and(|rest| rest.is_empty() || rest.ends_with('.'));
+        if domain.is_empty() || reserved {
+            return Err(ApiErrorKind::ValidationError(format!("Invalid custom domain: {}", domain)));
+        }
+        let owner = state.storage.sites.find(&Filter::all().field("domain", domain.as_str())).await?;
+        if owner.is_some_and(|s| Some(s.id.as_str()) != site_id) {
+            return Err(ApiErrorKind::ValidationError(format!("Domain {} is already taken", domain)));
+        }
+        Ok(domain)
+    }
+}
+
+// ============================================================================
+// Contact Form Handlers
+// ============================================================================
+
+pub mod contact {
+    use super::*;
+
+    /// GET /api/contact - List submissions on sites where the caller has `ViewEmailLogs`
+    ///
+    /// `status=read` / `status=unread` filters on the read flag.
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Principal,
+        site: Option<SiteContext>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::ViewEmailLogs)?;
+
+        let query = list_query(&params, site.as_ref())?;
+        let filter = query.storage_filter(&principal.sites_with(Permission::ViewEmailLogs));
+        Ok(json_response(&query.fetch(state.storage.contact_submissions.as_ref(), filter).await?))
+    }
+
+    /// GET /api/contact/:id - Get a specific submission
+    pub async fn get(
+        State(state): State<ApiState>,
+        principal: Principal,
+        Path(id): Path<String>,
+    ) -> HandlerResult {
+        let submission = load(&state.storage.contact_submissions, &id, "Submission not found").await?;
+        check_site_permission(&principal, Permission::ViewEmailLogs, &submission.site_id)?;
+        Ok(json_response(&submission))
+    }
+
+    /// POST /api/contact - Submit the contact form (public)
+    pub async fn create(State(state): State<ApiState>, site: SiteContext, body: Bytes) -> HandlerResult {
+        let create_req: CreateContactSubmissionRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let submission = ContactSubmission {
+            id: uuid::Uuid::new_v4().to_string(),
+            site_id: site.id().to_string(),
+            name: create_req.name,
+            email: normalize_email(&create_req.email),
+            subject: create_req.subject,
+            message: create_req.message,
+            submitted_at: chrono::Utc::now().timestamp(),
+            is_read: false,
+        };
+
+        state.storage.contact_submissions.put(&submission).await?;
+        Ok((StatusCode::CREATED, axum::Json(json!({ "id": submission.id }))).into_response())
+    }
+
+    /// PUT /api/contact/:id - Mark a submission read or unread
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        let update_req: UpdateContactSubmissionRequest = parse_json(&body)?;
+
+        let mut submission = load(&state.storage.contact_submissions, &id, "Submission not found").await?;
+        check_site_permission(&principal, Permission::ViewEmailLogs, &submission.site_id)?;
+
+        if let Some(is_read) = update_req.is_read {
+            submission.is_read = is_read;
+        }
+
+        state.storage.contact_submissions.put(&submission).await?;
+        Ok(json_response(&submission))
+    }
+
+    /// DELETE /api/contact/:id - Delete a submission
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        let submission = load(&state.storage.contact_submissions, &id, "Submission not found").await?;
+        check_site_permission(&principal, Permission::ViewEmailLogs, &submission.site_id)?;
+
+        state.storage.contact_submissions.delete(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
+
+// ============================================================================
+// Access Control Handlers
+// ============================================================================
+
+pub mod acl {
+    use super::*;
+
+    /// Site a resource belongs to, or `None` if it doesn't exist
+    async fn resource_site(state: &ApiState, resource_type: &str, resource_id: &str) -> std::result::Result<Option<String>, ApiErrorKind> {
+        async fn site_of<T: storage::Entity>(repo: &Arc<dyn storage::Repository<T>>, id: &str) -> std::result::Result<Option<String>, ApiErrorKind> {
+            Ok(repo.get(id).await?.and_then(|record| record.site_id().map(str::to_string)))
+        }
+
+        let storage = &state.storage;
+        match resource_type {
+            "site" => site_of(&storage.sites, resource_id).await,
+            "show" => site_of(&storage.shows, resource_id).await,
+            "song" => site_of(&storage.songs, resource_id).await,
+            "post" => site_of(&storage.posts, resource_id).await,
+            "photo" => site_of(&storage.photos, resource_id).await,
+            "gallery" => site_of(&storage.galleries, resource_id).await,
+            "video" => site_of(&storage.videos, resource_id).await,
+            "setlist" => site_of(&storage.setlists, resource_id).await,
+            _ => Ok(None),
+        }
+    }
+
+    /// Check if the caller manages access on `site_id`; entries whose
+    /// resource is gone are left to global admins
+    fn manages(principal: &Principal, site_id: Option<&str>) -> bool {
+        match site_id {
+            Some(site_id) => principal.has_site_permission(Permission::AssignRoles, site_id),
+            None => principal.has_global_permission(Permission::AssignRoles),
+        }
+    }
+
+    /// GET /api/acl - Look up access entries by user and/or resource
+    ///
+    /// Callers see their own entries, plus entries on sites where they hold
+    /// `AssignRoles`.
+    pub async fn list(
+        State(state): State<ApiState>,
+        principal: Principal,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> HandlerResult {
+        let user_id = match params.get("userId") {
+            _ if !principal.has_permission(Permission::AssignRoles) => Some(principal.user_id()),
+            Some(user_id) => Some(user_id.as_str()),
+            None => None,
+        };
+
+        let mut filter = user_id.map(Filter::user).unwrap_or_default();
+        if let Some(resource_type) = params.get("resourceType") {
+            filter = filter.field("resourceType", resource_type.as_str());
+        }
+        if let Some(resource_id) = params.get("resourceId") {
+            filter = filter.field("resourceId", resource_id.as_str());
+        }
+
+        let mut entries = Vec::new();
+        for entry in state.storage.access_entries.list(&filter).await? {
+            if entry.user_id == principal.user_id()
+                || manages(&principal, resource_site(&state, &entry.resource_type, &entry.resource_id).await?.as_deref())
+            {
+                entries.push(entry);
+            }
+        }
+
+        Ok(json_response(&entries))
+    }
+
+    /// POST /api/acl - Grant a user permissions on one resource
+    ///
+    /// Requires `AssignRoles` on the resource's site, and only passes on
+    /// permissions the grantor holds there.
+    pub async fn grant(State(state): State<ApiState>, principal: Principal, body: Bytes) -> HandlerResult {
+        check_permission(&principal, Permission::AssignRoles)?;
+
+        let grant_req: GrantAccessRequest = parse_json(&body)?;
+
+        if let Err(errors) = grant_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+        if !RESOURCE_TYPES.contains(&grant_req.resource_type.as_str()) {
+            return Err(ApiErrorKind::ValidationError(format!(
+                "Unknown resource type: {}", grant_req.resource_type
+            )).into());
+        }
 
-        // Add to state
-        let mut state = ctx.data.app_state.write().await;
-        state.videos.insert(id.clone(), video.clone());
+        let site_id = resource_site(&state, &grant_req.resource_type, &grant_req.resource_id)
+            .await?
+            .ok_or_else(|| ApiErrorKind::NotFound("Resource not found".to_string()))?;
+        if !principal.has_site_permission(Permission::AssignRoles, &site_id)
+            || !grant_req.permissions.iter().all(|p| principal.has_site_permission(*p, &site_id))
+        {
+            return Err(ApiErrorKind::Forbidden.into());
+        }
+
+        if state.storage.users.get(&grant_req.user_id).await?.is_none() {
+            return Err(ApiErrorKind::NotFound("User not found".to_string()).into());
+        }
+
+        // A repeat grant for the same user and resource extends the existing entry
+        let existing = state.storage.access_entries
+            .find(&Filter::user(&grant_req.user_id)
+                .field("resourceType", grant_req.resource_type.as_str())
+                .field("resourceId", grant_req.resource_id.as_str()))
+            .await?;
+        let entry = match existing {
+            Some(mut entry) => {
+                entry.permissions.extend(grant_req.permissions);
+                entry.granted_by = principal.user_id().to_string();
+                entry.granted_at = Utc::now().timestamp();
+                entry
+            }
+            None => ResourceAccess {
+                id: uuid::Uuid::new_v4().to_string(),
+                resource_type: grant_req.resource_type,
+                resource_id: grant_req.resource_id,
+                user_id: grant_req.user_id,
+                permissions: grant_req.permissions.into_iter().collect(),
+                granted_by: principal.user_id().to_string(),
+                granted_at: Utc::now().timestamp(),
+            },
+        };
+        state.storage.access_entries.put(&entry).await?;
+
+        Ok(json_response(&entry))
+    }
+
+    /// DELETE /api/acl/:id - Revoke an access entry
+    pub async fn revoke(
+        State(state): State<ApiState>,
+        Path(id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::AssignRoles)?;
+
+        let entry = load(&state.storage.access_entries, &id, "Access entry not found").await?;
+        if !manages(&principal, resource_site(&state, &entry.resource_type, &entry.resource_id).await?.as_deref()) {
+            return Err(ApiErrorKind::NotFound("Access entry not found".to_string()).into());
+        }
+        state.storage.access_entries.delete(&id).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
+
+// ============================================================================
+// Role Handlers
+// ============================================================================
+
+pub mod roles {
+    use super::*;
+
+    /// GET /api/sites/:site_id/roles - List a site's custom roles
+    pub async fn list(
+        State(state): State<ApiState>,
+        Path(site_id): Path<String>,
+        principal: Principal,
+    ) -> HandlerResult {
+        check_site_permission(&principal, Permission::AssignRoles, &site_id)?;
+
+        let roles = state.storage.role_definitions.list(&Filter::site(&site_id)).await?;
+        Ok(json_response(&roles))
+    }
+
+    /// Helper: Require the caller to hold every permission put into a role
+    /// on its site, as `acl::grant` does for grants
+    fn check_grantable<'a>(
+        principal: &Principal,
+        permissions: impl IntoIterator<Item = &'a Permission>,
+        site_id: &str,
+    ) -> std::result::Result<(), ApiErrorKind> {
+        if permissions.into_iter().all(|p| principal.has_site_permission(*p, site_id)) {
+            Ok(())
+        } else {
+            Err(ApiErrorKind::Forbidden)
+        }
+    }
+
+    /// POST /api/sites/:site_id/roles - Define a custom role
+    ///
+    /// Only permissions the caller holds on the site may go into it.
+    pub async fn create(
+        State(state): State<ApiState>,
+        Path(site_id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_site_permission(&principal, Permission::AssignRoles, &site_id)?;
+
+        let create_req: CreateRoleRequest = parse_json(&body)?;
+
+        if let Err(errors) = create_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        check_grantable(&principal, &create_req.permissions, &site_id)?;
+
+        let now = Utc::now().timestamp();
+        let role = RoleDefinition {
+            id: uuid::Uuid::new_v4().to_string(),
+            site_id,
+            name: create_req.name,
+            description: create_req.description,
+            permissions: create_req.permissions.into_iter().collect(),
+            created_at: now,
+            updated_at: now,
+        };
+
+        state.storage.role_definitions.put(&role).await?;
+        Ok(json_response(&role))
+    }
+
+    /// PUT /api/sites/:site_id/roles/:id - Update a custom role (with only
+    /// permissions the caller holds on the site)
+    pub async fn update(
+        State(state): State<ApiState>,
+        Path((site_id, id)): Path<(String, String)>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_site_permission(&principal, Permission::AssignRoles, &site_id)?;
+
+        let update_req: UpdateRoleRequest = parse_json(&body)?;
+
+        if let Err(errors) = update_req.validate() {
+            return Err(ApiErrorKind::ValidationError(format!("Validation failed: {:?}", errors)).into());
+        }
+
+        let mut role = state.storage.role_definitions.get(&id).await?
+            .filter(|r| r.site_id == site_id)
+            .ok_or_else(|| ApiErrorKind::NotFound("Role not found".to_string()))?;
+
+        if let Some(name) = update_req.name {
+            role.name = name;
+        }
+        if let Some(description) = update_req.description {
+            role.description = Some(description);
+        }
+        if let Some(permissions) = update_req.permissions {
+            check_grantable(&principal, &permissions, &site_id)?;
+            role.permissions = permissions.into_iter().collect();
+        }
+        role.updated_at = Utc::now().timestamp();
+
+        state.storage.role_definitions.put(&role).await?;
+        Ok(json_response(&role))
+    }
+
+    /// DELETE /api/sites/:site_id/roles/:id - Delete a custom role and unassign it
+    pub async fn delete(
+        State(state): State<ApiState>,
+        Path((site_id, id)): Path<(String, String)>,
+        principal: Principal,
+    ) -> HandlerResult {
+        check_site_permission(&principal, Permission::AssignRoles, &site_id)?;
+
+        if state.storage.role_definitions.get(&id).await?.filter(|r| r.site_id == site_id).is_none() {
+            return Err(ApiErrorKind::NotFound("Role not found".to_string()).into());
+        }
+        state.storage.role_definitions.delete(&id).await?;
 
-        Response::from_json(&video)
+        let role = Role::Custom { role_id: id, site_id };
+        for mut user in state.storage.users.list(&Filter::all().contains("roles", json!(role))).await? {
+            user.roles.retain(|r| *r != role);
+            state.storage.users.put(&user).await?;
+        }
+
+        Ok(StatusCode::NO_CONTENT.into_response())
     }
 
-    /// Helper: Extract video ID from URL
-    fn extract_video_id(url: &str) -> String {
-        // Simple extraction - in production would use proper URL parsing
-        url.split('/').last().unwrap_or_default().to_string()
+    /// PUT /api/users/:id/roles - Replace a user's roles
+    ///
+    /// Every role added or removed needs `AssignRoles` in that role's scope:
+    /// on its site for site roles, globally for global roles. Custom roles
+    /// added also need every permission they grant.
+    pub async fn assign(
+        State(state): State<ApiState>,
+        Path(user_id): Path<String>,
+        principal: Principal,
+        body: Bytes,
+    ) -> HandlerResult {
+        check_permission(&principal, Permission::AssignRoles)?;
+
+        let assign_req: AssignRolesRequest = parse_json(&body)?;
+
+        let mut user = state.storage.users.get(&user_id).await?
+            .ok_or_else(|| ApiErrorKind::NotFound("User not found".to_string()))?;
+
+        for role in &assign_req.roles {
+            if let Role::Custom { role_id, site_id } = role {
+                let definition = state.storage.role_definitions.get(role_id).await?.filter(|d| &d.site_id == site_id)
+                    .ok_or_else(|| ApiErrorKind::ValidationError(format!("Unknown role {} for site {}", role_id, site_id)))?;
+                if !user.roles.contains(role) {
+                    check_grantable(&principal, &definition.permissions, site_id)?;
+                }
+            }
+        }
+
+        let changed = assign_req.roles.iter().filter(|r| !user.roles.contains(r))
+            .chain(user.roles.iter().filter(|r| !assign_req.roles.contains(r)));
+        for role in changed {
+            let allowed = match role.site_id() {
+                Some(site_id) => principal.has_site_permission(Permission::AssignRoles, site_id),
+                None => principal.has_global_permission(Permission::AssignRoles),
+            };
+            if !allowed {
+                return Err(ApiErrorKind::Forbidden.into());
+            }
+        }
+
+        user.roles = assign_req.roles;
+        state.storage.users.put(&user).await?;
+
+        Ok(json_response(&user))
     }
 }
 
@@ -797,32 +4211,1654 @@ pub mod videos {
 
 pub mod middleware {
     use super::*;
+    use axum::{extract::Request, middleware::Next};
+
+    /// Extract the bearer token from the Authorization header
+    pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+    }
 
-    /// Authentication middleware - validates JWT token
-    pub fn auth(req: &mut Request) -> worker::Result<Option<String>> {
-        let auth_header_result = req.headers().get("Authorization");
+    /// Routes that may be called without a bearer token
+    fn is_public(method: &Method, path: &str) -> bool {
+        matches!(*method, Method::GET | Method::HEAD)
+            || matches!(path, "/api/auth/login" | "/api/auth/register" | "/api/auth/refresh" | "/api/contact")
+            || is_unlock(path)
+    }
 
-        let auth_header = match auth_header_result {
-            Ok(Some(header)) => header.to_string(),
-            Ok(None) => return Ok(None),
-            Err(_) => return Ok(None),
-        };
+    /// `/api/galleries/:id/unlock` and `/api/videos/:id/unlock`, where
+    /// visitors trade a password for an access token
+    fn is_unlock(path: &str) -> bool {
+        path.strip_prefix("/api/galleries/")
+            .or_else(|| path.strip_prefix("/api/videos/"))
+            .and_then(|rest| rest.strip_suffix("/unlock"))
+            .is_some_and(|id| !id.is_empty() && !id.contains('/'))
+    }
+
+    /// Authentication middleware - validates the bearer token once per request.
+    ///
+    /// A valid token attaches a `Principal` to the request; an invalid or
+    /// revoked token is always a 401, and protected routes require a token.
+    pub async fn auth(State(state): State<ApiState>, mut req: Request, next: Next) -> Response {
+        match bearer_token(req.headers()) {
+            Some(token) => match authenticate(token, &state).await {
+                Ok(principal) => {
+                    req.extensions_mut().insert(principal);
+                }
+                Err(e) => return error_response(e),
+            },
+            None if !is_public(req.method(), req.uri().path()) => {
+                return error_response(ApiErrorKind::Unauthorized);
+            }
+            None => {}
+        }
+
+        next.run(req).await
+    }
 
-        if !auth_header.starts_with("Bearer ") {
-            return Ok(None);
+    /// Site middleware - resolves the site a request is for (see `tenant`)
+    /// and attaches it as a `SiteContext`
+    pub async fn site(State(state): State<ApiState>, mut req: Request, next: Next) -> Response {
+        match tenant::resolve(&state.storage, req.headers()).await {
+            Ok(Some(site)) => {
+                req.extensions_mut().insert(SiteContext { site });
+            }
+            Ok(None) => {}
+            Err(e) => return error_response(e),
         }
 
-        let token = auth_header[7..].to_string();
-        // TODO: Validate JWT token
-        Ok(Some(token))
+        next.run(req).await
     }
 
-    /// CORS middleware - adds CORS headers
-    pub fn cors(res: &mut Response) -> worker::Result<()> {
+    /// CORS middleware - answers preflight requests and adds CORS headers
+    pub async fn cors(req: Request, next: Next) -> Response {
+        let mut res = if req.method() == Method::OPTIONS {
+            preflight()
+        } else {
+            next.run(req).await
+        };
+
         let headers = res.headers_mut();
-        headers.set("Access-Control-Allow-Origin", "*")?;
-        headers.set("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS")?;
-        headers.set("Access-Control-Allow-Headers", "Content-Type, Authorization")?;
-        Ok(())
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("GET, POST, PUT, DELETE, OPTIONS"),
+        );
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            HeaderValue::from_static("Content-Type, Authorization, X-Site-Id, X-Gallery-Token, X-Video-Token"),
+        );
+        res
+    }
+
+    /// Preflight response for OPTIONS requests
+    pub fn preflight() -> Response {
+        (
+            StatusCode::NO_CONTENT,
+            [(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static("86400"))],
+        )
+            .into_response()
+    }
+}
+
+// ============================================================================
+// Router
+// ============================================================================
+
+pub mod router {
+    use super::*;
+    use axum::extract::DefaultBodyLimit;
+    use axum::middleware::{from_fn, from_fn_with_state};
+
+    /// Build the route table for every endpoint advertised by `root_handler`.
+    ///
+    /// The same router is served by the Workers entrypoint and by `api-dev`.
+    pub fn build(state: ApiState) -> Router {
+        Router::new()
+            .route("/", get(root_handler))
+            .route("/health", get(health_handler))
+            .route("/api/auth/login", post(auth::login))
+            .route("/api/auth/register", post(auth::register))
+            .route("/api/auth/change-password", post(auth::change_password))
+            .route("/api/auth/refresh", post(auth::refresh))
+            .route("/api/auth/logout", post(auth::logout))
+            .route("/api/users/:id/sessions", axum::routing::delete(auth::revoke_user_sessions))
+            .route("/api/sites/:site_id/roles", get(roles::list).post(roles::create))
+            .route("/api/sites/:site_id/roles/:id", axum::routing::put(roles::update).delete(roles::delete))
+            .route("/api/users/:id/roles", axum::routing::put(roles::assign))
+            .route("/api/acl", get(acl::list).post(acl::grant))
+            .route("/api/acl/:id", axum::routing::delete(acl::revoke))
+            .route("/api/shows", get(shows::list).post(shows::create))
+            .route("/api/shows/:id", get(shows::get).put(shows::update).delete(shows::delete))
+            .route("/api/shows/:id/setlist", get(shows::setlist))
+            .route("/api/shows/:id/revisions", get(revisions::list::<Show>))
+            .route("/api/shows/:id/revisions/compare", get(revisions::compare::<Show>))
+            .route("/api/shows/:id/revisions/:number", get(revisions::get::<Show>))
+            .route("/api/shows/:id/revisions/:number/rollback", post(revisions::rollback::<Show>))
+            .route("/api/shows/:id/sets", axum::routing::put(shows::reorder_sets))
+            .route("/api/songs", get(songs::list).post(songs::create))
+            .route("/api/songs/:id", get(songs::get).put(songs::update).delete(songs::delete))
+            .route("/api/posts", get(posts::list).post(posts::create))
+            .route("/api/posts/:id", get(posts::get).put(posts::update).delete(posts::delete))
+            .route("/api/posts/:id/publish", post(posts::publish))
+            .route("/api/posts/:id/unpublish", post(posts::unpublish))
+            .route("/api/posts/:id/schedule", post(posts::schedule))
+            .route("/api/posts/:id/archive", post(posts::archive))
+            .route("/api/posts/:id/revisions", get(revisions::list::<BlogPost>))
+            .route("/api/posts/:id/revisions/compare", get(revisions::compare::<BlogPost>))
+            .route("/api/posts/:id/revisions/:number", get(revisions::get::<BlogPost>))
+            .route("/api/posts/:id/revisions/:number/rollback", post(revisions::rollback::<BlogPost>))
+            .route("/api/photos", get(photos::list).post(photos::create))
+            .route("/api/photos/:id", get(photos::get).put(photos::update).delete(photos::delete))
+            .route("/api/photos/:id/derivatives", post(photos::derive))
+            .route("/api/videos", get(videos::list).post(videos::create))
+            .route("/api/videos/:id", get(videos::get).put(videos::update).delete(videos::delete))
+            .route("/api/videos/:id/unlock", post(videos::unlock))
+            .route("/api/uploads", post(uploads::create))
+            .route("/api/uploads/:id", get(uploads::get).delete(uploads::abort))
+            .route(
+                "/api/uploads/:id/parts/:number",
+                axum::routing::put(uploads::upload_part).layer(DefaultBodyLimit::max(uploads::MAX_PART_BYTES)),
+            )
+            .route("/api/uploads/:id/complete", post(uploads::complete))
+            .route("/api/media/*key", get(uploads::serve))
+            .route("/api/galleries", get(galleries::list).post(galleries::create))
+            .route("/api/galleries/:id", get(galleries::get).put(galleries::update).delete(galleries::delete))
+            .route("/api/galleries/:id/images", get(galleries::images))
+            .route("/api/galleries/:id/photos", post(galleries::add_photos).put(galleries::reorder_photos))
+            .route("/api/galleries/:id/photos/:photo_id", axum::routing::delete(galleries::remove_photo))
+            .route("/api/galleries/:id/cover", axum::routing::put(galleries::set_cover))
+            .route("/api/galleries/:id/unlock", post(galleries::unlock))
+            .route("/api/setlists", get(setlists::list).post(setlists::create))
+            .route("/api/setlists/:id", get(setlists::get).put(setlists::update).delete(setlists::delete))
+            .route("/api/setlists/:id/export", get(setlists::export))
+            .route("/api/setlists/:id/songs", axum::routing::put(setlists::reorder_songs))
+            .route("/api/setlists/:id/songs/move", post(setlists::move_song))
+            .route("/api/members", get(members::list).post(members::create))
+            .route("/api/members/:id", get(members::get).put(members::update).delete(members::delete))
+            .route("/api/site", get(sites::current))
+            .route("/api/sites", get(sites::list).post(sites::create))
+            .route("/api/sites/:site_id/members", get(sites::members).post(sites::add_member))
+            .route("/api/sites/:site_id/members/:user_id", axum::routing::delete(sites::remove_member))
+            .route("/api/sites/:site_id", get(sites::get).put(sites::update).delete(sites::delete))
+            .route("/api/contact", get(contact::list).post(contact::create))
+            .route("/api/contact/:id", get(contact::get).put(contact::update).delete(contact::delete))
+            .method_not_allowed_fallback(method_not_allowed)
+            .route_layer(from_fn_with_state(state.clone(), middleware::auth))
+            .route_layer(from_fn_with_state(state.clone(), middleware::site))
+            .fallback(not_found)
+            .layer(from_fn(middleware::cors))
+            .with_state(state)
+    }
+
+    /// Fallback for paths with no route
+    async fn not_found() -> Response {
+        error_response(ApiErrorKind::NotFound("No route for this path".to_string()))
+    }
+
+    /// Fallback for known paths called with an unsupported method
+    async fn method_not_allowed() -> Response {
+        error_response(ApiErrorKind::MethodNotAllowed)
+    }
+}
+
+// ============================================================================
+// Workers Entrypoint
+// ============================================================================
+
+/// API state shared by every request served from this isolate
+static STATE: OnceLock<ApiState> = OnceLock::new();
+
+/// API state for this isolate, created from the bindings on first use
+fn isolate_state(env: &worker::Env) -> std::result::Result<ApiState, ConfigError> {
+    match STATE.get() {
+        Some(state) => Ok(state.clone()),
+        None => ApiState::from_env(env).map(|state| STATE.get_or_init(|| state).clone()),
+    }
+}
+
+/// Whether this isolate has hashed legacy passwords yet
+static LEGACY_PASSWORDS_HASHED: AtomicBool = AtomicBool::new(false);
+
+/// `isolate_state`, first hashing any passwords migrations left in plain
+/// text (once per isolate, retried on failure)
+async fn started_state(env: &worker::Env) -> std::result::Result<ApiState, ConfigError> {
+    let state = isolate_state(env)?;
+    if !LEGACY_PASSWORDS_HASHED.swap(true, Ordering::AcqRel) {
+        match state.hash_legacy_passwords().await {
+            Ok(hashed) if hashed > 0 => tracing::info!("hashed {} legacy passwords", hashed),
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("hashing legacy passwords failed: {}", e);
+                LEGACY_PASSWORDS_HASHED.store(false, Ordering::Release);
+            }
+        }
+    }
+    Ok(state)
+}
+
+/// Workers fetch entrypoint
+#[worker::event(fetch)]
+pub async fn main(
+    req: worker::HttpRequest,
+    env: worker::Env,
+    _ctx: worker::Context,
+) -> worker::Result<Response> {
+    use tower_service::Service;
+
+    let state = match started_state(&env).await {
+        Ok(state) => state,
+        // Refuse to serve rather than mint tokens with a bad key
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Ok(error_response(ApiErrorKind::Internal("API is not configured".to_string())));
+        }
+    };
+    match router::build(state).call(req).await {
+        Ok(res) => Ok(res),
+        Err(never) => match never {},
+    }
+}
+
+/// Workers cron entrypoint (`[triggers]` in wrangler.toml)
+#[worker::event(scheduled)]
+pub async fn cron(event: worker::ScheduledEvent, env: worker::Env, _ctx: worker::ScheduleContext) {
+    let state = match started_state(&env).await {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    };
+    scheduled(&state, (event.schedule() / 1000.0) as i64).await;
+}
+
+/// Most records each task of one scheduled run handles; the rest wait for
+/// the next run
+const CRON_BATCH: u64 = 100;
+
+/// Periodic housekeeping, run by the cron trigger (and api-dev's ticker):
+/// publishes scheduled posts that are due, clears out expired uploads,
+/// saves fresh renderings of stale posts and drops expired sessions and
+/// revocation entries
+pub async fn scheduled(state: &ApiState, now: i64) {
+    match posts::publish_due(state, now, CRON_BATCH).await {
+        Ok(published) if !published.is_empty() => tracing::info!("published {} scheduled posts", published.len()),
+        Ok(_) => {}
+        Err(e) => tracing::error!("publishing scheduled posts failed: {}", e),
+    }
+    match uploads::abort_expired(state, now, CRON_BATCH).await {
+        Ok(aborted) if aborted > 0 => tracing::info!("aborted {} expired uploads", aborted),
+        Ok(_) => {}
+        Err(e) => tracing::error!("aborting expired uploads failed: {}", e),
+    }
+    match posts::render_stale(state, CRON_BATCH).await {
+        Ok(rendered) if rendered > 0 => tracing::info!("rendered {} stale posts", rendered),
+        Ok(_) => {}
+        Err(e) => tracing::error!("rendering stale posts failed: {}", e),
+    }
+    match state.storage.sessions.purge_expired(now, CRON_BATCH).await {
+        Ok(purged) if purged > 0 => tracing::info!("purged {} expired sessions and revocations", purged),
+        Ok(_) => {}
+        Err(e) => tracing::error!("purging expired sessions failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use sha2::Digest;
+    use tower::ServiceExt;
+    use web_nexus_contracts::media::MediaUpload;
+
+    fn test_state() -> ApiState {
+        ApiState::new(Storage::memory(), KeyRing::ephemeral()).with_bootstrap_token(BOOTSTRAP_TOKEN)
+    }
+
+    const BOOTSTRAP_TOKEN: &str = "bootstrap-secret";
+
+    async fn user_id_by_email(state: &ApiState, email: &str) -> String {
+        state.find_user_by_email(email).await.unwrap().unwrap().id
+    }
+
+    async fn send(method: Method, uri: &str, auth: Option<&str>) -> Response {
+        send_json(&test_state(), method, uri, auth, None).await
+    }
+
+    async fn send_json(
+        state: &ApiState,
+        method: Method,
+        uri: &str,
+        auth: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> Response {
+        dispatch(state, axum::http::Request::builder().method(method).uri(uri), auth, body).await
+    }
+
+    /// Send a request for one site, the way the CMS does (`X-Site-Id`)
+    async fn send_to_site(
+        state: &ApiState,
+        site: &str,
+        method: Method,
+        uri: &str,
+        auth: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> Response {
+        let req = axum::http::Request::builder().method(method).uri(uri).header(tenant::SITE_HEADER, site);
+        dispatch(state, req, auth, body).await
+    }
+
+    async fn dispatch(
+        state: &ApiState,
+        mut req: axum::http::request::Builder,
+        auth: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> Response {
+        if let Some(token) = auth {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+        router::build(state.clone())
+            .oneshot(req.body(body).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// Store a site whose ID and slug are both `id`
+    async fn add_site(state: &ApiState, id: &str) {
+        let site = Site {
+            id: id.to_string(),
+            slug: id.to_string(),
+            name: id.to_string(),
+            domain: None,
+            description: None,
+            owner_id: "owner".to_string(),
+            member_ids: vec!["owner".to_string()],
+            theme: "default".to_string(),
+            config: json!({}),
+            status: SiteStatus::Active,
+            created_at: 0,
+        };
+        state.storage.sites.put(&site).await.unwrap();
+    }
+
+    async fn body_json(res: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn error_code(res: Response) -> String {
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice::<ApiError>(&body).unwrap().code
+    }
+
+    #[tokio::test]
+    async fn test_health_has_cors_headers() {
+        let res = send(Method::GET, "/health", None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_path_is_json_404() {
+        let res = send(Method::GET, "/api/nope", None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(error_code(res).await, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_method_is_json_405() {
+        let state = test_state();
+        let token = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let res = send_json(&state, Method::PATCH, "/api/songs", Some(&token), None).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(error_code(res).await, "METHOD_NOT_ALLOWED");
+    }
+
+    #[tokio::test]
+    async fn test_preflight_skips_auth() {
+        let res = send(Method::OPTIONS, "/api/shows", None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(res.headers().contains_key(header::ACCESS_CONTROL_ALLOW_METHODS));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_allows_unlock_token_headers() {
+        let res = send(Method::OPTIONS, "/api/videos/v1", None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let allowed = res.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap().to_ascii_lowercase();
+        let allowed: Vec<&str> = allowed.split(',').map(str::trim).collect();
+        for name in ["authorization", "x-site-id", galleries::GALLERY_TOKEN_HEADER, videos::VIDEO_TOKEN_HEADER] {
+            assert!(allowed.contains(&name), "{} missing from {:?}", name, allowed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protected_route_requires_token() {
+        let res = send(Method::POST, "/api/shows", None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(error_code(res).await, "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_token_is_rejected_everywhere() {
+        // Presenting a bad token is a 401 even on routes that allow anonymous reads
+        for (method, uri) in [(Method::GET, "/api/shows"), (Method::POST, "/api/shows"), (Method::POST, "/api/auth/logout")] {
+            let res = send(method, uri, Some("not-a-jwt")).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(error_code(res).await, "UNAUTHORIZED");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_principal_is_attached_to_request() {
+        let state = test_state();
+        let token = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        add_site(&state, "site-1").await;
+        let show = json!({ "date": 1900000000, "venue": "The Roxy" });
+
+        let res = send_to_site(&state, "site-1", Method::POST, "/api/shows", Some(&token), Some(show)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let admin_id = user_id_by_email(&state, "admin@example.com").await;
+        assert_eq!(body_json(res).await["createdBy"], admin_id);
+    }
+
+    /// Register an active account with the given roles and return its access token
+    async fn login_as(state: &ApiState, email: &str, roles: Vec<Role>) -> String {
+        if state.storage.users.find(&Filter::all()).await.unwrap().is_none() {
+            login_admin(state).await;
+        }
+        let account = json!({ "email": email, "name": "Member", "password": "monsters-rock" });
+        send_json(state, Method::POST, "/api/auth/register", None, Some(account.clone())).await;
+        let mut user = state.find_user_by_email(email).await.unwrap().unwrap();
+        user.status = UserStatus::Active;
+        user.roles = roles;
+        state.storage.users.put(&user).await.unwrap();
+        let res = send_json(state, Method::POST, "/api/auth/login", None, Some(account)).await;
+        body_json(res).await["token"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_endpoints_check_role_permissions() {
+        let state = test_state();
+        let token = login_as(&state, "media@example.com", vec![Role::Media]).await;
+        add_site(&state, "site-1").await;
+
+        let show = json!({ "date": 1900000000, "venue": "The Roxy" });
+        let res = send_to_site(&state, "site-1", Method::POST, "/api/shows", Some(&token), Some(show)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let photo = json!({ "title": "Crowd", "url": "https://cdn.example.com/crowd.jpg" });
+        let res = send_to_site(&state, "site-1", Method::POST, "/api/photos", Some(&token), Some(photo)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_show_list_sorts_filters_and_pages() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        add_site(&state, "monsters").await;
+        add_site(&state, "other").await;
+        for (site, date, venue) in [("monsters", 300, "C"), ("monsters", 100, "A"), ("other", 200, "B"), ("monsters", 200, "D")] {
+            let show = json!({ "date": 1900000000 + date, "venue": venue });
+            send_to_site(&state, site, Method::POST, "/api/shows", Some(&admin), Some(show)).await;
+        }
+        let venues = |body: &serde_json::Value| -> Vec<String> {
+            body["data"].as_array().unwrap().iter().map(|s| s["venue"].as_str().unwrap().to_string()).collect()
+        };
+
+        let res = send_json(&state, Method::GET, "/api/shows?site_id=monsters&per_page=2", None, None).await;
+        let body = body_json(res).await;
+        assert_eq!(venues(&body), vec!["A", "D"]);
+        assert_eq!((body["page"].as_i64(), body["total"].as_i64()), (Some(1), Some(3)));
+
+        let uri = format!("/api/shows?site_id=monsters&per_page=2&cursor={}", body["nextCursor"].as_str().unwrap());
+        let body = body_json(send_json(&state, Method::GET, &uri, None, None).await).await;
+        assert_eq!(venues(&body), vec!["C"]);
+        assert!(body.get("nextCursor").is_none());
+
+        let res = send_json(&state, Method::GET, "/api/shows?sort=venue&order=desc&from=1900000200", None, None).await;
+        assert_eq!(venues(&body_json(res).await), vec!["D", "C", "B"]);
+
+        let res = send_json(&state, Method::GET, "/api/shows?page=0", None, None).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_site_editor_is_confined_to_its_site() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+        add_site(&state, "other-band").await;
+        let show = json!({ "date": 1900000000, "venue": "The Roxy" });
+
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/shows", Some(&editor), Some(show.clone())).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send_to_site(&state, "other-band", Method::POST, "/api/shows", Some(&editor), Some(show.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // Another band's show is invisible and can't be changed
+        let res = send_to_site(&state, "other-band", Method::POST, "/api/shows", Some(&admin), Some(show.clone())).await;
+        let other_id = body_json(res).await["id"].as_str().unwrap().to_string();
+        let uri = format!("/api/shows/{}", other_id);
+        let res = send_json(&state, Method::GET, &uri, Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "venue": "Hijacked" }))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::DELETE, &uri, Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = send_json(&state, Method::GET, "/api/shows", Some(&editor), None).await;
+        assert_eq!(body_json(res).await["total"], 1);
+        let res = send_json(&state, Method::GET, "/api/shows", None, None).await;
+        assert_eq!(body_json(res).await["total"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_access_entry_grants_edit_on_one_show() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let guest = login_as(&state, "photographer@example.com", vec![]).await;
+        let guest_id = user_id_by_email(&state, "photographer@example.com").await;
+
+        add_site(&state, "monsters").await;
+        let show = json!({ "date": 1900000000, "venue": "The Roxy" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/shows", Some(&admin), Some(show)).await;
+        let uri = format!("/api/shows/{}", body_json(res).await["id"].as_str().unwrap());
+        let edit = json!({ "venue": "The Whisky" });
+        let res = send_json(&state, Method::PUT, &uri, Some(&guest), Some(edit.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let grant = json!({
+            "userId": guest_id,
+            "resourceType": "show",
+            "resourceId": uri.trim_start_matches("/api/shows/"),
+            "permissions": ["editShow"]
+        });
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&guest), Some(grant.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&admin), Some(grant)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let acl_id = body_json(res).await["id"].as_str().unwrap().to_string();
+
+        let res = send_json(&state, Method::PUT, &uri, Some(&guest), Some(edit.clone())).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send_json(&state, Method::PUT, &uri, Some(&guest), Some(json!({ "venue": "" }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = send_json(&state, Method::DELETE, &uri, Some(&guest), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::GET, "/api/acl", Some(&guest), None).await;
+        assert_eq!(body_json(res).await.as_array().unwrap().len(), 1);
+
+        let res = send_json(&state, Method::DELETE, &format!("/api/acl/{}", acl_id), Some(&admin), None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = send_json(&state, Method::PUT, &uri, Some(&guest), Some(edit)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_access_grants_stay_on_the_grantors_site() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let role = json!({ "name": "Crew Chief", "permissions": ["assignRoles", "editShow"] });
+        let res = send_json(&state, Method::POST, "/api/sites/monsters/roles", Some(&admin), Some(role)).await;
+        let role_id = body_json(res).await["id"].as_str().unwrap().to_string();
+        let chief = login_as(&state, "chief@example.com", vec![Role::Custom { role_id, site_id: "monsters".to_string() }]).await;
+        login_as(&state, "roadie@example.com", vec![]).await;
+        let roadie_id = user_id_by_email(&state, "roadie@example.com").await;
+
+        let mut show_ids = Vec::new();
+        for site in ["monsters", "other-band"] {
+            add_site(&state, site).await;
+            let show = json!({ "date": 1900000000, "venue": "The Roxy" });
+            let res = send_to_site(&state, site, Method::POST, "/api/shows", Some(&admin), Some(show)).await;
+            show_ids.push(body_json(res).await["id"].as_str().unwrap().to_string());
+        }
+        let grant = |show_id: &str, permission: &str| json!({
+            "userId": roadie_id,
+            "resourceType": "show",
+            "resourceId": show_id,
+            "permissions": [permission]
+        });
+
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&chief), Some(grant(&show_ids[1], "editShow"))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&chief), Some(grant(&show_ids[0], "deleteShow"))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&chief), Some(grant("missing", "editShow"))).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&chief), Some(grant(&show_ids[0], "editShow"))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&admin), Some(grant(&show_ids[1], "editShow"))).await;
+        let other_acl = body_json(res).await["id"].as_str().unwrap().to_string();
+
+        let uri = format!("/api/acl?userId={}", roadie_id);
+        let res = send_json(&state, Method::GET, &uri, Some(&chief), None).await;
+        let entries = body_json(res).await;
+        assert_eq!(entries.as_array().unwrap().len(), 1);
+        assert_eq!(entries[0]["resourceId"], show_ids[0].as_str());
+        let res = send_json(&state, Method::DELETE, &format!("/api/acl/{}", other_acl), Some(&chief), None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = send_json(&state, Method::GET, &uri, Some(&admin), None).await;
+        assert_eq!(body_json(res).await.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_custom_site_role_grants_its_permissions() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let manager = login_as(&state, "manager@example.com", vec![]).await;
+        let manager_id = user_id_by_email(&state, "manager@example.com").await;
+
+        let role = json!({ "name": "Tour Manager", "permissions": ["createShow", "editShow", "sendEmail"] });
+        let res = send_json(&state, Method::POST, "/api/sites/monsters/roles", Some(&manager), Some(role.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/sites/monsters/roles", Some(&admin), Some(role)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let role_id = body_json(res).await["id"].as_str().unwrap().to_string();
+
+        let assign = json!({ "roles": [{ "custom": { "role_id": role_id, "site_id": "monsters" } }] });
+        let res = send_json(&state, Method::PUT, &format!("/api/users/{}/roles", manager_id), Some(&admin), Some(assign)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        add_site(&state, "monsters").await;
+        add_site(&state, "other-band").await;
+        let show = json!({ "date": 1900000000, "venue": "The Roxy" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/shows", Some(&manager), Some(show.clone())).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send_to_site(&state, "other-band", Method::POST, "/api/shows", Some(&manager), Some(show.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // Deleting the definition unassigns it
+        let uri = format!("/api/sites/monsters/roles/{}", role_id);
+        let res = send_json(&state, Method::DELETE, &uri, Some(&admin), None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/shows", Some(&manager), Some(show.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_custom_roles_only_pass_on_held_permissions() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let booker = login_as(&state, "booker@example.com", vec![]).await;
+        let booker_id = user_id_by_email(&state, "booker@example.com").await;
+        let define = |token: String, role: serde_json::Value| {
+            let state = state.clone();
+            async move { send_json(&state, Method::POST, "/api/sites/monsters/roles", Some(&token), Some(role)).await }
+        };
+        let assign = |role_id: &str| json!({ "roles": [{ "custom": { "role_id": role_id, "site_id": "monsters" } }] });
+
+        let res = define(admin.clone(), json!({ "name": "Booker", "permissions": ["assignRoles", "createShow", "editShow"] })).await;
+        let booker_role = body_json(res).await["id"].as_str().unwrap().to_string();
+        let res = send_json(&state, Method::PUT, &format!("/api/users/{}/roles", booker_id), Some(&admin), Some(assign(&booker_role))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Roles hold at most what their author holds on the site
+        let res = define(booker.clone(), json!({ "name": "Boss", "permissions": ["createShow", "deleteSite"] })).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = define(booker.clone(), json!({ "name": "Promoter", "permissions": ["createShow"] })).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let uri = format!("/api/sites/monsters/roles/{}", body_json(res).await["id"].as_str().unwrap());
+        let res = send_json(&state, Method::PUT, &uri, Some(&booker), Some(json!({ "permissions": ["createShow", "deleteShow"] }))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::PUT, &uri, Some(&booker), Some(json!({ "permissions": ["editShow"] }))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Nor can a role someone else defined be taken on to gain more
+        let res = define(admin.clone(), json!({ "name": "Owner", "permissions": ["assignRoles", "deleteSite"] })).await;
+        let owner_role = body_json(res).await["id"].as_str().unwrap().to_string();
+        let roles = json!({ "roles": [
+            { "custom": { "role_id": booker_role, "site_id": "monsters" } },
+            { "custom": { "role_id": owner_role, "site_id": "monsters" } }
+        ] });
+        let res = send_json(&state, Method::PUT, &format!("/api/users/{}/roles", booker_id), Some(&booker), Some(roles)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_register_and_login_flow() {
+        let state = test_state();
+        let register = |email: &str| json!({ "email": email, "name": "Mike", "password": "monsters-rock" });
+        let bootstrap = |email: &str, token: &str| {
+            let mut account = register(email);
+            account["bootstrapToken"] = json!(token);
+            account
+        };
+
+        // Being first earns nothing; the first admin needs the deploy secret
+        let res = send_json(&state, Method::POST, "/api/auth/register", None, Some(register("first@example.com"))).await;
+        assert_eq!(body_json(res).await["status"], "pending");
+        let res = send_json(&state, Method::POST, "/api/auth/register", None, Some(bootstrap("Mike@Example.com", "guess"))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/auth/register", None, Some(bootstrap("Mike@Example.com", BOOTSTRAP_TOKEN))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = body_json(res).await;
+        assert_eq!((body["status"].as_str(), body["roles"][0].as_str()), (Some("active"), Some("admin")));
+        // ...and only while there is no admin
+        let res = send_json(&state, Method::POST, "/api/auth/register", None, Some(bootstrap("eve@example.com", BOOTSTRAP_TOKEN))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/auth/register", None, Some(register("mike@example.com"))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let login = |password: &str| json!({ "email": "mike@example.com", "password": password });
+        let res = send_json(&state, Method::POST, "/api/auth/login", None, Some(login("wrong-password"))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = send_json(&state, Method::POST, "/api/auth/login", None, Some(login("monsters-rock"))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(body_json(res).await["token"].is_string());
+        let user = state.find_user_by_email("mike@example.com").await.unwrap().unwrap();
+        assert!(user.last_login.is_some());
+
+        // Later accounts wait for activation
+        let res = send_json(&state, Method::POST, "/api/auth/register", None, Some(register("drummer@example.com"))).await;
+        assert_eq!(body_json(res).await["status"], "pending");
+        let pending = json!({ "email": "drummer@example.com", "password": "monsters-rock" });
+        let res = send_json(&state, Method::POST, "/api/auth/login", None, Some(pending)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    /// Register the bootstrap admin and return its login response
+    async fn login_admin(state: &ApiState) -> serde_json::Value {
+        let account = json!({ "email": "admin@example.com", "name": "Admin", "password": "monsters-rock" });
+        let mut register = account.clone();
+        register["bootstrapToken"] = json!(BOOTSTRAP_TOKEN);
+        send_json(state, Method::POST, "/api/auth/register", None, Some(register)).await;
+        let res = send_json(state, Method::POST, "/api/auth/login", None, Some(account)).await;
+        body_json(res).await
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rotation_and_logout() {
+        let state = test_state();
+        let session = login_admin(&state).await;
+        let first_refresh = session["refreshToken"].clone();
+
+        // Rotation hands out a fresh pair
+        let res = send_json(&state, Method::POST, "/api/auth/refresh", None,
+            Some(json!({ "refreshToken": first_refresh }))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let rotated = body_json(res).await;
+        let access = rotated["token"].as_str().unwrap().to_string();
+        assert_ne!(rotated["refreshToken"], first_refresh);
+
+        // Logout kills the access token immediately
+        let res = send_json(&state, Method::POST, "/api/auth/logout", Some(&access), None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = send_json(&state, Method::POST, "/api/auth/logout", Some(&access), None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = send_json(&state, Method::POST, "/api/auth/refresh", None,
+            Some(json!({ "refreshToken": rotated["refreshToken"] }))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_reuse_revokes_session() {
+        let state = test_state();
+        let session = login_admin(&state).await;
+        let access = session["token"].as_str().unwrap().to_string();
+        let stolen = json!({ "refreshToken": session["refreshToken"] });
+
+        let res = send_json(&state, Method::POST, "/api/auth/refresh", None, Some(stolen.clone())).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send_json(&state, Method::POST, "/api/auth/refresh", None, Some(stolen)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // Every token from the compromised session is now dead
+        let res = send_json(&state, Method::POST, "/api/auth/logout", Some(&access), None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_song_get_update_delete() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+        add_site(&state, "other-band").await;
+        let song = json!({ "title": "Opener" });
+
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/songs", Some(&editor), Some(song.clone())).await;
+        let uri = format!("/api/songs/{}", body_json(res).await["id"].as_str().unwrap());
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "title": "Closer", "musicalKey": "Am" }))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send_json(&state, Method::GET, &uri, None, None).await;
+        let body = body_json(res).await;
+        assert_eq!((body["title"].as_str(), body["musicalKey"].as_str()), (Some("Closer"), Some("Am")));
+
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "title": "" }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // Another band's song is invisible and can't be changed
+        let res = send_to_site(&state, "other-band", Method::POST, "/api/songs", Some(&admin), Some(song.clone())).await;
+        let other = format!("/api/songs/{}", body_json(res).await["id"].as_str().unwrap());
+        let res = send_json(&state, Method::GET, &other, Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = send_json(&state, Method::DELETE, &other, Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = send_json(&state, Method::DELETE, &uri, Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = send_json(&state, Method::GET, &uri, None, None).await;
+        assert_eq!(error_code(res).await, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_setlist_builder_orders_sets_and_songs() {
+        let state = test_state();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+
+        let mut songs = Vec::new();
+        for (title, key, duration) in [("One", "C", 200), ("Two", "Am", 180), ("Three", "F#m", 240)] {
+            let song = json!({ "title": title, "durationSeconds": duration });
+            let res = send_to_site(&state, "monsters", Method::POST, "/api/songs", Some(&editor), Some(song)).await;
+            let id = body_json(res).await["id"].as_str().unwrap().to_string();
+            let uri = format!("/api/songs/{}", id);
+            send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "musicalKey": key }))).await;
+            songs.push(id);
+        }
+
+        // A setlist passed when creating a show becomes its first set
+        let set = json!({ "name": "First Set", "songIds": [songs[0], songs[1]] });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/setlists", Some(&editor), Some(set)).await;
+        let first = body_json(res).await["id"].as_str().unwrap().to_string();
+        let show = json!({ "date": 1900000000, "venue": "The Roxy", "setlistId": first });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/shows", Some(&editor), Some(show)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let show_id = body_json(res).await["id"].as_str().unwrap().to_string();
+
+        let set = json!({ "name": "Encore", "showId": show_id, "songIds": [songs[2]] });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/setlists", Some(&editor), Some(set)).await;
+        let encore = body_json(res).await;
+        assert_eq!(encore["position"], 1);
+        let encore = encore["id"].as_str().unwrap().to_string();
+
+        // Drag the encore's song to the end of the first set
+        let uri = format!("/api/setlists/{}/songs/move", encore);
+        let drag = json!({ "from": 0, "to": 2, "toSetlistId": first });
+        let res = send_json(&state, Method::POST, &uri, Some(&editor), Some(drag)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let uri = format!("/api/setlists/{}/songs", first);
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "songIds": [songs[0]] }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let reordered = json!({ "songIds": [songs[1], songs[0], songs[2]] });
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(reordered)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let uri = format!("/api/shows/{}/sets", show_id);
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "setlistIds": [encore, first] }))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = send_json(&state, Method::GET, &format!("/api/shows/{}/setlist", show_id), None, None).await;
+        let body = body_json(res).await;
+        assert_eq!(body["durationSeconds"], 620);
+        assert_eq!(body["sets"][0]["id"].as_str(), Some(encore.as_str()));
+        assert_eq!(body["sets"][0]["songs"].as_array().unwrap().len(), 0);
+        let warnings = &body["sets"][1]["keyChanges"];
+        assert_eq!((warnings[0]["position"].as_u64(), warnings[0]["toKey"].as_str()), (Some(2), Some("F#m")));
+
+        // Deleting the show keeps its sets, unlinked
+        send_json(&state, Method::DELETE, &format!("/api/shows/{}", show_id), Some(&editor), None).await;
+        let res = send_json(&state, Method::GET, &format!("/api/setlists/{}", first), None, None).await;
+        assert!(body_json(res).await["showId"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_access_entry_grants_edit_on_one_setlist() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let guest = login_as(&state, "drummer@example.com", vec![]).await;
+        let guest_id = user_id_by_email(&state, "drummer@example.com").await;
+        add_site(&state, "monsters").await;
+
+        let mut song_ids = Vec::new();
+        for title in ["Opener", "Closer"] {
+            let res = send_to_site(&state, "monsters", Method::POST, "/api/songs", Some(&admin), Some(json!({ "title": title }))).await;
+            song_ids.push(body_json(res).await["id"].as_str().unwrap().to_string());
+        }
+        let member = json!({ "name": "Mike", "role": "Guitar" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/members", Some(&admin), Some(member)).await;
+        let member_notes = json!([{ "memberId": body_json(res).await["id"], "songId": null, "note": "Capo 2" }]);
+        let setlist = json!({ "songIds": song_ids, "memberNotes": member_notes });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/setlists", Some(&admin), Some(setlist)).await;
+        let setlist_id = body_json(res).await["id"].as_str().unwrap().to_string();
+        let uri = format!("/api/setlists/{}", setlist_id);
+        let reordered = json!({ "songIds": [song_ids[1], song_ids[0]] });
+        let notes = |set: &serde_json::Value| set["memberNotes"].as_array().map_or(0, Vec::len);
+
+        let res = send_json(&state, Method::PUT, &format!("{}/songs", uri), Some(&guest), Some(reordered.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let grant = json!({ "userId": guest_id, "resourceType": "setlist", "resourceId": setlist_id, "permissions": ["editShow"] });
+        let res = send_json(&state, Method::POST, "/api/acl", Some(&admin), Some(grant)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // A grantee edits the set, but member notes stay with site editors
+        let res = send_json(&state, Method::PUT, &format!("{}/songs", uri), Some(&guest), Some(reordered)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(notes(&body_json(res).await), 0);
+        let res = send_json(&state, Method::PUT, &uri, Some(&guest), Some(json!({ "name": "Encore" }))).await;
+        let set = body_json(res).await;
+        assert_eq!((set["name"].as_str(), notes(&set)), (Some("Encore"), 0));
+        let res = send_json(&state, Method::POST, &format!("{}/songs/move", uri), Some(&guest), Some(json!({ "from": 0, "to": 1 }))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(notes(&body_json(res).await[0]), 0);
+        let res = send_json(&state, Method::PUT, &format!("{}/songs", uri), Some(&admin), Some(json!({ "songIds": song_ids }))).await;
+        assert_eq!(notes(&body_json(res).await), 1);
+        let res = send_json(&state, Method::DELETE, &uri, Some(&guest), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_member_emails_are_only_shown_to_editors() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let fan = login_as(&state, "fan@example.com", vec![]).await;
+        add_site(&state, "monsters").await;
+
+        let member = json!({ "name": "Mike", "role": "Guitar", "email": "Mike@Example.com" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/members", Some(&admin), Some(member)).await;
+        let uri = format!("/api/members/{}", body_json(res).await["id"].as_str().unwrap());
+
+        for token in [None, Some(fan.as_str())] {
+            let res = send_to_site(&state, "monsters", Method::GET, "/api/members", token, None).await;
+            let list = body_json(res).await;
+            assert_eq!(list["data"][0]["name"], "Mike");
+            assert!(list["data"][0]["email"].is_null());
+            let res = send_json(&state, Method::GET, &uri, token, None).await;
+            assert!(body_json(res).await["email"].is_null());
+        }
+        let res = send_json(&state, Method::GET, &uri, Some(&admin), None).await;
+        assert_eq!(body_json(res).await["email"], "mike@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_setlist_export_as_text_and_pdf() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+
+        let mike = login_as(&state, "mike@example.com", vec![]).await;
+        let fan = login_as(&state, "fan@example.com", vec![]).await;
+
+        let member = json!({ "name": "Mike", "role": "Guitar", "email": "mike@example.com" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/members", Some(&admin), Some(member)).await;
+        let member_id = body_json(res).await["id"].as_str().unwrap().to_string();
+        let song = json!({ "title": "Midnight Train", "durationSeconds": 272 });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/songs", Some(&editor), Some(song)).await;
+        let song_id = body_json(res).await["id"].as_str().unwrap().to_string();
+
+        let bad = json!({ "songIds": [song_id], "memberNotes": [{ "memberId": "nobody", "note": "?" }] });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/setlists", Some(&editor), Some(bad)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let set = json!({
+            "name": "First Set",
+            "songIds": [song_id],
+            "memberNotes": [{ "memberId": member_id, "songId": song_id, "note": "Capo 2" }],
+        });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/setlists", Some(&editor), Some(set)).await;
+        let setlist_uri = format!("/api/setlists/{}", body_json(res).await["id"].as_str().unwrap());
+        let uri = format!("{}/export", setlist_uri);
+
+        let res = send_json(&state, Method::GET, &format!("{}?member_notes=all", uri), Some(&editor), None).await;
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+        let text = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(text.to_vec()).unwrap();
+        assert!(text.contains("1. Midnight Train") && text.contains("4:32") && text.contains("Mike: Capo 2"));
+
+        // Notes stay with the band: Mike gets his own copy, visitors none
+        let res = send_json(&state, Method::GET, &format!("{}?member_notes=all", uri), None, None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = send_json(&state, Method::GET, &format!("{}?member_notes=all", uri), Some(&mike), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let own_copy = format!("{}?member_notes={}", uri, member_id);
+        let res = send_json(&state, Method::GET, &own_copy, Some(&fan), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::GET, &own_copy, Some(&mike), None).await;
+        let text = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8(text.to_vec()).unwrap().contains("Capo 2"));
+        let res = send_json(&state, Method::GET, &setlist_uri, None, None).await;
+        assert_eq!(body_json(res).await["memberNotes"], json!([]));
+        let res = send_to_site(&state, "monsters", Method::GET, "/api/setlists", Some(&fan), None).await;
+        assert_eq!(body_json(res).await["data"][0]["memberNotes"], json!([]));
+        let res = send_json(&state, Method::GET, &setlist_uri, Some(&editor), None).await;
+        assert_eq!(body_json(res).await["memberNotes"][0]["note"], "Capo 2");
+
+        let res = send_json(&state, Method::GET, &format!("{}?format=pdf&layout=stage", uri), None, None).await;
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/pdf");
+        assert_eq!(res.headers()[header::CONTENT_DISPOSITION], "attachment; filename=\"first-set.pdf\"");
+        let pdf = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+
+        let res = send_json(&state, Method::GET, &format!("{}?format=docx", uri), None, None).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_post_publishing_workflow() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+        let now = chrono::Utc::now().timestamp();
+
+        // Editors write posts but can't make them live
+        let post = json!({ "title": "Tour", "slug": "tour", "content": "Dates!", "published": true });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/posts", Some(&editor), Some(post)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let post = json!({ "title": "Tour", "slug": "tour", "content": "Dates!" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/posts", Some(&editor), Some(post)).await;
+        let uri = format!("/api/posts/{}", body_json(res).await["id"].as_str().unwrap());
+        let res = send_json(&state, Method::POST, &format!("{}/publish", uri), Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // Until it is published, only the site's editors see it
+        let fan = login_as(&state, "fan@example.com", vec![]).await;
+        for token in [None, Some(fan.as_str())] {
+            let res = send_json(&state, Method::GET, &uri, token, None).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+            let res = send_to_site(&state, "monsters", Method::GET, "/api/posts", token, None).await;
+            assert_eq!(body_json(res).await["total"], 0);
+        }
+        let res = send_to_site(&state, "monsters", Method::GET, "/api/posts?status=draft", Some(&editor), None).await;
+        assert_eq!(body_json(res).await["total"], 1);
+
+        let step = |action: &str| format!("{}/{}", uri, action);
+        let res = send_json(&state, Method::POST, &step("schedule"), Some(&admin), Some(json!({ "publishedAt": now - 60 }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = send_json(&state, Method::POST, &step("schedule"), Some(&admin), Some(json!({ "publishedAt": now + 3600 }))).await;
+        assert_eq!(body_json(res).await["status"], "scheduled");
+        let res = send_json(&state, Method::POST, &step("archive"), Some(&admin), None).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // The cron tick publishes it once its time has passed
+        assert!(posts::publish_due(&state, now, 10).await.unwrap().is_empty());
+        assert_eq!(posts::publish_due(&state, now + 3600, 10).await.unwrap().len(), 1);
+        let res = send_json(&state, Method::GET, &uri, None, None).await;
+        assert_eq!(body_json(res).await["status"], "published");
+        let res = send_to_site(&state, "monsters", Method::GET, "/api/posts", None, None).await;
+        assert_eq!(body_json(res).await["total"], 1);
+
+        let res = send_json(&state, Method::POST, &step("archive"), Some(&admin), None).await;
+        assert_eq!(body_json(res).await["status"], "archived");
+        let res = send_json(&state, Method::POST, &step("publish"), Some(&admin), None).await;
+        assert_eq!(error_code(res).await, "VALIDATION_ERROR");
+        let res = send_json(&state, Method::POST, &step("unpublish"), Some(&admin), None).await;
+        let body = body_json(res).await;
+        assert_eq!((body["status"].as_str(), body["publishedAt"].is_null()), (Some("draft"), true));
+    }
+
+    #[tokio::test]
+    async fn test_post_content_is_rendered_and_sanitized() {
+        let state = test_state();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+
+        let content = "New **single** out now<script>alert(document.cookie)</script>\n\n[Listen](javascript:alert(1))";
+        let post = json!({ "title": "Single", "slug": "single", "content": content });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/posts", Some(&editor), Some(post)).await;
+        let body = body_json(res).await;
+        assert_eq!((body["contentFormat"].as_str(), body["content"].as_str()), (Some("markdown"), Some(content)));
+        assert_eq!(body["rendered"]["html"], "<p>New <strong>single</strong> out now</p>\n<p><a>Listen</a></p>\n");
+        assert_eq!(body["rendered"]["excerpt"], "New single out now Listen");
+
+        // Switching to HTML re-renders; an author's excerpt wins over the generated one
+        let uri = format!("/api/posts/{}", body["id"].as_str().unwrap());
+        let update = json!({ "content": "<h2 onclick=\"x()\">**Live**</h2>", "contentFormat": "html", "excerpt": "Out now" });
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(update)).await;
+        let body = body_json(res).await;
+        assert_eq!(body["rendered"], json!({ "html": "<h2>**Live**</h2>", "excerpt": "Out now", "version": 1 }));
+
+        // Posts saved before rendering existed are rendered on read, and
+        // saved rendered by the scheduled run
+        let mut legacy: BlogPost = serde_json::from_value(body).unwrap();
+        legacy.rendered = None;
+        state.storage.posts.put(&legacy).await.unwrap();
+        let res = send_json(&state, Method::GET, &uri, Some(&editor), None).await;
+        assert_eq!(body_json(res).await["rendered"]["html"], "<h2>**Live**</h2>");
+        assert!(state.storage.posts.get(&legacy.id).await.unwrap().unwrap().needs_render());
+        assert_eq!(posts::render_stale(&state, 10).await.unwrap(), 1);
+        assert!(!state.storage.posts.get(&legacy.id).await.unwrap().unwrap().needs_render());
+        assert_eq!(posts::render_stale(&state, 10).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_creates_photo_from_file() {
+        let state = test_state();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+        let upload = json!({ "kind": "photo", "filename": "../Live at the Roxy.png", "contentType": "image/png", "sizeBytes": png.len(), "title": "Encore" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/uploads", Some(&editor), Some(upload)).await;
+        let target = body_json(res).await;
+        assert_eq!((target["url"].clone(), target["upload"]["method"].as_str()), (json!(null), Some("multipart")));
+        let id = target["upload"]["id"].as_str().unwrap().to_string();
+        assert!(target["upload"]["key"].as_str().unwrap().ends_with("/Live-at-the-Roxy.png"));
+
+        // Parts are raw bytes, not JSON
+        let (first, second) = png.split_at(10);
+        let mut parts = Vec::new();
+        for (number, bytes) in [(1, first), (2, second)] {
+            let req = axum::http::Request::builder()
+                .method(Method::PUT)
+                .uri(format!("/api/uploads/{}/parts/{}", id, number))
+                .header(header::AUTHORIZATION, format!("Bearer {}", editor))
+                .body(Body::from(bytes.to_vec()))
+                .unwrap();
+            parts.push(body_json(router::build(state.clone()).oneshot(req).await.unwrap()).await);
+        }
+
+        let res = send_json(&state, Method::POST, &format!("/api/uploads/{}/complete", id), Some(&editor), Some(json!({ "parts": parts }))).await;
+        let upload = body_json(res).await;
+        assert_eq!(upload["status"], "completed");
+        assert_eq!(upload["info"]["contentHash"].as_str().unwrap(), format!("{:x}", sha2::Sha256::digest(&png)));
+        let photo = body_json(send_json(&state, Method::GET, &format!("/api/photos/{}", upload["resourceId"].as_str().unwrap()), Some(&editor), None).await).await;
+        assert_eq!((photo["dimensions"]["width"].as_u64(), photo["dimensions"]["height"].as_u64()), (Some(640), Some(480)));
+        assert_eq!((photo["sizeBytes"].as_u64(), photo["altText"].as_str()), (Some(png.len() as u64), Some("Encore")));
+
+        // The file is served back with its sniffed type
+        let res = send_json(&state, Method::GET, &format!("/api/media/{}", upload["key"].as_str().unwrap()), None, None).await;
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap().to_vec(), png);
+
+        // Single byte ranges are honored
+        let media = format!("/api/media/{}", upload["key"].as_str().unwrap());
+        let ranged = |range: &str| axum::http::Request::builder().method(Method::GET).uri(&media).header(header::RANGE, range);
+        for (range, bytes, content_range) in [("bytes=8-11", &png[8..12], "bytes 8-11/29"), ("bytes=-4", &png[25..], "bytes 25-28/29"), ("bytes=20-99", &png[20..], "bytes 20-28/29")] {
+            let res = dispatch(&state, ranged(range), None, None).await;
+            assert_eq!((res.status(), res.headers()[header::CONTENT_RANGE].to_str().unwrap()), (StatusCode::PARTIAL_CONTENT, content_range));
+            assert_eq!(axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap().to_vec(), bytes);
+        }
+        let res = dispatch(&state, ranged("bytes=29-"), None, None).await;
+        assert_eq!((res.status(), res.headers()[header::CONTENT_RANGE].to_str().unwrap()), (StatusCode::RANGE_NOT_SATISFIABLE, "bytes */29"));
+        assert_eq!(dispatch(&state, ranged("bytes=0-1,4-5"), None, None).await.status(), StatusCode::OK);
+
+        // In a gallery outsiders can't see, the file is missing to them too
+        let gallery = json!({ "title": "Backstage", "visibility": "password", "password": "encore", "photoIds": [upload["resourceId"]] });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/galleries", Some(&editor), Some(gallery)).await;
+        let gallery_uri = format!("/api/galleries/{}", body_json(res).await["id"].as_str().unwrap());
+        assert_eq!(send_json(&state, Method::GET, &media, None, None).await.status(), StatusCode::NOT_FOUND);
+        let res = send_json(&state, Method::GET, &media, Some(&editor), None).await;
+        assert_eq!((res.status(), res.headers()[header::CACHE_CONTROL].to_str().unwrap()), (StatusCode::OK, "private, no-store"));
+        let res = send_json(&state, Method::POST, &format!("{}/unlock", gallery_uri), None, Some(json!({ "password": "encore" }))).await;
+        let token = body_json(res).await["token"].as_str().unwrap().to_string();
+        assert_eq!(send_json(&state, Method::GET, &format!("{}?token={}", media, token), None, None).await.status(), StatusCode::OK);
+        send_json(&state, Method::PUT, &gallery_uri, Some(&editor), Some(json!({ "visibility": "public" }))).await;
+        assert_eq!(send_json(&state, Method::GET, &media, None, None).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_photo_derivatives_and_gallery_srcset() {
+        let state = test_state().with_images(Arc::new(images::CloudflareImages::new("https://media.example.com")));
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+
+        let photo: Photo = serde_json::from_value(json!({
+            "id": "crowd", "siteId": "monsters", "filename": "crowd.jpg", "urlFull": "https://media.example.com/sites/monsters/crowd.jpg",
+            "urlThumb": "https://media.example.com/sites/monsters/crowd.jpg", "sizeBytes": 1, "dimensions": { "width": 1600, "height": 1200 },
+            "altText": "The crowd", "caption": null, "tags": [], "uploadedAt": 0, "uploadedBy": "u", "contentType": "image/jpeg"
+        }))
+        .unwrap();
+        state.storage.photos.put(&photo).await.unwrap();
+
+        let res = send_json(&state, Method::POST, "/api/photos/crowd/derivatives", Some(&editor), None).await;
+        let photo = body_json(res).await;
+        assert_eq!(
+            photo["urlThumb"],
+            "https://media.example.com/cdn-cgi/image/width=400,height=400,fit=cover,format=webp,quality=82,metadata=none/sites/monsters/crowd.jpg"
+        );
+        assert_eq!(photo["derivatives"].as_array().unwrap().len(), 1 + 3 * 5);
+        assert_eq!(state.storage.photos.get("crowd").await.unwrap().unwrap().derivatives.len(), 16);
+
+        let gallery = json!({ "title": "Roxy", "photoIds": ["crowd"] });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/galleries", Some(&editor), Some(gallery)).await;
+        let uri = format!("/api/galleries/{}/images?sizes=50vw", body_json(res).await["id"].as_str().unwrap());
+        let body = body_json(send_to_site(&state, "monsters", Method::GET, &uri, None, None).await).await;
+        assert_eq!(body["cover"]["photoId"], "crowd");
+        let images = &body["images"];
+        assert_eq!(images.as_array().unwrap().len(), 1);
+        assert_eq!((images[0]["width"].as_i64(), images[0]["sizes"].as_str(), images[0]["alt"].as_str()), (Some(1600), Some("50vw"), Some("The crowd")));
+        assert_eq!(images[0]["sources"][0]["type"], "image/avif");
+        assert!(images[0]["srcset"].as_str().unwrap().ends_with("format=jpeg,quality=82,metadata=none/sites/monsters/crowd.jpg 1600w"));
+
+        // Without a processor there is nothing to remake
+        let res = send_json(&test_state(), Method::POST, "/api/photos/crowd/derivatives", Some(&editor), None).await;
+        assert_ne!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_video_source_comes_from_url_with_oembed_metadata() {
+        let thumbnail = |url: &str| oembed::OEmbed { thumbnail_url: Some(url.to_string()), ..Default::default() };
+        let mock = oembed::MockOEmbed::new()
+            .with("https://youtu.be/dQw4w9WgXcQ", thumbnail("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg"))
+            .with("https://vimeo.com/76979871/8272103f6e", oembed::OEmbed { duration: Some(231.0), ..thumbnail("https://i.vimeocdn.com/1.jpg") });
+        let state = test_state().with_oembed(Arc::new(mock));
+        let editor = login_as(&state, "content@example.com", vec![Role::Content]).await;
+        add_site(&state, "monsters").await;
+        let create = |video: serde_json::Value| send_to_site(&state, "monsters", Method::POST, "/api/videos", Some(&editor), Some(video));
+
+        // The claimed type is ignored; the timestamp doesn't end up in the ID
+        let video = json!({ "title": "Encore", "url": "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42s", "videoType": "vimeo" });
+        let body = body_json(create(video).await).await;
+        assert_eq!(body["source"], json!({ "youTube": { "video_id": "dQw4w9WgXcQ" } }));
+        assert_eq!((body["thumbnailUrl"].as_str(), body["durationSeconds"].as_i64()), (Some("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg"), None));
+
+        // Private Vimeo videos keep their hash; what the client sent wins
+        let video = json!({ "title": "Roxy", "url": "https://player.vimeo.com/video/76979871?h=8272103f6e", "thumbnailUrl": "/own.jpg" });
+        let body = body_json(create(video).await).await;
+        assert_eq!(body["source"], json!({ "vimeo": { "video_id": "76979871", "hash": "8272103f6e" } }));
+        assert_eq!((body["thumbnailUrl"].as_str(), body["durationSeconds"].as_i64()), (Some("/own.jpg"), Some(231)));
+
+        // A failed lookup still creates the video; links to pages don't
+        let res = create(json!({ "title": "Soundcheck", "url": "https://vimeo.com/1234" })).await;
+        assert_eq!((res.status(), body_json(res).await["thumbnailUrl"].clone()), (StatusCode::OK, serde_json::Value::Null));
+        let res = create(json!({ "title": "Tour", "url": "https://example.com/tour", "videoType": "youtube" })).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_video_visibility_and_passwords() {
+        let state = test_state();
+        let editor = login_as(&state, "content@example.com", vec![Role::Content]).await;
+        let fan = login_as(&state, "fan@example.com", vec![]).await;
+        add_site(&state, "monsters").await;
+        let create = |video: serde_json::Value| send_to_site(&state, "monsters", Method::POST, "/api/videos", Some(&editor), Some(video));
+        let list = |reader: Option<String>, token: Option<String>| {
+            let mut req = axum::http::Request::builder().method(Method::GET).uri("/api/videos").header(tenant::SITE_HEADER, "monsters");
+            if let Some(token) = token {
+                req = req.header(videos::VIDEO_TOKEN_HEADER, token);
+            }
+            let state = state.clone();
+            async move { body_json(dispatch(&state, req, reader.as_deref(), None).await).await["total"].as_u64() }
+        };
+
+        let res = create(json!({ "title": "Soundcheck", "url": "https://youtu.be/dQw4w9WgXcQ", "visibility": "password" })).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let video = json!({ "title": "Soundcheck", "url": "https://youtu.be/dQw4w9WgXcQ", "visibility": "password", "password": "encore" });
+        let body = String::from_utf8(axum::body::to_bytes(create(video).await.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(body.contains(r#""visibility":"password""#) && !body.contains("encore"));
+        let id = serde_json::from_str::<serde_json::Value>(&body).unwrap()["id"].as_str().unwrap().to_string();
+        let uri = format!("/api/videos/{}", id);
+        create(json!({ "title": "Encore", "url": "https://youtu.be/dQw4w9WgXcQ" })).await;
+
+        // Locked until unlocked with a video token, and only listed then
+        assert_eq!(send_json(&state, Method::GET, &uri, None, None).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!((list(None, None).await, list(Some(editor.clone()), None).await), (Some(1), Some(2)));
+        let unlock = format!("{}/unlock", uri);
+        let res = send_json(&state, Method::POST, &unlock, None, Some(json!({ "password": "wrong" }))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = send_json(&state, Method::POST, &unlock, None, Some(json!({ "password": "encore" }))).await;
+        let token = body_json(res).await["token"].as_str().unwrap().to_string();
+        let req = axum::http::Request::builder().method(Method::GET).uri(&uri).header(videos::VIDEO_TOKEN_HEADER, &token);
+        assert_eq!(dispatch(&state, req, None, None).await.status(), StatusCode::OK);
+        assert_eq!(list(None, Some(token.clone())).await, Some(2));
+        // A gallery token is no video token
+        let req = axum::http::Request::builder().method(Method::GET).uri(&uri).header(galleries::GALLERY_TOKEN_HEADER, &token);
+        assert_eq!(dispatch(&state, req, None, None).await.status(), StatusCode::UNAUTHORIZED);
+
+        // Members-only videos are missing to outsiders; dropping the
+        // password protection drops the password
+        send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "visibility": "membersOnly" }))).await;
+        assert!(state.storage.video_passwords.get(&id).await.unwrap().is_none());
+        for reader in [None, Some(fan.as_str())] {
+            assert_eq!(send_json(&state, Method::GET, &uri, reader, None).await.status(), StatusCode::NOT_FOUND);
+        }
+        assert_eq!(list(Some(fan.clone()), None).await, Some(1));
+        assert_eq!(send_json(&state, Method::GET, &uri, Some(&editor), None).await.status(), StatusCode::OK);
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "visibility": "password" }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // An uploaded file is missing to those who may not watch its video
+        let key = "sites/monsters/backstage.mp4";
+        state.objects.put(key, b"....ftypisom".to_vec(), "video/mp4").await.unwrap();
+        let upload = json!({
+            "id": "clip", "siteId": "monsters", "kind": "video", "filename": "backstage.mp4", "declaredContentType": "video/mp4",
+            "declaredSizeBytes": 12, "key": key, "method": "multipart", "status": "completed", "uploadedBy": "u", "createdAt": 0, "expiresAt": 0,
+            "info": { "contentType": "video/mp4", "sizeBytes": 12, "contentHash": "", "dimensions": null, "exif": null }, "url": null, "resourceId": null
+        });
+        state.storage.uploads.put(&serde_json::from_value(upload).unwrap()).await.unwrap();
+        let media = format!("/api/media/{}", key);
+        let video = json!({ "title": "Backstage", "url": media, "visibility": "password", "password": "backstage" });
+        let uri = format!("/api/videos/{}", body_json(create(video).await).await["id"].as_str().unwrap());
+        assert_eq!(send_json(&state, Method::GET, &media, None, None).await.status(), StatusCode::NOT_FOUND);
+        let res = send_json(&state, Method::POST, &format!("{}/unlock", uri), None, Some(json!({ "password": "backstage" }))).await;
+        let token = body_json(res).await["token"].as_str().unwrap().to_string();
+        let req = axum::http::Request::builder().method(Method::GET).uri(&media).header(videos::VIDEO_TOKEN_HEADER, &token);
+        let res = dispatch(&state, req, None, None).await;
+        assert_eq!((res.status(), res.headers()[header::CACHE_CONTROL].to_str().unwrap()), (StatusCode::OK, "private, no-store"));
+        assert_eq!(send_json(&state, Method::GET, &format!("{}?token={}", media, token), None, None).await.status(), StatusCode::OK);
+        send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "visibility": "hidden" }))).await;
+        assert_eq!(send_json(&state, Method::GET, &media, Some(&fan), None).await.status(), StatusCode::NOT_FOUND);
+        let res = send_json(&state, Method::GET, &media, Some(&editor), None).await;
+        assert_eq!((res.status(), res.headers()[header::CACHE_CONTROL].to_str().unwrap()), (StatusCode::OK, "private, no-store"));
+        send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "visibility": "public" }))).await;
+        let res = send_json(&state, Method::GET, &media, None, None).await;
+        assert_eq!((res.status(), res.headers()[header::CACHE_CONTROL].to_str().unwrap()), (StatusCode::OK, "public, max-age=31536000, immutable"));
+    }
+
+    #[tokio::test]
+    async fn test_gallery_photos_cover_and_visibility() {
+        let state = test_state();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        let fan = login_as(&state, "fan@example.com", vec![]).await;
+        add_site(&state, "monsters").await;
+
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/galleries", Some(&editor), Some(json!({ "title": "Tour" }))).await;
+        let uri = format!("/api/galleries/{}", body_json(res).await["id"].as_str().unwrap());
+        let mut photo_ids = Vec::new();
+        for name in ["a", "b", "c"] {
+            let photo = json!({ "title": name, "url": format!("https://cdn.example.com/{}.jpg", name), "galleryId": uri.rsplit('/').next() });
+            let res = send_to_site(&state, "monsters", Method::POST, "/api/photos", Some(&editor), Some(photo)).await;
+            photo_ids.push(body_json(res).await["id"].as_str().unwrap().to_string());
+        }
+        let (a, b, c) = (&photo_ids[0], &photo_ids[1], &photo_ids[2]);
+
+        // Reorder, pick a cover, then remove it: the first photo takes over
+        let res = send_json(&state, Method::PUT, &format!("{}/photos", uri), Some(&editor), Some(json!({ "photoIds": [c, a, b] }))).await;
+        assert_eq!(body_json(res).await["photoIds"], json!([c, a, b]));
+        let res = send_json(&state, Method::PUT, &format!("{}/photos", uri), Some(&editor), Some(json!({ "photoIds": [c, a] }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = send_json(&state, Method::PUT, &format!("{}/cover", uri), Some(&editor), Some(json!({ "photoId": b }))).await;
+        assert_eq!(body_json(res).await["coverPhotoId"], json!(b));
+        let res = send_json(&state, Method::DELETE, &format!("{}/photos/{}", uri, b), Some(&editor), None).await;
+        assert_eq!(body_json(res).await["coverPhotoId"], json!(null));
+        let res = send_json(&state, Method::GET, &format!("{}/images", uri), None, None).await;
+        assert_eq!(body_json(res).await["cover"]["photoId"], json!(c));
+        // Deleting a photo takes it out of its galleries
+        send_json(&state, Method::DELETE, &format!("/api/photos/{}", c), Some(&editor), None).await;
+        let res = send_json(&state, Method::GET, &uri, None, None).await;
+        assert_eq!(body_json(res).await["photoIds"], json!([a]));
+
+        // Members-only and hidden galleries are missing to outsiders, and so
+        // are their photos (photos in no gallery, like `b` now, stay public)
+        let loose = json!({ "title": "loose", "url": "https://cdn.example.com/loose.jpg" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/photos", Some(&editor), Some(loose)).await;
+        let loose = body_json(res).await["id"].as_str().unwrap().to_string();
+        let photo_uri = format!("/api/photos/{}", a);
+        for visibility in [json!("membersOnly"), json!("hidden")] {
+            send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "visibility": visibility }))).await;
+            for reader in [None, Some(fan.as_str())] {
+                assert_eq!(send_json(&state, Method::GET, &uri, reader, None).await.status(), StatusCode::NOT_FOUND);
+                let res = send_to_site(&state, "monsters", Method::GET, "/api/galleries", reader, None).await;
+                assert_eq!(body_json(res).await["total"], 0);
+                assert_eq!(send_json(&state, Method::GET, &photo_uri, reader, None).await.status(), StatusCode::NOT_FOUND);
+                let res = send_to_site(&state, "monsters", Method::GET, "/api/photos", reader, None).await;
+                let page = body_json(res).await;
+                let ids: Vec<&str> = page["data"].as_array().unwrap().iter().map(|p| p["id"].as_str().unwrap()).collect();
+                assert_eq!((page["total"].as_u64(), ids.contains(&a.as_str()), ids.contains(&loose.as_str())), (Some(2), false, true));
+            }
+            assert_eq!(send_json(&state, Method::GET, &uri, Some(&editor), None).await.status(), StatusCode::OK);
+            assert_eq!(send_json(&state, Method::GET, &photo_uri, Some(&editor), None).await.status(), StatusCode::OK);
+            let res = send_to_site(&state, "monsters", Method::GET, "/api/photos", Some(&editor), None).await;
+            assert_eq!(body_json(res).await["total"], 3);
+        }
+
+        // Password galleries are locked until unlocked with a gallery token
+        // (the password is stored hashed, apart from the gallery, and never echoed)
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "visibility": "password" }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let protect = json!({ "visibility": "password", "password": "encore" });
+        let res = send_json(&state, Method::PUT, &uri, Some(&editor), Some(protect)).await;
+        let body = String::from_utf8(axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(body.contains(r#""visibility":"password""#) && !body.contains("encore"));
+        let gallery_id = uri.trim_start_matches("/api/galleries/");
+        let stored = state.storage.gallery_passwords.get(gallery_id).await.unwrap().unwrap();
+        assert!(stored.hash.starts_with("$argon2id$"));
+        let locked = body_json(send_json(&state, Method::GET, &uri, None, None).await).await;
+        assert_eq!((locked["photoIds"].clone(), locked["visibility"].clone()), (json!([]), json!("password")));
+        assert_eq!(send_json(&state, Method::GET, &format!("{}/images", uri), None, None).await.status(), StatusCode::UNAUTHORIZED);
+        let unlock = format!("{}/unlock", uri);
+        let res = send_json(&state, Method::POST, &unlock, None, Some(json!({ "password": "wrong" }))).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = send_json(&state, Method::POST, &unlock, None, Some(json!({ "password": "encore" }))).await;
+        let token = body_json(res).await["token"].as_str().unwrap().to_string();
+        let req = axum::http::Request::builder().method(Method::GET).uri(&uri).header(galleries::GALLERY_TOKEN_HEADER, &token);
+        assert_eq!(body_json(dispatch(&state, req, None, None).await).await["photoIds"], json!([a]));
+        assert_eq!(send_json(&state, Method::GET, &photo_uri, None, None).await.status(), StatusCode::NOT_FOUND);
+        let req = axum::http::Request::builder().method(Method::GET).uri(&photo_uri).header(galleries::GALLERY_TOKEN_HEADER, &token);
+        assert_eq!(dispatch(&state, req, None, None).await.status(), StatusCode::OK);
+        // The token is for this gallery only, and is no user session
+        let other = send_to_site(&state, "monsters", Method::POST, "/api/galleries", Some(&editor), Some(json!({ "title": "Other", "visibility": "password", "password": "encore" }))).await;
+        let other_uri = format!("/api/galleries/{}", body_json(other).await["id"].as_str().unwrap());
+        let req = axum::http::Request::builder().method(Method::GET).uri(format!("{}/images", other_uri)).header(galleries::GALLERY_TOKEN_HEADER, &token);
+        assert_eq!(dispatch(&state, req, None, None).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(send_json(&state, Method::GET, "/api/uploads/x", Some(&token), None).await.status(), StatusCode::UNAUTHORIZED);
+
+        // Making the gallery public again drops its password
+        send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "visibility": "public" }))).await;
+        assert!(state.storage.gallery_passwords.get(gallery_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_upload_permissions_and_content_checks() {
+        let state = test_state();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+
+        // Site editors can upload photos and videos but not audio
+        let audio = json!({ "kind": "audio", "filename": "demo.mp3", "contentType": "audio/mpeg", "sizeBytes": 1000 });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/uploads", Some(&editor), Some(audio)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let svg = json!({ "kind": "photo", "filename": "logo.svg", "contentType": "image/svg+xml", "sizeBytes": 1000 });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/uploads", Some(&editor), Some(svg)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // A file that isn't the image it claimed to be is rejected and deleted
+        let upload = json!({ "kind": "photo", "filename": "cover.jpg", "contentType": "image/jpeg", "sizeBytes": 11 });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/uploads", Some(&editor), Some(upload)).await;
+        let upload: MediaUpload = serde_json::from_value(body_json(res).await["upload"].take()).unwrap();
+        let req = axum::http::Request::builder()
+            .method(Method::PUT)
+            .uri(format!("/api/uploads/{}/parts/1", upload.id))
+            .header(header::AUTHORIZATION, format!("Bearer {}", editor))
+            .body(Body::from("hello world"))
+            .unwrap();
+        let part = body_json(router::build(state.clone()).oneshot(req).await.unwrap()).await;
+        let uri = format!("/api/uploads/{}/complete", upload.id);
+        let res = send_json(&state, Method::POST, &uri, Some(&editor), Some(json!({ "parts": [part] }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(state.objects.size(&upload.key).await.unwrap(), None);
+        let res = send_json(&state, Method::GET, &format!("/api/uploads/{}", upload.id), Some(&editor), None).await;
+        assert_eq!(body_json(res).await["status"], "aborted");
+
+        // Other users can't see someone else's upload
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let res = send_json(&state, Method::GET, &format!("/api/uploads/{}", upload.id), Some(&admin), None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_revision_history_and_rollback() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        add_site(&state, "monsters").await;
+
+        let show = json!({ "date": 1900000000, "venue": "The Roxy" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/shows", Some(&editor), Some(show)).await;
+        let uri = format!("/api/shows/{}", body_json(res).await["id"].as_str().unwrap());
+        for venue in ["The Viper Room", "Whisky a Go Go"] {
+            send_json(&state, Method::PUT, &uri, Some(&editor), Some(json!({ "venue": venue }))).await;
+        }
+
+        let res = send_json(&state, Method::GET, &format!("{}/revisions", uri), Some(&editor), None).await;
+        let history = body_json(res).await;
+        assert_eq!(history.as_array().unwrap().len(), 3);
+        assert_eq!((history[0]["number"].as_u64(), history[0]["changedFields"].clone()), (Some(3), json!(["venue"])));
+        assert_eq!(history[2]["note"], "Created");
+        let res = send_json(&state, Method::GET, &format!("{}/revisions", uri), None, None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = send_json(&state, Method::GET, &format!("{}/revisions/compare?from=1", uri), Some(&editor), None).await;
+        let comparison = body_json(res).await;
+        assert_eq!(comparison["to"], 3);
+        assert_eq!((comparison["changes"][0]["before"].as_str(), comparison["changes"][0]["after"].as_str()), (Some("The Roxy"), Some("Whisky a Go Go")));
+        let res = send_json(&state, Method::GET, &format!("{}/revisions/9", uri), Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = send_json(&state, Method::POST, &format!("{}/revisions/1/rollback", uri), Some(&editor), None).await;
+        assert_eq!(body_json(res).await["venue"], "The Roxy");
+        let res = send_json(&state, Method::GET, &format!("{}/revisions/4", uri), Some(&editor), None).await;
+        let latest = body_json(res).await;
+        assert_eq!(latest["note"], "Rolled back to revision 1");
+        assert_eq!(latest["snapshot"]["venue"], "The Roxy");
+
+        // Rolling a post back restores its content, not whether it is live
+        let post = json!({ "title": "Tour", "slug": "tour", "content": "Dates:\nMay 1" });
+        let res = send_to_site(&state, "monsters", Method::POST, "/api/posts", Some(&editor), Some(post)).await;
+        let post_uri = format!("/api/posts/{}", body_json(res).await["id"].as_str().unwrap());
+        send_json(&state, Method::PUT, &post_uri, Some(&editor), Some(json!({ "content": "" }))).await;
+        send_json(&state, Method::POST, &format!("{}/publish", post_uri), Some(&admin), None).await;
+        let res = send_json(&state, Method::POST, &format!("{}/revisions/1/rollback", post_uri), Some(&editor), None).await;
+        let body = body_json(res).await;
+        assert_eq!((body["content"].as_str(), body["status"].as_str()), (Some("Dates:\nMay 1"), Some("published")));
+    }
+
+    #[tokio::test]
+    async fn test_contact_form_is_public_and_private_to_site_admins() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+
+        let site = json!({ "slug": "monsters", "name": "The Monsters" });
+        let res = send_json(&state, Method::POST, "/api/sites", Some(&editor), Some(site.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/sites", Some(&admin), Some(site.clone())).await;
+        let site_id = body_json(res).await["id"].as_str().unwrap().to_string();
+        let res = send_json(&state, Method::POST, "/api/sites", Some(&admin), Some(site)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let message = json!({ "name": "Fan", "email": "fan@example.com", "message": "Play Ohio!" });
+        let res = send_to_site(&state, &site_id, Method::POST, "/api/contact", None, Some(message.clone())).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let submission = format!("/api/contact/{}", body_json(res).await["id"].as_str().unwrap());
+        let res = send_to_site(&state, "nowhere", Method::POST, "/api/contact", None, Some(message.clone())).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = send_json(&state, Method::GET, "/api/contact", None, None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = send_json(&state, Method::GET, &submission, Some(&editor), None).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = send_json(&state, Method::PUT, &submission, Some(&admin), Some(json!({ "isRead": true }))).await;
+        assert_eq!(body_json(res).await["isRead"], true);
+        let res = send_json(&state, Method::GET, "/api/contact?status=unread", Some(&admin), None).await;
+        assert_eq!(body_json(res).await["total"], 0);
+        let res = send_json(&state, Method::GET, "/api/contact?status=read", Some(&admin), None).await;
+        assert_eq!(body_json(res).await["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_site_is_resolved_from_host() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        add_site(&state, "other-band").await;
+        let site = json!({ "slug": "monsters", "name": "The Monsters", "domain": "TheMonsters.com" });
+        let site_id = body_json(send_json(&state, Method::POST, "/api/sites", Some(&admin), Some(site)).await).await["id"]
+            .as_str().unwrap().to_string();
+        let on_host = |method: Method, uri: &str, host: &str| {
+            axum::http::Request::builder().method(method).uri(uri).header(header::HOST, host)
+        };
+
+        for host in ["themonsters.com", "monsters.webnexus.dev:443"] {
+            let res = dispatch(&state, on_host(Method::GET, "/api/site", host), None, None).await;
+            assert_eq!(body_json(res).await["id"], site_id.as_str());
+        }
+        let res = dispatch(&state, on_host(Method::GET, "/api/site", "api.webnexus.dev"), None, None).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let req = on_host(Method::GET, "/api/site", "themonsters.com").header(tenant::SITE_HEADER, "other-band");
+        assert_eq!(dispatch(&state, req, None, None).await.status(), StatusCode::BAD_REQUEST);
+
+        // Content is created on, and listed for, the site the request is for
+        let song = json!({ "title": "Opener" });
+        let res = dispatch(&state, on_host(Method::POST, "/api/songs", "themonsters.com"), Some(&admin), Some(song.clone())).await;
+        assert_eq!(body_json(res).await["siteId"], site_id.as_str());
+        send_to_site(&state, "other-band", Method::POST, "/api/songs", Some(&admin), Some(song.clone())).await;
+        let res = send_json(&state, Method::POST, "/api/songs", Some(&admin), Some(song)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = dispatch(&state, on_host(Method::GET, "/api/songs", "themonsters.com"), None, None).await;
+        assert_eq!(body_json(res).await["total"], 1);
+        let res = send_json(&state, Method::GET, "/api/songs", None, None).await;
+        assert_eq!(body_json(res).await["total"], 2);
+
+        // Custom domains are unique and can't shadow default domains
+        let taken = json!({ "slug": "tribute", "name": "Tribute", "domain": "themonsters.com" });
+        let res = send_json(&state, Method::POST, "/api/sites", Some(&admin), Some(taken)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let uri = format!("/api/sites/{}", site_id);
+        let res = send_json(&state, Method::PUT, &uri, Some(&admin), Some(json!({ "domain": "other-band.webnexus.dev" }))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_visitors_only_see_public_face_of_active_sites() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        let fan = login_as(&state, "fan@example.com", vec![]).await;
+        add_site(&state, "monsters").await;
+        add_site(&state, "demo").await;
+        let mut demo = state.storage.sites.get("demo").await.unwrap().unwrap();
+        demo.status = SiteStatus::Suspended;
+        state.storage.sites.put(&demo).await.unwrap();
+        let private = |site: &serde_json::Value| ["ownerId", "memberIds", "config", "status"].iter().any(|field| site.get(field).is_some());
+
+        for reader in [None, Some(fan.as_str())] {
+            let sites = body_json(send_json(&state, Method::GET, "/api/sites", reader, None).await).await;
+            assert_eq!((sites["total"].as_u64(), sites["data"][0]["name"].as_str()), (Some(1), Some("monsters")));
+            assert!(!private(&sites["data"][0]));
+            let site = body_json(send_json(&state, Method::GET, "/api/sites/monsters", reader, None).await).await;
+            assert_eq!((site["slug"].as_str(), private(&site)), (Some("monsters"), false));
+            assert_eq!(send_json(&state, Method::GET, "/api/sites/demo", reader, None).await.status(), StatusCode::NOT_FOUND);
+        }
+        let site = body_json(send_to_site(&state, "monsters", Method::GET, "/api/site", None, None).await).await;
+        assert_eq!((site["id"].as_str(), private(&site)), (Some("monsters"), false));
+
+        // Members and editors get the whole record
+        let site = body_json(send_json(&state, Method::GET, "/api/sites/monsters", Some(&editor), None).await).await;
+        assert_eq!(site["ownerId"], "owner");
+        let sites = body_json(send_json(&state, Method::GET, "/api/sites", Some(&admin), None).await).await;
+        assert_eq!(sites["total"], 2);
+        assert!(sites["data"].as_array().unwrap().iter().all(private));
+    }
+
+    #[tokio::test]
+    async fn test_site_member_management() {
+        let state = test_state();
+        let admin = login_admin(&state).await["token"].as_str().unwrap().to_string();
+        let editor = login_as(&state, "bassist@example.com", vec![Role::SiteEditor { site_id: "monsters".to_string() }]).await;
+        let editor_id = user_id_by_email(&state, "bassist@example.com").await;
+        add_site(&state, "monsters").await;
+
+        let add = json!({ "userId": editor_id });
+        let res = send_json(&state, Method::POST, "/api/sites/monsters/members", Some(&editor), Some(add.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = send_json(&state, Method::POST, "/api/sites/monsters/members", Some(&admin), Some(add)).await;
+        assert_eq!(body_json(res).await["memberIds"], json!(["owner", editor_id]));
+        let res = send_json(&state, Method::POST, "/api/sites/monsters/members", Some(&admin), Some(json!({ "userId": "ghost" }))).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = send_json(&state, Method::GET, "/api/sites/monsters/members", Some(&admin), None).await;
+        assert_eq!(body_json(res).await[0]["email"], "bassist@example.com");
+
+        let res = send_json(&state, Method::DELETE, "/api/sites/monsters/members/owner", Some(&admin), None).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let uri = format!("/api/sites/monsters/members/{}", editor_id);
+        let res = send_json(&state, Method::DELETE, &uri, Some(&admin), None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = send_json(&state, Method::DELETE, &uri, Some(&admin), None).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
     }
 }
@@ -0,0 +1,135 @@
+// Filesystem Object Storage
+//
+// `ObjectStore` over a local directory, used by the native dev server. Object
+// keys are relative paths under the root; multipart parts are kept under
+// `.multipart/<upload id>/` until the upload completes.
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use web_nexus_contracts::media::UploadedPart;
+
+use super::{check_key, join_url, ObjectResult, ObjectStore, ObjectStoreError};
+
+/// Objects stored as files under a directory
+pub struct FsObjectStore {
+    root: PathBuf,
+    public_base: String,
+}
+
+impl FsObjectStore {
+    /// Store objects under `root`, served at `public_base` (e.g.
+    /// `http://127.0.0.1:8787/api/media`)
+    pub fn new(root: impl Into<PathBuf>, public_base: impl Into<String>) -> Self {
+        Self { root: root.into(), public_base: public_base.into() }
+    }
+
+    fn object_path(&self, key: &str) -> ObjectResult<PathBuf> {
+        check_key(key)?;
+        if key.starts_with(".multipart") {
+            return Err(ObjectStoreError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(key))
+    }
+
+    fn upload_dir(&self, upload_id: &str) -> ObjectResult<PathBuf> {
+        if upload_id.is_empty() || !upload_id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Err(ObjectStoreError::UnknownUpload(upload_id.to_string()));
+        }
+        Ok(self.root.join(".multipart").join(upload_id))
+    }
+}
+
+fn backend(error: std::io::Error) -> ObjectStoreError {
+    ObjectStoreError::Backend(error.to_string())
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> ObjectResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(backend)?;
+    }
+    fs::write(path, bytes).map_err(backend)
+}
+
+fn etag(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().take(16).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[async_trait]
+impl ObjectStore for FsObjectStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> ObjectResult<()> {
+        write_file(&self.object_path(key)?, &bytes)
+    }
+
+    async fn size(&self, key: &str) -> ObjectResult<Option<u64>> {
+        match fs::metadata(self.object_path(key)?) {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    async fn read(&self, key: &str, offset: u64, length: u64) -> ObjectResult<Vec<u8>> {
+        let mut file = fs::File::open(self.object_path(key)?).map_err(backend)?;
+        file.seek(SeekFrom::Start(offset)).map_err(backend)?;
+        let mut bytes = Vec::new();
+        file.take(length).read_to_end(&mut bytes).map_err(backend)?;
+        Ok(bytes)
+    }
+
+    async fn delete(&self, key: &str) -> ObjectResult<()> {
+        match fs::remove_file(self.object_path(key)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(backend(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn create_multipart(&self, key: &str, _content_type: &str) -> ObjectResult<String> {
+        self.object_path(key)?;
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        fs::create_dir_all(self.upload_dir(&upload_id)?).map_err(backend)?;
+        Ok(upload_id)
+    }
+
+    async fn upload_part(&self, _key: &str, upload_id: &str, part_number: u16, bytes: Vec<u8>) -> ObjectResult<String> {
+        let dir = self.upload_dir(upload_id)?;
+        if !dir.is_dir() {
+            return Err(ObjectStoreError::UnknownUpload(upload_id.to_string()));
+        }
+        fs::write(dir.join(part_number.to_string()), &bytes).map_err(backend)?;
+        Ok(etag(&bytes))
+    }
+
+    async fn complete_multipart(&self, key: &str, upload_id: &str, parts: &[UploadedPart]) -> ObjectResult<()> {
+        let dir = self.upload_dir(upload_id)?;
+        if !dir.is_dir() {
+            return Err(ObjectStoreError::UnknownUpload(upload_id.to_string()));
+        }
+        let path = self.object_path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(backend)?;
+        }
+        let mut object = fs::File::create(&path).map_err(backend)?;
+        for part in parts {
+            let bytes = fs::read(dir.join(part.part_number.to_string()))
+                .ok()
+                .filter(|b| etag(b) == part.etag)
+                .ok_or_else(|| ObjectStoreError::Backend(format!("part {} was not uploaded", part.part_number)))?;
+            object.write_all(&bytes).map_err(backend)?;
+        }
+        fs::remove_dir_all(dir).map_err(backend)
+    }
+
+    async fn abort_multipart(&self, _key: &str, upload_id: &str) -> ObjectResult<()> {
+        match fs::remove_dir_all(self.upload_dir(upload_id)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(backend(e)),
+            _ => Ok(()),
+        }
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        join_url(&self.public_base, key)
+    }
+}
@@ -0,0 +1,97 @@
+// In-Memory Object Storage
+//
+// `ObjectStore` over maps, for tests and throwaway dev servers.
+
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use web_nexus_contracts::media::UploadedPart;
+
+use super::{check_key, join_url, ObjectResult, ObjectStore, ObjectStoreError, DEFAULT_PUBLIC_BASE};
+
+/// Objects and unfinished multipart uploads held in memory
+#[derive(Default)]
+pub struct MemoryObjectStore {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+    /// Upload ID -> part number -> bytes
+    uploads: Mutex<HashMap<String, BTreeMap<u16, Vec<u8>>>>,
+}
+
+impl MemoryObjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn poisoned<T>(_: T) -> ObjectStoreError {
+    ObjectStoreError::Backend("memory object store lock poisoned".to_string())
+}
+
+#[async_trait]
+impl ObjectStore for MemoryObjectStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> ObjectResult<()> {
+        check_key(key)?;
+        self.objects.lock().map_err(poisoned)?.insert(key.to_string(), bytes);
+        Ok(())
+    }
+
+    async fn size(&self, key: &str) -> ObjectResult<Option<u64>> {
+        Ok(self.objects.lock().map_err(poisoned)?.get(key).map(|b| b.len() as u64))
+    }
+
+    async fn read(&self, key: &str, offset: u64, length: u64) -> ObjectResult<Vec<u8>> {
+        let objects = self.objects.lock().map_err(poisoned)?;
+        let bytes = objects.get(key).ok_or_else(|| ObjectStoreError::Backend(format!("no object {}", key)))?;
+        let start = (offset as usize).min(bytes.len());
+        let end = start.saturating_add(length as usize).min(bytes.len());
+        Ok(bytes[start..end].to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> ObjectResult<()> {
+        self.objects.lock().map_err(poisoned)?.remove(key);
+        Ok(())
+    }
+
+    async fn create_multipart(&self, key: &str, _content_type: &str) -> ObjectResult<String> {
+        check_key(key)?;
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        self.uploads.lock().map_err(poisoned)?.insert(upload_id.clone(), BTreeMap::new());
+        Ok(upload_id)
+    }
+
+    async fn upload_part(&self, _key: &str, upload_id: &str, part_number: u16, bytes: Vec<u8>) -> ObjectResult<String> {
+        let mut uploads = self.uploads.lock().map_err(poisoned)?;
+        let parts = uploads.get_mut(upload_id).ok_or_else(|| ObjectStoreError::UnknownUpload(upload_id.to_string()))?;
+        let etag = format!("{}-{}", part_number, bytes.len());
+        parts.insert(part_number, bytes);
+        Ok(etag)
+    }
+
+    async fn complete_multipart(&self, key: &str, upload_id: &str, parts: &[UploadedPart]) -> ObjectResult<()> {
+        let mut stored = self
+            .uploads
+            .lock()
+            .map_err(poisoned)?
+            .remove(upload_id)
+            .ok_or_else(|| ObjectStoreError::UnknownUpload(upload_id.to_string()))?;
+        let mut object = Vec::new();
+        for part in parts {
+            let bytes = stored
+                .remove(&part.part_number)
+                .filter(|b| format!("{}-{}", part.part_number, b.len()) == part.etag)
+                .ok_or_else(|| ObjectStoreError::Backend(format!("part {} was not uploaded", part.part_number)))?;
+            object.extend(bytes);
+        }
+        self.objects.lock().map_err(poisoned)?.insert(key.to_string(), object);
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, _key: &str, upload_id: &str) -> ObjectResult<()> {
+        self.uploads.lock().map_err(poisoned)?.remove(upload_id);
+        Ok(())
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        join_url(DEFAULT_PUBLIC_BASE, key)
+    }
+}
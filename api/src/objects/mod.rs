@@ -0,0 +1,107 @@
+// Object Storage
+//
+// Where uploaded files live. Handlers go through `ObjectStore`; the backend is
+// an in-memory map (tests), a directory on disk (the native dev server), or an
+// R2 bucket (the Worker). Files bigger than one request go up as multipart
+// uploads; stores that can sign URLs also let clients PUT straight to them.
+
+pub mod memory;
+pub mod presign;
+pub mod r2;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fs;
+
+use async_trait::async_trait;
+use thiserror::Error;
+use web_nexus_contracts::media::UploadedPart;
+use web_nexus_contracts::ApiErrorKind;
+
+/// Object storage error
+#[derive(Debug, Error)]
+pub enum ObjectStoreError {
+    #[error("Object storage error: {0}")]
+    Backend(String),
+
+    #[error("Invalid object key: {0}")]
+    InvalidKey(String),
+
+    #[error("Unknown multipart upload: {0}")]
+    UnknownUpload(String),
+}
+
+impl From<ObjectStoreError> for ApiErrorKind {
+    fn from(error: ObjectStoreError) -> Self {
+        match error {
+            ObjectStoreError::UnknownUpload(_) => ApiErrorKind::ValidationError(error.to_string()),
+            error => {
+                tracing::error!("{}", error);
+                ApiErrorKind::Internal("Object storage error".to_string())
+            }
+        }
+    }
+}
+
+/// Result type for object storage operations
+pub type ObjectResult<T> = Result<T, ObjectStoreError>;
+
+/// Where objects are served from when the bucket has no public URL: the API's
+/// own media route
+pub const DEFAULT_PUBLIC_BASE: &str = "/api/media";
+
+/// A bucket of files addressed by key
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Store a whole object
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> ObjectResult<()>;
+
+    /// Size of an object, or `None` if there is none under `key`
+    async fn size(&self, key: &str) -> ObjectResult<Option<u64>>;
+
+    /// Bytes `offset..offset + length` of an object (fewer at its end)
+    async fn read(&self, key: &str, offset: u64, length: u64) -> ObjectResult<Vec<u8>>;
+
+    /// Delete an object (deleting a missing object is not an error)
+    async fn delete(&self, key: &str) -> ObjectResult<()>;
+
+    /// Start a multipart upload, returning its ID
+    async fn create_multipart(&self, key: &str, content_type: &str) -> ObjectResult<String>;
+
+    /// Store one part (numbered from 1), returning its ETag
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: u16, bytes: Vec<u8>) -> ObjectResult<String>;
+
+    /// Join the parts, in the order given, into the object
+    async fn complete_multipart(&self, key: &str, upload_id: &str, parts: &[UploadedPart]) -> ObjectResult<()>;
+
+    /// Discard a multipart upload and its parts
+    async fn abort_multipart(&self, key: &str, upload_id: &str) -> ObjectResult<()>;
+
+    /// A URL the client can PUT the object to directly, valid for
+    /// `expires_in` seconds from `now`, if this store can sign one. The PUT
+    /// must send `Content-Type: content_type`.
+    fn presign_put(&self, _key: &str, _content_type: &str, _expires_in: u64, _now: i64) -> Option<String> {
+        None
+    }
+
+    /// Public URL of an object
+    fn public_url(&self, key: &str) -> String;
+}
+
+/// Reject keys that could escape a directory or aren't plain paths
+pub fn check_key(key: &str) -> ObjectResult<()> {
+    let valid = !key.is_empty()
+        && key.len() <= 1024
+        && !key.starts_with('/')
+        && !key.contains(['\\', '\0'])
+        && key.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+    if valid {
+        Ok(())
+    } else {
+        Err(ObjectStoreError::InvalidKey(key.to_string()))
+    }
+}
+
+/// `base` + `/` + `key`
+pub fn join_url(base: &str, key: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), key)
+}
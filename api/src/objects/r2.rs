@@ -0,0 +1,148 @@
+// R2 Object Storage
+//
+// `ObjectStore` over a Cloudflare R2 bucket binding, used by the Worker.
+// Presigned uploads go to the bucket's S3-compatible endpoint, which needs an
+// R2 API token (see `S3Presigner`); without one clients upload in parts
+// through the API.
+
+use async_trait::async_trait;
+use web_nexus_contracts::media::UploadedPart;
+use worker::send::SendFuture;
+use worker::{Bucket, HttpMetadata, Range};
+
+use super::presign::S3Presigner;
+use super::{check_key, join_url, ObjectResult, ObjectStore, ObjectStoreError};
+
+/// R2 bucket binding
+pub struct R2ObjectStore {
+    bucket: Binding,
+    presigner: Option<S3Presigner>,
+    public_base: String,
+}
+
+/// The bucket binding, which is a JS object and so not `Send`
+struct Binding(Bucket);
+
+// SAFETY: a Worker isolate runs requests on a single thread, so the binding
+// is never touched from two threads (the same reasoning as `D1Database`).
+unsafe impl Send for Binding {}
+unsafe impl Sync for Binding {}
+
+impl R2ObjectStore {
+    /// Wrap a bucket binding (`env.bucket("MEDIA")`) whose objects are served
+    /// at `public_base`
+    pub fn new(bucket: Bucket, public_base: impl Into<String>) -> Self {
+        Self { bucket: Binding(bucket), presigner: None, public_base: public_base.into() }
+    }
+
+    /// Also hand out presigned URLs for direct uploads
+    pub fn with_presigner(mut self, presigner: S3Presigner) -> Self {
+        self.presigner = Some(presigner);
+        self
+    }
+}
+
+fn backend(error: worker::Error) -> ObjectStoreError {
+    ObjectStoreError::Backend(error.to_string())
+}
+
+fn metadata(content_type: &str) -> HttpMetadata {
+    HttpMetadata { content_type: Some(content_type.to_string()), ..Default::default() }
+}
+
+#[async_trait]
+impl ObjectStore for R2ObjectStore {
+    // JS values and promises are not `Send`; the Worker runtime is single
+    // threaded, so each call is wrapped in `SendFuture`.
+
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> ObjectResult<()> {
+        check_key(key)?;
+        SendFuture::new(async move {
+            self.bucket.0.put(key, bytes).http_metadata(metadata(content_type)).execute().await.map_err(backend)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn size(&self, key: &str) -> ObjectResult<Option<u64>> {
+        SendFuture::new(async move { Ok(self.bucket.0.head(key).await.map_err(backend)?.map(|object| object.size())) }).await
+    }
+
+    async fn read(&self, key: &str, offset: u64, length: u64) -> ObjectResult<Vec<u8>> {
+        SendFuture::new(async move {
+            let object = self
+                .bucket
+                .0
+                .get(key)
+                .range(Range::OffsetWithLength { offset, length })
+                .execute()
+                .await
+                .map_err(backend)?
+                .ok_or_else(|| ObjectStoreError::Backend(format!("no object {}", key)))?;
+            match object.body() {
+                Some(body) => body.bytes().await.map_err(backend),
+                None => Ok(Vec::new()),
+            }
+        })
+        .await
+    }
+
+    async fn delete(&self, key: &str) -> ObjectResult<()> {
+        SendFuture::new(async move { self.bucket.0.delete(key).await.map_err(backend) }).await
+    }
+
+    async fn create_multipart(&self, key: &str, content_type: &str) -> ObjectResult<String> {
+        check_key(key)?;
+        SendFuture::new(async move {
+            let upload = self
+                .bucket
+                .0
+                .create_multipart_upload(key)
+                .http_metadata(metadata(content_type))
+                .execute()
+                .await
+                .map_err(backend)?;
+            Ok(upload.upload_id().await)
+        })
+        .await
+    }
+
+    async fn upload_part(&self, key: &str, upload_id: &str, part_number: u16, bytes: Vec<u8>) -> ObjectResult<String> {
+        SendFuture::new(async move {
+            let upload = self.bucket.0.resume_multipart_upload(key, upload_id).map_err(backend)?;
+            let part = upload
+                .upload_part(part_number, bytes)
+                .await
+                .map_err(|_| ObjectStoreError::UnknownUpload(upload_id.to_string()))?;
+            Ok(part.etag())
+        })
+        .await
+    }
+
+    async fn complete_multipart(&self, key: &str, upload_id: &str, parts: &[UploadedPart]) -> ObjectResult<()> {
+        SendFuture::new(async move {
+            let upload = self.bucket.0.resume_multipart_upload(key, upload_id).map_err(backend)?;
+            let parts = parts.iter().map(|p| worker::UploadedPart::new(p.part_number, p.etag.clone()));
+            upload.complete(parts).await.map_err(backend)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn abort_multipart(&self, key: &str, upload_id: &str) -> ObjectResult<()> {
+        SendFuture::new(async move {
+            let upload = self.bucket.0.resume_multipart_upload(key, upload_id).map_err(backend)?;
+            upload.abort().await.map_err(backend)
+        })
+        .await
+    }
+
+    fn presign_put(&self, key: &str, content_type: &str, expires_in: u64, now: i64) -> Option<String> {
+        let presigner = self.presigner.as_ref()?;
+        Some(presigner.presign("PUT", key, &[("content-type", content_type)], expires_in, now))
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        join_url(&self.public_base, key)
+    }
+}
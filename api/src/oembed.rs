@@ -0,0 +1,162 @@
+// oEmbed Metadata
+//
+// YouTube and Vimeo describe their videos over oEmbed: a JSON document with
+// the title and thumbnail (and, from Vimeo, the duration). Videos added by
+// link get their missing thumbnail and duration from there. The Worker asks
+// the providers with `fetch`; tests and offline setups plug in `MockOEmbed`.
+// A provider that fails or doesn't answer in time just leaves the video
+// without that metadata.
+
+use async_trait::async_trait;
+use futures_util::future::{select, Either};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::pin::pin;
+use std::time::Duration;
+use thiserror::Error;
+use web_nexus_contracts::VideoSource;
+use worker::send::SendFuture;
+
+use crate::objects::presign::uri_encode;
+
+/// oEmbed lookup error
+#[derive(Debug, Error)]
+pub enum OEmbedError {
+    #[error("oEmbed request failed: {0}")]
+    Backend(String),
+
+    #[error("oEmbed provider answered {0}")]
+    Status(u16),
+
+    #[error("Invalid oEmbed response: {0}")]
+    Invalid(String),
+
+    #[error("oEmbed provider did not answer within {0:?}")]
+    Timeout(Duration),
+}
+
+/// How long a provider gets to answer
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The parts of an oEmbed response we use
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OEmbed {
+    pub title: Option<String>,
+    pub thumbnail_url: Option<String>,
+    /// Seconds; only some providers (Vimeo) send it
+    pub duration: Option<f64>,
+}
+
+impl OEmbed {
+    /// Duration in whole seconds
+    pub fn duration_seconds(&self) -> Option<i32> {
+        self.duration.filter(|d| d.is_finite() && *d >= 0.0).map(|d| d.round().min(i32::MAX as f64) as i32)
+    }
+}
+
+/// oEmbed endpoint describing `source`, if its provider has one
+pub fn endpoint(source: &VideoSource) -> Option<String> {
+    let provider = match source {
+        VideoSource::YouTube { .. } => "https://www.youtube.com/oembed?format=json&url=",
+        VideoSource::Vimeo { .. } => "https://vimeo.com/api/oembed.json?url=",
+        VideoSource::Direct { .. } | VideoSource::External { .. } => return None,
+    };
+    Some(format!("{}{}", provider, uri_encode(&source.canonical_url()?, true)))
+}
+
+/// Looks up oEmbed data for videos
+#[async_trait]
+pub trait OEmbedFetcher: Send + Sync {
+    /// oEmbed data for `source`, or `None` if its provider has no oEmbed
+    async fn fetch(&self, source: &VideoSource) -> Result<Option<OEmbed>, OEmbedError>;
+}
+
+/// Asks the providers over the Workers `fetch` API, giving up (and aborting
+/// the request) after `FETCH_TIMEOUT`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkerOEmbed;
+
+#[async_trait]
+impl OEmbedFetcher for WorkerOEmbed {
+    async fn fetch(&self, source: &VideoSource) -> Result<Option<OEmbed>, OEmbedError> {
+        let Some(endpoint) = endpoint(source) else {
+            return Ok(None);
+        };
+        let url = worker::Url::parse(&endpoint).map_err(|e| OEmbedError::Backend(e.to_string()))?;
+        // JS promises are not `Send`; see `R2ObjectStore`
+        SendFuture::new(async move {
+            let controller = worker::AbortController::default();
+            let signal = controller.signal();
+            let lookup = pin!(async move {
+                let fetch = worker::Fetch::Url(url);
+                let mut response = fetch.send_with_signal(&signal).await.map_err(|e| OEmbedError::Backend(e.to_string()))?;
+                match response.status_code() {
+                    200 => response.json::<OEmbed>().await.map(Some).map_err(|e| OEmbedError::Invalid(e.to_string())),
+                    status => Err(OEmbedError::Status(status)),
+                }
+            });
+            match select(lookup, pin!(worker::Delay::from(FETCH_TIMEOUT))).await {
+                Either::Left((result, _)) => result,
+                Either::Right(_) => {
+                    controller.abort();
+                    Err(OEmbedError::Timeout(FETCH_TIMEOUT))
+                }
+            }
+        })
+        .await
+    }
+}
+
+/// Canned oEmbed data keyed by canonical video URL. Videos it doesn't know
+/// answer 404, as a provider does for missing or private videos.
+#[derive(Debug, Clone, Default)]
+pub struct MockOEmbed {
+    responses: HashMap<String, OEmbed>,
+}
+
+impl MockOEmbed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `oembed` for the video at `url` (any form `classify` accepts)
+    pub fn with(mut self, url: &str, oembed: OEmbed) -> Self {
+        let key = web_nexus_contracts::video::classify(url).ok().and_then(|source| source.canonical_url());
+        self.responses.insert(key.unwrap_or_else(|| url.to_string()), oembed);
+        self
+    }
+}
+
+#[async_trait]
+impl OEmbedFetcher for MockOEmbed {
+    async fn fetch(&self, source: &VideoSource) -> Result<Option<OEmbed>, OEmbedError> {
+        if endpoint(source).is_none() {
+            return Ok(None);
+        }
+        let url = source.canonical_url().unwrap_or_default();
+        self.responses.get(&url).cloned().map(Some).ok_or(OEmbedError::Status(404))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints_and_vimeo_response() {
+        let youtube = VideoSource::YouTube { video_id: "dQw4w9WgXcQ".to_string() };
+        assert_eq!(
+            endpoint(&youtube).unwrap(),
+            "https://www.youtube.com/oembed?format=json&url=https%3A%2F%2Fwww.youtube.com%2Fwatch%3Fv%3DdQw4w9WgXcQ"
+        );
+        let vimeo = VideoSource::Vimeo { video_id: "76979871".to_string(), hash: Some("8272103f6e".to_string()) };
+        assert_eq!(endpoint(&vimeo).unwrap(), "https://vimeo.com/api/oembed.json?url=https%3A%2F%2Fvimeo.com%2F76979871%2F8272103f6e");
+        assert_eq!(endpoint(&VideoSource::Direct { url: "/a.mp4".to_string() }), None);
+
+        let oembed: OEmbed = serde_json::from_str(
+            r#"{"type":"video","version":"1.0","title":"Live at the Roxy","thumbnail_url":"https://i.vimeocdn.com/video/1.jpg","duration":231,"width":640}"#,
+        )
+        .unwrap();
+        assert_eq!((oembed.thumbnail_url.as_deref(), oembed.duration_seconds()), (Some("https://i.vimeocdn.com/video/1.jpg"), Some(231)));
+    }
+}
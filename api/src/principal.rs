@@ -0,0 +1,107 @@
+// Request Principal
+//
+// The authenticated caller. `middleware::auth` validates the bearer token once
+// per request and stores a `Principal` in the request extensions; handlers take
+// it as an extractor, which rejects with 401 if authentication did not happen.
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use web_nexus_contracts::{
+    has_permission, has_site_permission, ApiErrorKind, Permission, RoleDefinition, SiteScope, User,
+};
+
+use crate::HandlerError;
+
+/// Authenticated caller of the current request
+#[derive(Debug, Clone)]
+pub struct Principal {
+    /// Current user record (roles and status as of this request)
+    pub user: User,
+    /// Definitions of the user's custom roles
+    pub role_definitions: Vec<RoleDefinition>,
+    /// Sites the user's roles reach
+    pub site_scope: SiteScope,
+    /// Session the access token belongs to
+    pub session_id: String,
+    /// Access token ID
+    pub token_id: String,
+    /// Access token expiry
+    pub expires_at: i64,
+}
+
+impl Principal {
+    /// Build a principal for a user's access token
+    pub fn new(
+        user: User,
+        role_definitions: Vec<RoleDefinition>,
+        session_id: String,
+        token_id: String,
+        expires_at: i64,
+    ) -> Self {
+        let site_scope = SiteScope::for_user(&user);
+        Self { user, role_definitions, site_scope, session_id, token_id, expires_at }
+    }
+
+    /// User ID
+    pub fn user_id(&self) -> &str {
+        &self.user.id
+    }
+
+    /// Check if the principal is a global admin
+    pub fn is_admin(&self) -> bool {
+        self.user.is_admin()
+    }
+
+    /// Check a permission through the shared contracts RBAC rules
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        has_permission(&self.user, permission, &self.role_definitions)
+    }
+
+    /// Check a permission granted by a role that is not limited to one site
+    pub fn has_global_permission(&self, permission: Permission) -> bool {
+        self.has_permission(permission)
+            && self
+                .user
+                .roles
+                .iter()
+                .any(|role| role.site_id().is_none() && role.grants(permission, &self.role_definitions))
+    }
+
+    /// Check a permission on the site a resource belongs to
+    pub fn has_site_permission(&self, permission: Permission, site_id: &str) -> bool {
+        has_site_permission(&self.user, permission, site_id, &self.role_definitions)
+    }
+
+    /// Sites on which the principal holds a permission
+    pub fn sites_with(&self, permission: Permission) -> SiteScope {
+        if self.has_global_permission(permission) {
+            return SiteScope::All;
+        }
+        let site_ids = self
+            .user
+            .roles
+            .iter()
+            .filter_map(|role| role.site_id())
+            .filter(|site_id| self.has_site_permission(permission, site_id))
+            .map(str::to_string)
+            .collect();
+        SiteScope::Sites { site_ids }
+    }
+
+    /// Check if resources of a site are visible to the principal
+    pub fn can_read_site(&self, site_id: &str) -> bool {
+        self.site_scope.contains(site_id)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = HandlerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .ok_or_else(|| ApiErrorKind::Unauthorized.into())
+    }
+}
@@ -0,0 +1,428 @@
+// List Queries
+//
+// Sorting, filtering and pagination shared by every list endpoint. Query
+// parameters:
+//
+//   sort, order          - sort field (per type, see `Listable::SORT_FIELDS`) and asc/desc
+//   site_id              - only this site (combined with the caller's read scope)
+//   status, tag          - exact status / tag match, where the type has them
+//   from, to             - inclusive Unix timestamp range on the type's date
+//   page, per_page       - 1-based offset pagination
+//   cursor               - opaque `nextCursor` from a previous page (overrides page)
+//
+// Results are always ordered by the sort field, then by ID, so pages are
+// stable between calls. Filtering, sorting and paging all happen in storage
+// (see `Filter`); only the requested page is loaded.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use web_nexus_contracts::{
+    ApiErrorKind, BandMember, BlogPost, ContactSubmission, Gallery, PaginatedResponse, Photo, Setlist, Show,
+    Site, SiteScope, Song, Video,
+};
+
+use crate::storage::{Entity, Filter, Range, Repository, Sort};
+
+/// Default page size
+pub const DEFAULT_PER_PAGE: u32 = 20;
+
+/// Largest page size a client may request
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// Sort direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// A record type that list endpoints can sort and filter
+pub trait Listable: Entity {
+    /// Accepted `sort` fields (top-level JSON fields); the first is the default
+    const SORT_FIELDS: &'static [&'static str];
+    /// Order used when `order` is not given
+    const DEFAULT_ORDER: Order;
+    /// Whether `status` filtering applies
+    const HAS_STATUS: bool = false;
+    /// Array field that `tag` filters on, if any
+    const TAG_FIELD: Option<&'static str> = None;
+    /// Timestamp fields that `from` / `to` filter on, the first one set
+    /// counting; no date filtering if empty
+    const DATE_FIELDS: &'static [&'static str] = &[];
+
+    /// Field condition for a `status` value, as serialized in the API
+    fn status_condition(status: &str) -> (&'static str, Value) {
+        ("status", Value::from(status))
+    }
+}
+
+/// Position after which the next page starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Cursor {
+    sort: String,
+    order: Order,
+    key: Value,
+    id: String,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> Result<Self, ApiErrorKind> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| ApiErrorKind::ValidationError("Invalid cursor".to_string()))
+    }
+}
+
+/// Parsed list query parameters
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    pub sort: Option<String>,
+    pub order: Option<Order>,
+    pub site_id: Option<String>,
+    pub status: Option<String>,
+    pub tag: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub page: u32,
+    pub per_page: u32,
+    pub cursor: Option<String>,
+}
+
+fn invalid(param: &str, value: &str) -> ApiErrorKind {
+    ApiErrorKind::ValidationError(format!("Invalid {}: {}", param, value))
+}
+
+fn parse<T: std::str::FromStr>(params: &HashMap<String, String>, param: &str) -> Result<Option<T>, ApiErrorKind> {
+    params
+        .get(param)
+        .map(|value| value.parse().map_err(|_| invalid(param, value)))
+        .transpose()
+}
+
+impl ListQuery {
+    /// Parse and validate query parameters
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, ApiErrorKind> {
+        let order = match params.get("order").map(String::as_str) {
+            None => None,
+            Some("asc") => Some(Order::Asc),
+            Some("desc") => Some(Order::Desc),
+            Some(other) => return Err(invalid("order", other)),
+        };
+        let page = parse(params, "page")?.unwrap_or(1);
+        if page == 0 {
+            return Err(invalid("page", "0 (pages start at 1)"));
+        }
+        let per_page = parse(params, "per_page")?.unwrap_or(DEFAULT_PER_PAGE);
+        if per_page == 0 || per_page > MAX_PER_PAGE {
+            return Err(invalid("per_page", &format!("{} (must be 1-{})", per_page, MAX_PER_PAGE)));
+        }
+
+        Ok(Self {
+            sort: params.get("sort").cloned(),
+            order,
+            site_id: params.get("site_id").cloned(),
+            status: params.get("status").cloned(),
+            tag: params.get("tag").cloned(),
+            from: parse(params, "from")?,
+            to: parse(params, "to")?,
+            page,
+            per_page,
+            cursor: params.get("cursor").cloned(),
+        })
+    }
+
+    /// Storage filter for the requested site within the caller's read scope
+    pub fn storage_filter(&self, scope: &SiteScope) -> Filter {
+        match &self.site_id {
+            Some(site_id) if scope.contains(site_id) => Filter::site(site_id),
+            Some(_) => Filter { site_ids: Some(Vec::new()), ..Filter::default() },
+            None => Filter::scope(scope),
+        }
+    }
+
+    /// Load the requested page of the records matching `filter`, which is
+    /// usually `storage_filter` (possibly narrowed further)
+    pub async fn fetch<T: Listable>(
+        &self,
+        repo: &dyn Repository<T>,
+        mut filter: Filter,
+    ) -> Result<PaginatedResponse<T>, ApiErrorKind> {
+        let sort = self.sort.as_deref().unwrap_or(T::SORT_FIELDS[0]);
+        let Some(field) = T::SORT_FIELDS.iter().copied().find(|f| *f == sort) else {
+            return Err(ApiErrorKind::ValidationError(format!(
+                "Invalid sort: {} (expected one of {})",
+                sort,
+                T::SORT_FIELDS.join(", ")
+            )));
+        };
+        if let Some(status) = &self.status {
+            if !T::HAS_STATUS {
+                return Err(ApiErrorKind::ValidationError("status filter is not supported here".to_string()));
+            }
+            filter.fields.push(T::status_condition(status));
+        }
+        if let Some(tag) = &self.tag {
+            let Some(tags) = T::TAG_FIELD else {
+                return Err(ApiErrorKind::ValidationError("tag filter is not supported here".to_string()));
+            };
+            filter.contains.push((tags, Value::from(tag.as_str())));
+        }
+        if self.from.is_some() || self.to.is_some() {
+            if T::DATE_FIELDS.is_empty() {
+                return Err(ApiErrorKind::ValidationError("date range filter is not supported here".to_string()));
+            }
+            filter.ranges.push(Range { fields: T::DATE_FIELDS, min: self.from, max: self.to });
+        }
+        let order = self.order.unwrap_or(T::DEFAULT_ORDER);
+        filter.sort = Some(Sort { field, descending: order == Order::Desc });
+
+        let total = repo.count(&filter.conditions()).await?;
+        let per_page = u64::from(self.per_page);
+        let start = match &self.cursor {
+            Some(cursor) => {
+                let cursor = Cursor::decode(cursor)?;
+                if cursor.sort != sort || cursor.order != order {
+                    return Err(ApiErrorKind::ValidationError(
+                        "cursor was issued for a different sort".to_string(),
+                    ));
+                }
+                filter.after = Some((cursor.key, cursor.id));
+                total.saturating_sub(repo.count(&filter).await?)
+            }
+            None => {
+                filter.offset = u64::from(self.page - 1) * per_page;
+                filter.offset
+            }
+        };
+        filter.limit = Some(per_page);
+        let data = repo.list(&filter).await?;
+        let end = start + data.len() as u64;
+
+        let next_cursor = match data.last() {
+            Some(last) if end < total => {
+                let record = serde_json::to_value(last).unwrap_or_default();
+                let key = record.get(field).cloned().unwrap_or(Value::Null);
+                Some(Cursor { sort: sort.to_string(), order, key, id: last.id().to_string() }.encode())
+            }
+            _ => None,
+        };
+
+        Ok(PaginatedResponse {
+            data,
+            page: (start / per_page + 1) as i32,
+            per_page: per_page as i32,
+            total: total as i64,
+            total_pages: total.div_ceil(per_page) as i32,
+            has_next: end < total,
+            has_prev: start > 0,
+            next_cursor,
+        })
+    }
+}
+
+impl Listable for Show {
+    const SORT_FIELDS: &'static [&'static str] = &["date", "title", "venue", "createdAt", "updatedAt"];
+    const DEFAULT_ORDER: Order = Order::Asc;
+    const HAS_STATUS: bool = true;
+    const DATE_FIELDS: &'static [&'static str] = &["date"];
+}
+
+impl Listable for Song {
+    const SORT_FIELDS: &'static [&'static str] = &["title", "createdAt", "durationSeconds"];
+    const DEFAULT_ORDER: Order = Order::Asc;
+    /// Genres double as tags
+    const TAG_FIELD: Option<&'static str> = Some("genres");
+    const DATE_FIELDS: &'static [&'static str] = &["createdAt"];
+}
+
+impl Listable for BlogPost {
+    const SORT_FIELDS: &'static [&'static str] = &["publishedAt", "createdAt", "updatedAt", "title"];
+    const DEFAULT_ORDER: Order = Order::Desc;
+    const HAS_STATUS: bool = true;
+    /// Publication date, or creation date for unpublished posts
+    const DATE_FIELDS: &'static [&'static str] = &["publishedAt", "createdAt"];
+}
+
+impl Listable for Photo {
+    const SORT_FIELDS: &'static [&'static str] = &["uploadedAt", "filename", "sizeBytes"];
+    const DEFAULT_ORDER: Order = Order::Desc;
+    const TAG_FIELD: Option<&'static str> = Some("tags");
+    const DATE_FIELDS: &'static [&'static str] = &["uploadedAt"];
+}
+
+impl Listable for Video {
+    const SORT_FIELDS: &'static [&'static str] = &["publishedAt", "title", "viewCount", "durationSeconds"];
+    const DEFAULT_ORDER: Order = Order::Desc;
+    const DATE_FIELDS: &'static [&'static str] = &["publishedAt"];
+}
+
+impl Listable for Gallery {
+    const SORT_FIELDS: &'static [&'static str] = &["createdAt", "title"];
+    const DEFAULT_ORDER: Order = Order::Desc;
+    const DATE_FIELDS: &'static [&'static str] = &["createdAt"];
+}
+
+impl Listable for Setlist {
+    const SORT_FIELDS: &'static [&'static str] = &["position", "name"];
+    const DEFAULT_ORDER: Order = Order::Asc;
+}
+
+impl Listable for BandMember {
+    const SORT_FIELDS: &'static [&'static str] = &["displayOrder", "name"];
+    const DEFAULT_ORDER: Order = Order::Asc;
+}
+
+impl Listable for Site {
+    const SORT_FIELDS: &'static [&'static str] = &["name", "slug", "createdAt"];
+    const DEFAULT_ORDER: Order = Order::Asc;
+    const HAS_STATUS: bool = true;
+    const DATE_FIELDS: &'static [&'static str] = &["createdAt"];
+}
+
+impl Listable for ContactSubmission {
+    const SORT_FIELDS: &'static [&'static str] = &["submittedAt", "name"];
+    const DEFAULT_ORDER: Order = Order::Desc;
+    const HAS_STATUS: bool = true;
+    const DATE_FIELDS: &'static [&'static str] = &["submittedAt"];
+
+    /// `read` or `unread`; anything else matches nothing
+    fn status_condition(status: &str) -> (&'static str, Value) {
+        match status {
+            "read" => ("isRead", Value::Bool(true)),
+            "unread" => ("isRead", Value::Bool(false)),
+            other => ("isRead", Value::from(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryRepository;
+    use crate::storage::sqlite::SqliteExecutor;
+    use crate::storage::{migrations, Storage};
+    use std::sync::Arc;
+
+    fn song(id: &str, title: &str, genres: &[&str], created_at: i64) -> Song {
+        Song {
+            id: id.to_string(),
+            site_id: "site-1".to_string(),
+            title: title.to_string(),
+            artist: None,
+            genres: genres.iter().map(|g| g.to_string()).collect(),
+            duration_seconds: None,
+            is_original: true,
+            musical_key: None,
+            notes: None,
+            created_at,
+        }
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> ListQuery {
+        let params = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        ListQuery::from_params(&params).unwrap()
+    }
+
+    /// The same songs in memory and in SQLite, which must page alike
+    async fn songs() -> Vec<Arc<dyn Repository<Song>>> {
+        let executor = Arc::new(SqliteExecutor::open_in_memory().unwrap());
+        migrations::migrate(executor.as_ref()).await.unwrap();
+        let repos: Vec<Arc<dyn Repository<Song>>> = vec![Arc::new(MemoryRepository::new()), Storage::sql(executor).songs];
+        for repo in &repos {
+            for (mut song, duration) in [
+                (song("d", "Delta", &["rock"], 4), Some(100)),
+                (song("a", "alpha", &["rock"], 1), None),
+                (song("c", "Charlie", &["blues"], 3), None),
+                (song("b", "Bravo", &["rock"], 2), Some(200)),
+                (song("e", "Bravo", &["blues"], 5), None),
+            ] {
+                song.duration_seconds = duration;
+                repo.put(&song).await.unwrap();
+            }
+        }
+        repos
+    }
+
+    async fn fetch(repo: &Arc<dyn Repository<Song>>, pairs: &[(&str, &str)]) -> Result<PaginatedResponse<Song>, ApiErrorKind> {
+        query(pairs).fetch(repo.as_ref(), Filter::all()).await
+    }
+
+    fn ids(page: &PaginatedResponse<Song>) -> Vec<&str> {
+        page.data.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sort_is_stable_and_pages_are_one_based() {
+        for repo in &songs().await {
+            let first = fetch(repo, &[("per_page", "2")]).await.unwrap();
+            assert_eq!(ids(&first), vec!["a", "b"]);
+            assert_eq!((first.page, first.total, first.total_pages), (1, 5, 3));
+            assert!(first.has_next && !first.has_prev);
+
+            let last = fetch(repo, &[("per_page", "2"), ("page", "3")]).await.unwrap();
+            assert_eq!(ids(&last), vec!["d"]);
+            assert!(!last.has_next && last.has_prev);
+
+            let desc = fetch(repo, &[("sort", "createdAt"), ("order", "desc")]).await.unwrap();
+            assert_eq!(ids(&desc), vec!["e", "d", "c", "b", "a"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filters() {
+        for repo in &songs().await {
+            let rock = fetch(repo, &[("tag", "rock"), ("from", "2"), ("to", "4")]).await.unwrap();
+            assert_eq!(ids(&rock), vec!["b", "d"]);
+            assert_eq!(rock.total, 2);
+            let rest = query(&[]).fetch(repo.as_ref(), Filter::all().except(["a".to_string(), "c".to_string()])).await.unwrap();
+            assert_eq!((ids(&rest), rest.total), (vec!["b", "e", "d"], 3));
+
+            assert!(fetch(repo, &[("status", "live")]).await.is_err());
+            assert!(fetch(repo, &[("sort", "venue")]).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cursor_walks_every_record_once() {
+        for repo in &songs().await {
+            // Songs without a duration sort first
+            for (sort, ascending) in [("title", ["a", "b", "e", "c", "d"]), ("durationSeconds", ["a", "c", "e", "d", "b"])] {
+                for order in ["asc", "desc"] {
+                    let mut seen = Vec::new();
+                    let mut cursor: Option<String> = None;
+                    loop {
+                        let mut pairs = vec![("per_page", "2"), ("sort", sort), ("order", order)];
+                        if let Some(c) = &cursor {
+                            pairs.push(("cursor", c.as_str()));
+                        }
+                        let page = fetch(repo, &pairs).await.unwrap();
+                        assert_eq!(page.has_prev, cursor.is_some());
+                        seen.extend(page.data.iter().map(|s| s.id.clone()));
+                        match page.next_cursor {
+                            Some(next) => cursor = Some(next),
+                            None => break,
+                        }
+                    }
+                    let mut expected = ascending.to_vec();
+                    if order == "desc" {
+                        expected.reverse();
+                    }
+                    assert_eq!(seen, expected, "{} {}", sort, order);
+                }
+            }
+
+            let cursor = fetch(repo, &[("per_page", "2")]).await.unwrap().next_cursor.unwrap();
+            assert!(fetch(repo, &[("cursor", &cursor), ("order", "desc")]).await.is_err());
+            assert!(fetch(repo, &[("cursor", "not-a-cursor")]).await.is_err());
+        }
+    }
+}
@@ -0,0 +1,371 @@
+// Session Management
+//
+// Short-lived access tokens are tied to a server-side session. Each session
+// holds one rotating refresh token; presenting a recently rotated-out refresh
+// token, or refreshing with the same token twice at once, is treated as theft
+// and revokes the whole session.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use password_hash::rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::storage::{Entity, Filter, Range, Repository, StorageResult};
+
+/// Lifetime of an access token (seconds)
+pub const ACCESS_TOKEN_TTL: i64 = 15 * 60;
+
+/// Lifetime of a session / refresh token (seconds)
+pub const REFRESH_TOKEN_TTL: i64 = 30 * 24 * 60 * 60;
+
+/// Rotated-out refresh tokens remembered per session for reuse detection;
+/// a stolen token is replayed soon after it is taken, not days of
+/// refreshes later
+pub const ROTATED_HASHES_KEPT: usize = 8;
+
+/// Server-side login session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// Session ID (carried in access tokens as `sid`)
+    pub id: String,
+    /// User the session belongs to
+    pub user_id: String,
+    /// Hash of the current refresh token
+    refresh_hash: String,
+    /// Hashes of the latest refresh tokens rotated out, oldest first (at
+    /// most `ROTATED_HASHES_KEPT`)
+    rotated_hashes: VecDeque<String>,
+    /// Created timestamp
+    pub created_at: i64,
+    /// Session expiry (refresh tokens stop working after this)
+    pub expires_at: i64,
+    /// Whether the session has been revoked
+    pub revoked: bool,
+}
+
+impl Entity for Session {
+    const TABLE: &'static str = "sessions";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn user_id(&self) -> Option<&str> {
+        Some(&self.user_id)
+    }
+}
+
+/// Revoked access token, kept until the token would have expired anyway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevokedToken {
+    /// Access token ID (`jti`)
+    pub jti: String,
+    /// Access token expiry
+    pub expires_at: i64,
+}
+
+impl Entity for RevokedToken {
+    const TABLE: &'static str = "revoked_tokens";
+
+    fn id(&self) -> &str {
+        &self.jti
+    }
+}
+
+/// Why a refresh token was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshError {
+    /// Unknown or malformed token
+    Invalid,
+    /// Session has expired or was revoked
+    Expired,
+    /// A rotated-out token was presented again; the session is now revoked
+    Reused,
+}
+
+/// All sessions plus the access-token revocation list
+#[derive(Clone)]
+pub struct SessionStore {
+    sessions: Arc<dyn Repository<Session>>,
+    revoked_tokens: Arc<dyn Repository<RevokedToken>>,
+}
+
+impl SessionStore {
+    /// Create a session store over its repositories
+    pub fn new(
+        sessions: Arc<dyn Repository<Session>>,
+        revoked_tokens: Arc<dyn Repository<RevokedToken>>,
+    ) -> Self {
+        Self { sessions, revoked_tokens }
+    }
+
+    /// Start a new session, returning it with its first refresh token
+    pub async fn create(&self, user_id: &str, now: i64) -> StorageResult<(Session, String)> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let token = new_refresh_token(&id);
+        let session = Session {
+            id,
+            user_id: user_id.to_string(),
+            refresh_hash: hash_token(&token),
+            rotated_hashes: VecDeque::new(),
+            created_at: now,
+            expires_at: now + REFRESH_TOKEN_TTL,
+            revoked: false,
+        };
+        self.sessions.put(&session).await?;
+        Ok((session, token))
+    }
+
+    /// Exchange a refresh token for a new one, rotating the session. The
+    /// write only lands if no other refresh rotated the session since it
+    /// was read; losing that race counts as reuse.
+    pub async fn rotate(
+        &self,
+        refresh_token: &str,
+        now: i64,
+    ) -> StorageResult<Result<(Session, String), RefreshError>> {
+        let Some(session_id) = refresh_token.split('.').next() else {
+            return Ok(Err(RefreshError::Invalid));
+        };
+        let Some(mut session) = self.sessions.get(session_id).await? else {
+            return Ok(Err(RefreshError::Invalid));
+        };
+        let presented = hash_token(refresh_token);
+
+        if session.rotated_hashes.contains(&presented) {
+            session.revoked = true;
+            self.sessions.put(&session).await?;
+            return Ok(Err(RefreshError::Reused));
+        }
+        if presented != session.refresh_hash {
+            return Ok(Err(RefreshError::Invalid));
+        }
+        if session.revoked || session.expires_at <= now {
+            return Ok(Err(RefreshError::Expired));
+        }
+
+        let token = new_refresh_token(&session.id);
+        let previous = std::mem::replace(&mut session.refresh_hash, hash_token(&token));
+        let unrotated = Filter::all().field("refreshHash", previous.as_str());
+        session.rotated_hashes.push_back(previous);
+        while session.rotated_hashes.len() > ROTATED_HASHES_KEPT {
+            session.rotated_hashes.pop_front();
+        }
+        if !self.sessions.replace_if(&session, &unrotated).await? {
+            self.revoke_session(&session.id).await?;
+            return Ok(Err(RefreshError::Reused));
+        }
+        Ok(Ok((session, token)))
+    }
+
+    /// Revoke a single session
+    pub async fn revoke_session(&self, session_id: &str) -> StorageResult<()> {
+        if let Some(mut session) = self.sessions.get(session_id).await? {
+            session.revoked = true;
+            self.sessions.put(&session).await?;
+        }
+        Ok(())
+    }
+
+    /// Revoke every session belonging to a user, optionally keeping one
+    pub async fn revoke_user(&self, user_id: &str, keep_session: Option<&str>) -> StorageResult<()> {
+        for mut session in self.sessions.list(&Filter::user(user_id)).await? {
+            if !session.revoked && Some(session.id.as_str()) != keep_session {
+                session.revoked = true;
+                self.sessions.put(&session).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an access token to the revocation list until it expires
+    pub async fn revoke_access_token(&self, jti: &str, expires_at: i64) -> StorageResult<()> {
+        self.revoked_tokens
+            .put(&RevokedToken { jti: jti.to_string(), expires_at })
+            .await
+    }
+
+    /// Whether an access token (by session and token ID) may still be used
+    pub async fn is_active(&self, session_id: &str, jti: &str, now: i64) -> StorageResult<bool> {
+        if self.revoked_tokens.get(jti).await?.is_some() {
+            return Ok(false);
+        }
+        Ok(self
+            .sessions
+            .get(session_id)
+            .await?
+            .map(|s| !s.revoked && s.expires_at > now)
+            .unwrap_or(false))
+    }
+
+    /// Drop up to `limit` expired sessions and as many expired revocation
+    /// entries, returning how many were dropped
+    pub async fn purge_expired(&self, now: i64, limit: u64) -> StorageResult<usize> {
+        let expired = Filter {
+            ranges: vec![Range { fields: &["expiresAt"], min: None, max: Some(now) }],
+            ..Filter::all()
+        }
+        .limit(limit);
+        let sessions = self.sessions.list(&expired).await?;
+        for session in &sessions {
+            self.sessions.delete(&session.id).await?;
+        }
+        let tokens = self.revoked_tokens.list(&expired).await?;
+        for token in &tokens {
+            self.revoked_tokens.delete(&token.jti).await?;
+        }
+        Ok(sessions.len() + tokens.len())
+    }
+}
+
+/// Generate an opaque refresh token: `<session id>.<256 random bits>`
+fn new_refresh_token(session_id: &str) -> String {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    format!("{}.{}", session_id, URL_SAFE_NO_PAD.encode(secret))
+}
+
+/// Refresh tokens are stored hashed so a leaked store can't mint sessions
+fn hash_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryRepository;
+
+    fn store() -> SessionStore {
+        SessionStore::new(Arc::new(MemoryRepository::new()), Arc::new(MemoryRepository::new()))
+    }
+
+    #[tokio::test]
+    async fn test_rotation_issues_new_token() {
+        let store = store();
+        let (session, first) = store.create("user-1", 1000).await.unwrap();
+
+        let (rotated, second) = store.rotate(&first, 1001).await.unwrap().unwrap();
+        assert_eq!(rotated.id, session.id);
+        assert_ne!(first, second);
+        assert!(store.rotate(&second, 1002).await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reuse_revokes_session() {
+        let store = store();
+        let (session, first) = store.create("user-1", 1000).await.unwrap();
+        let (_, second) = store.rotate(&first, 1001).await.unwrap().unwrap();
+
+        assert_eq!(store.rotate(&first, 1002).await.unwrap().unwrap_err(), RefreshError::Reused);
+        assert_eq!(store.rotate(&second, 1003).await.unwrap().unwrap_err(), RefreshError::Expired);
+        assert!(!store.is_active(&session.id, "any-jti", 1003).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_only_recent_rotated_tokens_are_kept() {
+        let sessions = Arc::new(MemoryRepository::new());
+        let store = SessionStore::new(sessions.clone(), Arc::new(MemoryRepository::new()));
+        let (session, mut token) = store.create("user-1", 1000).await.unwrap();
+        let mut rotated = Vec::new();
+        for now in 1001..1001 + 3 * ROTATED_HASHES_KEPT as i64 {
+            rotated.push(token.clone());
+            token = store.rotate(&token, now).await.unwrap().unwrap().1;
+        }
+
+        let stored: Session = sessions.get(&session.id).await.unwrap().unwrap();
+        assert_eq!(stored.rotated_hashes.len(), ROTATED_HASHES_KEPT);
+        // Long-gone tokens are just invalid; recent ones still give reuse away
+        assert_eq!(store.rotate(&rotated[0], 2000).await.unwrap().unwrap_err(), RefreshError::Invalid);
+        assert_eq!(store.rotate(rotated.last().unwrap(), 2000).await.unwrap().unwrap_err(), RefreshError::Reused);
+    }
+
+    /// Sessions whose reads all return the first session read, as two
+    /// refreshes that read before either wrote would see it
+    struct StaleReads {
+        inner: MemoryRepository<Session>,
+        first: std::sync::Mutex<Option<Session>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Repository<Session> for StaleReads {
+        async fn get(&self, id: &str) -> StorageResult<Option<Session>> {
+            let current = self.inner.get(id).await?;
+            Ok(Some(self.first.lock().unwrap().get_or_insert_with(|| current.unwrap()).clone()))
+        }
+
+        async fn list(&self, filter: &Filter) -> StorageResult<Vec<Session>> {
+            self.inner.list(filter).await
+        }
+
+        async fn count(&self, filter: &Filter) -> StorageResult<u64> {
+            self.inner.count(filter).await
+        }
+
+        async fn put(&self, record: &Session) -> StorageResult<()> {
+            self.inner.put(record).await
+        }
+
+        async fn insert(&self, record: &Session) -> StorageResult<bool> {
+            self.inner.insert(record).await
+        }
+
+        async fn replace_if(&self, record: &Session, expected: &Filter) -> StorageResult<bool> {
+            self.inner.replace_if(record, expected).await
+        }
+
+        async fn delete(&self, id: &str) -> StorageResult<bool> {
+            self.inner.delete(id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refresh_counts_as_reuse() {
+        let sessions = Arc::new(StaleReads { inner: MemoryRepository::new(), first: Default::default() });
+        let store = SessionStore::new(sessions.clone(), Arc::new(MemoryRepository::new()));
+        let (session, token) = store.create("user-1", 1000).await.unwrap();
+
+        assert!(store.rotate(&token, 1001).await.unwrap().is_ok());
+        assert_eq!(store.rotate(&token, 1001).await.unwrap().unwrap_err(), RefreshError::Reused);
+        assert!(sessions.inner.get(&session.id).await.unwrap().unwrap().revoked);
+    }
+
+    #[tokio::test]
+    async fn test_revocation() {
+        let store = store();
+        let (keep, _) = store.create("user-1", 1000).await.unwrap();
+        let (drop, _) = store.create("user-1", 1000).await.unwrap();
+
+        store.revoke_access_token("jti-1", 2000).await.unwrap();
+        assert!(!store.is_active(&keep.id, "jti-1", 1001).await.unwrap());
+        assert!(store.is_active(&keep.id, "jti-2", 1001).await.unwrap());
+
+        store.revoke_user("user-1", Some(&keep.id)).await.unwrap();
+        assert!(store.is_active(&keep.id, "jti-2", 1001).await.unwrap());
+        assert!(!store.is_active(&drop.id, "jti-2", 1001).await.unwrap());
+        assert!(!store.is_active(&keep.id, "jti-2", keep.expires_at).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_purge_keeps_live_entries() {
+        let sessions = Arc::new(MemoryRepository::new());
+        let revoked = Arc::new(MemoryRepository::new());
+        let store = SessionStore::new(sessions.clone(), revoked.clone());
+        let (session, _) = store.create("user-1", 1000).await.unwrap();
+        store.revoke_access_token("jti-old", 1500).await.unwrap();
+        store.revoke_access_token("jti-new", session.expires_at + 10).await.unwrap();
+
+        store.revoke_access_token("jti-older", 1400).await.unwrap();
+
+        // In batches of at most `limit` of each
+        assert_eq!(store.purge_expired(session.expires_at, 1).await.unwrap(), 2);
+        assert_eq!(store.purge_expired(session.expires_at, 1).await.unwrap(), 1);
+        assert_eq!(store.purge_expired(session.expires_at, 1).await.unwrap(), 0);
+        assert!(sessions.list(&Filter::all()).await.unwrap().is_empty());
+        let left: Vec<String> = revoked.list(&Filter::all()).await.unwrap().into_iter().map(|t| t.jti).collect();
+        assert_eq!(left, ["jti-new"]);
+    }
+}
@@ -0,0 +1,65 @@
+// D1 Storage
+//
+// `SqlExecutor` over a Cloudflare D1 binding, used by the Worker.
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use worker::send::SendFuture;
+use worker::wasm_bindgen::JsValue;
+use worker::D1Database;
+
+use super::sql::SqlExecutor;
+use super::{StorageError, StorageResult};
+
+/// D1 database binding
+pub struct D1Executor {
+    database: D1Database,
+}
+
+impl D1Executor {
+    /// Wrap a D1 binding (`env.d1("DB")`)
+    pub fn new(database: D1Database) -> Self {
+        Self { database }
+    }
+}
+
+fn backend(error: worker::Error) -> StorageError {
+    StorageError::Backend(error.to_string())
+}
+
+fn to_js(value: &Value) -> JsValue {
+    match value {
+        Value::Null => JsValue::NULL,
+        Value::Bool(b) => JsValue::from_f64(if *b { 1.0 } else { 0.0 }),
+        Value::Number(n) => JsValue::from_f64(n.as_f64().unwrap_or_default()),
+        Value::String(s) => JsValue::from_str(s),
+        other => JsValue::from_str(&other.to_string()),
+    }
+}
+
+#[async_trait]
+impl SqlExecutor for D1Executor {
+    // JS values and promises are not `Send`; the Worker runtime is single
+    // threaded, so the whole call is wrapped in `SendFuture`.
+
+    async fn execute(&self, sql: &str, params: &[Value]) -> StorageResult<u64> {
+        SendFuture::new(async move {
+            let params: Vec<JsValue> = params.iter().map(to_js).collect();
+            let statement = self.database.prepare(sql).bind(&params).map_err(backend)?;
+            let result = statement.run().await.map_err(backend)?;
+            let changes = result.meta().map_err(backend)?.and_then(|m| m.changes).unwrap_or(0);
+            Ok(changes as u64)
+        })
+        .await
+    }
+
+    async fn query(&self, sql: &str, params: &[Value]) -> StorageResult<Vec<Map<String, Value>>> {
+        SendFuture::new(async move {
+            let params: Vec<JsValue> = params.iter().map(to_js).collect();
+            let statement = self.database.prepare(sql).bind(&params).map_err(backend)?;
+            let result = statement.all().await.map_err(backend)?;
+            result.results::<Map<String, Value>>().map_err(backend)
+        })
+        .await
+    }
+}
@@ -0,0 +1,161 @@
+// In-Memory Storage
+//
+// Repository backed by a map, for tests and throwaway dev servers.
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use super::{Entity, Filter, Repository, StorageError, StorageResult};
+
+/// Repository kept in process memory
+#[derive(Debug, Default)]
+pub struct MemoryRepository<T> {
+    records: RwLock<BTreeMap<String, T>>,
+}
+
+impl<T> MemoryRepository<T> {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self { records: RwLock::new(BTreeMap::new()) }
+    }
+}
+
+/// Check a record against a filter's conditions
+pub(crate) fn matches<T: Entity>(record: &T, filter: &Filter) -> bool {
+    if let Some(site_ids) = &filter.site_ids {
+        if !record.site_id().is_some_and(|id| site_ids.iter().any(|s| s == id)) {
+            return false;
+        }
+    }
+    if let Some(user_id) = &filter.user_id {
+        if record.user_id() != Some(user_id.as_str()) {
+            return false;
+        }
+    }
+    if filter.excluded_ids.iter().any(|id| id == record.id()) {
+        return false;
+    }
+    if filter.fields.is_empty() && filter.contains.is_empty() && filter.ranges.is_empty() && filter.any.is_empty() {
+        return true;
+    }
+
+    let value = serde_json::to_value(record).unwrap_or_default();
+    filter.fields.iter().all(|(name, expected)| lookup(&value, name).unwrap_or(&Value::Null) == expected)
+        && filter.contains.iter().all(|(name, expected)| {
+            lookup(&value, name).and_then(Value::as_array).is_some_and(|items| items.contains(expected))
+        })
+        && filter.ranges.iter().all(|range| {
+            let number = range.fields.iter().find_map(|name| lookup(&value, name).and_then(Value::as_i64));
+            number.is_some_and(|n| range.min.is_none_or(|min| n >= min) && range.max.is_none_or(|max| n <= max))
+        })
+        && (filter.any.is_empty() || filter.any.iter().any(|alternative| matches(record, alternative)))
+}
+
+/// Value at a field path (`name` or `outer.inner`), as SQL `json_extract`
+/// finds it
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, name| value.get(name))
+}
+
+/// How a sort value orders: unset first, then numbers, then text without
+/// regard to ASCII case (as SQLite orders `COLLATE NOCASE`)
+#[derive(Debug, PartialEq, PartialOrd)]
+enum SortKey {
+    Missing,
+    Number(f64),
+    Text(String),
+}
+
+impl SortKey {
+    fn of(value: Option<&Value>) -> Self {
+        match value {
+            Some(Value::Number(n)) => SortKey::Number(n.as_f64().unwrap_or_default()),
+            Some(Value::Bool(b)) => SortKey::Number(f64::from(u8::from(*b))),
+            Some(Value::String(s)) => SortKey::Text(s.to_ascii_lowercase()),
+            Some(Value::Null) | None => SortKey::Missing,
+            Some(other) => SortKey::Text(other.to_string()),
+        }
+    }
+}
+
+/// Order records by a filter's sort, then keep those after its resume
+/// point, offset and limit
+fn page<T: Entity>(mut records: Vec<T>, filter: &Filter) -> Vec<T> {
+    if let Some(sort) = filter.sort {
+        let mut keyed: Vec<(SortKey, T)> = records
+            .into_iter()
+            .map(|r| (SortKey::of(lookup(&serde_json::to_value(&r).unwrap_or_default(), sort.field)), r))
+            .collect();
+        let compare = |a: (&SortKey, &str), b: (&SortKey, &str)| {
+            let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+            if sort.descending { ordering.reverse() } else { ordering }
+        };
+        keyed.sort_by(|(ka, a), (kb, b)| compare((ka, a.id()), (kb, b.id())));
+        if let Some((key, id)) = &filter.after {
+            let key = SortKey::of(Some(key));
+            keyed.retain(|(k, r)| compare((k, r.id()), (&key, id)) == Ordering::Greater);
+        }
+        records = keyed.into_iter().map(|(_, r)| r).collect();
+    } else if let Some((_, id)) = &filter.after {
+        records.retain(|r| r.id() > id.as_str());
+    }
+
+    let limit = filter.limit.map_or(usize::MAX, |limit| limit as usize);
+    records.into_iter().skip(filter.offset as usize).take(limit).collect()
+}
+
+fn poisoned() -> StorageError {
+    StorageError::Backend("memory store lock poisoned".to_string())
+}
+
+#[async_trait]
+impl<T: Entity> Repository<T> for MemoryRepository<T> {
+    async fn get(&self, id: &str) -> StorageResult<Option<T>> {
+        Ok(self.records.read().map_err(|_| poisoned())?.get(id).cloned())
+    }
+
+    async fn list(&self, filter: &Filter) -> StorageResult<Vec<T>> {
+        let records = self.records.read().map_err(|_| poisoned())?;
+        let matching = records.values().filter(|r| matches(*r, filter)).cloned().collect();
+        Ok(page(matching, filter))
+    }
+
+    async fn count(&self, filter: &Filter) -> StorageResult<u64> {
+        let records = self.records.read().map_err(|_| poisoned())?;
+        let matching = records.values().filter(|r| matches(*r, filter)).cloned().collect();
+        Ok(page(matching, &Filter { offset: 0, limit: None, ..filter.clone() }).len() as u64)
+    }
+
+    async fn put(&self, record: &T) -> StorageResult<()> {
+        self.records
+            .write()
+            .map_err(|_| poisoned())?
+            .insert(record.id().to_string(), record.clone());
+        Ok(())
+    }
+
+    async fn insert(&self, record: &T) -> StorageResult<bool> {
+        let mut records = self.records.write().map_err(|_| poisoned())?;
+        if records.contains_key(record.id()) {
+            return Ok(false);
+        }
+        records.insert(record.id().to_string(), record.clone());
+        Ok(true)
+    }
+
+    async fn replace_if(&self, record: &T, expected: &Filter) -> StorageResult<bool> {
+        let mut records = self.records.write().map_err(|_| poisoned())?;
+        if !records.get(record.id()).is_some_and(|current| matches(current, &expected.conditions())) {
+            return Ok(false);
+        }
+        records.insert(record.id().to_string(), record.clone());
+        Ok(true)
+    }
+
+    async fn delete(&self, id: &str) -> StorageResult<bool> {
+        Ok(self.records.write().map_err(|_| poisoned())?.remove(id).is_some())
+    }
+}
@@ -0,0 +1,206 @@
+// Schema Migrations
+//
+// Versioned, forward-only SQL migrations from `api/migrations`. Applied
+// migrations are recorded in `schema_migrations`, laid out the way wrangler
+// records D1 migrations, so `wrangler d1 migrations apply` and `api-migrate`
+// agree on what has already run.
+
+use serde_json::Value;
+
+use super::sql::SqlExecutor;
+use super::{StorageError, StorageResult};
+
+/// Table recording applied migrations (also `migrations_table` in wrangler.toml)
+pub const MIGRATIONS_TABLE: &str = "schema_migrations";
+
+/// One migration file
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Version, from the file name prefix
+    pub version: u32,
+    /// File name, e.g. `0001_initial.sql`
+    pub name: &'static str,
+    /// SQL script
+    pub sql: &'static str,
+}
+
+/// Every migration, in version order. Never edit or reorder an entry once it
+/// has shipped; add a new file instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "0001_initial.sql",
+        sql: include_str!("../../migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "0002_galleries_setlists_members_contact.sql",
+        sql: include_str!("../../migrations/0002_galleries_setlists_members_contact.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "0003_revisions.sql",
+        sql: include_str!("../../migrations/0003_revisions.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "0004_uploads.sql",
+        sql: include_str!("../../migrations/0004_uploads.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "0005_gallery_passwords.sql",
+        sql: include_str!("../../migrations/0005_gallery_passwords.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "0006_unique_user_emails.sql",
+        sql: include_str!("../../migrations/0006_unique_user_emails.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "0007_video_passwords.sql",
+        sql: include_str!("../../migrations/0007_video_passwords.sql"),
+    },
+];
+
+/// Applied state of one migration
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: u32,
+    pub name: String,
+    /// When it was applied (`None` if pending)
+    pub applied_at: Option<String>,
+}
+
+async fn ensure_table(executor: &dyn SqlExecutor) -> StorageResult<()> {
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (\
+         id INTEGER PRIMARY KEY AUTOINCREMENT, \
+         name TEXT UNIQUE, \
+         applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL)",
+        MIGRATIONS_TABLE
+    );
+    executor.execute(&sql, &[]).await?;
+    Ok(())
+}
+
+/// Names and timestamps of applied migrations
+async fn applied(executor: &dyn SqlExecutor) -> StorageResult<Vec<(String, String)>> {
+    let sql = format!("SELECT name, applied_at FROM {} ORDER BY id", MIGRATIONS_TABLE);
+    let rows = executor.query(&sql, &[]).await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let text = |key: &str| row.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+            (text("name"), text("applied_at"))
+        })
+        .collect())
+}
+
+/// Report every known migration and whether it has been applied.
+///
+/// Fails if the database has migrations this build doesn't know about, which
+/// means it was migrated by a newer version.
+pub async fn status(executor: &dyn SqlExecutor, migrations: &[Migration]) -> StorageResult<Vec<MigrationStatus>> {
+    ensure_table(executor).await?;
+    let applied = applied(executor).await?;
+
+    if let Some((unknown, _)) = applied.iter().find(|(name, _)| !migrations.iter().any(|m| m.name == name)) {
+        return Err(StorageError::Backend(format!(
+            "database has migration {} which this build does not know; it was migrated by a newer version",
+            unknown
+        )));
+    }
+
+    Ok(migrations
+        .iter()
+        .map(|m| MigrationStatus {
+            version: m.version,
+            name: m.name.to_string(),
+            applied_at: applied.iter().find(|(name, _)| name == m.name).map(|(_, at)| at.clone()),
+        })
+        .collect())
+}
+
+/// Apply pending migrations in version order, returning the ones applied
+pub async fn apply(executor: &dyn SqlExecutor, migrations: &[Migration]) -> StorageResult<Vec<Migration>> {
+    let mut pending = Vec::new();
+    for (migration, state) in migrations.iter().zip(status(executor, migrations).await?) {
+        if state.applied_at.is_none() {
+            pending.push(*migration);
+        }
+    }
+
+    for migration in &pending {
+        tracing::info!("applying migration {}", migration.name);
+        executor.execute_batch(migration.sql).await?;
+        let sql = format!("INSERT INTO {} (name) VALUES (?1)", MIGRATIONS_TABLE);
+        executor.execute(&sql, &[Value::String(migration.name.to_string())]).await?;
+    }
+    Ok(pending)
+}
+
+/// Apply every pending migration in `MIGRATIONS`
+pub async fn migrate(executor: &dyn SqlExecutor) -> StorageResult<Vec<Migration>> {
+    apply(executor, MIGRATIONS).await
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::storage::sqlite::SqliteExecutor;
+
+    const NEXT: Migration = Migration {
+        version: 99,
+        name: "0099_show_tags.sql",
+        sql: "CREATE TABLE show_tags (id TEXT PRIMARY KEY, data TEXT NOT NULL);",
+    };
+
+    #[tokio::test]
+    async fn test_migrations_apply_once_in_order() {
+        let executor = SqliteExecutor::open_in_memory().unwrap();
+
+        let applied = apply(&executor, MIGRATIONS).await.unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert!(apply(&executor, MIGRATIONS).await.unwrap().is_empty());
+
+        let both = [MIGRATIONS, &[NEXT]].concat();
+        let report = status(&executor, &both).await.unwrap();
+        assert!(report[0].applied_at.is_some());
+        assert!(report.last().unwrap().applied_at.is_none());
+
+        let applied = apply(&executor, &both).await.unwrap();
+        assert_eq!(applied.iter().map(|m| m.version).collect::<Vec<_>>(), vec![99]);
+
+        // An older build refuses a database migrated past what it knows
+        assert!(status(&executor, MIGRATIONS).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_video_passwords_move_out_of_videos() {
+        use crate::credentials::LEGACY_PLAIN_PREFIX;
+        use crate::storage::Storage;
+        use std::sync::Arc;
+        use web_nexus_contracts::GalleryVisibility;
+
+        let executor = Arc::new(SqliteExecutor::open_in_memory().unwrap());
+        apply(executor.as_ref(), &MIGRATIONS[..6]).await.unwrap();
+        let legacy = serde_json::json!({
+            "id": "v1", "siteId": "s1", "title": "Soundcheck", "description": null,
+            "source": { "youTube": { "video_id": "dQw4w9WgXcQ" } }, "thumbnailUrl": null, "durationSeconds": null,
+            "visibility": { "password": { "password": "encore" } }, "viewCount": 0, "publishedAt": 0
+        });
+        let sql = "INSERT INTO videos (id, site_id, data) VALUES ('v1', 's1', ?1)";
+        executor.execute(sql, &[Value::String(legacy.to_string())]).await.unwrap();
+        migrate(executor.as_ref()).await.unwrap();
+
+        let storage = Storage::sql(executor);
+        let video = storage.videos.get("v1").await.unwrap().unwrap();
+        assert_eq!(video.visibility, GalleryVisibility::Password);
+        let stored = storage.video_passwords.get("v1").await.unwrap().unwrap();
+        assert!(stored.hash.starts_with(LEGACY_PLAIN_PREFIX));
+        assert_eq!(storage.video_passwords.hash_legacy().await.unwrap(), 1);
+        assert!(storage.video_passwords.verify("v1", "encore").await.unwrap());
+    }
+}
@@ -0,0 +1,426 @@
+// Storage
+//
+// Repositories for everything the API persists. Handlers go through `Storage`;
+// the backend is either an in-memory map (tests) or SQL (SQLite natively, D1
+// inside the Worker). Records are stored as JSON with a few indexed columns.
+
+pub mod d1;
+pub mod memory;
+pub mod migrations;
+pub mod sql;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sqlite;
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use thiserror::Error;
+use web_nexus_contracts::media::MediaUpload;
+use web_nexus_contracts::{
+    ApiErrorKind, BandMember, BlogPost, ContactSubmission, Gallery, Photo, ResourceAccess,
+    Revision, RoleDefinition, Setlist, Show, Site, SiteScope, Song, User, Video,
+};
+use web_nexus_state::AppState;
+
+use crate::credentials::{
+    CredentialStore, GalleryPassword, GalleryPasswordStore, PasswordCredential, VideoPassword, VideoPasswordStore,
+};
+use crate::sessions::{RevokedToken, Session, SessionStore};
+use memory::MemoryRepository;
+use sql::{SqlExecutor, SqlRepository};
+
+/// Storage backend error
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+
+    #[error("Corrupt record in {table}: {reason}")]
+    Corrupt { table: &'static str, reason: String },
+}
+
+impl From<StorageError> for ApiErrorKind {
+    fn from(error: StorageError) -> Self {
+        tracing::error!("{}", error);
+        ApiErrorKind::Internal("Storage error".to_string())
+    }
+}
+
+/// Result type for storage operations
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// A record type stored in its own table
+pub trait Entity: Serialize + DeserializeOwned + Clone + Send + Sync + 'static {
+    /// Table name
+    const TABLE: &'static str;
+
+    /// Primary key
+    fn id(&self) -> &str;
+
+    /// Owning site (indexed)
+    fn site_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Owning user (indexed)
+    fn user_id(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Conditions for `Repository::list`; all set conditions must match
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only records belonging to one of these sites
+    pub site_ids: Option<Vec<String>>,
+    /// Only records belonging to this user
+    pub user_id: Option<String>,
+    /// Records with these IDs never match
+    pub excluded_ids: Vec<String>,
+    /// JSON fields (top-level, or nested as `outer.inner`) that must equal
+    /// the given values; unset fields equal null
+    pub fields: Vec<(&'static str, Value)>,
+    /// Top-level JSON array fields that must contain the given values
+    pub contains: Vec<(&'static str, Value)>,
+    /// Inclusive bounds on numeric fields (named like `fields`)
+    pub ranges: Vec<Range>,
+    /// Alternatives of which at least one must match (when not empty)
+    pub any: Vec<Filter>,
+    /// Order of `list` results; by ID when not set
+    pub sort: Option<Sort>,
+    /// Only records after this sort value and ID (in `sort` order)
+    pub after: Option<(Value, String)>,
+    /// Records of the ordered result to skip
+    pub offset: u64,
+    /// Most records `list` returns
+    pub limit: Option<u64>,
+}
+
+/// Inclusive bounds on the first of `fields` that is set
+#[derive(Debug, Clone)]
+pub struct Range {
+    pub fields: &'static [&'static str],
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+/// Sort on a top-level JSON field, then by ID. Records without the field
+/// come first; text compares ASCII case-insensitively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub field: &'static str,
+    pub descending: bool,
+}
+
+impl Filter {
+    /// Match every record
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Match records of one site
+    pub fn site(site_id: &str) -> Self {
+        Self { site_ids: Some(vec![site_id.to_string()]), ..Self::default() }
+    }
+
+    /// Match records on the sites a scope reaches
+    pub fn scope(scope: &SiteScope) -> Self {
+        match scope {
+            SiteScope::All => Self::all(),
+            SiteScope::Sites { site_ids } => Self { site_ids: Some(site_ids.clone()), ..Self::default() },
+        }
+    }
+
+    /// Match records of one user
+    pub fn user(user_id: &str) -> Self {
+        Self { user_id: Some(user_id.to_string()), ..Self::default() }
+    }
+
+    /// Also require a top-level field to equal a value
+    pub fn field(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.fields.push((name, value.into()));
+        self
+    }
+
+    /// Also require a top-level array field to contain a value
+    pub fn contains(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.contains.push((name, value.into()));
+        self
+    }
+
+    /// Also leave out the records with these IDs
+    pub fn except(mut self, ids: impl IntoIterator<Item = String>) -> Self {
+        self.excluded_ids.extend(ids);
+        self
+    }
+
+    /// Also require one of `alternatives` to match
+    pub fn any(mut self, alternatives: Vec<Filter>) -> Self {
+        self.any = alternatives;
+        self
+    }
+
+    /// Also limit the records to the sites a scope reaches
+    pub fn within(mut self, scope: &SiteScope) -> Self {
+        if let SiteScope::Sites { site_ids } = scope {
+            self.site_ids = Some(match self.site_ids.take() {
+                Some(current) => current.into_iter().filter(|id| site_ids.contains(id)).collect(),
+                None => site_ids.clone(),
+            });
+        }
+        self
+    }
+
+    /// Return at most `limit` records
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The matching condition alone, without order or paging
+    pub fn conditions(&self) -> Self {
+        Self { sort: None, after: None, offset: 0, limit: None, ..self.clone() }
+    }
+}
+
+/// Typed access to one table
+#[async_trait]
+pub trait Repository<T: Entity>: Send + Sync {
+    /// Get a record by ID
+    async fn get(&self, id: &str) -> StorageResult<Option<T>>;
+
+    /// List records matching a filter, in its sort order (by ID if unset),
+    /// after its offset and up to its limit
+    async fn list(&self, filter: &Filter) -> StorageResult<Vec<T>>;
+
+    /// Count records matching a filter's conditions (ignoring its order,
+    /// offset and limit, but not `after`)
+    async fn count(&self, filter: &Filter) -> StorageResult<u64>;
+
+    /// Insert or replace a record
+    async fn put(&self, record: &T) -> StorageResult<()>;
+
+    /// Insert a record unless it clashes with an existing one (same ID, or a
+    /// unique index in SQL), returning whether it was inserted
+    async fn insert(&self, record: &T) -> StorageResult<bool>;
+
+    /// Replace a record only while the stored one still matches a filter's
+    /// conditions (compare-and-swap), returning whether it was replaced
+    async fn replace_if(&self, record: &T, expected: &Filter) -> StorageResult<bool>;
+
+    /// Delete a record, returning whether it existed
+    async fn delete(&self, id: &str) -> StorageResult<bool>;
+
+    /// Find the first record matching a filter
+    async fn find(&self, filter: &Filter) -> StorageResult<Option<T>> {
+        Ok(self.list(&filter.clone().limit(1)).await?.into_iter().next())
+    }
+}
+
+macro_rules! site_entity {
+    ($ty:ty, $table:literal) => {
+        impl Entity for $ty {
+            const TABLE: &'static str = $table;
+
+            fn id(&self) -> &str {
+                &self.id
+            }
+
+            fn site_id(&self) -> Option<&str> {
+                Some(&self.site_id)
+            }
+        }
+    };
+}
+
+site_entity!(Show, "shows");
+site_entity!(Song, "songs");
+site_entity!(BlogPost, "posts");
+site_entity!(Photo, "photos");
+site_entity!(Video, "videos");
+site_entity!(Gallery, "galleries");
+site_entity!(Setlist, "setlists");
+site_entity!(BandMember, "band_members");
+site_entity!(ContactSubmission, "contact_submissions");
+site_entity!(RoleDefinition, "role_definitions");
+
+impl Entity for Site {
+    const TABLE: &'static str = "sites";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn site_id(&self) -> Option<&str> {
+        Some(&self.id)
+    }
+}
+
+impl Entity for User {
+    const TABLE: &'static str = "users";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Entity for Revision {
+    const TABLE: &'static str = "revisions";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn site_id(&self) -> Option<&str> {
+        Some(&self.site_id)
+    }
+
+    fn user_id(&self) -> Option<&str> {
+        self.author_id.as_deref()
+    }
+}
+
+impl Entity for MediaUpload {
+    const TABLE: &'static str = "uploads";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn site_id(&self) -> Option<&str> {
+        Some(&self.site_id)
+    }
+
+    fn user_id(&self) -> Option<&str> {
+        Some(&self.uploaded_by)
+    }
+}
+
+impl Entity for ResourceAccess {
+    const TABLE: &'static str = "access_entries";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn user_id(&self) -> Option<&str> {
+        Some(&self.user_id)
+    }
+}
+
+/// Every repository the API uses
+#[derive(Clone)]
+pub struct Storage {
+    pub sites: Arc<dyn Repository<Site>>,
+    pub shows: Arc<dyn Repository<Show>>,
+    pub songs: Arc<dyn Repository<Song>>,
+    pub posts: Arc<dyn Repository<BlogPost>>,
+    pub photos: Arc<dyn Repository<Photo>>,
+    pub videos: Arc<dyn Repository<Video>>,
+    pub galleries: Arc<dyn Repository<Gallery>>,
+    pub setlists: Arc<dyn Repository<Setlist>>,
+    pub band_members: Arc<dyn Repository<BandMember>>,
+    pub contact_submissions: Arc<dyn Repository<ContactSubmission>>,
+    pub users: Arc<dyn Repository<User>>,
+    pub role_definitions: Arc<dyn Repository<RoleDefinition>>,
+    pub access_entries: Arc<dyn Repository<ResourceAccess>>,
+    pub revisions: Arc<dyn Repository<Revision>>,
+    pub uploads: Arc<dyn Repository<MediaUpload>>,
+    pub credentials: CredentialStore,
+    pub gallery_passwords: GalleryPasswordStore,
+    pub video_passwords: VideoPasswordStore,
+    pub sessions: SessionStore,
+}
+
+impl Storage {
+    /// In-memory storage (tests, throwaway dev servers)
+    pub fn memory() -> Self {
+        Self {
+            sites: Arc::new(MemoryRepository::new()),
+            shows: Arc::new(MemoryRepository::new()),
+            songs: Arc::new(MemoryRepository::new()),
+            posts: Arc::new(MemoryRepository::new()),
+            photos: Arc::new(MemoryRepository::new()),
+            videos: Arc::new(MemoryRepository::new()),
+            galleries: Arc::new(MemoryRepository::new()),
+            setlists: Arc::new(MemoryRepository::new()),
+            band_members: Arc::new(MemoryRepository::new()),
+            contact_submissions: Arc::new(MemoryRepository::new()),
+            users: Arc::new(MemoryRepository::new()),
+            role_definitions: Arc::new(MemoryRepository::new()),
+            access_entries: Arc::new(MemoryRepository::new()),
+            revisions: Arc::new(MemoryRepository::new()),
+            uploads: Arc::new(MemoryRepository::new()),
+            credentials: CredentialStore::new(Arc::new(MemoryRepository::<PasswordCredential>::new())),
+            gallery_passwords: GalleryPasswordStore::new(Arc::new(MemoryRepository::<GalleryPassword>::new())),
+            video_passwords: VideoPasswordStore::new(Arc::new(MemoryRepository::<VideoPassword>::new())),
+            sessions: SessionStore::new(
+                Arc::new(MemoryRepository::<Session>::new()),
+                Arc::new(MemoryRepository::<RevokedToken>::new()),
+            ),
+        }
+    }
+
+    /// SQL storage over a SQLite or D1 connection (schema from `migrations::migrate`)
+    pub fn sql(executor: Arc<dyn SqlExecutor>) -> Self {
+        let repo = || executor.clone();
+        Self {
+            sites: Arc::new(SqlRepository::new(repo())),
+            shows: Arc::new(SqlRepository::new(repo())),
+            songs: Arc::new(SqlRepository::new(repo())),
+            posts: Arc::new(SqlRepository::new(repo())),
+            photos: Arc::new(SqlRepository::new(repo())),
+            videos: Arc::new(SqlRepository::new(repo())),
+            galleries: Arc::new(SqlRepository::new(repo())),
+            setlists: Arc::new(SqlRepository::new(repo())),
+            band_members: Arc::new(SqlRepository::new(repo())),
+            contact_submissions: Arc::new(SqlRepository::new(repo())),
+            users: Arc::new(SqlRepository::new(repo())),
+            role_definitions: Arc::new(SqlRepository::new(repo())),
+            access_entries: Arc::new(SqlRepository::new(repo())),
+            revisions: Arc::new(SqlRepository::new(repo())),
+            uploads: Arc::new(SqlRepository::new(repo())),
+            credentials: CredentialStore::new(Arc::new(SqlRepository::<PasswordCredential>::new(repo()))),
+            gallery_passwords: GalleryPasswordStore::new(Arc::new(SqlRepository::<GalleryPassword>::new(repo()))),
+            video_passwords: VideoPasswordStore::new(Arc::new(SqlRepository::<VideoPassword>::new(repo()))),
+            sessions: SessionStore::new(
+                Arc::new(SqlRepository::<Session>::new(repo())),
+                Arc::new(SqlRepository::<RevokedToken>::new(repo())),
+            ),
+        }
+    }
+
+    /// Load every record from an `AppState` snapshot, replacing records with the same ID
+    pub async fn import(&self, state: &AppState) -> StorageResult<()> {
+        for site in state.sites.values() {
+            self.sites.put(site).await?;
+        }
+        for show in state.shows.values() {
+            self.shows.put(show).await?;
+        }
+        for song in state.songs.values() {
+            self.songs.put(song).await?;
+        }
+        for post in state.posts.values() {
+            self.posts.put(post).await?;
+        }
+        for photo in state.photos.values() {
+            self.photos.put(photo).await?;
+        }
+        for video in state.videos.values() {
+            self.videos.put(video).await?;
+        }
+        for user in state.users.values() {
+            self.users.put(user).await?;
+        }
+        for role in state.role_definitions.values() {
+            self.role_definitions.put(role).await?;
+        }
+        for entry in state.access_lists.values() {
+            self.access_entries.put(entry).await?;
+        }
+        Ok(())
+    }
+}
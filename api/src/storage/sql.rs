@@ -0,0 +1,272 @@
+// SQL Storage
+//
+// Repository over any SQLite dialect database (rusqlite natively, D1 in the
+// Worker). Each table holds the record as JSON in `data`, plus the `id`,
+// `site_id` and `user_id` columns that filters use.
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use super::{Entity, Filter, Repository, Sort, StorageError, StorageResult};
+
+/// A connection that runs parameterized SQL (`?1`, `?2`, ... placeholders)
+#[async_trait]
+pub trait SqlExecutor: Send + Sync {
+    /// Run a statement, returning the number of changed rows
+    async fn execute(&self, sql: &str, params: &[Value]) -> StorageResult<u64>;
+
+    /// Run a query, returning rows as column name → value maps
+    async fn query(&self, sql: &str, params: &[Value]) -> StorageResult<Vec<Map<String, Value>>>;
+
+    /// Run a script of `;`-separated statements without parameters
+    async fn execute_batch(&self, sql: &str) -> StorageResult<()> {
+        for statement in split_statements(sql) {
+            self.execute(&statement, &[]).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Split a script into statements, dropping `--` comments
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let stripped: String = sql
+        .lines()
+        .map(|line| line.split("--").next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+    stripped
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Repository for one entity type over a SQL connection
+pub struct SqlRepository<T> {
+    executor: Arc<dyn SqlExecutor>,
+    _entity: PhantomData<fn() -> T>,
+}
+
+impl<T: Entity> SqlRepository<T> {
+    /// Create a repository for `T::TABLE`
+    pub fn new(executor: Arc<dyn SqlExecutor>) -> Self {
+        Self { executor, _entity: PhantomData }
+    }
+
+    fn decode(row: &Map<String, Value>) -> StorageResult<T> {
+        let corrupt = |reason: String| StorageError::Corrupt { table: T::TABLE, reason };
+        let data = row
+            .get("data")
+            .and_then(Value::as_str)
+            .ok_or_else(|| corrupt("missing data column".to_string()))?;
+        serde_json::from_str(data).map_err(|e| corrupt(e.to_string()))
+    }
+
+    /// Parameters `?1..?4` (id, site_id, user_id, data) of a record's row
+    fn row(record: &T) -> StorageResult<[Value; 4]> {
+        let data = serde_json::to_string(record)
+            .map_err(|e| StorageError::Corrupt { table: T::TABLE, reason: e.to_string() })?;
+        Ok([
+            Value::String(record.id().to_string()),
+            optional(record.site_id()),
+            optional(record.user_id()),
+            Value::String(data),
+        ])
+    }
+}
+
+/// Bind a parameter, returning its placeholder
+fn bind(value: Value, params: &mut Vec<Value>) -> String {
+    params.push(value);
+    format!("?{}", params.len())
+}
+
+/// `json_extract` of a top-level field
+fn extract(name: &str, params: &mut Vec<Value>) -> String {
+    format!("json_extract(data, {})", bind(Value::String(format!("$.{}", name)), params))
+}
+
+/// SQL conditions for a filter, all of which must hold
+fn conditions(filter: &Filter, params: &mut Vec<Value>) -> Vec<String> {
+    let mut all = Vec::new();
+
+    if let Some(site_ids) = &filter.site_ids {
+        if site_ids.is_empty() {
+            all.push("0".to_string());
+        } else {
+            let placeholders: Vec<String> = site_ids
+                .iter()
+                .map(|id| bind(Value::String(id.clone()), params))
+                .collect();
+            all.push(format!("site_id IN ({})", placeholders.join(", ")));
+        }
+    }
+    if let Some(user_id) = &filter.user_id {
+        let placeholder = bind(Value::String(user_id.clone()), params);
+        all.push(format!("user_id = {}", placeholder));
+    }
+    if !filter.excluded_ids.is_empty() {
+        // One JSON array parameter however many IDs (D1 caps bound parameters)
+        let placeholder = bind(Value::from(filter.excluded_ids.clone()), params);
+        all.push(format!("id NOT IN (SELECT value FROM json_each({}))", placeholder));
+    }
+    for (name, value) in &filter.fields {
+        let field = extract(name, params);
+        let value = bind(value.clone(), params);
+        all.push(format!("{} IS {}", field, value));
+    }
+    for (name, value) in &filter.contains {
+        let path = bind(Value::String(format!("$.{}", name)), params);
+        let value = bind(value.clone(), params);
+        all.push(format!("EXISTS (SELECT 1 FROM json_each(data, {}) WHERE value IS {})", path, value));
+    }
+    for range in &filter.ranges {
+        let fields: Vec<String> = range.fields.iter().map(|name| extract(name, params)).collect();
+        let number = match fields.as_slice() {
+            [field] => field.clone(),
+            _ => format!("COALESCE({})", fields.join(", ")),
+        };
+        if let Some(min) = range.min {
+            all.push(format!("{} >= {}", number, bind(Value::from(min), params)));
+        }
+        if let Some(max) = range.max {
+            all.push(format!("{} <= {}", number, bind(Value::from(max), params)));
+        }
+    }
+    if !filter.any.is_empty() {
+        let alternatives: Vec<String> = filter
+            .any
+            .iter()
+            .map(|alternative| match conditions(alternative, params).as_slice() {
+                [] => "1".to_string(),
+                each => format!("({})", each.join(" AND ")),
+            })
+            .collect();
+        all.push(format!("({})", alternatives.join(" OR ")));
+    }
+    if let Some((key, id)) = &filter.after {
+        all.push(after(filter.sort, key, id, params));
+    }
+
+    all
+}
+
+/// Condition for rows past a resume point in sort order; NULLs sort first
+/// ascending and last descending, as in SQLite
+fn after(sort: Option<Sort>, key: &Value, id: &str, params: &mut Vec<Value>) -> String {
+    let id = bind(Value::String(id.to_string()), params);
+    let Some(sort) = sort else {
+        return format!("id > {}", id);
+    };
+    let field = format!("{} COLLATE NOCASE", extract(sort.field, params));
+    match (key.is_null(), sort.descending) {
+        (true, false) => format!("({} IS NOT NULL OR id > {})", field, id),
+        (true, true) => format!("({} IS NULL AND id < {})", field, id),
+        (false, false) => {
+            let key = bind(key.clone(), params);
+            format!("({field} > {key} OR ({field} = {key} AND id > {id}))")
+        }
+        (false, true) => {
+            let key = bind(key.clone(), params);
+            format!("({field} < {key} OR {field} IS NULL OR ({field} = {key} AND id < {id}))")
+        }
+    }
+}
+
+/// Build a WHERE clause and its parameters for a filter
+fn where_clause(filter: &Filter, params: &mut Vec<Value>) -> String {
+    let conditions = conditions(filter, params);
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// ORDER BY, LIMIT and OFFSET clauses for a filter
+fn page_clause(filter: &Filter, params: &mut Vec<Value>) -> String {
+    let mut clause = match filter.sort {
+        Some(sort) => {
+            let direction = if sort.descending { "DESC" } else { "ASC" };
+            format!(" ORDER BY {} COLLATE NOCASE {dir}, id {dir}", extract(sort.field, params), dir = direction)
+        }
+        None => " ORDER BY id".to_string(),
+    };
+    // Inlined: D1 binds every number as a float
+    if filter.limit.is_some() || filter.offset > 0 {
+        let limit = filter.limit.map_or(-1, |limit| limit.min(i64::MAX as u64) as i64);
+        clause.push_str(&format!(" LIMIT {} OFFSET {}", limit, filter.offset.min(i64::MAX as u64)));
+    }
+    clause
+}
+
+fn optional(value: Option<&str>) -> Value {
+    value.map(|v| Value::String(v.to_string())).unwrap_or(Value::Null)
+}
+
+#[async_trait]
+impl<T: Entity> Repository<T> for SqlRepository<T> {
+    async fn get(&self, id: &str) -> StorageResult<Option<T>> {
+        let sql = format!("SELECT data FROM {} WHERE id = ?1", T::TABLE);
+        let rows = self.executor.query(&sql, &[Value::String(id.to_string())]).await?;
+        rows.first().map(Self::decode).transpose()
+    }
+
+    async fn list(&self, filter: &Filter) -> StorageResult<Vec<T>> {
+        let mut params = Vec::new();
+        let clause = where_clause(filter, &mut params);
+        let page = page_clause(filter, &mut params);
+        let sql = format!("SELECT data FROM {}{}{}", T::TABLE, clause, page);
+        let rows = self.executor.query(&sql, &params).await?;
+        rows.iter().map(Self::decode).collect()
+    }
+
+    async fn count(&self, filter: &Filter) -> StorageResult<u64> {
+        let mut params = Vec::new();
+        let clause = where_clause(filter, &mut params);
+        let sql = format!("SELECT COUNT(*) AS n FROM {}{}", T::TABLE, clause);
+        let rows = self.executor.query(&sql, &params).await?;
+        let n = rows.first().and_then(|row| row.get("n")).and_then(Value::as_f64);
+        Ok(n.unwrap_or_default() as u64)
+    }
+
+    async fn put(&self, record: &T) -> StorageResult<()> {
+        let sql = format!(
+            "INSERT INTO {} (id, site_id, user_id, data) VALUES (?1, ?2, ?3, ?4) \
+             ON CONFLICT(id) DO UPDATE SET site_id = excluded.site_id, \
+             user_id = excluded.user_id, data = excluded.data",
+            T::TABLE
+        );
+        self.executor.execute(&sql, &Self::row(record)?).await?;
+        Ok(())
+    }
+
+    async fn insert(&self, record: &T) -> StorageResult<bool> {
+        let sql = format!(
+            "INSERT INTO {} (id, site_id, user_id, data) VALUES (?1, ?2, ?3, ?4) ON CONFLICT DO NOTHING",
+            T::TABLE
+        );
+        Ok(self.executor.execute(&sql, &Self::row(record)?).await? > 0)
+    }
+
+    async fn replace_if(&self, record: &T, expected: &Filter) -> StorageResult<bool> {
+        let mut params = Self::row(record)?.to_vec();
+        let mut clause = vec!["id = ?1".to_string()];
+        clause.extend(conditions(&expected.conditions(), &mut params));
+        let sql = format!(
+            "UPDATE {} SET site_id = ?2, user_id = ?3, data = ?4 WHERE {}",
+            T::TABLE,
+            clause.join(" AND ")
+        );
+        Ok(self.executor.execute(&sql, &params).await? > 0)
+    }
+
+    async fn delete(&self, id: &str) -> StorageResult<bool> {
+        let sql = format!("DELETE FROM {} WHERE id = ?1", T::TABLE);
+        let changed = self.executor.execute(&sql, &[Value::String(id.to_string())]).await?;
+        Ok(changed > 0)
+    }
+}
@@ -0,0 +1,221 @@
+// SQLite Storage
+//
+// `SqlExecutor` over a local SQLite file, used by the native dev server.
+
+use async_trait::async_trait;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::sql::SqlExecutor;
+use super::{StorageError, StorageResult};
+
+/// SQLite connection shared behind a mutex
+pub struct SqliteExecutor {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteExecutor {
+    /// Open (or create) a database file
+    pub fn open(path: impl AsRef<Path>) -> StorageResult<Self> {
+        Connection::open(path).map(Self::new).map_err(backend)
+    }
+
+    /// Open a private in-memory database
+    pub fn open_in_memory() -> StorageResult<Self> {
+        Connection::open_in_memory().map(Self::new).map_err(backend)
+    }
+
+    fn new(connection: Connection) -> Self {
+        Self { connection: Mutex::new(connection) }
+    }
+
+    fn lock(&self) -> StorageResult<std::sync::MutexGuard<'_, Connection>> {
+        self.connection
+            .lock()
+            .map_err(|_| StorageError::Backend("sqlite connection lock poisoned".to_string()))
+    }
+}
+
+fn backend(error: rusqlite::Error) -> StorageError {
+    StorageError::Backend(error.to_string())
+}
+
+fn to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn from_sql(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+        ValueRef::Blob(b) => Value::String(String::from_utf8_lossy(b).into_owned()),
+    }
+}
+
+#[async_trait]
+impl SqlExecutor for SqliteExecutor {
+    async fn execute(&self, sql: &str, params: &[Value]) -> StorageResult<u64> {
+        let connection = self.lock()?;
+        let changed = connection
+            .execute(sql, params_from_iter(params.iter().map(to_sql)))
+            .map_err(backend)?;
+        Ok(changed as u64)
+    }
+
+    async fn query(&self, sql: &str, params: &[Value]) -> StorageResult<Vec<Map<String, Value>>> {
+        let connection = self.lock()?;
+        let mut statement = connection.prepare(sql).map_err(backend)?;
+        let columns: Vec<String> = statement.column_names().into_iter().map(str::to_string).collect();
+        let mut rows = statement
+            .query(params_from_iter(params.iter().map(to_sql)))
+            .map_err(backend)?;
+
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().map_err(backend)? {
+            let mut map = Map::new();
+            for (i, name) in columns.iter().enumerate() {
+                map.insert(name.clone(), from_sql(row.get_ref(i).map_err(backend)?));
+            }
+            result.push(map);
+        }
+        Ok(result)
+    }
+
+    async fn execute_batch(&self, sql: &str) -> StorageResult<()> {
+        self.lock()?.execute_batch(sql).map_err(backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{migrations, Filter, Storage};
+    use std::sync::Arc;
+    use web_nexus_contracts::{Role, Song, User, UserStatus};
+
+    fn song(id: &str, site_id: &str, title: &str) -> Song {
+        Song {
+            id: id.to_string(),
+            site_id: site_id.to_string(),
+            title: title.to_string(),
+            artist: None,
+            genres: Vec::new(),
+            duration_seconds: None,
+            is_original: true,
+            musical_key: None,
+            notes: None,
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sql_repository_round_trip() {
+        let executor = Arc::new(SqliteExecutor::open_in_memory().unwrap());
+        migrations::migrate(executor.as_ref()).await.unwrap();
+        let storage = Storage::sql(executor);
+
+        storage.songs.put(&song("song-1", "site-a", "Opener")).await.unwrap();
+        storage.songs.put(&song("song-2", "site-b", "Closer")).await.unwrap();
+        storage.songs.put(&song("song-1", "site-a", "Opener (live)")).await.unwrap();
+
+        assert_eq!(storage.songs.get("song-1").await.unwrap().unwrap().title, "Opener (live)");
+        assert_eq!(storage.songs.list(&Filter::all()).await.unwrap().len(), 2);
+        let site_a = storage.songs.list(&Filter::site("site-a")).await.unwrap();
+        assert_eq!(site_a.len(), 1);
+        assert_eq!(site_a[0].id, "song-1");
+
+        let found = storage
+            .songs
+            .find(&Filter::all().field("title", "Closer"))
+            .await
+            .unwrap();
+        assert_eq!(found.map(|s| s.id), Some("song-2".to_string()));
+
+        // Replaced only while the stored record still matches
+        let closer = Filter::all().field("title", "Closer");
+        let encore = song("song-2", "site-b", "Encore");
+        assert!(storage.songs.replace_if(&encore, &closer).await.unwrap());
+        assert!(!storage.songs.replace_if(&song("song-2", "site-b", "Lost"), &closer).await.unwrap());
+        assert!(!storage.songs.replace_if(&song("song-3", "site-b", "Closer"), &Filter::all()).await.unwrap());
+        assert_eq!(storage.songs.get("song-2").await.unwrap().unwrap().title, "Encore");
+
+        assert!(storage.songs.delete("song-2").await.unwrap());
+        assert!(!storage.songs.delete("song-2").await.unwrap());
+        assert!(storage.songs.get("song-2").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_users_are_found_by_role_in_sql() {
+        let executor = Arc::new(SqliteExecutor::open_in_memory().unwrap());
+        migrations::migrate(executor.as_ref()).await.unwrap();
+        let storage = Storage::sql(executor);
+
+        let custom = Role::Custom { role_id: "booker".to_string(), site_id: "site-a".to_string() };
+        for (id, roles) in [("user-1", vec![Role::Admin]), ("user-2", vec![Role::Content, custom.clone()]), ("user-3", vec![])] {
+            let user = User {
+                id: id.to_string(),
+                email: format!("{}@example.com", id),
+                name: id.to_string(),
+                roles,
+                status: UserStatus::Active,
+                created_at: 0,
+                last_login: None,
+            };
+            storage.users.put(&user).await.unwrap();
+        }
+
+        let ids = |users: Vec<User>| users.into_iter().map(|u| u.id).collect::<Vec<_>>();
+        let admins = storage.users.list(&Filter::all().contains("roles", serde_json::json!(Role::Admin))).await.unwrap();
+        assert_eq!(ids(admins), ["user-1"]);
+        let bookers = storage.users.list(&Filter::all().contains("roles", serde_json::json!(custom))).await.unwrap();
+        assert_eq!(ids(bookers), ["user-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_persist_in_sql() {
+        let executor = Arc::new(SqliteExecutor::open_in_memory().unwrap());
+        migrations::migrate(executor.as_ref()).await.unwrap();
+        let storage = Storage::sql(executor.clone());
+
+        let user = User {
+            id: "user-1".to_string(),
+            email: "a@example.com".to_string(),
+            name: "A".to_string(),
+            roles: Vec::new(),
+            status: UserStatus::Active,
+            created_at: 0,
+            last_login: None,
+        };
+        assert!(storage.users.insert(&user).await.unwrap());
+        // Emails are unique, whatever the ID
+        let twin = User { id: "user-2".to_string(), ..user.clone() };
+        assert!(!storage.users.insert(&twin).await.unwrap());
+        assert!(!storage.users.insert(&user).await.unwrap());
+        let (session, token) = storage.sessions.create(&user.id, 1000).await.unwrap();
+
+        // A second handle on the same database sees the session
+        let reopened = Storage::sql(executor);
+        assert!(reopened.sessions.is_active(&session.id, "jti", 1001).await.unwrap());
+        assert!(reopened.sessions.rotate(&token, 1001).await.unwrap().is_ok());
+        let by_email = reopened
+            .users
+            .find(&Filter::all().field("email", "a@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(by_email.map(|u| u.id), Some("user-1".to_string()));
+    }
+}
@@ -0,0 +1,103 @@
+// Site Resolution
+//
+// Every request served on a site's domain is about that site. The `site`
+// middleware resolves it once per request from the Host header (the site's
+// custom domain, or `Site::default_domain`), falling back to an `X-Site-Id`
+// header holding a site ID or slug on the shared API host the CMS calls.
+// Handlers take the result as a `SiteContext` rather than trusting a site ID
+// from the request body.
+
+use axum::{async_trait, extract::FromRequestParts, http::header, http::request::Parts, http::HeaderMap};
+use web_nexus_contracts::{ApiErrorKind, Site, SITE_DOMAIN_SUFFIX};
+
+use crate::storage::{Filter, Storage, StorageResult};
+use crate::HandlerError;
+
+/// Header naming the site on the shared API host
+pub const SITE_HEADER: &str = "x-site-id";
+
+/// The site a request is for
+#[derive(Debug, Clone)]
+pub struct SiteContext {
+    pub site: Site,
+}
+
+impl SiteContext {
+    /// ID of the current site
+    pub fn id(&self) -> &str {
+        &self.site.id
+    }
+}
+
+/// Host the request was sent to, lowercased and without a port
+pub fn host_name(headers: &HeaderMap) -> Option<String> {
+    let host = headers.get(header::HOST)?.to_str().ok()?;
+    let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    Some(name.trim_end_matches('.').to_lowercase())
+}
+
+/// Normalize a custom domain for storage and lookup
+pub fn normalize_domain(domain: &str) -> String {
+    domain.trim().trim_end_matches('.').to_lowercase()
+}
+
+/// Find the site served at `host`: a custom domain, or `<slug>.webnexus.dev`
+pub async fn site_for_host(storage: &Storage, host: &str) -> StorageResult<Option<Site>> {
+    if let Some(site) = storage.sites.find(&Filter::all().field("domain", host)).await? {
+        return Ok(Some(site));
+    }
+    match host.strip_suffix(SITE_DOMAIN_SUFFIX).and_then(|s| s.strip_suffix('.')) {
+        Some(slug) if !slug.is_empty() && !slug.contains('.') => {
+            storage.sites.find(&Filter::all().field("slug", slug)).await
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Find a site by ID or slug
+pub async fn site_by_reference(storage: &Storage, reference: &str) -> StorageResult<Option<Site>> {
+    match storage.sites.get(reference).await? {
+        Some(site) => Ok(Some(site)),
+        None => storage.sites.find(&Filter::all().field("slug", reference)).await,
+    }
+}
+
+/// Resolve the site a request is for.
+///
+/// A Host that belongs to no site is the shared API host, so the request
+/// simply has no site; an `X-Site-Id` that names no site, or names a
+/// different site than the Host, is an error.
+pub async fn resolve(storage: &Storage, headers: &HeaderMap) -> Result<Option<Site>, ApiErrorKind> {
+    let requested = headers.get(SITE_HEADER).and_then(|v| v.to_str().ok());
+
+    let by_host = match host_name(headers) {
+        Some(host) => site_for_host(storage, &host).await?,
+        None => None,
+    };
+
+    match (by_host, requested) {
+        (Some(site), Some(reference)) if site.id != reference && site.slug != reference => Err(
+            ApiErrorKind::ValidationError("X-Site-Id does not match the site for this host".to_string()),
+        ),
+        (Some(site), _) => Ok(Some(site)),
+        (None, Some(reference)) => match site_by_reference(storage, reference).await? {
+            Some(site) => Ok(Some(site)),
+            None => Err(ApiErrorKind::NotFound("Site not found".to_string())),
+        },
+        (None, None) => Ok(None),
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SiteContext {
+    type Rejection = HandlerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<SiteContext>().cloned().ok_or_else(|| {
+            ApiErrorKind::ValidationError(
+                "No site for this request: call the site's domain or set X-Site-Id".to_string(),
+            )
+            .into()
+        })
+    }
+}
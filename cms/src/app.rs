@@ -6,7 +6,7 @@ use leptos::prelude::*;
 use leptos_router::components::{Router, Routes, Route};
 use leptos_router::path;
 use crate::stores::{AuthStore, UIStore};
-use crate::pages::{LoginPage, DashboardPage, ShowsPage, SongsPage};
+use crate::pages::{LoginPage, DashboardPage, ShowsPage, SongsPage, SetlistsPage};
 
 /// Main App component - root of the CMS admin portal
 #[component]
@@ -19,7 +19,8 @@ pub fn App() -> impl IntoView {
     let auth_store_login = auth_store.clone();
     let auth_store_dashboard = auth_store.clone();
     let auth_store_shows = auth_store.clone();
-    let auth_store_songs = auth_store;
+    let auth_store_songs = auth_store.clone();
+    let auth_store_setlists = auth_store;
 
     view! {
         <div class="cms-app">
@@ -42,6 +43,9 @@ pub fn App() -> impl IntoView {
                     <Route path=path!("/songs") view=move || {
                         view! { <SongsPage auth_store=auth_store_songs.clone() /> }
                     } />
+                    <Route path=path!("/setlists") view=move || {
+                        view! { <SetlistsPage auth_store=auth_store_setlists.clone() /> }
+                    } />
                 </Routes>
             </Router>
         </div>
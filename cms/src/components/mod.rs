@@ -4,6 +4,7 @@
 
 use leptos::prelude::*;
 use leptos_router::components::*;
+use web_nexus_contracts::revision::{DiffOp, FieldChange, Revision};
 use crate::stores::AuthStore;
 
 /// Header component for CMS pages
@@ -52,6 +53,9 @@ pub fn Sidebar() -> impl IntoView {
                 <li>
                     <A href="/songs">"Songs"</A>
                 </li>
+                <li>
+                    <A href="/setlists">"Setlists"</A>
+                </li>
                 <li>
                     <A href="/posts">"Blog Posts"</A>
                 </li>
@@ -230,3 +234,112 @@ pub fn Table(
         </div>
     }
 }
+
+/// Revision history panel: every saved version of a record, newest first,
+/// with what changed and a button to roll back to it
+#[component]
+pub fn HistoryPanel(
+    revisions: Signal<Vec<Revision>>,
+    on_restore: Callback<u32>,
+    on_close: Callback<leptos::ev::MouseEvent>,
+) -> impl IntoView {
+    let selected = RwSignal::new(None::<u32>);
+
+    view! {
+        <div class="history-panel">
+            <div class="history-header">
+                <h3>"History"</h3>
+                <Button
+                    label="Close".to_string()
+                    on_click=Some(on_close)
+                    variant=Some("secondary".to_string())
+                />
+            </div>
+            <ol class="history-list">
+                {move || {
+                    let revisions = revisions.get();
+                    let latest = revisions.iter().map(|r| r.number).max();
+                    revisions.into_iter().rev().map(|revision| {
+                        let number = revision.number;
+                        let when = js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(revision.created_at as f64 * 1000.0))
+                            .to_locale_string("en-US", &wasm_bindgen::JsValue::UNDEFINED)
+                            .as_string()
+                            .unwrap_or_default();
+                        let summary = revision.note.clone().unwrap_or_else(|| {
+                            let fields: Vec<&str> = revision.changes.iter().map(|c| c.field.as_str()).collect();
+                            format!("Changed {}", fields.join(", "))
+                        });
+                        let changes = revision.changes.clone();
+                        view! {
+                            <li class="history-entry">
+                                <button class="history-summary" on:click=move |_| {
+                                    selected.update(|s| *s = if *s == Some(number) { None } else { Some(number) });
+                                }>
+                                    <strong>{format!("#{}", number)}</strong>
+                                    <span>{summary}</span>
+                                    <span class="history-meta">
+                                        {revision.author_id.clone().unwrap_or_else(|| "Scheduler".to_string())}
+                                        " · "
+                                        {when}
+                                    </span>
+                                </button>
+                                {(latest != Some(number)).then(|| view! {
+                                    <Button
+                                        label="Restore".to_string()
+                                        variant=Some("secondary".to_string())
+                                        on_click=Some(Callback::new(move |_| on_restore.run(number)))
+                                    />
+                                })}
+                                {move || (selected.get() == Some(number)).then(|| view! {
+                                    <FieldChanges changes=changes.clone() />
+                                })}
+                            </li>
+                        }
+                    }).collect::<Vec<_>>()
+                }}
+            </ol>
+        </div>
+    }
+}
+
+/// What changed in one revision, field by field
+#[component]
+fn FieldChanges(changes: Vec<FieldChange>) -> impl IntoView {
+    let show = |value: &serde_json::Value| match value {
+        serde_json::Value::Null => "(empty)".to_string(),
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+
+    view! {
+        <dl class="field-changes">
+            {changes.into_iter().map(|change| {
+                let detail = match &change.lines {
+                    Some(lines) => view! {
+                        <pre class="line-diff">
+                            {lines.iter().map(|line| {
+                                let (class, marker) = match line.op {
+                                    DiffOp::Insert => ("diff-insert", "+ "),
+                                    DiffOp::Delete => ("diff-delete", "- "),
+                                    DiffOp::Equal => ("diff-equal", "  "),
+                                };
+                                view! { <span class=class>{format!("{}{}\n", marker, line.text)}</span> }
+                            }).collect::<Vec<_>>()}
+                        </pre>
+                    }.into_any(),
+                    None => view! {
+                        <span>
+                            <del>{show(&change.before)}</del>
+                            " → "
+                            <ins>{show(&change.after)}</ins>
+                        </span>
+                    }.into_any(),
+                };
+                view! {
+                    <dt>{change.field.clone()}</dt>
+                    <dd>{detail}</dd>
+                }
+            }).collect::<Vec<_>>()}
+        </dl>
+    }
+}
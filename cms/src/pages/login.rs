@@ -26,10 +26,6 @@ pub fn LoginPage(auth_store: AuthStore) -> impl IntoView {
     });
 
     let handle_login = {
-        let email = email.clone();
-        let password = password.clone();
-        let is_loading = is_loading.clone();
-        let error_message = error_message.clone();
         let auth_store = auth_store.clone();
 
         move |_| {
@@ -46,9 +42,9 @@ pub fn LoginPage(auth_store: AuthStore) -> impl IntoView {
             // For now, simulate login with a hardcoded user
             // Simulate API call with set_timeout
             let auth_store_clone = auth_store.clone();
-            let is_loading_clone = is_loading.clone();
-            let error_message_clone = error_message.clone();
-            let email_clone = email.clone();
+            let is_loading_clone = is_loading;
+            let error_message_clone = error_message;
+            let email_clone = email;
 
             // Simulate API call - for now just synchronous mock
             // In production, this would be a real API call
@@ -6,8 +6,10 @@ pub mod login;
 pub mod dashboard;
 pub mod shows;
 pub mod songs;
+pub mod setlists;
 
 pub use login::*;
 pub use dashboard::*;
 pub use shows::*;
 pub use songs::*;
+pub use setlists::*;
@@ -0,0 +1,316 @@
+// Web Nexus CMS - Setlist Builder Page
+//
+// Build a show's sets by dragging songs from the repertoire and between sets,
+// and print them for the stage
+
+use leptos::prelude::*;
+use leptos_router::components::Redirect;
+use web_nexus_contracts::export::{ExportFormat, ExportLayout, ExportOptions, SetlistChart};
+use web_nexus_contracts::setlist::{format_duration, key_changes, total_duration};
+use web_nexus_contracts::{Setlist, Song};
+use crate::stores::AuthStore;
+use crate::components::{Layout, Card, Button, Input};
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlannedSet {
+    id: String,
+    name: String,
+    song_ids: Vec<String>,
+}
+
+/// What is being dragged
+#[derive(Debug, Clone, PartialEq)]
+enum Dragged {
+    /// A song from the repertoire (added as a copy)
+    Repertoire(String),
+    /// A song already in a set
+    Placed { set: usize, index: usize },
+}
+
+fn mock_song(id: &str, title: &str, key: &str, duration_seconds: i32) -> Song {
+    Song {
+        id: id.to_string(),
+        site_id: "mike-and-the-monsters".to_string(),
+        title: title.to_string(),
+        artist: None,
+        genres: Vec::new(),
+        duration_seconds: Some(duration_seconds),
+        is_original: true,
+        musical_key: Some(key.to_string()),
+        notes: None,
+        created_at: 0,
+    }
+}
+
+/// A `data:` URL for a generated file, so export links work without a round trip
+fn data_url(content_type: &str, bytes: &[u8]) -> String {
+    let mut url = format!("data:{},", content_type.replace(' ', ""));
+    for &byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+/// Setlist builder page component
+#[component]
+pub fn SetlistsPage(auth_store: AuthStore) -> impl IntoView {
+    let is_authenticated = auth_store.is_authenticated;
+
+    // Redirect if not authenticated
+    let redirect = Signal::derive(move || {
+        if !is_authenticated.get() {
+            Some("/login".to_string())
+        } else {
+            None
+        }
+    });
+
+    // Mock repertoire and sets
+    let repertoire = StoredValue::new(vec![
+        mock_song("1", "Midnight Train", "E", 272),
+        mock_song("2", "Neon Dreams", "C#m", 225),
+        mock_song("3", "Hotel California", "Bm", 390),
+        mock_song("4", "Long Way Home", "G", 248),
+        mock_song("5", "Static", "Bb", 201),
+    ]);
+
+    let sets = RwSignal::new(vec![
+        PlannedSet {
+            id: "set-1".to_string(),
+            name: "First Set".to_string(),
+            song_ids: vec!["1".to_string(), "2".to_string(), "5".to_string()],
+        },
+        PlannedSet {
+            id: "set-2".to_string(),
+            name: "Encore".to_string(),
+            song_ids: vec!["3".to_string()],
+        },
+    ]);
+
+    let dragging = RwSignal::new(None::<Dragged>);
+    let new_set_name = RwSignal::new(String::new());
+    let stage_layout = RwSignal::new(false);
+
+    let songs_of = move |song_ids: &[String]| -> Vec<Song> {
+        repertoire.with_value(|songs| {
+            song_ids
+                .iter()
+                .filter_map(|id| songs.iter().find(|s| s.id == *id).cloned())
+                .collect()
+        })
+    };
+
+    // Drop the dragged song at `index` of set `target` (None = at the end)
+    let drop_at = move |target: usize, index: Option<usize>| {
+        let Some(dragged) = dragging.get_untracked() else {
+            return;
+        };
+        sets.update(|sets| {
+            let song_id = match dragged {
+                Dragged::Repertoire(song_id) => song_id,
+                Dragged::Placed { set, index: from } => {
+                    let song_id = sets[set].song_ids.remove(from);
+                    // Removing from earlier in the same set shifts the target left
+                    if let Some(index) = index {
+                        if set == target && from < index {
+                            let to = index - 1;
+                            sets[target].song_ids.insert(to, song_id);
+                            return;
+                        }
+                    }
+                    song_id
+                }
+            };
+            let songs = &mut sets[target].song_ids;
+            let index = index.unwrap_or(songs.len()).min(songs.len());
+            songs.insert(index, song_id);
+        });
+        dragging.set(None);
+    };
+
+    let handle_add_set = Callback::new(move |_| {
+        let name = new_set_name.get();
+        let name = if name.trim().is_empty() {
+            format!("Set {}", sets.with(Vec::len) + 1)
+        } else {
+            name
+        };
+        sets.update(|s| s.push(PlannedSet {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            song_ids: Vec::new(),
+        }));
+        new_set_name.set(String::new());
+    });
+
+    // Download link for one set in the chosen layout
+    let export_link = move |set: &PlannedSet, format: ExportFormat| {
+        let chart = SetlistChart {
+            setlist: Setlist {
+                id: set.id.clone(),
+                site_id: "mike-and-the-monsters".to_string(),
+                show_id: None,
+                song_ids: set.song_ids.clone(),
+                name: Some(set.name.clone()),
+                notes: None,
+                position: 0,
+                member_notes: Vec::new(),
+            },
+            songs: songs_of(&set.song_ids),
+            show: None,
+            members: Vec::new(),
+        };
+        let layout = if stage_layout.get() { ExportLayout::Stage } else { ExportLayout::Standard };
+        let options = ExportOptions { format, layout, ..Default::default() };
+        (data_url(format.content_type(), &chart.render(&options)), chart.file_name(format))
+    };
+
+    let show_total = move || {
+        sets.with(|sets| sets.iter().map(|set| total_duration(&songs_of(&set.song_ids)).0).sum::<i32>())
+    };
+
+    view! {
+        {move || {
+            redirect.get().map(|path| view! {
+                <Redirect path=path />
+            })
+        }}
+
+        <Layout title="Setlists".to_string() auth_store=auth_store>
+            <div class="setlists-page">
+                <div class="page-actions">
+                    <span class="setlist-total">
+                        "Show length: " {move || format_duration(show_total())}
+                    </span>
+                    <label>
+                        <input
+                            type="checkbox"
+                            prop:checked=move || stage_layout.get()
+                            on:change=move |e| stage_layout.set(event_target_checked(&e))
+                        />
+                        " Large-font stage layout"
+                    </label>
+                </div>
+
+                <div class="setlist-builder">
+                    <Card title=Some("Repertoire".to_string())>
+                        <ul class="setlist-repertoire">
+                            {repertoire.get_value().into_iter().map(|song| {
+                                let id = song.id.clone();
+                                view! {
+                                    <li
+                                        class="setlist-song"
+                                        draggable="true"
+                                        on:dragstart=move |_| dragging.set(Some(Dragged::Repertoire(id.clone())))
+                                    >
+                                        <span class="song-title">{song.title.clone()}</span>
+                                        <span class="song-key">{song.musical_key.clone().unwrap_or_default()}</span>
+                                        <span class="song-duration">
+                                            {song.duration_seconds.map(format_duration).unwrap_or_else(|| "--".to_string())}
+                                        </span>
+                                    </li>
+                                }
+                            }).collect::<Vec<_>>()}
+                        </ul>
+                    </Card>
+
+                    <div class="setlist-sets">
+                        {move || {
+                            sets.get().into_iter().enumerate().map(|(set_index, set)| {
+                                let songs = songs_of(&set.song_ids);
+                                let (duration, unknown) = total_duration(&songs);
+                                let warnings = key_changes(&songs);
+                                let (pdf_url, pdf_name) = export_link(&set, ExportFormat::Pdf);
+                                let (text_url, text_name) = export_link(&set, ExportFormat::Text);
+                                let summary = if unknown > 0 {
+                                    format!("{} ({} without a duration)", format_duration(duration), unknown)
+                                } else {
+                                    format_duration(duration)
+                                };
+
+                                view! {
+                                    <Card title=Some(set.name.clone())>
+                                        <ol
+                                            class="setlist-songs"
+                                            on:dragover=|e| e.prevent_default()
+                                            on:drop=move |e| {
+                                                e.prevent_default();
+                                                drop_at(set_index, None);
+                                            }
+                                        >
+                                            {songs.into_iter().enumerate().map(|(index, song)| {
+                                                let warning = warnings.iter()
+                                                    .find(|w| w.position as usize == index)
+                                                    .map(|w| format!("Key change {} → {}", w.from_key, w.to_key));
+                                                view! {
+                                                    <li
+                                                        class="setlist-song"
+                                                        draggable="true"
+                                                        on:dragstart=move |_| dragging.set(Some(Dragged::Placed { set: set_index, index }))
+                                                        on:dragover=|e| e.prevent_default()
+                                                        on:drop=move |e| {
+                                                            e.prevent_default();
+                                                            e.stop_propagation();
+                                                            drop_at(set_index, Some(index));
+                                                        }
+                                                    >
+                                                        <span class="song-title">{song.title.clone()}</span>
+                                                        <span class="song-key">{song.musical_key.clone().unwrap_or_default()}</span>
+                                                        <span class="song-duration">
+                                                            {song.duration_seconds.map(format_duration).unwrap_or_else(|| "--".to_string())}
+                                                        </span>
+                                                        {warning.map(|w| view! { <span class="key-change-warning">{w}</span> })}
+                                                        <button
+                                                            class="btn btn-link"
+                                                            on:click=move |_| sets.update(|s| { s[set_index].song_ids.remove(index); })
+                                                        >
+                                                            "Remove"
+                                                        </button>
+                                                    </li>
+                                                }
+                                            }).collect::<Vec<_>>()}
+                                        </ol>
+                                        <div class="setlist-footer">
+                                            <span>{summary}</span>
+                                            <a class="btn btn-secondary" href=pdf_url download=pdf_name>"Print (PDF)"</a>
+                                            <a class="btn btn-secondary" href=text_url download=text_name>"Text"</a>
+                                            <Button
+                                                label="Delete Set".to_string()
+                                                variant=Some("danger".to_string())
+                                                on_click=Some(Callback::new(move |_| {
+                                                    sets.update(|s| { s.remove(set_index); });
+                                                }))
+                                            />
+                                        </div>
+                                    </Card>
+                                }
+                            }).collect::<Vec<_>>()
+                        }}
+
+                        <Card title=Some("New Set".to_string())>
+                            <form class="setlist-form" on:submit=|e| e.prevent_default()>
+                                <Input
+                                    label="Name".to_string()
+                                    name="set-name".to_string()
+                                    placeholder=Some("Encore".to_string())
+                                    value=new_set_name
+                                />
+                                <div class="form-actions">
+                                    <Button
+                                        label="Add Set".to_string()
+                                        on_click=Some(handle_add_set)
+                                        variant=None
+                                    />
+                                </div>
+                            </form>
+                        </Card>
+                    </div>
+                </div>
+            </div>
+        </Layout>
+    }
+}
@@ -1,14 +1,17 @@
 // Web Nexus CMS - Shows Management Page
 //
-// CRUD interface for shows
+// CRUD interface for shows, with each show's revision history
 
+use std::collections::HashMap;
 use leptos::prelude::*;
 use leptos::either::Either;
 use leptos_router::components::Redirect;
+use serde::{Deserialize, Serialize};
+use web_nexus_contracts::revision::{diff, Revision};
 use crate::stores::AuthStore;
-use crate::components::{Layout, Card, Button, Table, Input};
+use crate::components::{Layout, Card, Button, Table, Input, HistoryPanel};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct Show {
     id: String,
     venue: String,
@@ -59,6 +62,34 @@ pub fn ShowsPage(auth_store: AuthStore) -> impl IntoView {
     let show_form = RwSignal::new(None::<Show>);
     let is_editing = RwSignal::new(false);
 
+    // Revisions per show, and the show whose history is open
+    let histories = RwSignal::new(HashMap::<String, Vec<Revision>>::new());
+    let history_for = RwSignal::new(None::<String>);
+    let author = auth_store.user_id;
+
+    let record = move |show: &Show, note: Option<String>| {
+        let snapshot = serde_json::to_value(show).unwrap_or_default();
+        histories.update(|h| {
+            let history = h.entry(show.id.clone()).or_default();
+            let previous = history.last().map(|r| r.snapshot.clone()).unwrap_or_default();
+            history.push(Revision {
+                id: uuid::Uuid::new_v4().to_string(),
+                site_id: String::new(),
+                resource_type: "show".to_string(),
+                resource_id: show.id.clone(),
+                number: history.len() as u32 + 1,
+                author_id: author.get_untracked(),
+                created_at: (js_sys::Date::now() / 1000.0) as i64,
+                note,
+                changes: diff(&previous, &snapshot),
+                snapshot,
+            });
+        });
+    };
+    for show in shows.get_untracked() {
+        record(&show, Some("Created".to_string()));
+    }
+
     let handle_new_show = Callback::new(move |_| {
         show_form.set(Some(Show {
             id: uuid::Uuid::new_v4().to_string(),
@@ -71,9 +102,6 @@ pub fn ShowsPage(auth_store: AuthStore) -> impl IntoView {
     });
 
     let handle_edit_show = Callback::new({
-        let shows = shows.clone();
-        let show_form = show_form.clone();
-        let is_editing = is_editing.clone();
         move |id: String| {
             let show = shows.get().into_iter().find(|s| s.id == id).unwrap();
             show_form.set(Some(show));
@@ -82,25 +110,25 @@ pub fn ShowsPage(auth_store: AuthStore) -> impl IntoView {
     });
 
     let handle_delete_show = Callback::new({
-        let shows = shows.clone();
         move |id: String| {
             shows.update(|s| s.retain(|show| show.id != id));
+            histories.update(|h| { h.remove(&id); });
         }
     });
 
     let handle_save_show = Callback::new({
-        let shows = shows.clone();
-        let show_form = show_form.clone();
-        let is_editing = is_editing.clone();
         move |_| {
             if let Some(show) = show_form.get() {
+                let mut is_new = false;
                 shows.update(|s| {
                     if let Some(existing) = s.iter().position(|x| x.id == show.id) {
                         s[existing] = show.clone();
                     } else {
                         s.push(show.clone());
+                        is_new = true;
                     }
                 });
+                record(&show, is_new.then(|| "Created".to_string()));
                 show_form.set(None);
                 is_editing.set(false);
             }
@@ -112,6 +140,23 @@ pub fn ShowsPage(auth_store: AuthStore) -> impl IntoView {
         is_editing.set(false);
     });
 
+    let handle_restore = Callback::new(move |number: u32| {
+        let Some(id) = history_for.get_untracked() else {
+            return;
+        };
+        let snapshot = histories.with_untracked(|h| {
+            h.get(&id).and_then(|r| r.iter().find(|r| r.number == number)).map(|r| r.snapshot.clone())
+        });
+        if let Some(show) = snapshot.and_then(|s| serde_json::from_value::<Show>(s).ok()) {
+            shows.update(|s| {
+                if let Some(existing) = s.iter().position(|x| x.id == show.id) {
+                    s[existing] = show.clone();
+                }
+            });
+            record(&show, Some(format!("Rolled back to revision {}", number)));
+        }
+    });
+
     view! {
         {move || {
             redirect.get().map(|path| view! {
@@ -171,6 +216,14 @@ pub fn ShowsPage(auth_store: AuthStore) -> impl IntoView {
                                                                     move |_| handle_edit_show.run(id.clone())
                                                                 }))
                                                             />
+                                                            <Button
+                                                                label="History".to_string()
+                                                                variant=Some("secondary".to_string())
+                                                                on_click=Some(Callback::new({
+                                                                    let id = show_clone.id.clone();
+                                                                    move |_| history_for.set(Some(id.clone()))
+                                                                }))
+                                                            />
                                                             <Button
                                                                 label="Delete".to_string()
                                                                 variant=Some("danger".to_string())
@@ -186,6 +239,21 @@ pub fn ShowsPage(auth_store: AuthStore) -> impl IntoView {
                                         }}
                                     </Table>
                                 </Card>
+
+                                {move || history_for.get().map(|id| {
+                                    let revisions = Signal::derive(move || {
+                                        histories.with(|h| h.get(&id).cloned().unwrap_or_default())
+                                    });
+                                    view! {
+                                        <Card title=None>
+                                            <HistoryPanel
+                                                revisions=revisions
+                                                on_restore=handle_restore
+                                                on_close=Callback::new(move |_| history_for.set(None))
+                                            />
+                                        </Card>
+                                    }
+                                })}
                             </div>
                         })
                     }
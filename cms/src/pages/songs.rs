@@ -67,12 +67,6 @@ pub fn SongsPage(auth_store: AuthStore) -> impl IntoView {
     });
 
     let handle_save_song = Callback::new({
-        let songs = songs.clone();
-        let show_form = show_form.clone();
-        let new_title = new_title.clone();
-        let new_artist = new_artist.clone();
-        let new_duration = new_duration.clone();
-        let new_is_original = new_is_original.clone();
         move |_| {
             let song = Song {
                 id: uuid::Uuid::new_v4().to_string(),
@@ -92,14 +86,12 @@ pub fn SongsPage(auth_store: AuthStore) -> impl IntoView {
     });
 
     let handle_cancel = Callback::new({
-        let show_form = show_form.clone();
         move |_| {
             show_form.set(false);
         }
     });
 
     let handle_delete_song = Callback::new({
-        let songs = songs.clone();
         move |id: String| {
             songs.update(|s| s.retain(|song| song.id != id));
         }
@@ -0,0 +1,133 @@
+// Content Rendering Module
+//
+// Turns authored post content into what the public site shows: Markdown is
+// rendered to HTML, then Markdown and HTML content alike go through the
+// allowlist sanitizer. The result is cached on the post (`BlogPost::rendered`)
+// together with an excerpt, generated from the content when the author didn't
+// write one.
+
+use crate::{markdown, sanitize, BlogPost, ContentFormat, RenderedContent};
+
+/// Bump when rendering or sanitizing changes, so cached renderings are redone
+pub const RENDERER_VERSION: u32 = 1;
+
+/// Length limit of generated excerpts, in characters
+pub const EXCERPT_LENGTH: usize = 200;
+
+/// Render content to sanitized HTML
+pub fn to_safe_html(content: &str, format: ContentFormat) -> String {
+    match format {
+        ContentFormat::Markdown => sanitize::sanitize(&markdown::to_html(content)),
+        ContentFormat::Html => sanitize::sanitize(content),
+    }
+}
+
+/// Render content, using `excerpt` if given or generating one otherwise
+pub fn render(content: &str, format: ContentFormat, excerpt: Option<&str>) -> RenderedContent {
+    let html = to_safe_html(content, format);
+    let excerpt = match excerpt.map(str::trim).filter(|e| !e.is_empty()) {
+        Some(excerpt) => excerpt.to_string(),
+        None => excerpt_of(&html, EXCERPT_LENGTH),
+    };
+    RenderedContent { html, excerpt, version: RENDERER_VERSION }
+}
+
+/// Plain-text excerpt of sanitized HTML: its text with whitespace collapsed,
+/// cut at a word boundary within `max_chars` (with an ellipsis if cut)
+pub fn excerpt_of(html: &str, max_chars: usize) -> String {
+    let text = collapse_whitespace(&decode_text(&strip_tags(html)));
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => &cut,
+    };
+    format!("{}…", cut.trim_end_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace()))
+}
+
+/// Tags that don't separate words
+const INLINE_TAGS: &[&str] = &["a", "abbr", "b", "code", "del", "em", "i", "ins", "s", "strong", "sub", "sup", "u"];
+
+/// Text of sanitized HTML. Block tags become spaces so paragraphs don't run
+/// together; code blocks are left out.
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(lt) = rest.find('<') {
+        text.push_str(&rest[..lt]);
+        rest = &rest[lt..];
+        let name: String = rest[1..]
+            .trim_start_matches('/')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect();
+        if name == "pre" {
+            rest = rest.find("</pre>").map_or("", |end| &rest[end + 6..]);
+        } else {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        }
+        if !INLINE_TAGS.contains(&name.as_str()) {
+            text.push(' ');
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Decode the entities the sanitizer leaves in text
+fn decode_text(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl BlogPost {
+    /// Re-render the cached HTML and excerpt (after content, format or
+    /// excerpt changes)
+    pub fn render(&mut self) {
+        self.rendered = Some(render(&self.content, self.content_format, self.excerpt.as_deref()));
+    }
+
+    /// Whether the cached rendering is missing or from an older renderer
+    pub fn needs_render(&self) -> bool {
+        self.rendered.as_ref().is_none_or(|r| r.version != RENDERER_VERSION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_sanitizes_markdown_and_html() {
+        let rendered = render(
+            "## New single\n\nOut **Friday**.<script>alert(1)</script> [Listen](javascript:alert(1))",
+            ContentFormat::Markdown,
+            None,
+        );
+        assert_eq!(rendered.html, "<h2>New single</h2>\n<p>Out <strong>Friday</strong>. <a>Listen</a></p>\n");
+        assert_eq!(rendered.excerpt, "New single Out Friday. Listen");
+
+        let html = to_safe_html("<p style=\"x\">**kept as is**</p><img src=x onerror=alert(1)>", ContentFormat::Html);
+        assert_eq!(html, "<p>**kept as is**</p><img src=\"x\" />");
+    }
+
+    #[test]
+    fn test_excerpts() {
+        let rendered = render("Intro", ContentFormat::Markdown, Some("  Written by hand "));
+        assert_eq!(rendered.excerpt, "Written by hand");
+
+        let html = "<p>We played the Roxy &amp; sold out,</p>\n<pre><code>skip me</code></pre><p>then the Troubadour.</p>";
+        assert_eq!(excerpt_of(html, 100), "We played the Roxy & sold out, then the Troubadour.");
+        assert_eq!(excerpt_of(html, 31), "We played the Roxy & sold out…");
+    }
+}
@@ -0,0 +1,524 @@
+// Setlist Export Module
+//
+// Turns a set into something to tape to the stage floor: plain text, or a PDF
+// written directly against the standard Helvetica fonts (nothing embedded, so
+// every viewer and printer has them). Both formats print the same lines. The
+// stage layout drops durations and uses type big enough to read mid-song.
+// The API serves exports and the CMS builds the same files in the browser.
+
+use std::str::FromStr;
+
+use crate::setlist::{format_duration, total_duration};
+use crate::{BandMember, Setlist, Show, Song};
+
+// ============================================================================
+// OPTIONS
+// ============================================================================
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Text,
+    Pdf,
+}
+
+impl ExportFormat {
+    /// MIME type of the exported file
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "text/plain; charset=utf-8",
+            ExportFormat::Pdf => "application/pdf",
+        }
+    }
+
+    /// File extension of the exported file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" | "txt" => Ok(ExportFormat::Text),
+            "pdf" => Ok(ExportFormat::Pdf),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+/// Page layout of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportLayout {
+    /// Everything, at reading size
+    #[default]
+    Standard,
+    /// Large type without durations, for the stage floor
+    Stage,
+}
+
+impl FromStr for ExportLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(ExportLayout::Standard),
+            "stage" => Ok(ExportLayout::Stage),
+            other => Err(format!("Unknown export layout: {}", other)),
+        }
+    }
+}
+
+/// Which band members' notes to print
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MemberNotes {
+    #[default]
+    None,
+    All,
+    /// One member's notes (a personal copy)
+    Member(String),
+}
+
+impl MemberNotes {
+    fn includes(&self, member_id: &str) -> bool {
+        match self {
+            MemberNotes::None => false,
+            MemberNotes::All => true,
+            MemberNotes::Member(id) => id == member_id,
+        }
+    }
+}
+
+/// How to export a set
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub layout: ExportLayout,
+    pub member_notes: MemberNotes,
+}
+
+// ============================================================================
+// LAYOUT
+// ============================================================================
+
+/// Kind of printed line, which decides its type size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Title,
+    Subtitle,
+    Song,
+    Note,
+    Footer,
+    Blank,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    style: Style,
+    text: String,
+    /// Right-hand column (key and duration)
+    aside: Option<String>,
+}
+
+impl Line {
+    fn new(style: Style, text: impl Into<String>) -> Self {
+        Self { style, text: text.into(), aside: None }
+    }
+
+    fn blank() -> Self {
+        Self::new(Style::Blank, "")
+    }
+}
+
+/// A set and everything printed with it
+#[derive(Debug, Clone)]
+pub struct SetlistChart {
+    pub setlist: Setlist,
+    /// The set's songs, in order
+    pub songs: Vec<Song>,
+    /// The show the set is for
+    pub show: Option<Show>,
+    /// Band members, to name the authors of member notes
+    pub members: Vec<BandMember>,
+}
+
+impl SetlistChart {
+    /// Render in the format the options ask for
+    pub fn render(&self, options: &ExportOptions) -> Vec<u8> {
+        match options.format {
+            ExportFormat::Text => self.to_text(options).into_bytes(),
+            ExportFormat::Pdf => self.to_pdf(options),
+        }
+    }
+
+    /// Download file name, e.g. `first-set.pdf`
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        let name = self.setlist.name.as_deref().unwrap_or("setlist");
+        let mut slug = String::new();
+        for c in name.chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let slug = slug.trim_end_matches('-');
+        format!("{}.{}", if slug.is_empty() { "setlist" } else { slug }, format.extension())
+    }
+
+    fn member_name<'a>(&'a self, member_id: &'a str) -> &'a str {
+        self.members.iter().find(|m| m.id == member_id).map_or(member_id, |m| m.name.as_str())
+    }
+
+    /// Member notes on one song (or on the whole set, for `None`)
+    fn member_notes(&self, song_id: Option<&str>, options: &ExportOptions) -> Vec<Line> {
+        self.setlist
+            .member_notes
+            .iter()
+            .filter(|n| n.song_id.as_deref() == song_id && options.member_notes.includes(&n.member_id))
+            .map(|n| Line::new(Style::Note, format!("{}: {}", self.member_name(&n.member_id), n.note)))
+            .collect()
+    }
+
+    fn lines(&self, options: &ExportOptions) -> Vec<Line> {
+        let stage = options.layout == ExportLayout::Stage;
+        let mut lines = Vec::new();
+
+        let title = self.setlist.name.clone().unwrap_or_else(|| "Setlist".to_string());
+        lines.push(Line::new(Style::Title, if stage { title.to_uppercase() } else { title }));
+        if let Some(show) = &self.show {
+            let date = chrono::DateTime::from_timestamp(show.date, 0)
+                .map(|d| d.format("%a %b %-d, %Y").to_string())
+                .unwrap_or_default();
+            lines.push(Line::new(Style::Subtitle, format!("{} - {} {}", show.venue, date, show.start_time)));
+        }
+        if let Some(notes) = &self.setlist.notes {
+            lines.push(Line::new(Style::Note, notes.clone()));
+        }
+        lines.extend(self.member_notes(None, options));
+        lines.push(Line::blank());
+
+        for (index, song) in self.songs.iter().enumerate() {
+            let key = song.musical_key.clone().unwrap_or_default();
+            let aside = if stage {
+                key
+            } else {
+                let duration = song.duration_seconds.map(format_duration).unwrap_or_else(|| "--".to_string());
+                format!("{:<5}{:>7}", key, duration)
+            };
+            let title = if stage { song.title.to_uppercase() } else { song.title.clone() };
+            lines.push(Line {
+                style: Style::Song,
+                text: format!("{}. {}", index + 1, title),
+                aside: Some(aside).filter(|a| !a.trim().is_empty()),
+            });
+            if let Some(notes) = &song.notes {
+                lines.push(Line::new(Style::Note, notes.clone()));
+            }
+            lines.extend(self.member_notes(Some(&song.id), options));
+            if stage {
+                lines.push(Line::blank());
+            }
+        }
+
+        if !stage {
+            let (seconds, unknown) = total_duration(&self.songs);
+            let mut footer = format!("{} songs, {}", self.songs.len(), format_duration(seconds));
+            if unknown > 0 {
+                footer.push_str(&format!(" (+{} without a duration)", unknown));
+            }
+            lines.push(Line::blank());
+            lines.push(Line::new(Style::Footer, footer));
+        }
+        lines
+    }
+
+    // ------------------------------------------------------------------------
+    // Plain text
+    // ------------------------------------------------------------------------
+
+    /// Plain text, one line per song
+    pub fn to_text(&self, options: &ExportOptions) -> String {
+        const SONG_WIDTH: usize = 48;
+        let mut out = String::new();
+        for line in self.lines(options) {
+            match line.style {
+                Style::Title => {
+                    out.push_str(&format!("{}\n{}\n", line.text, "=".repeat(line.text.chars().count())));
+                }
+                Style::Song => match &line.aside {
+                    Some(aside) => out.push_str(&format!("{:<width$} {}\n", line.text, aside, width = SONG_WIDTH)),
+                    None => out.push_str(&format!("{}\n", line.text)),
+                },
+                Style::Note => out.push_str(&format!("     {}\n", line.text)),
+                _ => out.push_str(&format!("{}\n", line.text)),
+            }
+        }
+        out
+    }
+
+    // ------------------------------------------------------------------------
+    // PDF
+    // ------------------------------------------------------------------------
+
+    /// A US Letter PDF
+    pub fn to_pdf(&self, options: &ExportOptions) -> Vec<u8> {
+        let stage = options.layout == ExportLayout::Stage;
+        let mut pages: Vec<String> = Vec::new();
+        let mut page = String::new();
+        let mut y = PAGE_HEIGHT - MARGIN;
+
+        for line in self.lines(options) {
+            let (font, size) = font_for(line.style, stage);
+            let leading = size * 1.3;
+            let indent = if line.style == Style::Note { size * 1.5 } else { 0.0 };
+            let aside_width = line.aside.as_ref().map_or(0.0, |_| if stage { 90.0 } else { 110.0 });
+            let width = PAGE_WIDTH - 2.0 * MARGIN - indent - aside_width;
+
+            for (i, text) in wrap(&line.text, width, size).into_iter().enumerate() {
+                if y - leading < MARGIN {
+                    pages.push(std::mem::take(&mut page));
+                    y = PAGE_HEIGHT - MARGIN;
+                }
+                y -= leading;
+                if line.style != Style::Blank {
+                    page.push_str(&text_op(font, size, MARGIN + indent, y, &text));
+                }
+                if let Some(aside) = line.aside.as_ref().filter(|_| i == 0) {
+                    page.push_str(&text_op(font, size, PAGE_WIDTH - MARGIN - aside_width, y, aside));
+                }
+            }
+        }
+        pages.push(page);
+        write_pdf(&pages)
+    }
+}
+
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 54.0;
+
+/// Font resource names, in the order `write_pdf` declares them
+const REGULAR: &str = "F1";
+const BOLD: &str = "F2";
+const OBLIQUE: &str = "F3";
+
+fn font_for(style: Style, stage: bool) -> (&'static str, f32) {
+    match (style, stage) {
+        (Style::Title, false) => (BOLD, 20.0),
+        (Style::Title, true) => (BOLD, 36.0),
+        (Style::Subtitle, false) => (REGULAR, 12.0),
+        (Style::Subtitle, true) => (REGULAR, 18.0),
+        (Style::Song, false) => (REGULAR, 13.0),
+        (Style::Song, true) => (BOLD, 30.0),
+        (Style::Note, false) => (OBLIQUE, 10.0),
+        (Style::Note, true) => (OBLIQUE, 18.0),
+        (Style::Footer, _) => (REGULAR, 10.0),
+        (Style::Blank, false) => (REGULAR, 8.0),
+        (Style::Blank, true) => (REGULAR, 14.0),
+    }
+}
+
+/// Break text into lines that fit `width` points. Helvetica averages about
+/// half an em per character; this errs wide so nothing runs off the page.
+fn wrap(text: &str, width: f32, size: f32) -> Vec<String> {
+    let max_chars = ((width / (size * 0.56)) as usize).max(8);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > max_chars {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    lines.push(current);
+    lines
+}
+
+/// Content stream operators drawing one run of text
+fn text_op(font: &str, size: f32, x: f32, y: f32, text: &str) -> String {
+    format!("BT /{} {} Tf {:.1} {:.1} Td ({}) Tj ET\n", font, size, x, y, pdf_text(text))
+}
+
+/// Escape text for a PDF string in WinAnsiEncoding, keeping the file ASCII
+fn pdf_text(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+                continue;
+            }
+            ' '..='~' => {
+                out.push(c);
+                continue;
+            }
+            '♭' => b'b',
+            '♯' => b'#',
+            '…' => 0x85,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '\u{a0}'..='\u{ff}' => c as u8,
+            _ => b'?',
+        };
+        if byte.is_ascii() {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("\\{:03o}", byte));
+        }
+    }
+    out
+}
+
+/// Assemble pages of content streams into a PDF file
+fn write_pdf(pages: &[String]) -> Vec<u8> {
+    // 1 catalog, 2 page tree, 3-5 fonts, then a page and its contents per page
+    let page_id = |i: usize| 6 + 2 * i;
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len()).map(|i| format!("{} 0 R", page_id(i))).collect::<Vec<_>>().join(" "),
+            pages.len()
+        ),
+    ];
+    for font in ["Helvetica", "Helvetica-Bold", "Helvetica-Oblique"] {
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", font));
+    }
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /{} 3 0 R /{} 4 0 R /{} 5 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, REGULAR, BOLD, OBLIQUE, page_id(i) + 1
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = out.len();
+    out.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref));
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemberNote, ShowStatus};
+
+    fn chart(count: usize) -> SetlistChart {
+        let songs: Vec<Song> = (0..count)
+            .map(|i| Song {
+                id: format!("song-{}", i),
+                site_id: "site-1".to_string(),
+                title: format!("Song (Take {})", i + 1),
+                artist: None,
+                genres: Vec::new(),
+                duration_seconds: if i == 1 { None } else { Some(200) },
+                is_original: true,
+                musical_key: Some("F#m".to_string()),
+                notes: (i == 0).then(|| "Segue — no count-in".to_string()),
+                created_at: 0,
+            })
+            .collect();
+        SetlistChart {
+            setlist: Setlist {
+                id: "set-1".to_string(),
+                site_id: "site-1".to_string(),
+                show_id: Some("show-1".to_string()),
+                song_ids: songs.iter().map(|s| s.id.clone()).collect(),
+                name: Some("First Set".to_string()),
+                notes: None,
+                position: 0,
+                member_notes: vec![
+                    MemberNote { member_id: "m1".to_string(), song_id: Some("song-0".to_string()), note: "Capo 2".to_string() },
+                    MemberNote { member_id: "m2".to_string(), song_id: None, note: "Bring the spare snare".to_string() },
+                ],
+            },
+            songs,
+            show: Some(Show {
+                id: "show-1".to_string(),
+                site_id: "site-1".to_string(),
+                title: "Show at The Roxy".to_string(),
+                venue: "The Roxy".to_string(),
+                address: None,
+                date: 1900000000,
+                start_time: "20:00".to_string(),
+                ticket_url: None,
+                description: None,
+                status: ShowStatus::Upcoming,
+                created_by: "user-1".to_string(),
+                created_at: 0,
+                updated_at: 0,
+            }),
+            members: vec![BandMember {
+                id: "m1".to_string(),
+                site_id: "site-1".to_string(),
+                name: "Mike".to_string(),
+                role: "Guitar".to_string(),
+                bio: None,
+                photo_id: None,
+                email: None,
+                display_order: 0,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_text_export_layouts_and_member_notes() {
+        let chart = chart(3);
+        let mine = ExportOptions { member_notes: MemberNotes::Member("m1".to_string()), ..Default::default() };
+        let text = chart.to_text(&mine);
+        assert!(text.starts_with("First Set\n=========\nThe Roxy - Sun Mar 17, 2030 20:00\n"));
+        assert!(text.contains("1. Song (Take 1)"));
+        assert!(text.contains("F#m     3:20\n     Segue — no count-in\n     Mike: Capo 2\n"));
+        assert!(!text.contains("spare snare"));
+        assert!(text.ends_with("3 songs, 6:40 (+1 without a duration)\n"));
+
+        let stage = ExportOptions { layout: ExportLayout::Stage, ..Default::default() };
+        let text = chart.to_text(&stage);
+        assert!(text.contains("1. SONG (TAKE 1)"));
+        assert!(!text.contains("3:20") && !text.contains("Capo 2"));
+        assert_eq!(chart.file_name(ExportFormat::Pdf), "first-set.pdf");
+    }
+
+    #[test]
+    fn test_pdf_export_is_well_formed() {
+        let options = ExportOptions { format: ExportFormat::Pdf, layout: ExportLayout::Stage, member_notes: MemberNotes::All };
+        let pdf = String::from_utf8(chart(20).render(&options)).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n") && pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("(1. SONG \\(TAKE 1\\)) Tj"));
+        assert!(pdf.contains("(Segue \\227 no count-in) Tj"));
+        assert!(pdf.contains("(m2: Bring the spare snare) Tj"));
+
+        // Twenty songs in stage type don't fit on one page
+        assert!(pdf.contains("/Count 2 >>"));
+
+        // startxref points at the cross-reference table
+        let start: usize = pdf.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(pdf[start..].starts_with("xref\n0 10\n"));
+    }
+}
@@ -0,0 +1,219 @@
+// Gallery Module
+//
+// A gallery is an ordered list of photos on one site, with an optional cover
+// and a visibility. This module holds the list operations the API and CMS
+// share (add, remove, reorder, cover fallback) and the read rule: who gets to
+// see a gallery's photos given its `GalleryVisibility` (videos follow it too).
+
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::images::ResponsiveImage;
+use crate::{Gallery, GalleryVisibility};
+
+/// Who is reading a gallery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GalleryReader {
+    /// May edit the gallery's site photos (sees every gallery in full)
+    pub manages_site: bool,
+    /// Belongs to the gallery's site (member list or a role on the site)
+    pub is_member: bool,
+    /// Holds an access token for this gallery
+    pub unlocked: bool,
+}
+
+/// What a reader may see of a gallery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GalleryAccess {
+    /// Everything
+    Full,
+    /// That it exists, but not its photos: a password unlocks it
+    Locked,
+    /// Nothing; the gallery is reported as missing
+    Denied,
+}
+
+impl GalleryVisibility {
+    /// What `reader` may see of a gallery (or video) with this visibility
+    pub fn access(&self, reader: GalleryReader) -> GalleryAccess {
+        if reader.manages_site {
+            return GalleryAccess::Full;
+        }
+        match self {
+            GalleryVisibility::Public => GalleryAccess::Full,
+            GalleryVisibility::Password if reader.unlocked => GalleryAccess::Full,
+            GalleryVisibility::Password => GalleryAccess::Locked,
+            GalleryVisibility::MembersOnly if reader.is_member => GalleryAccess::Full,
+            GalleryVisibility::MembersOnly | GalleryVisibility::Hidden => GalleryAccess::Denied,
+        }
+    }
+}
+
+impl Gallery {
+    /// What `reader` may see of this gallery
+    pub fn access(&self, reader: GalleryReader) -> GalleryAccess {
+        self.visibility.access(reader)
+    }
+
+    /// The gallery as shown while locked: no photos, no cover
+    pub fn locked(mut self) -> Self {
+        self.photo_ids.clear();
+        self.cover_photo_id = None;
+        self
+    }
+
+    /// The cover photo: the chosen one while it is still in the gallery,
+    /// otherwise the first photo
+    pub fn cover_id(&self) -> Option<&str> {
+        self.cover_photo_id
+            .as_deref()
+            .filter(|cover| self.photo_ids.iter().any(|id| id == cover))
+            .or_else(|| self.photo_ids.first().map(String::as_str))
+    }
+
+    /// Insert photos at `position` (the end if `None` or past it), skipping
+    /// ones already in the gallery. Returns how many were added.
+    pub fn add_photos(&mut self, photo_ids: &[String], position: Option<usize>) -> usize {
+        let mut new_ids: Vec<String> = Vec::new();
+        for id in photo_ids {
+            if !self.photo_ids.contains(id) && !new_ids.contains(id) {
+                new_ids.push(id.clone());
+            }
+        }
+        let added = new_ids.len();
+        let at = position.unwrap_or(self.photo_ids.len()).min(self.photo_ids.len());
+        self.photo_ids.splice(at..at, new_ids);
+        added
+    }
+
+    /// Take a photo out of the gallery (and off the cover). Returns whether
+    /// it was in the gallery.
+    pub fn remove_photo(&mut self, photo_id: &str) -> bool {
+        let before = self.photo_ids.len();
+        self.photo_ids.retain(|id| id != photo_id);
+        if self.cover_photo_id.as_deref() == Some(photo_id) {
+            self.cover_photo_id = None;
+        }
+        self.photo_ids.len() != before
+    }
+}
+
+// ============================================================================
+// REQUESTS AND RESPONSES
+// ============================================================================
+
+/// Request to add photos to a gallery
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddGalleryPhotosRequest {
+    /// Photos to add, in order
+    #[garde(length(min = 1))]
+    pub photo_ids: Vec<String>,
+    /// Index to insert them at (defaults to the end)
+    #[garde(skip)]
+    pub position: Option<usize>,
+}
+
+/// Request to put a gallery's photos in a new order
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderGalleryPhotosRequest {
+    /// The gallery's photo IDs, in their new order
+    #[garde(skip)]
+    pub photo_ids: Vec<String>,
+}
+
+/// Request to choose (or, with `null`, clear) a gallery's cover
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetGalleryCoverRequest {
+    #[garde(skip)]
+    pub photo_id: Option<String>,
+}
+
+/// Request to unlock a password-protected gallery (or video)
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockGalleryRequest {
+    #[garde(length(min = 1))]
+    pub password: String,
+}
+
+/// Access token for one gallery, sent back in the `X-Gallery-Token` header
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GalleryAccessToken {
+    pub gallery_id: String,
+    pub token: String,
+    pub expires_at: i64,
+}
+
+/// A gallery's photos ready for `srcset` markup, with its cover
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GalleryImages {
+    pub gallery_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// The chosen cover, or the first photo
+    pub cover: Option<ResponsiveImage>,
+    pub images: Vec<ResponsiveImage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gallery(visibility: GalleryVisibility) -> Gallery {
+        Gallery {
+            id: "g".to_string(),
+            site_id: "s".to_string(),
+            title: "Tour".to_string(),
+            description: None,
+            photo_ids: vec!["a".to_string(), "b".to_string()],
+            cover_photo_id: Some("b".to_string()),
+            visibility,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_photo_order_and_cover_fallback() {
+        let mut g = gallery(GalleryVisibility::Public);
+        assert_eq!(g.cover_id(), Some("b"));
+        let ids = ["c", "a", "c", "d"].map(String::from);
+        assert_eq!(g.add_photos(&ids, Some(1)), 2);
+        assert_eq!(g.photo_ids, ["a", "c", "d", "b"]);
+
+        assert!(g.remove_photo("b"));
+        assert_eq!((g.cover_photo_id.as_deref(), g.cover_id()), (None, Some("a")));
+        assert!(!g.remove_photo("b"));
+        g.photo_ids.clear();
+        assert_eq!(g.cover_id(), None);
+    }
+
+    #[test]
+    fn test_access_by_visibility() {
+        let visitor = GalleryReader::default();
+        let member = GalleryReader { is_member: true, ..visitor };
+        let manager = GalleryReader { manages_site: true, ..visitor };
+        let unlocked = GalleryReader { unlocked: true, ..visitor };
+
+        let password = gallery(GalleryVisibility::Password);
+        assert_eq!(password.access(visitor), GalleryAccess::Locked);
+        assert_eq!(password.access(member), GalleryAccess::Locked);
+        assert_eq!(password.access(unlocked), GalleryAccess::Full);
+        assert_eq!(gallery(GalleryVisibility::MembersOnly).access(visitor), GalleryAccess::Denied);
+        assert_eq!(gallery(GalleryVisibility::MembersOnly).access(member), GalleryAccess::Full);
+        assert_eq!(gallery(GalleryVisibility::Hidden).access(member), GalleryAccess::Denied);
+        assert_eq!(gallery(GalleryVisibility::Hidden).access(manager), GalleryAccess::Full);
+        assert!(password.locked().photo_ids.is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+// Image Derivatives Module
+//
+// Photos are shown at many sizes (grid thumbnails, lightboxes, phone and
+// desktop widths), so each one is offered as a square thumbnail plus a ladder
+// of responsive widths, in AVIF and WebP with a JPEG/PNG fallback. This module
+// decides which derivatives a photo gets (`plan`) and turns the ones stored on
+// a photo into `srcset`-ready markup data (`Photo::responsive`). Producing the
+// derivatives themselves is the API's image processor's job.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{ImageDimensions, Photo};
+
+/// Edge of the square thumbnail, in pixels
+pub const THUMBNAIL_SIZE: u32 = 400;
+
+/// Responsive widths offered, in pixels (never wider than the original)
+pub const RESPONSIVE_WIDTHS: [u32; 6] = [320, 640, 960, 1280, 1920, 2560];
+
+/// Formats every responsive width is offered in, best first
+pub const MODERN_FORMATS: [ImageFormat; 2] = [ImageFormat::Avif, ImageFormat::Webp];
+
+/// `sizes` used when the caller doesn't say how wide images are shown
+pub const DEFAULT_SIZES: &str = "100vw";
+
+/// Image encoding of a derivative
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFormat {
+    Avif,
+    Webp,
+    Jpeg,
+    Png,
+    Gif,
+}
+
+impl ImageFormat {
+    /// MIME type of the format
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "image/avif",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+
+    /// Format of a MIME type, if it is one of these
+    pub fn from_mime(content_type: &str) -> Option<Self> {
+        match content_type.trim().to_ascii_lowercase().as_str() {
+            "image/avif" => Some(ImageFormat::Avif),
+            "image/webp" => Some(ImageFormat::Webp),
+            "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+            "image/png" => Some(ImageFormat::Png),
+            "image/gif" => Some(ImageFormat::Gif),
+            _ => None,
+        }
+    }
+
+    /// Format older browsers get for an original of this format: PNG and GIF
+    /// keep their transparency (and animation), everything else is JPEG
+    pub fn fallback_for(original: Option<ImageFormat>) -> Self {
+        match original {
+            Some(ImageFormat::Png) => ImageFormat::Png,
+            Some(ImageFormat::Gif) => ImageFormat::Gif,
+            _ => ImageFormat::Jpeg,
+        }
+    }
+}
+
+/// What a derivative is for
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum DerivativeKind {
+    /// Square crop for grids and pickers
+    Thumbnail,
+    /// Scaled-down copy for `srcset`
+    Responsive,
+}
+
+/// A derivative to produce: `width` x `height`, cropped to fill for
+/// thumbnails and scaled (keeping the aspect ratio) otherwise
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivativeSpec {
+    pub kind: DerivativeKind,
+    pub width: u32,
+    pub height: u32,
+    pub format: ImageFormat,
+}
+
+impl DerivativeSpec {
+    /// The produced derivative, served at `url`
+    pub fn at(&self, url: String) -> ImageDerivative {
+        ImageDerivative {
+            kind: self.kind,
+            width: self.width as i32,
+            height: self.height as i32,
+            format: self.format,
+            url,
+        }
+    }
+}
+
+/// A stored derivative of a photo
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDerivative {
+    pub kind: DerivativeKind,
+    pub width: i32,
+    pub height: i32,
+    pub format: ImageFormat,
+    pub url: String,
+}
+
+/// The derivatives a photo of `dimensions` gets, given its original format.
+/// Nothing is upscaled; an original narrower than the widest responsive width
+/// is also offered at its own width. Photos of unknown size get none.
+pub fn plan(dimensions: &ImageDimensions, original: Option<ImageFormat>) -> Vec<DerivativeSpec> {
+    let (Ok(width), Ok(height)) = (u32::try_from(dimensions.width), u32::try_from(dimensions.height)) else {
+        return Vec::new();
+    };
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let edge = THUMBNAIL_SIZE.min(width).min(height);
+    let mut specs = vec![DerivativeSpec {
+        kind: DerivativeKind::Thumbnail,
+        width: edge,
+        height: edge,
+        format: ImageFormat::Webp,
+    }];
+
+    let widest = RESPONSIVE_WIDTHS[RESPONSIVE_WIDTHS.len() - 1];
+    let mut widths: Vec<u32> = RESPONSIVE_WIDTHS.iter().copied().filter(|w| *w < width).collect();
+    if width <= widest {
+        widths.push(width);
+    } else if widths.last() != Some(&widest) {
+        widths.push(widest);
+    }
+
+    let formats = MODERN_FORMATS.into_iter().chain(std::iter::once(ImageFormat::fallback_for(original)));
+    for format in formats {
+        for &w in &widths {
+            let h = ((height as u64 * w as u64 + width as u64 / 2) / width as u64).max(1) as u32;
+            specs.push(DerivativeSpec { kind: DerivativeKind::Responsive, width: w, height: h, format });
+        }
+    }
+    specs
+}
+
+// ============================================================================
+// SRCSET RESPONSES
+// ============================================================================
+
+/// One `<source>` of a `<picture>`
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSource {
+    /// MIME type (`type` attribute)
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub srcset: String,
+}
+
+/// A photo ready for `<picture>`/`<img srcset>` markup
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponsiveImage {
+    pub photo_id: String,
+    /// `src` for browsers without `srcset`: the largest fallback derivative,
+    /// or the original
+    pub src: String,
+    /// `srcset` in the fallback format (empty if the size is unknown)
+    pub srcset: String,
+    /// Modern-format sources, best first
+    pub sources: Vec<ImageSource>,
+    pub sizes: String,
+    /// Original size, for the `width`/`height` attributes
+    pub width: i32,
+    pub height: i32,
+    pub alt: String,
+    pub thumbnail_url: String,
+    pub caption: Option<String>,
+}
+
+impl Photo {
+    /// This photo's derivatives as `srcset` data, for images shown at `sizes`
+    pub fn responsive(&self, sizes: &str) -> ResponsiveImage {
+        let srcset_of = |format: ImageFormat| {
+            let mut derivatives: Vec<&ImageDerivative> = self
+                .derivatives
+                .iter()
+                .filter(|d| d.kind == DerivativeKind::Responsive && d.format == format)
+                .collect();
+            derivatives.sort_by_key(|d| d.width);
+            derivatives
+        };
+        let join = |derivatives: &[&ImageDerivative]| {
+            derivatives.iter().map(|d| format!("{} {}w", d.url, d.width)).collect::<Vec<_>>().join(", ")
+        };
+
+        let sources = MODERN_FORMATS
+            .into_iter()
+            .filter_map(|format| {
+                let derivatives = srcset_of(format);
+                (!derivatives.is_empty()).then(|| ImageSource { content_type: format.mime().to_string(), srcset: join(&derivatives) })
+            })
+            .collect();
+
+        let original = self.content_type.as_deref().and_then(ImageFormat::from_mime);
+        let fallback = srcset_of(ImageFormat::fallback_for(original));
+        let (src, srcset) = match fallback.last() {
+            Some(largest) => (largest.url.clone(), join(&fallback)),
+            None if self.dimensions.width > 0 => (self.url_full.clone(), format!("{} {}w", self.url_full, self.dimensions.width)),
+            None => (self.url_full.clone(), String::new()),
+        };
+
+        ResponsiveImage {
+            photo_id: self.id.clone(),
+            src,
+            srcset,
+            sources,
+            sizes: sizes.to_string(),
+            width: self.dimensions.width,
+            height: self.dimensions.height,
+            alt: self.alt_text.clone().unwrap_or_default(),
+            thumbnail_url: self.url_thumb.clone(),
+            caption: self.caption.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_never_upscales() {
+        let specs = plan(&ImageDimensions { width: 1000, height: 750 }, Some(ImageFormat::Png));
+        assert_eq!(specs[0], DerivativeSpec { kind: DerivativeKind::Thumbnail, width: 400, height: 400, format: ImageFormat::Webp });
+        let png: Vec<(u32, u32)> = specs.iter().filter(|s| s.format == ImageFormat::Png).map(|s| (s.width, s.height)).collect();
+        assert_eq!(png, vec![(320, 240), (640, 480), (960, 720), (1000, 750)]);
+        assert_eq!(specs.len(), 1 + 3 * 4);
+
+        // Huge originals stop at the widest width; tiny ones get one of each
+        let specs = plan(&ImageDimensions { width: 6000, height: 4000 }, None);
+        assert_eq!(specs.iter().map(|s| s.width).max(), Some(2560));
+        assert!(specs.iter().any(|s| s.format == ImageFormat::Jpeg));
+        let specs = plan(&ImageDimensions { width: 200, height: 100 }, None);
+        assert_eq!(specs.iter().map(|s| (s.width, s.height)).collect::<Vec<_>>(), vec![(100, 100), (200, 100), (200, 100), (200, 100)]);
+        assert!(plan(&ImageDimensions { width: 0, height: 0 }, None).is_empty());
+    }
+
+    #[test]
+    fn test_responsive_image_groups_srcsets_by_format() {
+        let mut photo: Photo = serde_json::from_value(serde_json::json!({
+            "id": "p1", "siteId": "s", "filename": "a.jpg", "urlFull": "/a.jpg", "urlThumb": "/a.jpg",
+            "sizeBytes": 1, "dimensions": { "width": 700, "height": 350 }, "altText": "Crowd",
+            "caption": null, "tags": [], "uploadedAt": 0, "uploadedBy": "u", "contentType": "image/jpeg"
+        }))
+        .unwrap();
+
+        // No derivatives yet: the original is the only candidate
+        let image = photo.responsive(DEFAULT_SIZES);
+        assert_eq!((image.src.as_str(), image.srcset.as_str()), ("/a.jpg", "/a.jpg 700w"));
+        assert!(image.sources.is_empty());
+
+        photo.derivatives = plan(&photo.dimensions, Some(ImageFormat::Jpeg))
+            .iter()
+            .map(|s| s.at(format!("/d/{}.{:?}", s.width, s.format).to_lowercase()))
+            .collect();
+        let image = photo.responsive("50vw");
+        assert_eq!(image.src, "/d/700.jpeg");
+        assert_eq!(image.srcset, "/d/320.jpeg 320w, /d/640.jpeg 640w, /d/700.jpeg 700w");
+        assert_eq!(image.sources[0], ImageSource {
+            content_type: "image/avif".to_string(),
+            srcset: "/d/320.avif 320w, /d/640.avif 640w, /d/700.avif 700w".to_string(),
+        });
+        assert_eq!((image.sources[1].content_type.as_str(), image.sizes.as_str(), image.alt.as_str()), ("image/webp", "50vw", "Crowd"));
+    }
+}
@@ -11,6 +11,26 @@ use serde::{Deserialize, Serialize};
 use garde::Validate;
 use utoipa::ToSchema;
 
+pub mod content;
+pub mod export;
+pub mod gallery;
+pub mod images;
+pub mod markdown;
+pub mod media;
+pub mod rbac;
+pub mod revision;
+pub mod sanitize;
+pub mod setlist;
+pub mod video;
+
+pub use rbac::{
+    can_access_resource, has_all_permissions, has_any_permission, has_permission,
+    has_site_permission, Permission, ResourceAccess, RoleDefinition, RolePermissions, SiteScope,
+    RESOURCE_TYPES,
+};
+pub use revision::{FieldChange, Revision, RevisionComparison, RevisionSummary};
+pub use setlist::{KeyChangeWarning, MusicalKey, SetlistDetail, ShowSetlist};
+
 // ============================================================================
 // USER & AUTHENTICATION CONTRACTS
 // ============================================================================
@@ -51,6 +71,8 @@ pub enum Role {
     ReadOnly,
     /// Site-specific access (e.g., band member)
     SiteEditor { site_id: String },
+    /// Site-defined role; permissions come from its `RoleDefinition`
+    Custom { role_id: String, site_id: String },
 }
 
 /// User account status
@@ -158,6 +180,8 @@ pub struct Song {
 pub struct Setlist {
     /// Unique setlist ID
     pub id: String,
+    /// Site this setlist belongs to
+    pub site_id: String,
     /// Associated show ID
     pub show_id: Option<String>,
     /// List of song IDs in order
@@ -166,6 +190,25 @@ pub struct Setlist {
     pub name: Option<String>,
     /// Notes
     pub notes: Option<String>,
+    /// Order among the show's sets (0 first)
+    #[serde(default)]
+    pub position: i32,
+    /// Notes for individual band members
+    #[serde(default)]
+    pub member_notes: Vec<MemberNote>,
+}
+
+/// A setlist note for one band member, on the whole set or on one song
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberNote {
+    /// Band member ID
+    pub member_id: String,
+    /// Song the note is about (`None` for the whole set)
+    pub song_id: Option<String>,
+    /// The note (e.g., "Capo 2", "Switch to the Tele")
+    pub note: String,
 }
 
 // ============================================================================
@@ -201,11 +244,26 @@ pub struct Photo {
     pub uploaded_at: i64,
     /// Uploaded by user ID
     pub uploaded_by: String,
+    /// Content type detected from the file (uploaded photos)
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// SHA-256 of the original file, hex encoded (uploaded photos)
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Object storage key of the original (uploaded photos)
+    #[serde(default)]
+    pub storage_key: Option<String>,
+    /// Camera data (uploaded photos)
+    #[serde(default)]
+    pub exif: Option<media::ExifData>,
+    /// Thumbnail and responsive sizes (see `images`)
+    #[serde(default)]
+    pub derivatives: Vec<images::ImageDerivative>,
 }
 
 /// Image dimensions
 
-#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ImageDimensions {
     pub width: i32,
@@ -242,8 +300,10 @@ pub struct Gallery {
 pub enum GalleryVisibility {
     /// Publicly visible
     Public,
-    /// Password protected
-    Password { password: String },
+    /// Password protected. The password itself is never part of the
+    /// gallery: it is set through `password` on create/update requests and
+    /// stored only as a hash, apart from the gallery.
+    Password,
     /// Only site members can see
     MembersOnly,
     /// Hidden (draft)
@@ -273,7 +333,8 @@ pub struct Video {
     pub thumbnail_url: Option<String>,
     /// Duration in seconds
     pub duration_seconds: Option<i32>,
-    /// Video visibility
+    /// Video visibility (read as for galleries; a password-protected video
+    /// is unlocked with its own token)
     pub visibility: GalleryVisibility,
     /// View count
     pub view_count: i64,
@@ -287,8 +348,12 @@ pub struct Video {
 pub enum VideoSource {
     /// YouTube video
     YouTube { video_id: String },
-    /// Vimeo video
-    Vimeo { video_id: String },
+    /// Vimeo video (`hash` unlocks private videos)
+    Vimeo {
+        video_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hash: Option<String>,
+    },
     /// Direct upload (MP4, WebM)
     Direct { url: String },
     /// External embed
@@ -312,10 +377,16 @@ pub struct BlogPost {
     pub title: String,
     /// Post slug (URL-friendly)
     pub slug: String,
-    /// Post content (Markdown or HTML)
+    /// Post content, as written by the author
     pub content: String,
-    /// Post excerpt
+    /// Format of `content`
+    #[serde(default)]
+    pub content_format: ContentFormat,
+    /// Post excerpt (written by the author)
     pub excerpt: Option<String>,
+    /// Sanitized HTML and excerpt rendered from `content` (cached on save)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<RenderedContent>,
     /// Cover image ID
     pub cover_image_id: Option<String>,
     /// Author user ID
@@ -330,6 +401,31 @@ pub struct BlogPost {
     pub updated_at: i64,
 }
 
+/// Format of authored content
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentFormat {
+    /// Markdown (inline HTML allowed, and sanitized like HTML content)
+    #[default]
+    Markdown,
+    /// HTML
+    Html,
+}
+
+/// Content rendered for display
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedContent {
+    /// Sanitized HTML, safe to insert into a page
+    pub html: String,
+    /// Plain-text excerpt: the author's, or generated from the content
+    pub excerpt: String,
+    /// Renderer version that produced this (older renderings are redone on read)
+    pub version: u32,
+}
+
 /// Blog post status
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
@@ -345,6 +441,36 @@ pub enum PostStatus {
     Archived,
 }
 
+/// A step in the blog post publishing workflow
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PostTransition {
+    /// Make a draft or scheduled post live now
+    Publish,
+    /// Take a post back to draft (also cancels a schedule or restores from the archive)
+    Unpublish,
+    /// Publish a draft at a later time (or move an existing schedule)
+    Schedule,
+    /// Retire a post without deleting it
+    Archive,
+}
+
+impl PostStatus {
+    /// Status after a workflow step, or `None` if the step isn't allowed from
+    /// this status
+    pub fn after(&self, transition: PostTransition) -> Option<PostStatus> {
+        use PostStatus::*;
+        match (self, transition) {
+            (Draft | Scheduled, PostTransition::Publish) => Some(Published),
+            (Published | Scheduled | Archived, PostTransition::Unpublish) => Some(Draft),
+            (Draft | Scheduled, PostTransition::Schedule) => Some(Scheduled),
+            (Draft | Published, PostTransition::Archive) => Some(Archived),
+            _ => None,
+        }
+    }
+}
+
 // ============================================================================
 // SITE CONTRACTS
 // ============================================================================
@@ -378,6 +504,36 @@ pub struct Site {
     pub created_at: i64,
 }
 
+/// A site as shown to visitors: its name and look, without its owner,
+/// members or configuration
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicSite {
+    /// Unique site ID
+    pub id: String,
+    /// Site identifier (used in URLs)
+    pub slug: String,
+    /// Site name
+    pub name: String,
+    /// Custom domain
+    pub domain: Option<String>,
+    /// Site description
+    pub description: Option<String>,
+    /// Site theme
+    pub theme: String,
+}
+
+/// A site as the caller may see it: in full for its members and editors,
+/// its public face for everyone else
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum SiteView {
+    Full(Site),
+    Public(PublicSite),
+}
+
 /// Site deployment status
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
@@ -476,6 +632,25 @@ pub struct PaginatedResponse<T> {
     pub has_next: bool,
     /// Has previous page?
     pub has_prev: bool,
+    /// Opaque cursor for the next page (pass back as `cursor`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> PaginatedResponse<T> {
+    /// The same page with each item converted
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> PaginatedResponse<U> {
+        PaginatedResponse {
+            data: self.data.into_iter().map(f).collect(),
+            page: self.page,
+            per_page: self.per_page,
+            total: self.total,
+            total_pages: self.total_pages,
+            has_next: self.has_next,
+            has_prev: self.has_prev,
+            next_cursor: self.next_cursor,
+        }
+    }
 }
 
 /// API error response
@@ -506,6 +681,9 @@ pub enum ApiErrorKind {
     #[error("Forbidden")]
     Forbidden,
 
+    #[error("Method not allowed")]
+    MethodNotAllowed,
+
     #[error("Validation error: {0}")]
     ValidationError(String),
 
@@ -517,7 +695,6 @@ pub enum ApiErrorKind {
 pub type Result<T> = std::result::Result<T, ApiErrorKind>;
 
 /// Convert ApiErrorKind to ApiError response struct
-
 impl From<ApiErrorKind> for ApiError {
     fn from(kind: ApiErrorKind) -> Self {
         let (code, message) = match &kind {
@@ -526,6 +703,8 @@ kind.to_string()),
             ApiErrorKind::Unauthorized => ("UNAUTHORIZED".to_string(),
 kind.to_string()),
             ApiErrorKind::Forbidden => ("FORBIDDEN".to_string(),
+kind.to_string()),
+            ApiErrorKind::MethodNotAllowed => ("METHOD_NOT_ALLOWED".to_string(),
 kind.to_string()),
             ApiErrorKind::ValidationError(_) => ("VALIDATION_ERROR".to_string(),
 kind.to_string()),
@@ -568,18 +747,51 @@ pub struct LoginResponse {
     pub expires_at: i64,
 }
 
+/// Account registration request
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterRequest {
+    /// Email address
+    #[garde(email)]
+    pub email: String,
+    /// Display name
+    #[garde(length(min = 1))]
+    pub name: String,
+    /// Password
+    #[garde(length(min = 8))]
+    pub password: String,
+    /// Deploy-time `ADMIN_BOOTSTRAP_TOKEN`: registers the instance's first
+    /// admin (only while there is none)
+    #[garde(skip)]
+    pub bootstrap_token: Option<String>,
+}
+
+/// Password change request
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordRequest {
+    /// Current password
+    #[garde(length(min = 1))]
+    pub current_password: String,
+    /// New password
+    #[garde(length(min = 8))]
+    pub new_password: String,
+}
+
 // ============================================================================
 // API REQUEST/RESPONSE DTOs
 // ============================================================================
+//
+// Create requests for site content carry no site ID: the API takes the site
+// from the request itself (its Host, or `X-Site-Id` on the shared API host).
 
 /// Request to create a new show
 
 #[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateShowRequest {
-    /// Site ID
-    #[garde(length(min = 1))]
-    pub site_id: String,
     /// Show date (Unix timestamp)
     #[garde(skip)]
     pub date: i64,
@@ -603,7 +815,7 @@ pub struct UpdateShowRequest {
     #[garde(skip)]
     pub date: Option<i64>,
     /// Venue name
-    #[garde(skip)]
+    #[garde(inner(length(min = 1)))]
     pub venue: Option<String>,
     /// City
     #[garde(skip)]
@@ -613,6 +825,67 @@ pub struct UpdateShowRequest {
     pub setlist_id: Option<String>,
 }
 
+/// Request to create a custom role for a site
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateRoleRequest {
+    /// Role name (e.g. "Tour Manager")
+    #[garde(length(min = 1, max = 64))]
+    pub name: String,
+    /// Description
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// Permissions granted by the role
+    #[garde(skip)]
+    pub permissions: Vec<Permission>,
+}
+
+/// Request to update a custom role
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRoleRequest {
+    /// Role name
+    #[garde(inner(length(min = 1, max = 64)))]
+    pub name: Option<String>,
+    /// Description
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// Permissions granted by the role (replaces the current set)
+    #[garde(skip)]
+    pub permissions: Option<Vec<Permission>>,
+}
+
+/// Request to replace a user's roles
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignRolesRequest {
+    /// New role list
+    #[garde(skip)]
+    pub roles: Vec<Role>,
+}
+
+/// Request to grant a user permissions on a single resource
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantAccessRequest {
+    /// User receiving the access
+    #[garde(length(min = 1))]
+    pub user_id: String,
+    /// Resource type (e.g. "gallery", "show")
+    #[garde(length(min = 1))]
+    pub resource_type: String,
+    /// Resource ID
+    #[garde(length(min = 1))]
+    pub resource_id: String,
+    /// Permissions to grant
+    #[garde(length(min = 1))]
+    pub permissions: Vec<Permission>,
+}
+
 /// Request to create a new song
 
 #[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
@@ -637,19 +910,19 @@ pub struct CreateSongRequest {
 #[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateBlogPostRequest {
-    /// Site ID
-    #[garde(length(min = 1))]
-    pub site_id: String,
     /// Post title
     #[garde(length(min = 1))]
     pub title: String,
     /// URL slug
     #[garde(length(min = 1))]
     pub slug: String,
-    /// Post content (Markdown or HTML)
+    /// Post content
     #[garde(length(min = 1))]
     pub content: String,
-    /// Short excerpt
+    /// Format of `content` (defaults to Markdown)
+    #[garde(skip)]
+    pub content_format: Option<ContentFormat>,
+    /// Short excerpt (generated from the content if not given)
     #[garde(skip)]
     pub excerpt: Option<String>,
     /// Featured image URL
@@ -663,14 +936,21 @@ pub struct CreateBlogPostRequest {
     pub published_at: Option<i64>,
 }
 
+/// Request to schedule a blog post
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulePostRequest {
+    /// When to publish (Unix timestamp, must be in the future)
+    #[garde(skip)]
+    pub published_at: i64,
+}
+
 /// Request to create a photo
 
 #[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreatePhotoRequest {
-    /// Site ID
-    #[garde(length(min = 1))]
-    pub site_id: String,
     /// Photo title
     #[garde(length(min = 1))]
     pub title: String,
@@ -696,19 +976,18 @@ pub struct CreatePhotoRequest {
 #[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateVideoRequest {
-    /// Site ID
-    #[garde(length(min = 1))]
-    pub site_id: String,
     /// Video title
     #[garde(length(min = 1))]
     pub title: String,
-    /// Video URL
+    /// Video URL: a YouTube or Vimeo link, or a video file (see
+    /// `video::classify`)
     #[garde(length(min = 1))]
     pub url: String,
-    /// Thumbnail URL
+    /// Thumbnail URL (looked up with oEmbed when omitted)
     #[garde(skip)]
     pub thumbnail_url: Option<String>,
-    /// Video type (youtube, vimeo, etc.)
+    /// Ignored: the source is detected from `url`. Kept so older clients
+    /// still validate.
     #[garde(skip)]
     pub video_type: Option<String>,
     /// Duration in seconds
@@ -717,6 +996,389 @@ pub struct CreateVideoRequest {
     /// Description
     #[garde(skip)]
     pub description: Option<String>,
+    /// Visibility (defaults to public)
+    #[garde(skip)]
+    pub visibility: Option<GalleryVisibility>,
+    /// Password, for `Password` visibility
+    #[garde(inner(length(min = 4, max = 128)))]
+    pub password: Option<String>,
+}
+
+/// Request to update a song
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSongRequest {
+    /// Song title
+    #[garde(inner(length(min = 1)))]
+    pub title: Option<String>,
+    /// Original artist (for covers)
+    #[garde(skip)]
+    pub artist: Option<String>,
+    /// Genre tags
+    #[garde(skip)]
+    pub genres: Option<Vec<String>>,
+    /// Duration in seconds
+    #[garde(skip)]
+    pub duration_seconds: Option<u32>,
+    /// Whether it's original or cover
+    #[garde(skip)]
+    pub is_original: Option<bool>,
+    /// Song key (musical key)
+    #[garde(skip)]
+    pub musical_key: Option<String>,
+    /// Notes for band members
+    #[garde(skip)]
+    pub notes: Option<String>,
+}
+
+/// Request to update a blog post
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBlogPostRequest {
+    /// Post title
+    #[garde(inner(length(min = 1)))]
+    pub title: Option<String>,
+    /// URL slug
+    #[garde(inner(length(min = 1)))]
+    pub slug: Option<String>,
+    /// Post content
+    #[garde(inner(length(min = 1)))]
+    pub content: Option<String>,
+    /// Format of `content`
+    #[garde(skip)]
+    pub content_format: Option<ContentFormat>,
+    /// Short excerpt
+    #[garde(skip)]
+    pub excerpt: Option<String>,
+    /// Cover image (photo ID)
+    #[garde(skip)]
+    pub cover_image_id: Option<String>,
+}
+
+/// Request to update a photo
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePhotoRequest {
+    /// Alt text
+    #[garde(skip)]
+    pub alt_text: Option<String>,
+    /// Caption
+    #[garde(skip)]
+    pub caption: Option<String>,
+    /// Tags
+    #[garde(skip)]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Request to update a video
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateVideoRequest {
+    /// Video title
+    #[garde(inner(length(min = 1)))]
+    pub title: Option<String>,
+    /// Description
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// Thumbnail URL
+    #[garde(skip)]
+    pub thumbnail_url: Option<String>,
+    /// Duration in seconds
+    #[garde(skip)]
+    pub duration_seconds: Option<u32>,
+    /// Visibility
+    #[garde(skip)]
+    pub visibility: Option<GalleryVisibility>,
+    /// New password, for `Password` visibility (required when switching to
+    /// it, kept when omitted)
+    #[garde(inner(length(min = 4, max = 128)))]
+    pub password: Option<String>,
+}
+
+/// Access token for one video, sent back in the `X-Video-Token` header
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoAccessToken {
+    pub video_id: String,
+    pub token: String,
+    pub expires_at: i64,
+}
+
+/// Request to create a gallery
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateGalleryRequest {
+    /// Gallery title
+    #[garde(length(min = 1))]
+    pub title: String,
+    /// Description
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// Photo IDs in display order
+    #[serde(default)]
+    #[garde(skip)]
+    pub photo_ids: Vec<String>,
+    /// Cover photo ID
+    #[garde(skip)]
+    pub cover_photo_id: Option<String>,
+    /// Visibility (defaults to public)
+    #[garde(skip)]
+    pub visibility: Option<GalleryVisibility>,
+    /// Password, for `Password` visibility
+    #[garde(inner(length(min = 4, max = 128)))]
+    pub password: Option<String>,
+}
+
+/// Request to update a gallery
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateGalleryRequest {
+    /// Gallery title
+    #[garde(inner(length(min = 1)))]
+    pub title: Option<String>,
+    /// Description
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// Photo IDs in display order
+    #[garde(skip)]
+    pub photo_ids: Option<Vec<String>>,
+    /// Cover photo ID
+    #[garde(skip)]
+    pub cover_photo_id: Option<String>,
+    /// Visibility
+    #[garde(skip)]
+    pub visibility: Option<GalleryVisibility>,
+    /// New password, for `Password` visibility (required when switching to
+    /// it, kept when omitted)
+    #[garde(inner(length(min = 4, max = 128)))]
+    pub password: Option<String>,
+}
+
+/// Request to create a setlist
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSetlistRequest {
+    /// Associated show ID
+    #[garde(skip)]
+    pub show_id: Option<String>,
+    /// Song IDs in order
+    #[serde(default)]
+    #[garde(skip)]
+    pub song_ids: Vec<String>,
+    /// Set name
+    #[garde(skip)]
+    pub name: Option<String>,
+    /// Notes
+    #[garde(skip)]
+    pub notes: Option<String>,
+    /// Notes for individual band members
+    #[serde(default)]
+    #[garde(skip)]
+    pub member_notes: Vec<MemberNote>,
+}
+
+/// Request to update a setlist
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSetlistRequest {
+    /// Associated show ID
+    #[garde(skip)]
+    pub show_id: Option<String>,
+    /// Song IDs in order
+    #[garde(skip)]
+    pub song_ids: Option<Vec<String>>,
+    /// Set name
+    #[garde(skip)]
+    pub name: Option<String>,
+    /// Notes
+    #[garde(skip)]
+    pub notes: Option<String>,
+    /// Notes for individual band members (replaces all of them)
+    #[garde(skip)]
+    pub member_notes: Option<Vec<MemberNote>>,
+}
+
+/// Request to put a set's songs in a new order
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderSongsRequest {
+    /// The set's song IDs, in their new order
+    #[garde(skip)]
+    pub song_ids: Vec<String>,
+}
+
+/// Request to move one song (drag and drop), within its set or into another
+/// set of the same show
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveSongRequest {
+    /// Current index of the song
+    #[garde(skip)]
+    pub from: usize,
+    /// Index to move it to (in the target set)
+    #[garde(skip)]
+    pub to: usize,
+    /// Target set (defaults to the same set)
+    #[garde(skip)]
+    pub to_setlist_id: Option<String>,
+}
+
+/// Request to put a show's sets in a new order
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderSetsRequest {
+    /// The show's setlist IDs, in their new order
+    #[garde(skip)]
+    pub setlist_ids: Vec<String>,
+}
+
+/// Request to add a band member
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBandMemberRequest {
+    /// Member name
+    #[garde(length(min = 1))]
+    pub name: String,
+    /// Role in the band (e.g., "Lead Guitar")
+    #[garde(length(min = 1))]
+    pub role: String,
+    /// Biography
+    #[garde(skip)]
+    pub bio: Option<String>,
+    /// Photo ID
+    #[garde(skip)]
+    pub photo_id: Option<String>,
+    /// Contact email
+    #[garde(inner(email))]
+    pub email: Option<String>,
+    /// Display order (lowest first)
+    #[serde(default)]
+    #[garde(skip)]
+    pub display_order: i32,
+}
+
+/// Request to update a band member
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBandMemberRequest {
+    /// Member name
+    #[garde(inner(length(min = 1)))]
+    pub name: Option<String>,
+    /// Role in the band
+    #[garde(inner(length(min = 1)))]
+    pub role: Option<String>,
+    /// Biography
+    #[garde(skip)]
+    pub bio: Option<String>,
+    /// Photo ID
+    #[garde(skip)]
+    pub photo_id: Option<String>,
+    /// Contact email
+    #[garde(inner(email))]
+    pub email: Option<String>,
+    /// Display order
+    #[garde(skip)]
+    pub display_order: Option<i32>,
+}
+
+/// Request to create a site
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSiteRequest {
+    /// URL-safe slug (lowercase letters, digits and dashes)
+    #[garde(length(min = 1, max = 63), custom(is_slug))]
+    pub slug: String,
+    /// Site name
+    #[garde(length(min = 1))]
+    pub name: String,
+    /// Custom domain
+    #[garde(skip)]
+    pub domain: Option<String>,
+    /// Description
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// Theme name (defaults to "default")
+    #[garde(skip)]
+    pub theme: Option<String>,
+}
+
+/// Request to add a user to a site's members
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddSiteMemberRequest {
+    /// User to add
+    #[garde(length(min = 1))]
+    pub user_id: String,
+}
+
+/// Request to update a site
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSiteRequest {
+    /// Site name
+    #[garde(inner(length(min = 1)))]
+    pub name: Option<String>,
+    /// Custom domain
+    #[garde(skip)]
+    pub domain: Option<String>,
+    /// Description
+    #[garde(skip)]
+    pub description: Option<String>,
+    /// Theme name
+    #[garde(skip)]
+    pub theme: Option<String>,
+    /// Site configuration (replaces the existing config)
+    #[garde(skip)]
+    pub config: Option<serde_json::Value>,
+    /// Site status
+    #[garde(skip)]
+    pub status: Option<SiteStatus>,
+}
+
+/// Contact form submission from a public site
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateContactSubmissionRequest {
+    /// Sender name
+    #[garde(length(min = 1, max = 200))]
+    pub name: String,
+    /// Sender email
+    #[garde(email)]
+    pub email: String,
+    /// Subject
+    #[garde(inner(length(max = 200)))]
+    pub subject: Option<String>,
+    /// Message body
+    #[garde(length(min = 1, max = 5000))]
+    pub message: String,
+}
+
+/// Request to update a contact submission
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateContactSubmissionRequest {
+    /// Read flag
+    #[garde(skip)]
+    pub is_read: Option<bool>,
 }
 
 // ============================================================================
@@ -746,10 +1408,33 @@ impl User {
 }
 
 
+/// Parent domain of every site's default domain (`<slug>.webnexus.dev`)
+pub const SITE_DOMAIN_SUFFIX: &str = "webnexus.dev";
+
+impl BandMember {
+    /// The member as shown to visitors: without their contact email
+    pub fn public(mut self) -> Self {
+        self.email = None;
+        self
+    }
+}
+
 impl Site {
     /// Get the default domain for this site
     pub fn default_domain(&self) -> String {
-        format!("{}.webnexus.dev", self.slug)
+        format!("{}.{}", self.slug, SITE_DOMAIN_SUFFIX)
+    }
+
+    /// The site as shown to visitors
+    pub fn public(self) -> PublicSite {
+        PublicSite {
+            id: self.id,
+            slug: self.slug,
+            name: self.name,
+            domain: self.domain,
+            description: self.description,
+            theme: self.theme,
+        }
     }
 }
 
@@ -757,7 +1442,21 @@ impl Site {
 // VALIDATION HELPERS
 // ============================================================================
 
+/// Custom validation: slug is lowercase letters, digits and single dashes
+fn is_slug(slug: &str, _context: &()) -> garde::Result {
+    let valid = slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && !slug.contains("--");
+    if valid {
+        Ok(())
+    } else {
+        Err(garde::Error::new("slug may only contain lowercase letters, digits and dashes"))
+    }
+}
+
 /// Custom validation: date must be in the future
+#[allow(dead_code)]
 fn is_future_date(date: i64) -> std::result::Result<(), garde::Error> {
     let now = chrono::Utc::now().timestamp();
     if date > now {
@@ -798,10 +1497,50 @@ export type Role =
   | {{ type: "Content" }}
   | {{ type: "Media" }}
   | {{ type: "ReadOnly" }}
-  | {{ type: "SiteEditor", siteId: string }};
+  | {{ type: "SiteEditor", siteId: string }}
+  | {{ type: "Custom", roleId: string, siteId: string }};
 
 export type UserStatus = "Active" | "Pending" | "Suspended" | "Deleted";
 
+export interface Gallery {{
+  id: string;
+  siteId: string;
+  title: string;
+  description?: string;
+  photoIds: string[];
+  coverPhotoId?: string;
+  visibility: GalleryVisibility;
+  createdAt: number;
+}}
+
+// Password galleries (and videos) never carry their password; unlock one with
+// POST /api/galleries/:id/unlock and send the token as X-Gallery-Token (for
+// videos: POST /api/videos/:id/unlock and X-Video-Token)
+export type GalleryVisibility = "public" | "password" | "membersOnly" | "hidden";
+
+export interface GalleryAccessToken {{
+  galleryId: string;
+  token: string;
+  expiresAt: number;
+}}
+
+// Sites are returned in full to their members and editors; everyone else
+// gets a PublicSite, and only of active sites
+export interface PublicSite {{
+  id: string;
+  slug: string;
+  name: string;
+  domain?: string;
+  description?: string;
+  theme: string;
+}}
+
+export interface VideoAccessToken {{
+  videoId: string;
+  token: string;
+  expiresAt: number;
+}}
+
 // ... (more types would be generated)
 "#
         )
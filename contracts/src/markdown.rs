@@ -0,0 +1,769 @@
+// Markdown Module
+//
+// Renders the Markdown authors write in blog posts to HTML: ATX and setext
+// headings, paragraphs, block quotes, nested bullet and ordered lists, fenced
+// and indented code, thematic breaks, emphasis, strong, strikethrough, code
+// spans, links, images and autolinks. Raw HTML is passed through, so the
+// output is NOT safe to serve until it has been through `sanitize`.
+
+/// Render Markdown to (unsanitized) HTML
+pub fn to_html(markdown: &str) -> String {
+    let lines: Vec<String> = markdown.lines().map(|l| l.replace('\t', "    ")).collect();
+    let mut out = String::new();
+    render_blocks(&lines, false, &mut out);
+    out
+}
+
+// ============================================================================
+// BLOCKS
+// ============================================================================
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// `# Heading` -> (level, text)
+fn atx_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    // Optional closing #s
+    let text = rest.trim();
+    let text = match text.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with(' ') => stripped.trim_end(),
+        _ => text,
+    };
+    Some((level, text))
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_'].iter().any(|&m| marks.chars().all(|c| c == m))
+}
+
+/// `===` or `---` under a paragraph line
+fn setext_level(line: &str) -> Option<usize> {
+    let line = line.trim();
+    if !line.is_empty() && line.chars().all(|c| c == '=') {
+        Some(1)
+    } else if !line.is_empty() && line.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn fence_of(line: &str) -> Option<(char, usize)> {
+    let c = line.chars().next()?;
+    if c != '`' && c != '~' {
+        return None;
+    }
+    let len = line.chars().take_while(|&x| x == c).count();
+    (len >= 3).then_some((c, len))
+}
+
+/// A list item marker
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Marker {
+    ordered: bool,
+    /// Bullet character, or the delimiter after the number (`.` or `)`)
+    symbol: char,
+    start: u64,
+    /// Columns from the marker to the item's content
+    width: usize,
+}
+
+fn list_marker(line: &str) -> Option<Marker> {
+    let bytes = line.as_bytes();
+    let (ordered, symbol, start, marker_len) = match bytes.first()? {
+        b'-' | b'*' | b'+' => (false, bytes[0] as char, 0, 1),
+        b'0'..=b'9' => {
+            let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+            if digits > 9 {
+                return None;
+            }
+            match bytes.get(digits) {
+                Some(&d @ (b'.' | b')')) => (true, d as char, line[..digits].parse().ok()?, digits + 1),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let rest = &line[marker_len..];
+    if rest.is_empty() {
+        return Some(Marker { ordered, symbol, start, width: marker_len + 1 });
+    }
+    if !rest.starts_with(' ') {
+        return None;
+    }
+    let spaces = indent_of(rest);
+    // Five or more spaces means the content is indented code; count one
+    let spaces = if spaces > 4 { 1 } else { spaces };
+    Some(Marker { ordered, symbol, start, width: marker_len + spaces })
+}
+
+fn is_html_block_start(line: &str) -> bool {
+    let Some(rest) = line.strip_prefix('<') else {
+        return false;
+    };
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    rest.starts_with("!--") || rest.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && {
+        let name: String = rest.chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        BLOCK_TAGS.contains(&name.to_ascii_lowercase().as_str())
+    }
+}
+
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "div", "dl", "figure", "figcaption", "footer",
+    "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "iframe", "nav", "ol", "p", "pre", "script",
+    "section", "style", "summary", "table", "ul",
+];
+
+/// Whether a line starts a block that interrupts a paragraph
+fn interrupts_paragraph(line: &str) -> bool {
+    if indent_of(line) >= 4 {
+        return false;
+    }
+    let trimmed = line.trim_start();
+    atx_heading(trimmed).is_some()
+        || fence_of(trimmed).is_some()
+        || trimmed.starts_with('>')
+        || is_thematic_break(trimmed)
+        || is_html_block_start(trimmed)
+        || list_marker(trimmed).is_some_and(|m| !is_blank(&trimmed[m.width.min(trimmed.len())..]) && (!m.ordered || m.start == 1))
+}
+
+/// Render block-level Markdown. In a tight list, paragraphs aren't wrapped
+/// in `<p>`.
+fn render_blocks(lines: &[String], tight: bool, out: &mut String) {
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        if is_blank(line) {
+            i += 1;
+            continue;
+        }
+        let indent = indent_of(line);
+        let trimmed = line.trim_start();
+
+        // Indented code
+        if indent >= 4 {
+            let mut end = i;
+            let mut j = i;
+            while j < lines.len() && (is_blank(&lines[j]) || indent_of(&lines[j]) >= 4) {
+                if !is_blank(&lines[j]) {
+                    end = j;
+                }
+                j += 1;
+            }
+            let code: Vec<&str> = lines[i..=end].iter().map(|l| l.get(4..).unwrap_or("")).collect();
+            out.push_str(&format!("<pre><code>{}\n</code></pre>\n", escape(&code.join("\n"))));
+            i = end + 1;
+            continue;
+        }
+
+        // Fenced code
+        if let Some((fence, len)) = fence_of(trimmed) {
+            let info = trimmed[len..].trim();
+            let mut code = Vec::new();
+            let mut j = i + 1;
+            while j < lines.len() {
+                let candidate = lines[j].trim();
+                if fence_of(candidate).is_some_and(|(c, l)| c == fence && l >= len) && candidate.chars().all(|c| c == fence) {
+                    break;
+                }
+                // Drop up to the fence's own indentation
+                let strip = indent_of(&lines[j]).min(indent);
+                code.push(&lines[j][strip..]);
+                j += 1;
+            }
+            let class = info
+                .split_whitespace()
+                .next()
+                .map(|lang| format!(" class=\"language-{}\"", escape(lang)))
+                .unwrap_or_default();
+            let body = if code.is_empty() { String::new() } else { format!("{}\n", escape(&code.join("\n"))) };
+            out.push_str(&format!("<pre><code{}>{}</code></pre>\n", class, body));
+            i = j + 1;
+            continue;
+        }
+
+        if let Some((level, text)) = atx_heading(trimmed) {
+            out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(text)));
+            i += 1;
+            continue;
+        }
+
+        if is_thematic_break(trimmed) {
+            out.push_str("<hr />\n");
+            i += 1;
+            continue;
+        }
+
+        // Block quote (with lazy continuation lines)
+        if trimmed.starts_with('>') {
+            let mut inner = Vec::new();
+            let mut j = i;
+            while j < lines.len() {
+                let t = lines[j].trim_start();
+                if let Some(rest) = t.strip_prefix('>') {
+                    inner.push(rest.strip_prefix(' ').unwrap_or(rest).to_string());
+                } else if !is_blank(&lines[j]) && !inner.last().is_none_or(|l| is_blank(l)) && !interrupts_paragraph(&lines[j]) {
+                    inner.push(lines[j].clone());
+                } else {
+                    break;
+                }
+                j += 1;
+            }
+            out.push_str("<blockquote>\n");
+            render_blocks(&inner, false, out);
+            out.push_str("</blockquote>\n");
+            i = j;
+            continue;
+        }
+
+        if let Some(marker) = list_marker(trimmed) {
+            i = render_list(lines, i, marker, out);
+            continue;
+        }
+
+        if is_html_block_start(trimmed) {
+            while i < lines.len() && !is_blank(&lines[i]) {
+                out.push_str(&lines[i]);
+                out.push('\n');
+                i += 1;
+            }
+            continue;
+        }
+
+        // Paragraph, possibly a setext heading
+        let mut text = vec![trimmed.to_string()];
+        let mut j = i + 1;
+        let mut heading = None;
+        while j < lines.len() && !is_blank(&lines[j]) {
+            if let Some(level) = setext_level(&lines[j]).filter(|_| indent_of(&lines[j]) < 4) {
+                heading = Some(level);
+                j += 1;
+                break;
+            }
+            if interrupts_paragraph(&lines[j]) {
+                break;
+            }
+            text.push(lines[j].trim_start().to_string());
+            j += 1;
+        }
+        let body = inline(text.join("\n").trim_end());
+        match heading {
+            Some(level) => out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, body)),
+            None if tight => {
+                out.push_str(&body);
+                out.push('\n');
+            }
+            None => out.push_str(&format!("<p>{}</p>\n", body)),
+        }
+        i = j;
+    }
+}
+
+/// Render a list starting at `lines[start]`, returning the index after it
+fn render_list(lines: &[String], start: usize, first: Marker, out: &mut String) -> usize {
+    let base = indent_of(&lines[start]);
+    let mut items: Vec<Vec<String>> = Vec::new();
+    let mut loose = false;
+    let mut i = start;
+
+    while i < lines.len() {
+        let indent = indent_of(&lines[i]);
+        let trimmed = lines[i].trim_start();
+        let Some(marker) = list_marker(trimmed).filter(|m| {
+            indent < base + 4 && m.ordered == first.ordered && m.symbol == first.symbol && !is_thematic_break(trimmed)
+        }) else {
+            break;
+        };
+
+        let content_indent = indent + marker.width;
+        let mut item = vec![trimmed.get(marker.width..).unwrap_or("").to_string()];
+        i += 1;
+        while i < lines.len() {
+            let line = &lines[i];
+            if is_blank(line) {
+                item.push(String::new());
+            } else if indent_of(line) >= content_indent {
+                item.push(line[content_indent..].to_string());
+            } else if !item.last().is_some_and(|l| l.is_empty())
+                && !interrupts_paragraph(line)
+                && list_marker(line.trim_start()).is_none()
+            {
+                // Lazy continuation of the item's paragraph
+                item.push(line.trim_start().to_string());
+            } else {
+                break;
+            }
+            i += 1;
+        }
+
+        // Trailing blank lines separate items; blank lines inside make the list loose
+        let mut trailing = 0;
+        while item.last().is_some_and(|l| l.is_empty()) {
+            item.pop();
+            trailing += 1;
+        }
+        if item.iter().any(|l| l.is_empty()) {
+            loose = true;
+        }
+        items.push(item);
+
+        if trailing > 0 {
+            let next_is_item = i < lines.len()
+                && list_marker(lines[i].trim_start()).is_some_and(|m| m.ordered == first.ordered && m.symbol == first.symbol)
+                && indent_of(&lines[i]) < base + 4;
+            if next_is_item {
+                loose = true;
+            } else {
+                break;
+            }
+        }
+    }
+
+    let (open, close) = match (first.ordered, first.start) {
+        (true, 1) => ("<ol>".to_string(), "</ol>"),
+        (true, n) => (format!("<ol start=\"{}\">", n), "</ol>"),
+        (false, _) => ("<ul>".to_string(), "</ul>"),
+    };
+    out.push_str(&open);
+    out.push('\n');
+    for item in items {
+        let mut body = String::new();
+        render_blocks(&item, !loose, &mut body);
+        out.push_str("<li>");
+        let body = body.trim_end_matches('\n');
+        if body.contains('\n') || body.starts_with('<') && loose {
+            out.push('\n');
+            out.push_str(body);
+            out.push('\n');
+        } else {
+            out.push_str(body);
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str(close);
+    out.push('\n');
+    i
+}
+
+// ============================================================================
+// INLINES
+// ============================================================================
+
+/// Escape text for HTML
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Length of an HTML entity (`&amp;`, `&#39;`, `&#x27;`) at the start of `s`
+pub(crate) fn entity_len(s: &str) -> Option<usize> {
+    let body = s.strip_prefix('&')?;
+    let end = body.find(';')?;
+    let name = &body[..end];
+    let valid = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        (1..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(digits) = name.strip_prefix('#') {
+        (1..=7).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit())
+    } else {
+        (1..=31).contains(&name.len())
+            && name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+    valid.then_some(end + 2)
+}
+
+/// Render inline Markdown
+fn inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                out.push_str(&escape(&chars[i + 1].to_string()));
+                i += 2;
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                out.push_str("<br />\n");
+                i += 2;
+            }
+            '`' => {
+                let run = run_length(&chars, i, '`');
+                match find_backtick_close(&chars, i + run, run) {
+                    Some(close) => {
+                        let code: String = chars[i + run..close].iter().collect::<String>().replace('\n', " ");
+                        let code = if code.len() > 2 && code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty() {
+                            &code[1..code.len() - 1]
+                        } else {
+                            &code
+                        };
+                        out.push_str(&format!("<code>{}</code>", escape(code)));
+                        i = close + run;
+                    }
+                    None => {
+                        out.push_str(&"`".repeat(run));
+                        i += run;
+                    }
+                }
+            }
+            '!' if chars.get(i + 1) == Some(&'[') => match parse_link(&chars, i + 1) {
+                Some(link) => {
+                    let title = link.title.map(|t| format!(" title=\"{}\"", escape(&t))).unwrap_or_default();
+                    out.push_str(&format!(
+                        "<img src=\"{}\" alt=\"{}\"{} />",
+                        escape(&link.destination),
+                        escape(&plain_text(&link.label)),
+                        title
+                    ));
+                    i = link.end;
+                }
+                None => {
+                    out.push('!');
+                    i += 1;
+                }
+            },
+            '[' => match parse_link(&chars, i) {
+                Some(link) => {
+                    let title = link.title.map(|t| format!(" title=\"{}\"", escape(&t))).unwrap_or_default();
+                    out.push_str(&format!("<a href=\"{}\"{}>{}</a>", escape(&link.destination), title, inline(&link.label)));
+                    i = link.end;
+                }
+                None => {
+                    out.push('[');
+                    i += 1;
+                }
+            },
+            '<' => match autolink_or_tag(&chars, i) {
+                Some((html, end)) => {
+                    out.push_str(&html);
+                    i = end;
+                }
+                None => {
+                    out.push_str("&lt;");
+                    i += 1;
+                }
+            },
+            '&' => {
+                let rest: String = chars[i..chars.len().min(i + 40)].iter().collect();
+                match entity_len(&rest) {
+                    Some(len) => {
+                        out.push_str(&rest[..len]);
+                        i += rest[..len].chars().count();
+                    }
+                    None => {
+                        out.push_str("&amp;");
+                        i += 1;
+                    }
+                }
+            }
+            '*' | '_' | '~' => match emphasis(&chars, i) {
+                Some((html, end)) => {
+                    out.push_str(&html);
+                    i = end;
+                }
+                None => {
+                    let run = run_length(&chars, i, c);
+                    out.extend(std::iter::repeat_n(c, run));
+                    i += run;
+                }
+            },
+            '\n' => {
+                // Two trailing spaces make a hard break
+                if out.ends_with("  ") {
+                    let trimmed = out.trim_end_matches(' ').len();
+                    out.truncate(trimmed);
+                    out.push_str("<br />\n");
+                } else {
+                    let trimmed = out.trim_end_matches(' ').len();
+                    out.truncate(trimmed);
+                    out.push('\n');
+                }
+                i += 1;
+            }
+            _ => {
+                out.push_str(&escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn run_length(chars: &[char], start: usize, c: char) -> usize {
+    chars[start..].iter().take_while(|&&x| x == c).count()
+}
+
+fn find_backtick_close(chars: &[char], from: usize, run: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let len = run_length(chars, i, '`');
+            if len == run {
+                return Some(i);
+            }
+            i += len;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Text of a link label without Markdown punctuation (for `alt`)
+fn plain_text(label: &str) -> String {
+    label.chars().filter(|c| !matches!(c, '*' | '_' | '`' | '~')).collect()
+}
+
+struct Link {
+    label: String,
+    destination: String,
+    title: Option<String>,
+    /// Index after the closing `)`
+    end: usize,
+}
+
+/// `[label](destination "title")` starting at the `[`
+fn parse_link(chars: &[char], open: usize) -> Option<Link> {
+    // Matching ]
+    let mut depth = 0;
+    let mut i = open;
+    let close = loop {
+        match chars.get(i)? {
+            '\\' => i += 1,
+            '`' => {
+                let run = run_length(chars, i, '`');
+                i = find_backtick_close(chars, i + run, run).map_or(i + run - 1, |c| c + run - 1);
+            }
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+
+    let mut i = close + 2;
+    let skip_spaces = |i: &mut usize| {
+        while chars.get(*i).is_some_and(|c| c.is_whitespace()) {
+            *i += 1;
+        }
+    };
+    skip_spaces(&mut i);
+
+    let mut destination = String::new();
+    if chars.get(i) == Some(&'<') {
+        i += 1;
+        while *chars.get(i)? != '>' {
+            if chars[i] == '\n' {
+                return None;
+            }
+            destination.push(chars[i]);
+            i += 1;
+        }
+        i += 1;
+    } else {
+        let mut parens = 0;
+        while let Some(&c) = chars.get(i) {
+            match c {
+                c if c.is_whitespace() => break,
+                '(' => parens += 1,
+                ')' if parens == 0 => break,
+                ')' => parens -= 1,
+                '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                    i += 1;
+                }
+                _ => {}
+            }
+            destination.push(chars[i]);
+            i += 1;
+        }
+    }
+
+    skip_spaces(&mut i);
+    let mut title = None;
+    if let Some(&quote @ ('"' | '\'' | '(')) = chars.get(i) {
+        let end_quote = if quote == '(' { ')' } else { quote };
+        let mut text = String::new();
+        i += 1;
+        while *chars.get(i)? != end_quote {
+            if chars[i] == '\\' && chars.get(i + 1) == Some(&end_quote) {
+                i += 1;
+            }
+            text.push(chars[i]);
+            i += 1;
+        }
+        i += 1;
+        title = Some(text);
+        skip_spaces(&mut i);
+    }
+    if chars.get(i) != Some(&')') {
+        return None;
+    }
+
+    Some(Link {
+        label: chars[open + 1..close].iter().collect(),
+        destination,
+        title,
+        end: i + 1,
+    })
+}
+
+/// `<https://...>`, `<someone@example.com>`, or a raw inline HTML tag
+fn autolink_or_tag(chars: &[char], open: usize) -> Option<(String, usize)> {
+    let close = open + chars[open..].iter().position(|&c| c == '>' || c == '\n')?;
+    if chars[close] != '>' {
+        return None;
+    }
+    let inner: String = chars[open + 1..close].iter().collect();
+
+    let scheme_len = inner.find(':').unwrap_or(0);
+    let is_uri = (2..=32).contains(&scheme_len)
+        && inner[..scheme_len].starts_with(|c: char| c.is_ascii_alphabetic())
+        && inner[..scheme_len].chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+        && !inner.contains(|c: char| c.is_whitespace() || c == '<');
+    if is_uri {
+        return Some((format!("<a href=\"{0}\">{0}</a>", escape(&inner)), close + 1));
+    }
+    let is_email = inner.contains('@')
+        && !inner.contains(|c: char| c.is_whitespace() || c == '<')
+        && inner.split('@').count() == 2
+        && inner.split('@').all(|part| !part.is_empty());
+    if is_email {
+        return Some((format!("<a href=\"mailto:{0}\">{0}</a>", escape(&inner)), close + 1));
+    }
+
+    // Raw HTML: a tag name, or a comment
+    let tag = inner.strip_prefix('/').unwrap_or(&inner);
+    if inner.starts_with("!--") || tag.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Some((chars[open..=close].iter().collect(), close + 1));
+    }
+    None
+}
+
+/// `*em*`, `**strong**`, `***both***`, `_em_`, `__strong__`, `~~del~~`
+fn emphasis(chars: &[char], open: usize) -> Option<(String, usize)> {
+    let c = chars[open];
+    let run = run_length(chars, open, c);
+    if c == '~' && run != 2 || run > 3 {
+        return None;
+    }
+    let after = *chars.get(open + run)?;
+    if after.is_whitespace() {
+        return None;
+    }
+    if c == '_' && open > 0 && chars[open - 1].is_alphanumeric() {
+        return None;
+    }
+
+    // Find a closing run of the same length
+    let mut i = open + run;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '`' => {
+                let len = run_length(chars, i, '`');
+                i = find_backtick_close(chars, i + len, len).map_or(i + len, |close| close + len);
+            }
+            x if x == c => {
+                let len = run_length(chars, i, c);
+                let before_ok = !chars[i - 1].is_whitespace();
+                let after_ok = c != '_' || !chars.get(i + len).is_some_and(|n| n.is_alphanumeric());
+                if len == run && before_ok && after_ok {
+                    let inner: String = chars[open + run..i].iter().collect();
+                    let body = inline(&inner);
+                    let html = match (c, run) {
+                        ('~', _) => format!("<del>{}</del>", body),
+                        (_, 1) => format!("<em>{}</em>", body),
+                        (_, 2) => format!("<strong>{}</strong>", body),
+                        _ => format!("<em><strong>{}</strong></em>", body),
+                    };
+                    return Some((html, i + len));
+                }
+                i += len;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let html = to_html(
+            "# Tour *dates*\n\nWe're back.\nSee you there  \nsoon!\n\n> Best show\n> of the year\n\n---\n\n```rust\nlet x = 1 < 2;\n```\n",
+        );
+        assert_eq!(
+            html,
+            "<h1>Tour <em>dates</em></h1>\n\
+             <p>We're back.\nSee you there<br />\nsoon!</p>\n\
+             <blockquote>\n<p>Best show\nof the year</p>\n</blockquote>\n\
+             <hr />\n\
+             <pre><code class=\"language-rust\">let x = 1 &lt; 2;\n</code></pre>\n"
+        );
+
+        assert_eq!(to_html("Title\n=====\n"), "<h1>Title</h1>\n");
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            to_html("- one\n- two\n  - nested\n- three\n"),
+            "<ul>\n<li>one</li>\n<li>\ntwo\n<ul>\n<li>nested</li>\n</ul>\n</li>\n<li>three</li>\n</ul>\n"
+        );
+        assert_eq!(to_html("3. three\n4. four\n"), "<ol start=\"3\">\n<li>three</li>\n<li>four</li>\n</ol>\n");
+        assert_eq!(to_html("- a\n\n- b\n"), "<ul>\n<li>\n<p>a</p>\n</li>\n<li>\n<p>b</p>\n</li>\n</ul>\n");
+    }
+
+    #[test]
+    fn test_inlines() {
+        assert_eq!(
+            inline("**Tickets** at [our shop](https://shop.example \"Shop\") ~~now~~ `a<b` \\*not\\* snake_case_word"),
+            "<strong>Tickets</strong> at <a href=\"https://shop.example\" title=\"Shop\">our shop</a> \
+             <del>now</del> <code>a&lt;b</code> *not* snake_case_word"
+        );
+        assert_eq!(
+            inline("![Poster](/img/poster.jpg) <https://example.com> <b>raw</b> 1 < 2 & AT&amp;T"),
+            "<img src=\"/img/poster.jpg\" alt=\"Poster\" /> <a href=\"https://example.com\">https://example.com</a> \
+             <b>raw</b> 1 &lt; 2 &amp; AT&amp;T"
+        );
+        assert_eq!(inline("*a **b** c*"), "<em>a <strong>b</strong> c</em>");
+    }
+}
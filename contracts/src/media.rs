@@ -0,0 +1,645 @@
+// Media Module
+//
+// Uploads go straight to object storage (a presigned PUT, or multipart parts
+// through the API) and are only trusted once completed: the server then reads
+// the file back and works out what it really is. This module holds the upload
+// contracts and that inspection: content-type sniffing from magic bytes,
+// image dimensions from the file headers, and camera EXIF data. Nothing here
+// decodes pixels; only the first bytes of a file are needed.
+
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::rbac::Permission;
+use crate::ImageDimensions;
+
+// ============================================================================
+// UPLOAD CONTRACTS
+// ============================================================================
+
+/// What an upload becomes once completed
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MediaKind {
+    /// An image, saved as a `Photo`
+    Photo,
+    /// A video file, saved as a `Video` with a direct source
+    Video,
+    /// An audio file (kept as the upload itself)
+    Audio,
+}
+
+impl MediaKind {
+    /// Permission needed to upload this kind of file
+    pub fn upload_permission(&self) -> Permission {
+        match self {
+            MediaKind::Photo => Permission::UploadPhoto,
+            MediaKind::Video => Permission::UploadVideo,
+            MediaKind::Audio => Permission::UploadAudio,
+        }
+    }
+
+    /// Largest accepted file, in bytes
+    pub fn max_bytes(&self) -> u64 {
+        match self {
+            MediaKind::Photo => 50 * 1024 * 1024,
+            MediaKind::Video => 5 * 1024 * 1024 * 1024,
+            MediaKind::Audio => 1024 * 1024 * 1024,
+        }
+    }
+
+    /// Whether a MIME type is this kind of media. SVG is not an accepted
+    /// image: it can carry script.
+    pub fn accepts(&self, content_type: &str) -> bool {
+        let content_type = content_type.trim().to_ascii_lowercase();
+        match self {
+            MediaKind::Photo => content_type.starts_with("image/") && !content_type.starts_with("image/svg"),
+            MediaKind::Video => content_type.starts_with("video/"),
+            MediaKind::Audio => content_type.starts_with("audio/"),
+        }
+    }
+}
+
+/// Where an upload is in its life
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum UploadStatus {
+    /// Waiting for the file
+    Pending,
+    /// File received, inspected and saved
+    Completed,
+    /// Cancelled, expired, or rejected on inspection
+    Aborted,
+}
+
+/// How the client sends the file
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum UploadMethod {
+    /// One PUT of the whole file to `url`, straight to object storage
+    Presigned,
+    /// Parts PUT to `/api/uploads/:id/parts/:number`
+    Multipart,
+}
+
+/// A file upload, from the request to upload until it is completed
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaUpload {
+    /// Unique upload ID
+    pub id: String,
+    /// Site the file belongs to
+    pub site_id: String,
+    /// What the file will become
+    pub kind: MediaKind,
+    /// Original filename
+    pub filename: String,
+    /// Content type the client declared (checked against the file on completion)
+    pub declared_content_type: String,
+    /// Size the client declared
+    pub declared_size_bytes: u64,
+    /// Object storage key
+    pub key: String,
+    pub method: UploadMethod,
+    /// Object storage's multipart upload ID
+    #[serde(skip_serializing)]
+    pub multipart_id: Option<String>,
+    pub status: UploadStatus,
+    /// Title for the record created on completion
+    pub title: Option<String>,
+    /// Caption for the record created on completion
+    pub caption: Option<String>,
+    /// Uploading user ID
+    pub uploaded_by: String,
+    pub created_at: i64,
+    /// When an unfinished upload stops being accepted
+    pub expires_at: i64,
+    /// What the file turned out to be (once completed)
+    pub info: Option<MediaInfo>,
+    /// Public URL of the file (once completed)
+    pub url: Option<String>,
+    /// ID of the `Photo` or `Video` created from the file
+    pub resource_id: Option<String>,
+    /// Gallery the photo is added to once completed
+    #[serde(default)]
+    pub gallery_id: Option<String>,
+}
+
+/// Facts read from an uploaded file
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    /// Content type detected from the file's bytes
+    pub content_type: String,
+    pub size_bytes: u64,
+    /// SHA-256 of the file, hex encoded
+    pub content_hash: String,
+    /// Pixel size as displayed (EXIF orientation applied), for images
+    pub dimensions: Option<ImageDimensions>,
+    /// Camera data, for photos that carry it
+    pub exif: Option<ExifData>,
+}
+
+/// Camera data from a photo's EXIF block. GPS position is deliberately not
+/// read: photos are published, and the location is often someone's home.
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExifData {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_model: Option<String>,
+    /// When the photo was taken, camera local time (`YYYY-MM-DDTHH:MM:SS`)
+    pub taken_at: Option<String>,
+    /// EXIF orientation (1-8; 5-8 are rotated a quarter turn)
+    pub orientation: Option<u16>,
+    /// Exposure time, e.g. "1/250"
+    pub exposure_time: Option<String>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    pub focal_length_mm: Option<f64>,
+}
+
+/// Request to start an upload
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUploadRequest {
+    #[garde(skip)]
+    pub kind: MediaKind,
+    /// Original filename
+    #[garde(length(min = 1, max = 255))]
+    pub filename: String,
+    /// MIME type of the file
+    #[garde(length(min = 1))]
+    pub content_type: String,
+    /// File size in bytes
+    #[garde(range(min = 1))]
+    pub size_bytes: u64,
+    /// Send the file in parts through the API even if a presigned URL is available
+    #[garde(skip)]
+    #[serde(default)]
+    pub multipart: bool,
+    /// Title (the photo's alt text, or the video's title)
+    #[garde(skip)]
+    pub title: Option<String>,
+    #[garde(skip)]
+    pub caption: Option<String>,
+    /// Gallery to add the photo to (photos only)
+    #[garde(skip)]
+    pub gallery_id: Option<String>,
+}
+
+/// Where and how to send the file of a new upload
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadTarget {
+    pub upload: MediaUpload,
+    /// Presigned PUT URL (presigned uploads)
+    pub url: Option<String>,
+    /// Headers the PUT must send as given (they are part of the signature)
+    pub headers: Vec<(String, String)>,
+    /// Size of every part but the last (multipart uploads)
+    pub part_size: Option<u64>,
+}
+
+/// One uploaded part of a multipart upload
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedPart {
+    pub part_number: u16,
+    pub etag: String,
+}
+
+/// Request to complete an upload
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteUploadRequest {
+    /// Every uploaded part, for multipart uploads
+    #[serde(default)]
+    pub parts: Vec<UploadedPart>,
+}
+
+// ============================================================================
+// INSPECTION
+// ============================================================================
+
+/// Bytes from the start of a file that `probe` needs to see everything it
+/// reads (EXIF and JPEG frame headers can sit behind large thumbnails)
+pub const PROBE_BYTES: usize = 1024 * 1024;
+
+/// What the start of a file says about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    pub content_type: &'static str,
+    pub dimensions: Option<ImageDimensions>,
+    pub exif: Option<ExifData>,
+}
+
+/// Inspect the first bytes of a file (up to `PROBE_BYTES`). `None` if the
+/// format isn't recognized.
+pub fn probe(head: &[u8]) -> Option<Probe> {
+    let content_type = sniff(head)?;
+    let (dimensions, exif) = match content_type {
+        "image/jpeg" => {
+            let exif = jpeg_exif(head).and_then(read_exif);
+            (jpeg_dimensions(head), exif)
+        }
+        "image/tiff" => (tiff_dimensions(head), read_exif(head)),
+        "image/png" => (png_dimensions(head), None),
+        "image/gif" => (gif_dimensions(head), None),
+        "image/webp" => (webp_dimensions(head), None),
+        "image/avif" | "image/heic" => (ispe_dimensions(head), None),
+        _ => (None, None),
+    };
+
+    // Quarter-turn orientations display the image on its side
+    let rotated = exif.as_ref().and_then(|e| e.orientation).is_some_and(|o| (5..=8).contains(&o));
+    let dimensions = dimensions.map(|(width, height)| {
+        let (width, height) = if rotated { (height, width) } else { (width, height) };
+        ImageDimensions { width: width as i32, height: height as i32 }
+    });
+    Some(Probe { content_type, dimensions, exif })
+}
+
+/// Content type from magic bytes
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    let starts = |magic: &[u8]| bytes.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+
+    if starts(&[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if starts(b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if starts(b"GIF87a") || starts(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if starts(b"II*\0") || starts(b"MM\0*") {
+        return Some("image/tiff");
+    }
+    if starts(b"RIFF") {
+        return match bytes.get(8..12)? {
+            b"WEBP" => Some("image/webp"),
+            b"WAVE" => Some("audio/wav"),
+            b"AVI " => Some("video/x-msvideo"),
+            _ => None,
+        };
+    }
+    if at(4, b"ftyp") {
+        return Some(match bytes.get(8..12)? {
+            b"avif" | b"avis" => "image/avif",
+            b"heic" | b"heix" | b"mif1" => "image/heic",
+            b"M4A " | b"M4B " => "audio/mp4",
+            b"qt  " => "video/quicktime",
+            b"3gp4" | b"3gp5" | b"3g2a" => "video/3gpp",
+            _ => "video/mp4",
+        });
+    }
+    if starts(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        let header = &bytes[..bytes.len().min(64)];
+        let is_webm = header.windows(4).any(|w| w == b"webm");
+        return Some(if is_webm { "video/webm" } else { "video/x-matroska" });
+    }
+    if starts(b"OggS") {
+        return Some("audio/ogg");
+    }
+    if starts(b"fLaC") {
+        return Some("audio/flac");
+    }
+    if starts(b"ID3") {
+        return Some("audio/mpeg");
+    }
+    // MPEG audio frame sync; layer bits 00 are AAC in an ADTS stream
+    if bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0 {
+        return Some(if bytes[1] & 0x06 == 0 { "audio/aac" } else { "audio/mpeg" });
+    }
+    None
+}
+
+// ============================================================================
+// DIMENSIONS
+// ============================================================================
+
+fn be16(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32)
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32)
+}
+
+fn le24(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 3).map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn be32(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    (bytes.get(12..16)? == b"IHDR").then_some(())?;
+    Some((be32(bytes, 16)?, be32(bytes, 20)?))
+}
+
+fn gif_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    Some((le16(bytes, 6)?, le16(bytes, 8)?))
+}
+
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        // Lossy: frame header after a 3-byte tag and start code
+        b"VP8 " => Some((le16(bytes, 26)? & 0x3FFF, le16(bytes, 28)? & 0x3FFF)),
+        // Lossless: 14-bit width-1 and height-1 after the signature byte
+        b"VP8L" => {
+            let b = bytes.get(21..25)?;
+            let width = 1 + (((b[1] as u32 & 0x3F) << 8) | b[0] as u32);
+            let height = 1 + (((b[3] as u32 & 0x0F) << 10) | (b[2] as u32) << 2 | (b[1] as u32 & 0xC0) >> 6);
+            Some((width, height))
+        }
+        // Extended: 24-bit canvas width-1 and height-1
+        b"VP8X" => Some((1 + le24(bytes, 24)?, 1 + le24(bytes, 27)?)),
+        _ => None,
+    }
+}
+
+/// AVIF and HEIC keep the size in an `ispe` (image spatial extents) box
+fn ispe_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let at = bytes.windows(4).position(|w| w == b"ispe")?;
+    // Box type, then version and flags
+    Some((be32(bytes, at + 8)?, be32(bytes, at + 12)?))
+}
+
+/// JPEG segments as (marker, segment body), up to the start of scan
+fn jpeg_segments(bytes: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut at = 2;
+    std::iter::from_fn(move || loop {
+        if *bytes.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // Fill byte
+            0xFF => at += 1,
+            // Markers without a length
+            0x01 | 0xD0..=0xD7 => at += 2,
+            // Start of scan: compressed data follows
+            0xDA | 0xD9 => return None,
+            _ => {
+                let length = be16(bytes, at + 2)? as usize;
+                let body = bytes.get(at + 4..(at + 2 + length).min(bytes.len()))?;
+                at += 2 + length;
+                return Some((marker, body));
+            }
+        }
+    })
+}
+
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    jpeg_segments(bytes)
+        .find(|(marker, _)| matches!(marker, 0xC0..=0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF))
+        .and_then(|(_, frame)| Some((be16(frame, 3)?, be16(frame, 1)?)))
+}
+
+/// The TIFF structure inside a JPEG's APP1 `Exif` segment
+fn jpeg_exif(bytes: &[u8]) -> Option<&[u8]> {
+    jpeg_segments(bytes)
+        .find(|(marker, body)| *marker == 0xE1 && body.starts_with(b"Exif\0\0"))
+        .map(|(_, body)| &body[6..])
+}
+
+// ============================================================================
+// EXIF
+// ============================================================================
+
+/// A TIFF structure (the container EXIF data uses)
+struct Tiff<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+}
+
+/// One IFD entry's value
+enum TagValue {
+    Text(String),
+    Numbers(Vec<u32>),
+    Rationals(Vec<(u32, u32)>),
+}
+
+impl<'a> Tiff<'a> {
+    fn new(bytes: &'a [u8]) -> Option<Self> {
+        let little_endian = match bytes.get(0..4)? {
+            b"II*\0" => true,
+            b"MM\0*" => false,
+            _ => return None,
+        };
+        Some(Self { bytes, little_endian })
+    }
+
+    fn u16(&self, at: usize) -> Option<u32> {
+        let b = self.bytes.get(at..at + 2)?;
+        Some(if self.little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) } as u32)
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let b: [u8; 4] = self.bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    /// Entries of the IFD at `offset` as (tag, value)
+    fn ifd(&self, offset: usize) -> Vec<(u32, TagValue)> {
+        let Some(count) = self.u16(offset) else {
+            return Vec::new();
+        };
+        (0..count as usize).filter_map(|i| {
+            let entry = offset + 2 + i * 12;
+            let tag = self.u16(entry)?;
+            let kind = self.u16(entry + 2)?;
+            let count = self.u32(entry + 4)? as usize;
+            let size = match kind {
+                1 | 2 | 7 => 1,
+                3 => 2,
+                4 => 4,
+                5 | 10 => 8,
+                _ => return None,
+            };
+            if count == 0 || count > 1024 {
+                return None;
+            }
+            // Values that fit in four bytes are stored in place of the offset
+            let at = if size * count <= 4 { entry + 8 } else { self.u32(entry + 8)? as usize };
+            let value = match kind {
+                2 => {
+                    let raw = self.bytes.get(at..at + count)?;
+                    let text = raw.split(|&b| b == 0).next().unwrap_or_default();
+                    TagValue::Text(String::from_utf8_lossy(text).trim().to_string())
+                }
+                3 => TagValue::Numbers((0..count).map(|n| self.u16(at + n * 2)).collect::<Option<_>>()?),
+                4 => TagValue::Numbers((0..count).map(|n| self.u32(at + n * 4)).collect::<Option<_>>()?),
+                5 | 10 => TagValue::Rationals(
+                    (0..count).map(|n| Some((self.u32(at + n * 8)?, self.u32(at + n * 8 + 4)?))).collect::<Option<_>>()?,
+                ),
+                _ => TagValue::Numbers(self.bytes.get(at..at + count)?.iter().map(|&b| b as u32).collect()),
+            };
+            Some((tag, value))
+        })
+        .collect()
+    }
+
+    fn first_ifd(&self) -> Vec<(u32, TagValue)> {
+        self.u32(4).map(|offset| self.ifd(offset as usize)).unwrap_or_default()
+    }
+}
+
+fn tag(entries: &[(u32, TagValue)], id: u32) -> Option<&TagValue> {
+    entries.iter().find(|(tag, _)| *tag == id).map(|(_, value)| value)
+}
+
+fn text(entries: &[(u32, TagValue)], id: u32) -> Option<String> {
+    match tag(entries, id)? {
+        TagValue::Text(text) if !text.is_empty() => Some(text.clone()),
+        _ => None,
+    }
+}
+
+fn number(entries: &[(u32, TagValue)], id: u32) -> Option<u32> {
+    match tag(entries, id)? {
+        TagValue::Numbers(numbers) => numbers.first().copied(),
+        _ => None,
+    }
+}
+
+fn rational(entries: &[(u32, TagValue)], id: u32) -> Option<(u32, u32)> {
+    match tag(entries, id)? {
+        TagValue::Rationals(values) => values.first().copied().filter(|(_, d)| *d != 0),
+        _ => None,
+    }
+}
+
+fn tiff_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let ifd = Tiff::new(bytes)?.first_ifd();
+    Some((number(&ifd, 0x0100)?, number(&ifd, 0x0101)?))
+}
+
+/// Read camera data from a TIFF structure. `None` if it has none of it.
+fn read_exif(bytes: &[u8]) -> Option<ExifData> {
+    let tiff = Tiff::new(bytes)?;
+    let ifd0 = tiff.first_ifd();
+    let exif = number(&ifd0, 0x8769).map(|offset| tiff.ifd(offset as usize)).unwrap_or_default();
+
+    let data = ExifData {
+        camera_make: text(&ifd0, 0x010F),
+        camera_model: text(&ifd0, 0x0110),
+        lens_model: text(&exif, 0xA434),
+        taken_at: text(&exif, 0x9003).or_else(|| text(&ifd0, 0x0132)).and_then(|t| exif_timestamp(&t)),
+        orientation: number(&ifd0, 0x0112).filter(|o| (1..=8).contains(o)).map(|o| o as u16),
+        exposure_time: rational(&exif, 0x829A).map(|(n, d)| match (n, d) {
+            (n, d) if n >= d => format!("{}", n as f64 / d as f64),
+            (n, d) if d % n == 0 => format!("1/{}", d / n),
+            (n, d) => format!("{}/{}", n, d),
+        }),
+        f_number: rational(&exif, 0x829D).map(|(n, d)| n as f64 / d as f64),
+        iso: number(&exif, 0x8827),
+        focal_length_mm: rational(&exif, 0x920A).map(|(n, d)| n as f64 / d as f64),
+    };
+    (data != ExifData::default()).then_some(data)
+}
+
+/// "2024:06:01 21:30:05" -> "2024-06-01T21:30:05"
+fn exif_timestamp(text: &str) -> Option<String> {
+    let (date, time) = text.split_once(' ')?;
+    let date = date.replace(':', "-");
+    let valid = date.len() == 10 && time.len() == 8 && !date.starts_with("0000");
+    valid.then(|| format!("{}T{}", date, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A big-endian TIFF block with Make, Orientation and an EXIF IFD holding
+    /// DateTimeOriginal, ExposureTime, FNumber and ISO
+    fn exif_block() -> Vec<u8> {
+        let mut t = b"MM\0*\0\0\0\x08".to_vec();
+        // IFD0 at 8: 3 entries
+        t.extend([0, 3]);
+        t.extend([0x01, 0x0F, 0, 2, 0, 0, 0, 6, 0, 0, 0, 50]); // Make -> "Canon" at 50
+        t.extend([0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0]); // Orientation = 6
+        t.extend([0x87, 0x69, 0, 4, 0, 0, 0, 1, 0, 0, 0, 56]); // EXIF IFD at 56
+        t.extend([0, 0, 0, 0]);
+        t.resize(50, 0);
+        t.extend(b"Canon\0");
+        // EXIF IFD at 56: 4 entries, values from 110
+        t.extend([0, 4]);
+        t.extend([0x90, 0x03, 0, 2, 0, 0, 0, 20, 0, 0, 0, 110]);
+        t.extend([0x82, 0x9A, 0, 5, 0, 0, 0, 1, 0, 0, 0, 130]);
+        t.extend([0x82, 0x9D, 0, 5, 0, 0, 0, 1, 0, 0, 0, 138]);
+        t.extend([0x88, 0x27, 0, 3, 0, 0, 0, 1, 0x0C, 0x80, 0, 0]);
+        t.extend([0, 0, 0, 0]);
+        t.resize(110, 0);
+        t.extend(b"2024:06:01 21:30:05\0");
+        t.extend([0, 0, 0, 1, 0, 0, 0, 250]);
+        t.extend([0, 0, 0, 28, 0, 0, 0, 10]);
+        t
+    }
+
+    #[test]
+    fn test_probe_jpeg_with_exif() {
+        let exif = exif_block();
+        let mut jpeg = vec![0xFF, 0xD8];
+        jpeg.extend([0xFF, 0xE1]);
+        jpeg.extend(((exif.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(&exif);
+        // SOF0: 8-bit, 3000 high, 4000 wide
+        jpeg.extend([0xFF, 0xC0, 0, 11, 8, 0x0B, 0xB8, 0x0F, 0xA0, 3, 1, 0x22, 0]);
+        jpeg.extend([0xFF, 0xDA]);
+
+        let probe = probe(&jpeg).unwrap();
+        assert_eq!(probe.content_type, "image/jpeg");
+        // Orientation 6 is a quarter turn, so it displays portrait
+        assert_eq!(probe.dimensions.map(|d| (d.width, d.height)), Some((3000, 4000)));
+        assert_eq!(
+            probe.exif.unwrap(),
+            ExifData {
+                camera_make: Some("Canon".to_string()),
+                taken_at: Some("2024-06-01T21:30:05".to_string()),
+                orientation: Some(6),
+                exposure_time: Some("1/250".to_string()),
+                f_number: Some(2.8),
+                iso: Some(3200),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_sniff_and_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        let probe_of = |bytes: &[u8]| probe(bytes).map(|p| (p.content_type, p.dimensions.map(|d| (d.width, d.height))));
+        assert_eq!(probe_of(&png), Some(("image/png", Some((640, 480)))));
+        assert_eq!(probe_of(b"GIF89a\x20\x03\x58\x02"), Some(("image/gif", Some((800, 600)))));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        webp.extend([0x7F, 0x07, 0x00, 0x37, 0x04, 0x00]);
+        assert_eq!(probe_of(&webp), Some(("image/webp", Some((1920, 1080)))));
+
+        assert_eq!(sniff(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+        assert_eq!(sniff(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x84webm"), Some("video/webm"));
+        assert_eq!(sniff(b"ID3\x04"), Some("audio/mpeg"));
+        assert_eq!(sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"), None);
+        assert!(!MediaKind::Photo.accepts("image/svg+xml"));
+        assert!(MediaKind::Audio.accepts("audio/flac") && !MediaKind::Audio.accepts("video/mp4"));
+    }
+}
@@ -1,16 +1,20 @@
 // RBAC & Permissions Module
-// Add this to contracts/src/lib.rs or separate module
+//
+// Single source of truth for what each role may do. The API authorizes every
+// endpoint through `has_permission`, and the CMS uses the same checks to decide
+// what to show.
 
 use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::*;
+use utoipa::ToSchema;
 use std::collections::HashSet;
 
+use crate::{Role, User, UserStatus};
+
 // ============================================================================
 // PERMISSIONS SYSTEM
 // ============================================================================
 
 /// Granular permissions for fine-grained access control
-#[wasm_bindgen]
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum Permission {
@@ -82,7 +86,6 @@ pub enum Permission {
 }
 
 /// Permission set for a role
-#[wasm_bindgen]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RolePermissions {
@@ -90,79 +93,160 @@ pub struct RolePermissions {
     pub permissions: HashSet<Permission>,
 }
 
+/// Custom role defined by a site
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleDefinition {
+    /// Role ID (referenced by `Role::Custom`)
+    pub id: String,
+    /// Site the role belongs to
+    pub site_id: String,
+    /// Display name (e.g. "Tour Manager")
+    pub name: String,
+    /// Description
+    pub description: Option<String>,
+    /// Permissions granted by the role
+    pub permissions: HashSet<Permission>,
+    /// Created timestamp
+    pub created_at: i64,
+    /// Updated timestamp
+    pub updated_at: i64,
+}
+
 /// Check if user has specific permission
-#[wasm_bindgen]
-pub fn has_permission(user: &User, permission: Permission) -> bool {
-    match permission {
-        // Admins have everything
-        _ if user.is_admin() => true,
-
-        // Content editors
-        Permission::CreateShow | Permission::EditShow | Permission::DeleteShow
-        | Permission::CreateSong | Permission::EditSong | Permission::DeleteSong
-        | Permission::CreatePhoto | Permission::EditPhoto | Permission::DeletePhoto
-        | Permission::CreateVideo | Permission::EditVideo | Permission::DeleteVideo
-        | Permission::CreatePost | Permission::EditPost | Permission::DeletePost
-            if user.can_edit_content() => true,
-
-        // Media uploads
-        Permission::UploadPhoto | Permission::UploadVideo | Permission::UploadAudio
-            if user.can_upload_media() => true,
-
-        // View analytics for any authenticated user
-        Permission::ViewAnalytics if user.status == UserStatus::Active => true,
-
-        _ => false,
-    }
+///
+/// Only active accounts hold permissions; each role contributes the set from
+/// `Role::permissions`, or from its definition in `definitions` for custom roles.
+pub fn has_permission(user: &User, permission: Permission, definitions: &[RoleDefinition]) -> bool {
+    user.status == UserStatus::Active
+        && user.roles.iter().any(|role| role.grants(permission, definitions))
+}
+
+/// Check if user has a permission on a specific site
+///
+/// Global roles apply to every site; site roles only to their own site.
+pub fn has_site_permission(
+    user: &User,
+    permission: Permission,
+    site_id: &str,
+    definitions: &[RoleDefinition],
+) -> bool {
+    user.status == UserStatus::Active
+        && user
+            .roles
+            .iter()
+            .any(|role| role.applies_to_site(site_id) && role.grants(permission, definitions))
 }
 
 /// Check if user has ANY of the specified permissions
-#[wasm_bindgen]
-pub fn has_any_permission(user: &User, permissions: &[Permission]) -> bool {
-    permissions.iter().any(|p| has_permission(user, *p))
+pub fn has_any_permission(user: &User, permissions: &[Permission], definitions: &[RoleDefinition]) -> bool {
+    permissions.iter().any(|p| has_permission(user, *p, definitions))
 }
 
 /// Check if user has ALL of the specified permissions
-#[wasm_bindgen]
-pub fn has_all_permissions(user: &User, permissions: &[Permission]) -> bool {
-    permissions.iter().all(|p| has_permission(user, *p))
+pub fn has_all_permissions(user: &User, permissions: &[Permission], definitions: &[RoleDefinition]) -> bool {
+    permissions.iter().all(|p| has_permission(user, *p, definitions))
+}
+
+// ============================================================================
+// SITE SCOPES
+// ============================================================================
+
+/// Sites a user's roles reach (carried in access tokens)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SiteScope {
+    /// At least one global role: every site
+    All,
+    /// Only site-scoped roles: just these sites
+    Sites {
+        #[serde(rename = "siteIds")]
+        site_ids: Vec<String>,
+    },
+}
+
+impl SiteScope {
+    /// Compute the scope granted by a user's roles
+    ///
+    /// Only users whose roles are all site-scoped are confined; users with
+    /// no roles hold no permissions and read like anonymous visitors.
+    pub fn for_user(user: &User) -> Self {
+        let mut site_ids = Vec::new();
+        for role in &user.roles {
+            match role.site_id() {
+                Some(site_id) => site_ids.push(site_id.to_string()),
+                None => return SiteScope::All,
+            }
+        }
+        if site_ids.is_empty() {
+            SiteScope::All
+        } else {
+            SiteScope::Sites { site_ids }
+        }
+    }
+
+    /// Check if a site falls inside this scope
+    pub fn contains(&self, site_id: &str) -> bool {
+        match self {
+            SiteScope::All => true,
+            SiteScope::Sites { site_ids } => site_ids.iter().any(|id| id == site_id),
+        }
+    }
 }
 
 // ============================================================================
 // RESOURCE-LEVEL PERMISSIONS
 // ============================================================================
 
+/// Resource types that access entries may target
+pub const RESOURCE_TYPES: &[&str] = &["site", "show", "setlist", "song", "post", "photo", "gallery", "video"];
+
 /// Access control for specific resources
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceAccess {
+    /// Entry ID
+    pub id: String,
+    /// Resource type (one of `RESOURCE_TYPES`)
     pub resource_type: String,
+    /// Resource ID
     pub resource_id: String,
+    /// User the access is granted to
     pub user_id: String,
+    /// Permissions granted on the resource
     pub permissions: HashSet<Permission>,
+    /// User who granted the access
+    pub granted_by: String,
+    /// Grant timestamp
+    pub granted_at: i64,
 }
 
-/// Check if user can access specific resource
-#[wasm_bindgen]
+/// Check if user holds a permission on a specific resource
+///
+/// Admins pass everything, site editors pass for their own site, and anyone
+/// else needs an access entry granting the permission on that resource.
 pub fn can_access_resource(
     user: &User,
     resource_type: &str,
     resource_id: &str,
+    permission: Permission,
+    definitions: &[RoleDefinition],
     access_lists: &[ResourceAccess],
 ) -> bool {
+    if user.status != UserStatus::Active {
+        return false;
+    }
+
     // Admins can access everything
     if user.is_admin() {
         return true;
     }
 
     // Check site-specific access
-    if let Some(Role::SiteEditor { site_id }) = user.roles.iter().find(|r| {
-        matches!(r, Role::SiteEditor { .. })
-    }) {
-        if resource_type == "site" && resource_id == site_id {
-            return true;
-        }
+    if resource_type == "site"
+        && user.roles.iter().any(|r| r.site_id() == Some(resource_id) && r.grants(permission, definitions))
+    {
+        return true;
     }
 
     // Check explicit access lists
@@ -172,6 +256,7 @@ pub fn can_access_resource(
             acl.user_id == user.id
                 && acl.resource_type == resource_type
                 && acl.resource_id == resource_id
+                && acl.permissions.contains(&permission)
         })
 }
 
@@ -180,7 +265,37 @@ pub fn can_access_resource(
 // ============================================================================
 
 impl Role {
-    /// Get all permissions for this role
+    /// Site this role is limited to, if any
+    pub fn site_id(&self) -> Option<&str> {
+        match self {
+            Role::SiteEditor { site_id } | Role::Custom { site_id, .. } => Some(site_id),
+            _ => None,
+        }
+    }
+
+    /// Check if this role applies to a site
+    pub fn applies_to_site(&self, site_id: &str) -> bool {
+        self.site_id().is_none_or(|id| id == site_id)
+    }
+
+    /// Check if this role grants a permission
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    /// Check if this role grants a permission, resolving custom roles
+    /// against their site's definitions
+    pub fn grants(&self, permission: Permission, definitions: &[RoleDefinition]) -> bool {
+        match self {
+            Role::Custom { role_id, site_id } => definitions
+                .iter()
+                .any(|d| &d.id == role_id && &d.site_id == site_id && d.permissions.contains(&permission)),
+            _ => self.has_permission(permission),
+        }
+    }
+
+    /// Get all permissions for this role (empty for custom roles, whose
+    /// permissions live in a `RoleDefinition`)
     pub fn permissions(&self) -> HashSet<Permission> {
         match self {
             Role::Admin => {
@@ -260,6 +375,8 @@ impl Role {
 
             Role::Media => {
                 let mut perms = HashSet::new();
+                perms.insert(Permission::CreatePhoto);
+                perms.insert(Permission::CreateVideo);
                 perms.insert(Permission::UploadPhoto);
                 perms.insert(Permission::UploadVideo);
                 perms.insert(Permission::UploadAudio);
@@ -292,6 +409,94 @@ impl Role {
                 perms.insert(Permission::ViewAnalytics);
                 perms
             }
+
+            Role::Custom { .. } => HashSet::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(roles: Vec<Role>, status: UserStatus) -> User {
+        User {
+            id: "user-1".to_string(),
+            email: "user@example.com".to_string(),
+            name: "User".to_string(),
+            roles,
+            status,
+            created_at: 0,
+            last_login: None,
+        }
+    }
+
+    #[test]
+    fn test_permissions_come_from_roles() {
+        let media = user(vec![Role::Media], UserStatus::Active);
+        assert!(has_permission(&media, Permission::UploadPhoto, &[]));
+        assert!(has_permission(&media, Permission::CreatePhoto, &[]));
+        assert!(!has_permission(&media, Permission::CreateShow, &[]));
+
+        let admin = user(vec![Role::Admin], UserStatus::Active);
+        assert!(has_all_permissions(&admin, &[Permission::AssignRoles, Permission::PublishPost], &[]));
+    }
+
+    #[test]
+    fn test_site_editor_is_limited_to_its_site() {
+        let editor = user(vec![Role::SiteEditor { site_id: "monsters".to_string() }], UserStatus::Active);
+        assert!(has_site_permission(&editor, Permission::EditShow, "monsters", &[]));
+        assert!(!has_site_permission(&editor, Permission::EditShow, "other-band", &[]));
+        assert_eq!(SiteScope::for_user(&editor), SiteScope::Sites { site_ids: vec!["monsters".to_string()] });
+
+        let content = user(vec![Role::Content, Role::SiteEditor { site_id: "monsters".to_string() }], UserStatus::Active);
+        assert!(has_site_permission(&content, Permission::EditShow, "other-band", &[]));
+        assert!(SiteScope::for_user(&content).contains("other-band"));
+    }
+
+    #[test]
+    fn test_access_entries_grant_single_resources() {
+        let guest = user(vec![], UserStatus::Active);
+        let acl = ResourceAccess {
+            id: "acl-1".to_string(),
+            resource_type: "gallery".to_string(),
+            resource_id: "gallery-1".to_string(),
+            user_id: guest.id.clone(),
+            permissions: HashSet::from([Permission::EditPhoto]),
+            granted_by: "admin".to_string(),
+            granted_at: 0,
+        };
+        let acls = [acl];
+
+        assert!(can_access_resource(&guest, "gallery", "gallery-1", Permission::EditPhoto, &[], &acls));
+        assert!(!can_access_resource(&guest, "gallery", "gallery-1", Permission::DeletePhoto, &[], &acls));
+        assert!(!can_access_resource(&guest, "gallery", "gallery-2", Permission::EditPhoto, &[], &acls));
+    }
+
+    #[test]
+    fn test_custom_roles_resolve_from_definitions() {
+        let tour_manager = RoleDefinition {
+            id: "role-1".to_string(),
+            site_id: "monsters".to_string(),
+            name: "Tour Manager".to_string(),
+            description: None,
+            permissions: HashSet::from([Permission::EditShow, Permission::SendEmail]),
+            created_at: 0,
+            updated_at: 0,
+        };
+        let role = Role::Custom { role_id: "role-1".to_string(), site_id: "monsters".to_string() };
+        let member = user(vec![role], UserStatus::Active);
+        let definitions = [tour_manager];
+
+        assert!(has_site_permission(&member, Permission::SendEmail, "monsters", &definitions));
+        assert!(!has_site_permission(&member, Permission::DeleteMedia, "monsters", &definitions));
+        assert!(!has_site_permission(&member, Permission::EditShow, "other-band", &definitions));
+        assert!(!has_permission(&member, Permission::EditShow, &[]));
+    }
+
+    #[test]
+    fn test_inactive_users_have_no_permissions() {
+        let suspended = user(vec![Role::Admin], UserStatus::Suspended);
+        assert!(!has_any_permission(&suspended, &[Permission::CreateShow, Permission::ViewAnalytics], &[]));
+    }
+}
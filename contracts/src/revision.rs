@@ -0,0 +1,204 @@
+// Revision History Module
+//
+// Every change to a versioned record (blog posts and shows) is kept as a
+// `Revision`: a full snapshot of the record after the change, who made it and
+// when, and the field-level diff against the revision before. Snapshots make
+// rollback exact; diffs make the history readable without comparing JSON.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+/// Fields that change on every save, or are derived from other fields, and
+/// would only add noise to a diff
+const IGNORED_FIELDS: &[&str] = &["updatedAt", "rendered"];
+
+/// One saved version of a record
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    /// Unique revision ID
+    pub id: String,
+    /// Site the record belongs to
+    pub site_id: String,
+    /// Kind of record ("post" or "show")
+    pub resource_type: String,
+    /// ID of the record
+    pub resource_id: String,
+    /// Revision number, counting from 1 for each record
+    pub number: u32,
+    /// User who made the change (`None` for automatic changes, like scheduled publishing)
+    pub author_id: Option<String>,
+    /// When the change was saved
+    pub created_at: i64,
+    /// What happened, e.g. "Published" or "Rolled back to revision 3"
+    pub note: Option<String>,
+    /// Fields changed since the previous revision
+    pub changes: Vec<FieldChange>,
+    /// The record as saved
+    #[schema(value_type = Object)]
+    pub snapshot: Value,
+}
+
+/// A revision without its snapshot, for history lists
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionSummary {
+    pub id: String,
+    pub number: u32,
+    pub author_id: Option<String>,
+    pub created_at: i64,
+    pub note: Option<String>,
+    /// Names of the fields that changed
+    pub changed_fields: Vec<String>,
+}
+
+impl From<&Revision> for RevisionSummary {
+    fn from(revision: &Revision) -> Self {
+        Self {
+            id: revision.id.clone(),
+            number: revision.number,
+            author_id: revision.author_id.clone(),
+            created_at: revision.created_at,
+            note: revision.note.clone(),
+            changed_fields: revision.changes.iter().map(|c| c.field.clone()).collect(),
+        }
+    }
+}
+
+/// The differences between two revisions of a record
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionComparison {
+    /// Older revision number
+    pub from: u32,
+    /// Newer revision number
+    pub to: u32,
+    pub changes: Vec<FieldChange>,
+}
+
+/// One field's change between two versions
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    /// Field name (as serialized)
+    pub field: String,
+    /// Value before (`null` if the field was unset)
+    #[schema(value_type = Object)]
+    pub before: Value,
+    /// Value after (`null` if the field was cleared)
+    #[schema(value_type = Object)]
+    pub after: Value,
+    /// Line-by-line diff, for text that spans several lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<Vec<DiffLine>>,
+}
+
+/// Whether a diff line was kept, added or removed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// One line of a text diff
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// Field-level differences between two serialized records (objects), in
+/// field order of `after`, then fields only `before` has
+pub fn diff(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut fields: Vec<&String> = after.keys().collect();
+    fields.extend(before.keys().filter(|k| !after.contains_key(*k)));
+
+    fields
+        .into_iter()
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let old = before.get(field).unwrap_or(&Value::Null);
+            let new = after.get(field).unwrap_or(&Value::Null);
+            if old == new {
+                return None;
+            }
+            let lines = match (old, new) {
+                (Value::String(a), Value::String(b)) if a.contains('\n') || b.contains('\n') => Some(diff_lines(a, b)),
+                _ => None,
+            };
+            Some(FieldChange { field: field.clone(), before: old.clone(), after: new.clone(), lines })
+        })
+        .collect()
+}
+
+/// Line diff of two texts (longest common subsequence)
+pub fn diff_lines(before: &str, after: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+
+    // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let line = |op, text: &str| DiffLine { op, text: text.to_string() };
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            out.push(line(DiffOp::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            out.push(line(DiffOp::Delete, a[i]));
+            i += 1;
+        } else {
+            out.push(line(DiffOp::Insert, b[j]));
+            j += 1;
+        }
+    }
+    out.extend(a[i..].iter().map(|t| line(DiffOp::Delete, t)));
+    out.extend(b[j..].iter().map(|t| line(DiffOp::Insert, t)));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_reports_changed_fields_and_lines() {
+        let before = json!({ "title": "Tour", "description": "Dates:\nMay 1\nMay 2", "venue": "Roxy", "updatedAt": 1 });
+        let after = json!({ "title": "Tour", "description": "Dates:\nMay 2\nMay 3", "ticketUrl": "x", "updatedAt": 2 });
+
+        let changes = diff(&before, &after);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["description", "ticketUrl", "venue"]);
+        assert_eq!(changes[2].after, Value::Null);
+
+        let ops: Vec<(DiffOp, &str)> =
+            changes[0].lines.as_ref().unwrap().iter().map(|l| (l.op, l.text.as_str())).collect();
+        assert_eq!(
+            ops,
+            vec![
+                (DiffOp::Equal, "Dates:"),
+                (DiffOp::Delete, "May 1"),
+                (DiffOp::Equal, "May 2"),
+                (DiffOp::Insert, "May 3"),
+            ]
+        );
+        assert!(changes[1].lines.is_none());
+    }
+}
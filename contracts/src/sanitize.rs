@@ -0,0 +1,358 @@
+// HTML Sanitizer Module
+//
+// Allowlist sanitizer for user-authored HTML (blog post content, whether
+// written as HTML or rendered from Markdown). Anything not explicitly allowed
+// is removed: unknown tags are dropped but keep their text, script-like
+// elements are dropped with their content, attributes are checked per tag,
+// and URLs must use a safe scheme. The output is well-formed: text is escaped
+// and every open tag is closed.
+
+use crate::markdown::{entity_len, escape};
+
+/// Tags kept in the output
+const ALLOWED_TAGS: &[&str] = &[
+    "a", "abbr", "b", "blockquote", "br", "code", "del", "em", "figcaption", "figure", "h1", "h2", "h3", "h4",
+    "h5", "h6", "hr", "i", "img", "ins", "li", "ol", "p", "pre", "s", "strong", "sub", "sup", "table", "tbody",
+    "td", "tfoot", "th", "thead", "tr", "u", "ul",
+];
+
+/// Tags removed together with everything inside them
+const DROPPED_WITH_CONTENT: &[&str] = &[
+    "applet", "embed", "frame", "frameset", "head", "iframe", "math", "noembed", "noframes", "noscript",
+    "object", "script", "select", "style", "svg", "template", "textarea", "title",
+];
+
+/// Tags without a closing tag
+const VOID_TAGS: &[&str] = &["br", "hr", "img"];
+
+/// Attributes kept, by tag
+fn allowed_attributes(tag: &str) -> &'static [&'static str] {
+    match tag {
+        "a" => &["href", "title"],
+        "img" => &["src", "alt", "title", "width", "height"],
+        "abbr" => &["title"],
+        "code" => &["class"],
+        "ol" => &["start"],
+        "td" | "th" => &["colspan", "rowspan", "align"],
+        _ => &[],
+    }
+}
+
+/// Sanitize HTML against the allowlist
+pub fn sanitize(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut open: Vec<String> = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            push_text(&mut out, rest);
+            break;
+        };
+        push_text(&mut out, &rest[..lt]);
+        rest = &rest[lt..];
+
+        // Comments, doctypes and processing instructions
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+
+        let Some((tag, after)) = parse_tag(rest) else {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+            continue;
+        };
+        rest = after;
+
+        if tag.closing {
+            if let Some(index) = open.iter().rposition(|t| *t == tag.name) {
+                for name in open.drain(index..).rev() {
+                    out.push_str(&format!("</{}>", name));
+                }
+            }
+            continue;
+        }
+
+        if DROPPED_WITH_CONTENT.contains(&tag.name.as_str()) {
+            if !tag.self_closing {
+                rest = skip_element(rest, &tag.name);
+            }
+            continue;
+        }
+        if !ALLOWED_TAGS.contains(&tag.name.as_str()) {
+            continue;
+        }
+
+        out.push('<');
+        out.push_str(&tag.name);
+        let allowed = allowed_attributes(&tag.name);
+        let mut seen: Vec<&str> = Vec::new();
+        for (name, value) in &tag.attributes {
+            let Some(&name) = allowed.iter().find(|a| **a == name) else {
+                continue;
+            };
+            if seen.contains(&name) {
+                continue;
+            }
+            if let Some(value) = clean_attribute(&tag.name, name, value) {
+                out.push_str(&format!(" {}=\"{}\"", name, escape(&value)));
+                seen.push(name);
+            }
+        }
+        if tag.name == "a" && seen.contains(&"href") {
+            out.push_str(" rel=\"nofollow noopener noreferrer\"");
+        }
+
+        if VOID_TAGS.contains(&tag.name.as_str()) {
+            out.push_str(" />");
+        } else {
+            out.push('>');
+            open.push(tag.name);
+        }
+    }
+
+    for name in open.into_iter().rev() {
+        out.push_str(&format!("</{}>", name));
+    }
+    out
+}
+
+/// Escape text, keeping well-formed entities as they are
+fn push_text(out: &mut String, text: &str) {
+    let mut i = 0;
+    while let Some(offset) = text[i..].find(['&', '<', '>', '"']) {
+        let at = i + offset;
+        out.push_str(&text[i..at]);
+        match text.as_bytes()[at] {
+            b'&' => match entity_len(&text[at..]) {
+                Some(len) => {
+                    out.push_str(&text[at..at + len]);
+                    i = at + len;
+                    continue;
+                }
+                None => out.push_str("&amp;"),
+            },
+            b'<' => out.push_str("&lt;"),
+            b'>' => out.push_str("&gt;"),
+            _ => out.push_str("&quot;"),
+        }
+        i = at + 1;
+    }
+    out.push_str(&text[i..]);
+}
+
+struct Tag {
+    /// Lowercase tag name
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    /// Lowercase names and decoded values
+    attributes: Vec<(String, String)>,
+}
+
+/// Parse a start or end tag at the start of `input` (which begins with `<`),
+/// returning it and the input after it
+fn parse_tag(input: &str) -> Option<(Tag, &str)> {
+    let mut rest = &input[1..];
+    let closing = rest.starts_with('/');
+    if closing {
+        rest = &rest[1..];
+    }
+    if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name_len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-')).unwrap_or(rest.len());
+    let name = rest[..name_len].to_ascii_lowercase();
+    rest = &rest[name_len..];
+
+    let mut attributes = Vec::new();
+    let mut self_closing = false;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace());
+        if let Some(after) = rest.strip_prefix('>') {
+            return Some((Tag { name, closing, self_closing, attributes }, after));
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            self_closing = true;
+            rest = after;
+            continue;
+        }
+        if rest.is_empty() {
+            return None;
+        }
+        self_closing = false;
+
+        let attr_len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len())
+            .max(1);
+        let attr = rest[..attr_len].to_ascii_lowercase();
+        rest = rest[attr_len..].trim_start_matches(|c: char| c.is_whitespace());
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            rest = after.trim_start_matches(|c: char| c.is_whitespace());
+            let raw = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = rest[1..].find(quote)? + 1;
+                    let raw = &rest[1..end];
+                    rest = &rest[end + 1..];
+                    raw
+                }
+                _ => {
+                    let end = rest.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(rest.len());
+                    let raw = &rest[..end];
+                    rest = &rest[end..];
+                    raw
+                }
+            };
+            value = decode_entities(raw);
+        }
+        attributes.push((attr, value));
+    }
+}
+
+/// Skip past the end of an element whose start tag has been consumed
+fn skip_element<'a>(input: &'a str, name: &str) -> &'a str {
+    let lower = input.to_ascii_lowercase();
+    let closing = format!("</{}", name);
+    let mut from = 0;
+    while let Some(offset) = lower[from..].find(&closing) {
+        let at = from + offset + closing.len();
+        // `</scriptx` doesn't close `<script>`
+        if lower[at..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            return lower[at..].find('>').map_or("", |end| &input[at + end + 1..]);
+        }
+        from = at;
+    }
+    ""
+}
+
+/// Validate an allowed attribute's value, returning the value to keep
+fn clean_attribute(tag: &str, name: &str, value: &str) -> Option<String> {
+    match name {
+        "href" => safe_url(value, &["http", "https", "mailto", "tel"]),
+        "src" => safe_url(value, &["http", "https"]),
+        "class" => (tag == "code"
+            && value.strip_prefix("language-").is_some_and(|lang| {
+                !lang.is_empty() && lang.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+'))
+            }))
+        .then(|| value.to_string()),
+        "start" | "colspan" | "rowspan" | "width" | "height" => {
+            let value = value.trim();
+            (!value.is_empty() && value.len() <= 6 && value.chars().all(|c| c.is_ascii_digit()))
+                .then(|| value.to_string())
+        }
+        "align" => matches!(value, "left" | "center" | "right").then(|| value.to_string()),
+        _ => Some(value.to_string()),
+    }
+}
+
+/// Keep a URL if it is relative or uses one of `schemes`. Browsers ignore
+/// whitespace and control characters inside a scheme, so they are ignored
+/// here too (`java\tscript:` is `javascript:`).
+fn safe_url(url: &str, schemes: &[&str]) -> Option<String> {
+    let url = url.trim();
+    let compact: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    let scheme_end = compact.find(['/', '?', '#', ':']);
+    match scheme_end {
+        Some(end) if compact[end..].starts_with(':') => {
+            let scheme = compact[..end].to_ascii_lowercase();
+            schemes.contains(&scheme.as_str()).then(|| url.to_string())
+        }
+        _ => Some(url.to_string()),
+    }
+}
+
+/// Decode character references in an attribute value
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        // Numeric references may omit the semicolon
+        let body = &rest[1..];
+        let (decoded, len) = if let Some(hex) = body.strip_prefix(['#']).and_then(|b| b.strip_prefix(['x', 'X'])) {
+            let digits = hex.chars().take_while(char::is_ascii_hexdigit).count();
+            let code = u32::from_str_radix(&hex[..digits.min(8)], 16).ok();
+            (code.map(|c| char::from_u32(c).unwrap_or('\u{FFFD}')), 3 + digits)
+        } else if let Some(dec) = body.strip_prefix('#') {
+            let digits = dec.chars().take_while(char::is_ascii_digit).count();
+            let code = dec[..digits.min(9)].parse::<u32>().ok();
+            (code.map(|c| char::from_u32(c).unwrap_or('\u{FFFD}')), 2 + digits)
+        } else {
+            let name_len = body.chars().take_while(char::is_ascii_alphanumeric).count();
+            let named = match &body[..name_len] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                "colon" => Some(':'),
+                "Tab" => Some('\t'),
+                "NewLine" => Some('\n'),
+                _ => None,
+            };
+            (named, 1 + name_len)
+        };
+
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[len..];
+                rest = rest.strip_prefix(';').unwrap_or(rest);
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_strips_scripts_and_handlers() {
+        assert_eq!(
+            sanitize("<p onclick=\"steal()\">Hi<script>alert('x')</script> <b>there</b></p><style>p{}</style>"),
+            "<p>Hi <b>there</b></p>"
+        );
+        assert_eq!(sanitize("<div><span>kept</span></div><!-- note -->"), "kept");
+        assert_eq!(sanitize("<SCRIPT src=x></SCRIPT >after"), "after");
+        assert_eq!(sanitize("<svg><script>x</script></svg>ok"), "ok");
+    }
+
+    #[test]
+    fn test_sanitize_urls() {
+        assert_eq!(
+            sanitize("<a href=\"https://example.com/?a=1&b=2\" target=\"_blank\">x</a>"),
+            "<a href=\"https://example.com/?a=1&amp;b=2\" rel=\"nofollow noopener noreferrer\">x</a>"
+        );
+        for href in ["javascript:alert(1)", "JaVaScRiPt:alert(1)", "java\tscript:x", "jav&#x61;script:x", "javascript&colon;x", " data:text/html,x"] {
+            assert_eq!(sanitize(&format!("<a href=\"{}\">x</a>", href)), "<a>x</a>", "{}", href);
+        }
+        assert_eq!(sanitize("<a href='/shows#next'>x</a>"), "<a href=\"/shows#next\" rel=\"nofollow noopener noreferrer\">x</a>");
+        assert_eq!(sanitize("<img src=\"data:image/png;base64,xx\" alt=\"a\" onerror=\"x()\">"), "<img alt=\"a\" />");
+        assert_eq!(sanitize("<code class=\"language-rust x\">"), "<code></code>");
+    }
+
+    #[test]
+    fn test_sanitize_balances_and_escapes() {
+        assert_eq!(sanitize("<em><strong>a</em> b</p> 1 < 2 &amp; 3 & 4"), "<em><strong>a</strong></em> b 1 &lt; 2 &amp; 3 &amp; 4");
+        assert_eq!(sanitize("<ul><li>open"), "<ul><li>open</li></ul>");
+        assert_eq!(sanitize("<img src=x \"<script>\">"), "<img src=\"x\" />&quot;&gt;");
+    }
+}
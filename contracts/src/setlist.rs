@@ -0,0 +1,281 @@
+// Setlist Module
+//
+// A show's setlist is one or more named sets (`Setlist` records with the same
+// `show_id`, ordered by `position`). This module resolves a set's songs into
+// the totals and warnings the API returns and the CMS shows while building:
+// running time from `Song::duration_seconds`, and key changes between
+// consecutive songs from `Song::musical_key`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::{Setlist, Song};
+
+// ============================================================================
+// MUSICAL KEYS
+// ============================================================================
+
+/// Major or minor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+/// A parsed musical key, e.g. `"F#m"`, `"Bb major"`, `"A minor"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MusicalKey {
+    /// Pitch class of the tonic (C = 0 ... B = 11)
+    pub tonic: u8,
+    pub mode: Mode,
+}
+
+impl MusicalKey {
+    /// Tonic of the major key with the same key signature
+    fn relative_major(&self) -> u8 {
+        match self.mode {
+            Mode::Major => self.tonic,
+            Mode::Minor => (self.tonic + 3) % 12,
+        }
+    }
+
+    /// Steps between two key signatures around the circle of fifths (0-6)
+    pub fn fifths_apart(&self, other: &MusicalKey) -> u8 {
+        let position = |key: &MusicalKey| (key.relative_major() * 7) % 12;
+        let distance = (12 + position(self) - position(other)) % 12;
+        distance.min(12 - distance)
+    }
+
+    /// Whether the keys are closely related (same signature, or one step
+    /// apart on the circle of fifths), so moving between them doesn't sound
+    /// like a key change. For C: C, Am, G, Em, F and Dm.
+    pub fn is_closely_related(&self, other: &MusicalKey) -> bool {
+        self.fifths_apart(other) <= 1
+    }
+}
+
+/// Error parsing a musical key
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unrecognized musical key: {0}")]
+pub struct KeyParseError(pub String);
+
+impl FromStr for MusicalKey {
+    type Err = KeyParseError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let error = || KeyParseError(key.to_string());
+        let trimmed = key.trim();
+        let mut chars = trimmed.chars();
+
+        let natural = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            Some('C') => 0,
+            Some('D') => 2,
+            Some('E') => 4,
+            Some('F') => 5,
+            Some('G') => 7,
+            Some('A') => 9,
+            Some('B') => 11,
+            _ => return Err(error()),
+        };
+        let rest = chars.as_str();
+        let (tonic, rest) = if let Some(rest) = rest.strip_prefix(['#', '♯']) {
+            ((natural + 1) % 12, rest)
+        } else if let Some(rest) = rest.strip_prefix(['b', '♭']) {
+            ((natural + 11) % 12, rest)
+        } else {
+            (natural, rest)
+        };
+
+        // "m" is minor and "M" major; longer spellings are case-insensitive
+        let mode = match rest.trim() {
+            "" | "M" => Mode::Major,
+            "m" | "-" => Mode::Minor,
+            other => match other.to_lowercase().as_str() {
+                "maj" | "major" => Mode::Major,
+                "min" | "minor" => Mode::Minor,
+                _ => return Err(error()),
+            },
+        };
+        Ok(MusicalKey { tonic, mode })
+    }
+}
+
+impl fmt::Display for MusicalKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const NAMES: [&str; 12] = ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+        let suffix = if self.mode == Mode::Minor { "m" } else { "" };
+        write!(f, "{}{}", NAMES[self.tonic as usize], suffix)
+    }
+}
+
+// ============================================================================
+// SETLIST DETAILS
+// ============================================================================
+
+/// A key change between two consecutive songs of a set
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyChangeWarning {
+    /// Index (in the set) of the song that changes key
+    pub position: u32,
+    /// Song that changes key
+    pub song_id: String,
+    /// Key of the previous song
+    pub from_key: String,
+    /// Key of this song
+    pub to_key: String,
+}
+
+impl Setlist {
+    /// The set as shown to visitors: without the members' notes
+    pub fn public(mut self) -> Self {
+        self.member_notes.clear();
+        self
+    }
+}
+
+/// A set with its songs resolved, as returned by the setlist endpoints
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetlistDetail {
+    /// The set
+    #[serde(flatten)]
+    pub setlist: Setlist,
+    /// Songs in order (IDs that no longer resolve are skipped)
+    pub songs: Vec<Song>,
+    /// Running time of the songs with a known duration
+    pub duration_seconds: i32,
+    /// Number of songs without a duration
+    pub songs_without_duration: u32,
+    /// Key changes between consecutive songs
+    pub key_changes: Vec<KeyChangeWarning>,
+}
+
+impl SetlistDetail {
+    /// Resolve a set against its songs (in the set's order)
+    pub fn new(setlist: Setlist, songs: Vec<Song>) -> Self {
+        let (duration_seconds, songs_without_duration) = total_duration(&songs);
+        let key_changes = key_changes(&songs);
+        Self { setlist, songs, duration_seconds, songs_without_duration, key_changes }
+    }
+}
+
+/// Every set of a show, in order
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShowSetlist {
+    /// Show ID
+    pub show_id: String,
+    /// Sets ordered by position
+    pub sets: Vec<SetlistDetail>,
+    /// Running time of all sets
+    pub duration_seconds: i32,
+}
+
+impl ShowSetlist {
+    /// Collect a show's sets, ordering them by position
+    pub fn new(show_id: String, mut sets: Vec<SetlistDetail>) -> Self {
+        sets.sort_by(|a, b| a.setlist.position.cmp(&b.setlist.position).then_with(|| a.setlist.id.cmp(&b.setlist.id)));
+        let duration_seconds = sets.iter().map(|s| s.duration_seconds).sum();
+        Self { show_id, sets, duration_seconds }
+    }
+}
+
+/// Total running time, and how many songs have no duration
+pub fn total_duration(songs: &[Song]) -> (i32, u32) {
+    songs.iter().fold((0, 0), |(total, unknown), song| match song.duration_seconds {
+        Some(seconds) => (total + seconds, unknown),
+        None => (total, unknown + 1),
+    })
+}
+
+/// Key changes between consecutive songs. Songs without a recognizable key
+/// don't break the chain: the next keyed song is compared with the last one.
+pub fn key_changes(songs: &[Song]) -> Vec<KeyChangeWarning> {
+    let mut warnings = Vec::new();
+    let mut previous: Option<MusicalKey> = None;
+    for (position, song) in songs.iter().enumerate() {
+        let Some(key) = song.musical_key.as_deref().and_then(|k| k.parse::<MusicalKey>().ok()) else {
+            continue;
+        };
+        if let Some(previous) = previous.filter(|p| !p.is_closely_related(&key)) {
+            warnings.push(KeyChangeWarning {
+                position: position as u32,
+                song_id: song.id.clone(),
+                from_key: previous.to_string(),
+                to_key: key.to_string(),
+            });
+        }
+        previous = Some(key);
+    }
+    warnings
+}
+
+/// Format seconds as `m:ss` (or `h:mm:ss`)
+pub fn format_duration(seconds: i32) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(id: &str, key: Option<&str>, duration: Option<i32>) -> Song {
+        Song {
+            id: id.to_string(),
+            site_id: "site-1".to_string(),
+            title: id.to_string(),
+            artist: None,
+            genres: Vec::new(),
+            duration_seconds: duration,
+            is_original: true,
+            musical_key: key.map(str::to_string),
+            notes: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_musical_key_parsing() {
+        let key = |s: &str| s.parse::<MusicalKey>().unwrap();
+        assert_eq!(key("C"), MusicalKey { tonic: 0, mode: Mode::Major });
+        assert_eq!(key("F#m"), MusicalKey { tonic: 6, mode: Mode::Minor });
+        assert_eq!(key("Bb major"), MusicalKey { tonic: 10, mode: Mode::Major });
+        assert_eq!(key(" a Minor "), MusicalKey { tonic: 9, mode: Mode::Minor });
+        assert_eq!(key("E♭").to_string(), "Eb");
+        assert!("H".parse::<MusicalKey>().is_err());
+        assert!("C lydian".parse::<MusicalKey>().is_err());
+    }
+
+    #[test]
+    fn test_key_changes_skip_related_keys() {
+        // C -> Am (relative) -> G (neighbour) -> G -> C#m (far) -> [none] -> C
+        let songs = vec![
+            song("1", Some("C"), Some(200)),
+            song("2", Some("Am"), Some(180)),
+            song("3", Some("G"), None),
+            song("4", Some("G major"), Some(240)),
+            song("5", Some("C#m"), Some(300)),
+            song("6", None, Some(60)),
+            song("7", Some("C"), Some(100)),
+        ];
+        let warnings = key_changes(&songs);
+        assert_eq!(
+            warnings.iter().map(|w| (w.position, w.from_key.as_str(), w.to_key.as_str())).collect::<Vec<_>>(),
+            vec![(4, "G", "C#m"), (6, "C#m", "C")]
+        );
+        assert_eq!(total_duration(&songs), (1080, 1));
+        assert_eq!(format_duration(1080), "18:00");
+        assert_eq!(format_duration(3725), "1:02:05");
+    }
+}
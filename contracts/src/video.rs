@@ -0,0 +1,204 @@
+// Video URL Module
+//
+// Videos are added by pasting a link, in whatever form the band copied it:
+// a YouTube watch page, a `youtu.be` share link with a timestamp, a Shorts or
+// embed URL, a Vimeo page (public, private or inside a showcase), or a plain
+// MP4/WebM file. `classify` works out which from the URL alone and pulls out
+// the canonical video ID, so nothing depends on what the client claims the
+// link is.
+
+use thiserror::Error;
+
+use crate::VideoSource;
+
+/// File extensions served as direct video
+pub const DIRECT_EXTENSIONS: [&str; 6] = ["mp4", "m4v", "webm", "mov", "ogv", "ogg"];
+
+/// Why a URL isn't a video we can show
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum VideoUrlError {
+    #[error("Unsupported video URL: {0}")]
+    Unsupported(String),
+
+    #[error("No video ID in URL: {0}")]
+    MissingId(String),
+}
+
+/// The video a URL points at.
+///
+/// Recognizes `youtube.com/watch?v=`, `youtu.be/`, `/embed/`, `/shorts/`,
+/// `/live/` and `/v/` YouTube links (on `www.`, `m.`, `music.` and
+/// `youtube-nocookie.com` too), Vimeo pages and players (keeping the hash of
+/// private videos), and `http(s)` or root-relative links to video files.
+/// Timestamps and other query parameters are ignored.
+pub fn classify(url: &str) -> Result<VideoSource, VideoUrlError> {
+    let url = url.trim();
+    let unsupported = || VideoUrlError::Unsupported(url.to_string());
+    let missing = || VideoUrlError::MissingId(url.to_string());
+
+    // Root-relative links are our own media route
+    if url.starts_with('/') && !url.starts_with("//") {
+        let (path, _) = split_query(url);
+        return if is_video_file(path) { Ok(VideoSource::Direct { url: url.to_string() }) } else { Err(unsupported()) };
+    }
+
+    let rest = match url.split_once("://") {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") => rest,
+        Some(_) => return Err(unsupported()),
+        // Pasted without a scheme
+        None => url,
+    };
+    let (authority, path_and_query) = match rest.find(['/', '?', '#']) {
+        Some(at) => rest.split_at(at),
+        None => (rest, ""),
+    };
+    let (path, query) = split_query(path_and_query);
+    let host = host_of(authority);
+    if !is_host(&host) {
+        return Err(unsupported());
+    }
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match host.as_str() {
+        "youtu.be" => youtube(segments.first().copied()).ok_or_else(missing),
+        "youtube.com" | "music.youtube.com" | "youtube-nocookie.com" => {
+            let id = match segments.as_slice() {
+                ["watch"] => param(query, "v"),
+                ["embed" | "shorts" | "live" | "v" | "e", id, ..] => Some(*id),
+                _ => None,
+            };
+            youtube(id).ok_or_else(missing)
+        }
+        "vimeo.com" | "player.vimeo.com" => {
+            // In showcases and groups the ID follows `video(s)/`, otherwise
+            // it is the first number in the path
+            let at = (1..segments.len())
+                .find(|&i| matches!(segments[i - 1], "video" | "videos") && is_digits(segments[i]))
+                .or_else(|| segments.iter().position(|s| is_digits(s)))
+                .ok_or_else(missing)?;
+            // Private videos carry a hash: `vimeo.com/<id>/<hash>` or `?h=<hash>`
+            let hash = segments
+                .get(at + 1)
+                .copied()
+                .filter(|_| at == 0)
+                .or_else(|| param(query, "h"))
+                .filter(|h| is_hash(h));
+            Ok(VideoSource::Vimeo { video_id: segments[at].to_string(), hash: hash.map(str::to_string) })
+        }
+        _ if is_video_file(path) => {
+            let url = if url.contains("://") { url.to_string() } else { format!("https://{}", url) };
+            Ok(VideoSource::Direct { url })
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+impl VideoSource {
+    /// The page a provider knows this video by (what oEmbed is asked about),
+    /// or the file itself for direct videos
+    pub fn canonical_url(&self) -> Option<String> {
+        match self {
+            VideoSource::YouTube { video_id } => Some(format!("https://www.youtube.com/watch?v={}", video_id)),
+            VideoSource::Vimeo { video_id, hash: Some(hash) } => Some(format!("https://vimeo.com/{}/{}", video_id, hash)),
+            VideoSource::Vimeo { video_id, hash: None } => Some(format!("https://vimeo.com/{}", video_id)),
+            VideoSource::Direct { url } => Some(url.clone()),
+            VideoSource::External { .. } => None,
+        }
+    }
+}
+
+/// Split `path?query#fragment` into path and query
+fn split_query(s: &str) -> (&str, &str) {
+    let s = s.split('#').next().unwrap_or_default();
+    s.split_once('?').unwrap_or((s, ""))
+}
+
+/// Lowercased host without user info, port, or a `www.`/`m.` prefix
+fn host_of(authority: &str) -> String {
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().trim_end_matches('.').to_ascii_lowercase();
+    let host = host.strip_prefix("www.").or_else(|| host.strip_prefix("m.")).unwrap_or(&host);
+    host.to_string()
+}
+
+/// Value of query parameter `name`
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| pair.split_once('=').filter(|(key, _)| *key == name).map(|(_, value)| value))
+}
+
+/// A YouTube video ID: 11 characters of URL-safe base64
+fn youtube(id: Option<&str>) -> Option<VideoSource> {
+    let id = id?;
+    let valid = id.len() == 11 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    valid.then(|| VideoSource::YouTube { video_id: id.to_string() })
+}
+
+/// A dotted DNS name
+fn is_host(host: &str) -> bool {
+    host.contains('.') && host.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-')
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_hash(s: &str) -> bool {
+    !s.is_empty() && s.len() <= 64 && s.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn is_video_file(path: &str) -> bool {
+    path.rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, ext)| DIRECT_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(ext)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn youtube(id: &str) -> VideoSource {
+        VideoSource::YouTube { video_id: id.to_string() }
+    }
+
+    #[test]
+    fn test_youtube_links_in_every_shape() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtube.com/watch?feature=share&v=dQw4w9WgXcQ&t=42s",
+            "http://m.youtube.com/watch?v=dQw4w9WgXcQ#comments",
+            "youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ?t=42",
+            "https://www.youtube.com/embed/dQw4w9WgXcQ?start=10",
+            "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ",
+            "https://youtube.com/shorts/dQw4w9WgXcQ",
+            "https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=RD",
+            " HTTPS://WWW.YOUTUBE.COM:443/watch?v=dQw4w9WgXcQ ",
+        ] {
+            assert_eq!(classify(url), Ok(youtube("dQw4w9WgXcQ")), "{}", url);
+        }
+        assert!(matches!(classify("https://www.youtube.com/watch?list=RD"), Err(VideoUrlError::MissingId(_))));
+        assert!(matches!(classify("https://youtu.be/short"), Err(VideoUrlError::MissingId(_))));
+        assert!(matches!(classify("https://www.youtube.com/@band"), Err(VideoUrlError::MissingId(_))));
+    }
+
+    #[test]
+    fn test_vimeo_and_direct_links() {
+        let vimeo = |hash: Option<&str>| VideoSource::Vimeo { video_id: "76979871".to_string(), hash: hash.map(String::from) };
+        assert_eq!(classify("https://vimeo.com/76979871"), Ok(vimeo(None)));
+        assert_eq!(classify("https://vimeo.com/76979871/8272103f6e"), Ok(vimeo(Some("8272103f6e"))));
+        assert_eq!(classify("https://player.vimeo.com/video/76979871?h=8272103f6e&badge=0"), Ok(vimeo(Some("8272103f6e"))));
+        assert_eq!(classify("https://vimeo.com/channels/staffpicks/76979871"), Ok(vimeo(None)));
+        assert_eq!(classify("https://vimeo.com/showcase/1234/video/76979871"), Ok(vimeo(None)));
+        assert_eq!(vimeo(Some("8272103f6e")).canonical_url().unwrap(), "https://vimeo.com/76979871/8272103f6e");
+        assert!(matches!(classify("https://vimeo.com/bandname"), Err(VideoUrlError::MissingId(_))));
+
+        let direct = |url: &str| VideoSource::Direct { url: url.to_string() };
+        assert_eq!(classify("https://cdn.example.com/live/encore.MP4?sig=1"), Ok(direct("https://cdn.example.com/live/encore.MP4?sig=1")));
+        assert_eq!(classify("/api/media/sites/s/clip.webm"), Ok(direct("/api/media/sites/s/clip.webm")));
+        assert_eq!(classify("cdn.example.com/clip.mov"), Ok(direct("https://cdn.example.com/clip.mov")));
+        for url in ["https://example.com/watch?v=dQw4w9WgXcQ", "ftp://example.com/a.mp4", "javascript:alert(1)//a.mp4", "/about", ""] {
+            assert!(matches!(classify(url), Err(VideoUrlError::Unsupported(_))), "{}", url);
+        }
+    }
+}
@@ -17,6 +17,7 @@ use serde_json::json;
 /// requires #[durable_object] attribute from worker crate,
 /// which will be added in a future version.
 pub struct SiteDurableObject {
+    #[allow(dead_code)]
     state: AppState,
 }
 
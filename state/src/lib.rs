@@ -8,7 +8,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use thiserror::Error;
-use web_nexus_contracts::{Show, Song, Photo, Video, BlogPost, Site, User};
+use web_nexus_contracts::{Show, Song, Photo, Video, BlogPost, Site, User, ResourceAccess, Role, RoleDefinition};
 
 /// State synchronization error
 #[derive(Error, Debug)]
@@ -24,6 +24,9 @@ pub enum SyncError {
 
     #[error("Authentication required")]
     Unauthorized,
+
+    #[error("Snapshot schema version {0} is newer than this build supports ({STATE_SCHEMA_VERSION})")]
+    UnsupportedVersion(u32),
 }
 
 /// Sync status for state
@@ -59,6 +62,12 @@ pub struct AppState {
     pub posts: HashMap<String, BlogPost>,
     /// All users
     pub users: HashMap<String, User>,
+    /// Per-resource access entries
+    #[serde(default)]
+    pub access_lists: HashMap<String, ResourceAccess>,
+    /// Custom role definitions (all sites)
+    #[serde(default)]
+    pub role_definitions: HashMap<String, RoleDefinition>,
     /// Sync status
     pub sync_status: SyncStatus,
     /// Last sync timestamp
@@ -78,6 +87,8 @@ impl AppState {
             videos: HashMap::new(),
             posts: HashMap::new(),
             users: HashMap::new(),
+            access_lists: HashMap::new(),
+            role_definitions: HashMap::new(),
             sync_status: SyncStatus::Synced,
             last_sync: None,
             clock: 0,
@@ -139,6 +150,22 @@ impl AppState {
             self.users.insert(id, user);
         }
 
+        // Merge access entries
+        for (id, acl) in other.access_lists {
+            self.access_lists.insert(id, acl);
+        }
+
+        // Merge role definitions
+        for (id, role) in other.role_definitions {
+            if let Some(existing) = self.role_definitions.get(&id) {
+                if role.updated_at > existing.updated_at {
+                    self.role_definitions.insert(id, role);
+                }
+            } else {
+                self.role_definitions.insert(id, role);
+            }
+        }
+
         // Update clock (take max)
         self.clock = self.clock.max(other.clock);
 
@@ -230,6 +257,85 @@ impl AppState {
         Ok(())
     }
 
+    /// Grant access on a resource, merging with an existing entry for the
+    /// same user and resource
+    pub fn grant_access(&mut self, acl: ResourceAccess) -> ResourceAccess {
+        self.clock += 1;
+        self.sync_status = SyncStatus::Pending;
+
+        let existing = self.access_lists.values_mut().find(|a| {
+            a.user_id == acl.user_id
+                && a.resource_type == acl.resource_type
+                && a.resource_id == acl.resource_id
+        });
+        match existing {
+            Some(entry) => {
+                entry.permissions.extend(acl.permissions);
+                entry.granted_by = acl.granted_by;
+                entry.granted_at = acl.granted_at;
+                entry.clone()
+            }
+            None => {
+                self.access_lists.insert(acl.id.clone(), acl.clone());
+                acl
+            }
+        }
+    }
+
+    /// Revoke an access entry
+    pub fn revoke_access(&mut self, acl_id: &str) -> Option<ResourceAccess> {
+        self.clock += 1;
+        self.sync_status = SyncStatus::Pending;
+        self.access_lists.remove(acl_id)
+    }
+
+    /// Access entries for a user on a resource
+    pub fn get_resource_access(&self, user_id: &str, resource_type: &str, resource_id: &str) -> Vec<ResourceAccess> {
+        self.access_lists
+            .values()
+            .filter(|a| a.user_id == user_id && a.resource_type == resource_type && a.resource_id == resource_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Add or replace a custom role definition
+    pub fn save_role_definition(&mut self, role: RoleDefinition) {
+        self.clock += 1;
+        self.role_definitions.insert(role.id.clone(), role);
+        self.sync_status = SyncStatus::Pending;
+    }
+
+    /// Delete a custom role definition and unassign it from every user
+    pub fn delete_role_definition(&mut self, role_id: &str) -> Option<RoleDefinition> {
+        let removed = self.role_definitions.remove(role_id)?;
+        self.clock += 1;
+        for user in self.users.values_mut() {
+            user.roles.retain(|r| !matches!(r, Role::Custom { role_id: id, .. } if id == role_id));
+        }
+        self.sync_status = SyncStatus::Pending;
+        Some(removed)
+    }
+
+    /// Get all custom role definitions for a site
+    pub fn get_site_roles(&self, site_id: &str) -> Vec<RoleDefinition> {
+        self.role_definitions
+            .values()
+            .filter(|r| r.site_id == site_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Definitions of the custom roles assigned to a user
+    pub fn get_user_role_definitions(&self, user: &User) -> Vec<RoleDefinition> {
+        user.roles
+            .iter()
+            .filter_map(|role| match role {
+                Role::Custom { role_id, .. } => self.role_definitions.get(role_id).cloned(),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Check if sync is needed
     pub fn needs_sync(&self) -> bool {
         matches!(self.sync_status, SyncStatus::Pending)
@@ -328,15 +434,72 @@ pub enum ConflictResolution {
 // STATE SERIALIZATION
 // ============================================================================
 
+/// Schema version written into every snapshot by `serialize_state`
+///
+/// Bump this and append to `UPGRADES` whenever a change to `AppState` or the
+/// contracts types would stop older snapshots from deserializing.
+pub const STATE_SCHEMA_VERSION: u32 = 3;
+
+/// Snapshot key holding the schema version
+const SCHEMA_VERSION_KEY: &str = "schemaVersion";
+
+/// Forward-only snapshot upgrades; `UPGRADES[n]` turns version `n + 1` into `n + 2`
+const UPGRADES: &[fn(&mut serde_json::Map<String, serde_json::Value>)] = &[upgrade_v1_to_v2, upgrade_v2_to_v3];
+
+/// v1 snapshots (unversioned) predate per-resource access entries and custom roles
+fn upgrade_v1_to_v2(snapshot: &mut serde_json::Map<String, serde_json::Value>) {
+    for key in ["access_lists", "role_definitions"] {
+        snapshot.entry(key).or_insert_with(|| serde_json::json!({}));
+    }
+}
+
+/// v2 snapshots carry video passwords in plain text inside `visibility`
+/// (`{"password": {"password": ...}}`). The password now lives only on the
+/// server, hashed, so the video keeps a bare `"password"` visibility.
+fn upgrade_v2_to_v3(snapshot: &mut serde_json::Map<String, serde_json::Value>) {
+    let Some(serde_json::Value::Object(videos)) = snapshot.get_mut("videos") else {
+        return;
+    };
+    for video in videos.values_mut() {
+        if video.pointer("/visibility/password").is_some() {
+            video["visibility"] = serde_json::json!("password");
+        }
+    }
+}
+
 /// Serialize state to JSON for storage/transmission
 pub fn serialize_state(state: &AppState) -> Result<Vec<u8>, SyncError> {
-    serde_json::to_vec(state)
+    let mut snapshot = match serde_json::to_value(state) {
+        Ok(serde_json::Value::Object(map)) => map,
+        Ok(_) => return Err(SyncError::Serialization("state is not a JSON object".to_string())),
+        Err(e) => return Err(SyncError::Serialization(e.to_string())),
+    };
+    snapshot.insert(SCHEMA_VERSION_KEY.to_string(), STATE_SCHEMA_VERSION.into());
+    serde_json::to_vec(&snapshot)
         .map_err(|e| SyncError::Serialization(e.to_string()))
 }
 
-/// Deserialize state from JSON
+/// Deserialize state from JSON, upgrading snapshots written by older versions
 pub fn deserialize_state(data: &[u8]) -> Result<AppState, SyncError> {
-    serde_json::from_slice(data)
+    let mut snapshot: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(data)
+        .map_err(|e| SyncError::Serialization(e.to_string()))?;
+
+    // Snapshots from before versioning carry no version key
+    let version = match snapshot.remove(SCHEMA_VERSION_KEY) {
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| SyncError::Serialization(format!("invalid {}: {}", SCHEMA_VERSION_KEY, value)))?,
+        None => 1,
+    };
+    if version > STATE_SCHEMA_VERSION {
+        return Err(SyncError::UnsupportedVersion(version));
+    }
+    for upgrade in &UPGRADES[(version.max(1) - 1) as usize..] {
+        upgrade(&mut snapshot);
+    }
+
+    serde_json::from_value(serde_json::Value::Object(snapshot))
         .map_err(|e| SyncError::Serialization(e.to_string()))
 }
 
@@ -349,7 +512,7 @@ pub fn export_state_json(state: &AppState) -> Result<String, SyncError> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use web_nexus_contracts::{ShowStatus, Role, UserStatus};
+    use web_nexus_contracts::ShowStatus;
 
     #[test]
     fn test_state_creation() {
@@ -431,4 +594,41 @@ mod tests {
         let shows = state1.get_site_shows("site-1");
         assert_eq!(shows.len(), 2);
     }
+
+    #[test]
+    fn test_snapshot_schema_upgrade() {
+        let snapshot = serialize_state(&AppState::new()).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&snapshot).unwrap();
+        assert_eq!(value["schemaVersion"], STATE_SCHEMA_VERSION);
+        assert!(deserialize_state(&snapshot).is_ok());
+
+        // Unversioned v1 snapshot without access entries or custom roles
+        let v1 = serde_json::json!({
+            "sites": {}, "shows": {}, "songs": {}, "photos": {}, "videos": {},
+            "posts": {}, "users": {}, "sync_status": "Synced", "last_sync": null, "clock": 3
+        });
+        let state = deserialize_state(v1.to_string().as_bytes()).unwrap();
+        assert_eq!(state.clock, 3);
+        assert!(state.access_lists.is_empty());
+
+        // v2 snapshot with a video whose password sits in its visibility
+        let v2 = serde_json::json!({
+            "schemaVersion": 2, "sites": {}, "shows": {}, "songs": {}, "photos": {}, "posts": {}, "users": {},
+            "videos": { "v1": {
+                "id": "v1", "siteId": "s1", "title": "Soundcheck", "description": null,
+                "source": { "youTube": { "video_id": "dQw4w9WgXcQ" } }, "thumbnailUrl": null, "durationSeconds": null,
+                "visibility": { "password": { "password": "encore" } }, "viewCount": 0, "publishedAt": 0
+            } },
+            "access_lists": {}, "role_definitions": {}, "sync_status": "Synced", "last_sync": null, "clock": 4
+        });
+        let state = deserialize_state(v2.to_string().as_bytes()).unwrap();
+        assert_eq!(state.videos["v1"].visibility, web_nexus_contracts::GalleryVisibility::Password);
+        assert!(!String::from_utf8(serialize_state(&state).unwrap()).unwrap().contains("encore"));
+
+        let future = serde_json::json!({ "schemaVersion": STATE_SCHEMA_VERSION + 1 });
+        assert!(matches!(
+            deserialize_state(future.to_string().as_bytes()),
+            Err(SyncError::UnsupportedVersion(_))
+        ));
+    }
 }